@@ -0,0 +1,37 @@
+CREATE TABLE tracks (
+    rowid INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+
+    -- ISO 3166-1 alpha-2 country code
+    country TEXT NOT NULL DEFAULT ''
+);
+
+CREATE TABLE track_layouts (
+    rowid INTEGER PRIMARY KEY,
+    track INTEGER NOT NULL,
+    name TEXT NOT NULL,
+
+    -- track length in meters
+    length REAL,
+
+    -- length of the pit-lane in meters
+    pit_lane_length REAL,
+
+    -- speed limit in the pit-lane in km/h
+    pit_speed_limit REAL,
+
+    -- amount of pit boxes
+    pit_boxes INTEGER,
+
+    FOREIGN KEY(track) REFERENCES tracks(rowid)
+);
+
+-- identifiers of a layout within the simulators (e.g. folder names)
+CREATE TABLE track_layout_sim_ids (
+    rowid INTEGER PRIMARY KEY,
+    layout INTEGER NOT NULL,
+    simulator INTEGER NOT NULL,
+    identifier TEXT NOT NULL,
+    UNIQUE(layout, simulator),
+    FOREIGN KEY(layout) REFERENCES track_layouts(rowid)
+);
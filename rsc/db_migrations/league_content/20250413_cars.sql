@@ -0,0 +1,45 @@
+CREATE TABLE car_classes (
+    rowid INTEGER PRIMARY KEY,
+    name TEXT NOT NULL
+);
+
+CREATE TABLE cars (
+    rowid INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    manufacturer TEXT NOT NULL DEFAULT '',
+
+    -- the class this car belongs to (NULL if not assigned)
+    car_class INTEGER,
+
+    FOREIGN KEY(car_class) REFERENCES car_classes(rowid)
+);
+
+-- identifiers of a car within the simulators (e.g. folder names)
+CREATE TABLE car_sim_ids (
+    rowid INTEGER PRIMARY KEY,
+    car INTEGER NOT NULL,
+    simulator INTEGER NOT NULL,
+    identifier TEXT NOT NULL,
+    UNIQUE(car, simulator),
+    FOREIGN KEY(car) REFERENCES cars(rowid)
+);
+
+-- Balance-of-Performance history of a car
+-- the entry with the latest 'since' is the currently valid one
+CREATE TABLE car_bop (
+    rowid INTEGER PRIMARY KEY,
+    car INTEGER NOT NULL,
+
+    -- additional weight in kg
+    ballast REAL NOT NULL DEFAULT 0.0,
+
+    -- intake restriction in percent
+    restrictor REAL NOT NULL DEFAULT 0.0,
+
+    since DATETIME NOT NULL,
+
+    -- the user that changed the BoP
+    user INTEGER,
+
+    FOREIGN KEY(car) REFERENCES cars(rowid)
+);
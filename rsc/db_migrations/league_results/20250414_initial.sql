@@ -0,0 +1,85 @@
+-- an imported session (practice, qualifying, race) of a simulator server
+CREATE TABLE sessions (
+    rowid INTEGER PRIMARY KEY,
+    simulator INTEGER NOT NULL,
+    session_type INTEGER NOT NULL,
+    server_name TEXT NOT NULL DEFAULT '',
+
+    -- name of the imported result file
+    file_name TEXT NOT NULL,
+
+    -- identifier of the track within the simulator
+    track_identifier TEXT NOT NULL DEFAULT '',
+
+    -- rowid of the track layout in the content database (if known)
+    track_layout INTEGER,
+
+    wet INTEGER NOT NULL DEFAULT 0,
+    started DATETIME,
+    imported DATETIME NOT NULL,
+    UNIQUE(simulator, server_name, file_name)
+);
+
+-- the final classification of a session (one row per driver)
+CREATE TABLE session_results (
+    rowid INTEGER PRIMARY KEY,
+    session INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+
+    -- rowid of the user in the members database (if known)
+    user INTEGER,
+
+    driver_name TEXT NOT NULL DEFAULT '',
+
+    -- unique identifier of the driver within the simulator (e.g. steam id)
+    driver_guid TEXT NOT NULL DEFAULT '',
+
+    -- rowid of the car in the content database (if known)
+    car INTEGER,
+
+    car_identifier TEXT NOT NULL DEFAULT '',
+    car_number INTEGER NOT NULL DEFAULT 0,
+    team TEXT NOT NULL DEFAULT '',
+    lap_count INTEGER NOT NULL DEFAULT 0,
+
+    -- times in milliseconds
+    best_lap INTEGER,
+    total_time INTEGER,
+
+    incidents INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY(session) REFERENCES sessions(rowid)
+);
+
+CREATE TABLE session_laps (
+    rowid INTEGER PRIMARY KEY,
+    session INTEGER NOT NULL,
+    user INTEGER,
+    driver_guid TEXT NOT NULL DEFAULT '',
+    car_number INTEGER NOT NULL DEFAULT 0,
+    lap_number INTEGER NOT NULL,
+
+    -- lap time in milliseconds
+    lap_time INTEGER NOT NULL,
+
+    -- comma separated sector times in milliseconds
+    sectors TEXT NOT NULL DEFAULT '',
+
+    valid INTEGER NOT NULL DEFAULT 1,
+    FOREIGN KEY(session) REFERENCES sessions(rowid)
+);
+
+-- penalties that were issued by the simulator server
+CREATE TABLE session_penalties (
+    rowid INTEGER PRIMARY KEY,
+    session INTEGER NOT NULL,
+    user INTEGER,
+    driver_guid TEXT NOT NULL DEFAULT '',
+    car_number INTEGER NOT NULL DEFAULT 0,
+    reason TEXT NOT NULL DEFAULT '',
+    penalty TEXT NOT NULL DEFAULT '',
+    penalty_value INTEGER NOT NULL DEFAULT 0,
+    violation_lap INTEGER NOT NULL DEFAULT 0,
+    cleared_lap INTEGER,
+    post_race INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY(session) REFERENCES sessions(rowid)
+);
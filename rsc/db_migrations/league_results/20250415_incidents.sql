@@ -0,0 +1,21 @@
+-- incidents (e.g. collisions) that were reported by the simulator server
+CREATE TABLE session_incidents (
+    rowid INTEGER PRIMARY KEY,
+    session INTEGER NOT NULL,
+    user INTEGER,
+    driver_guid TEXT NOT NULL DEFAULT '',
+    car_number INTEGER NOT NULL DEFAULT 0,
+
+    -- the other involved driver (NULL for collisions with the environment)
+    other_user INTEGER,
+    other_driver_guid TEXT,
+
+    -- simulator specific description (e.g. COLLISION_WITH_CAR)
+    incident_type TEXT NOT NULL DEFAULT '',
+
+    -- impact speed in km/h
+    impact_speed REAL,
+
+    lap INTEGER,
+    FOREIGN KEY(session) REFERENCES sessions(rowid)
+);
@@ -0,0 +1,33 @@
+-- current driver ratings (Glicko-2), computed from race results
+CREATE TABLE ratings (
+    rowid INTEGER PRIMARY KEY,
+
+    -- rowid of the user in the members database
+    user INTEGER NOT NULL,
+
+    -- rowid of the car class in the content database (0 for the overall rating)
+    car_class INTEGER NOT NULL DEFAULT 0,
+
+    rating REAL NOT NULL,
+    deviation REAL NOT NULL,
+    volatility REAL NOT NULL,
+
+    -- amount of rated races
+    races INTEGER NOT NULL DEFAULT 0,
+
+    updated DATETIME NOT NULL,
+    UNIQUE(user, car_class)
+);
+
+-- the rating of a driver after each race
+CREATE TABLE rating_history (
+    rowid INTEGER PRIMARY KEY,
+    user INTEGER NOT NULL,
+    car_class INTEGER NOT NULL DEFAULT 0,
+    session INTEGER NOT NULL,
+    rating REAL NOT NULL,
+    deviation REAL NOT NULL,
+    volatility REAL NOT NULL,
+    date DATETIME NOT NULL,
+    FOREIGN KEY(session) REFERENCES sessions(rowid)
+);
@@ -0,0 +1,41 @@
+-- a penalty that was pronounced by the stewards (in contrast to session_penalties from the simulator server)
+CREATE TABLE steward_penalties (
+    rowid INTEGER PRIMARY KEY,
+    session INTEGER NOT NULL,
+
+    -- the penalized driver (rowid of the user in the members database, if known)
+    user INTEGER,
+    driver_guid TEXT NOT NULL DEFAULT '',
+
+    -- time (seconds), position drop (positions), points deduction (points), race ban (races), license points (points)
+    penalty_type INTEGER NOT NULL,
+    value INTEGER NOT NULL DEFAULT 0,
+
+    reason TEXT NOT NULL DEFAULT '',
+
+    -- rowid of the issuing user in the members database
+    issued_by INTEGER NOT NULL,
+    issued DATETIME NOT NULL,
+
+    -- none, pending, rejected, accepted
+    appeal_state INTEGER NOT NULL DEFAULT 0,
+    appeal_text TEXT NOT NULL DEFAULT '',
+
+    -- revoked penalties are kept for the audit trail, but not applied anymore
+    revoked BOOLEAN NOT NULL DEFAULT FALSE,
+
+    FOREIGN KEY(session) REFERENCES sessions(rowid)
+);
+
+-- every change of a steward penalty
+CREATE TABLE steward_penalty_audit (
+    rowid INTEGER PRIMARY KEY,
+    penalty INTEGER NOT NULL,
+
+    -- rowid of the acting user in the members database
+    user INTEGER NOT NULL,
+
+    date DATETIME NOT NULL,
+    action TEXT NOT NULL,
+    FOREIGN KEY(penalty) REFERENCES steward_penalties(rowid)
+);
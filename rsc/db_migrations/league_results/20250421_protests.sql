@@ -0,0 +1,50 @@
+-- an incident report that was filed by a driver after a session
+CREATE TABLE protests (
+    rowid INTEGER PRIMARY KEY,
+    session INTEGER NOT NULL,
+    lap INTEGER,
+
+    -- rowid of the reporting user in the members database
+    reporter INTEGER NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    filed DATETIME NOT NULL,
+
+    -- open, penalized, no further action
+    state INTEGER NOT NULL DEFAULT 1,
+    resolution TEXT NOT NULL DEFAULT '',
+    resolved_by INTEGER,
+    resolved DATETIME,
+
+    -- the penalty that was pronounced when resolving the protest
+    penalty INTEGER,
+
+    FOREIGN KEY(session) REFERENCES sessions(rowid),
+    FOREIGN KEY(penalty) REFERENCES steward_penalties(rowid)
+);
+
+-- drivers that are involved in a protest
+CREATE TABLE protest_drivers (
+    rowid INTEGER PRIMARY KEY,
+    protest INTEGER NOT NULL,
+    user INTEGER,
+    driver_guid TEXT NOT NULL DEFAULT '',
+    FOREIGN KEY(protest) REFERENCES protests(rowid)
+);
+
+-- links to evidence (e.g. videos)
+CREATE TABLE protest_evidence (
+    rowid INTEGER PRIMARY KEY,
+    protest INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    FOREIGN KEY(protest) REFERENCES protests(rowid)
+);
+
+-- comments of stewards and the reporter
+CREATE TABLE protest_comments (
+    rowid INTEGER PRIMARY KEY,
+    protest INTEGER NOT NULL,
+    user INTEGER NOT NULL,
+    date DATETIME NOT NULL,
+    text TEXT NOT NULL,
+    FOREIGN KEY(protest) REFERENCES protests(rowid)
+);
@@ -0,0 +1,47 @@
+CREATE TABLE championships (
+    rowid INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+
+    -- amount of worst round results that are not counted
+    drop_worst INTEGER NOT NULL DEFAULT 0,
+
+    -- extra points for the best qualifying position and the fastest race lap
+    points_pole INTEGER NOT NULL DEFAULT 0,
+    points_fastest_lap INTEGER NOT NULL DEFAULT 0
+);
+
+-- car classes that are allowed in a championship (all classes if empty)
+CREATE TABLE championship_car_classes (
+    rowid INTEGER PRIMARY KEY,
+    championship INTEGER NOT NULL,
+
+    -- rowid of the car class in the content database
+    car_class INTEGER NOT NULL,
+
+    UNIQUE(championship, car_class),
+    FOREIGN KEY(championship) REFERENCES championships(rowid)
+);
+
+-- points for the finishing positions
+CREATE TABLE championship_points (
+    rowid INTEGER PRIMARY KEY,
+    championship INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    points INTEGER NOT NULL,
+    UNIQUE(championship, position),
+    FOREIGN KEY(championship) REFERENCES championships(rowid)
+);
+
+CREATE TABLE championship_rounds (
+    rowid INTEGER PRIMARY KEY,
+    championship INTEGER NOT NULL,
+    round_number INTEGER NOT NULL,
+    name TEXT NOT NULL DEFAULT '',
+
+    -- rowids of the sessions in the results database (NULL until results are available)
+    race_session INTEGER,
+    qualifying_session INTEGER,
+
+    FOREIGN KEY(championship) REFERENCES championships(rowid)
+);
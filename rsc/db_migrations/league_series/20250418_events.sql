@@ -0,0 +1,56 @@
+-- a scheduled event (practice, qualifying and race on a server)
+CREATE TABLE events (
+    rowid INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    simulator INTEGER NOT NULL,
+
+    -- rowid of the track layout in the content database
+    track_layout INTEGER,
+
+    -- name of the simulator server that hosts the event
+    server TEXT NOT NULL DEFAULT '',
+
+    -- session timings (NULL if the session is not held), durations in minutes
+    practice_start DATETIME,
+    practice_duration INTEGER NOT NULL DEFAULT 0,
+    qualifying_start DATETIME,
+    qualifying_duration INTEGER NOT NULL DEFAULT 0,
+    race_start DATETIME,
+    race_duration INTEGER NOT NULL DEFAULT 0,
+
+    -- registration window (NULL means: open since creation, closed at the first session)
+    registration_opens DATETIME,
+    registration_closes DATETIME,
+
+    -- maximum amount of drivers on the grid (0 for unlimited), further registrations are waitlisted
+    max_grid INTEGER NOT NULL DEFAULT 0
+);
+
+-- car classes that are allowed at an event (all classes if empty)
+CREATE TABLE event_car_classes (
+    rowid INTEGER PRIMARY KEY,
+    event INTEGER NOT NULL,
+
+    -- rowid of the car class in the content database
+    car_class INTEGER NOT NULL,
+
+    UNIQUE(event, car_class),
+    FOREIGN KEY(event) REFERENCES events(rowid)
+);
+
+-- driver sign-ups, the order of registration decides about grid or waitlist
+CREATE TABLE event_registrations (
+    rowid INTEGER PRIMARY KEY,
+    event INTEGER NOT NULL,
+
+    -- rowid of the user in the members database
+    user INTEGER NOT NULL,
+
+    -- rowid of the car in the content database
+    car INTEGER,
+
+    registered DATETIME NOT NULL,
+    UNIQUE(event, user),
+    FOREIGN KEY(event) REFERENCES events(rowid)
+);
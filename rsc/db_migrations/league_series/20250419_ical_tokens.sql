@@ -0,0 +1,12 @@
+-- secret tokens for the personal calendar feeds (one per user)
+CREATE TABLE ical_tokens (
+    rowid INTEGER PRIMARY KEY,
+
+    -- rowid of the user in the members database
+    user INTEGER NOT NULL UNIQUE,
+
+    -- the encrypted token
+    token TEXT NOT NULL,
+
+    creation DATETIME NOT NULL
+);
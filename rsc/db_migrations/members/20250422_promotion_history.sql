@@ -0,0 +1,13 @@
+-- every change of a user promotion
+CREATE TABLE promotion_history (
+    rowid INTEGER PRIMARY KEY,
+    user INTEGER NOT NULL,
+
+    -- the granting user (NULL for offline administration)
+    granted_by INTEGER,
+
+    promotion_level INTEGER NOT NULL,
+    promotion_authority INTEGER NOT NULL,
+    date TEXT NOT NULL,
+    FOREIGN KEY(user) REFERENCES users(rowid)
+);
@@ -0,0 +1,5 @@
+-- the token that is sent via email to reset the password of the assigned user
+ALTER TABLE email_accounts ADD COLUMN reset_token BLOB;
+
+-- when the reset token was created (valid for one hour)
+ALTER TABLE email_accounts ADD COLUMN reset_token_creation TEXT;
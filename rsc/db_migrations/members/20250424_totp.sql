@@ -0,0 +1,19 @@
+-- the TOTP secret of a user (encrypted with the server key)
+ALTER TABLE users ADD COLUMN totp_secret BLOB;
+
+-- since when TOTP is enabled (NULL while the enrollment is not confirmed)
+ALTER TABLE users ADD COLUMN totp_enabled TEXT;
+
+-- the last accepted TOTP time step (a code cannot be used twice)
+ALTER TABLE users ADD COLUMN totp_last_step INTEGER;
+
+-- one-time codes to login without the TOTP authenticator
+CREATE TABLE totp_recovery_codes (
+    rowid INTEGER PRIMARY KEY,
+    user INTEGER NOT NULL,
+    code BLOB NOT NULL,
+    FOREIGN KEY(user) REFERENCES users(rowid)
+);
+
+-- when the login was verified with a TOTP code (NULL for logins without second factor)
+ALTER TABLE cookie_logins ADD COLUMN totp_verified TEXT;
@@ -0,0 +1,29 @@
+-- passkeys (WebAuthn public key credentials) of the users
+CREATE TABLE webauthn_credentials (
+    rowid INTEGER PRIMARY KEY,
+    user INTEGER NOT NULL,
+
+    -- base64url encoded credential id (as used by the browser)
+    credential_id TEXT NOT NULL UNIQUE,
+
+    -- SEC1 encoded P-256 public key
+    public_key BLOB NOT NULL,
+
+    -- the signature counter of the authenticator (to detect cloned authenticators)
+    sign_count INTEGER NOT NULL DEFAULT 0,
+
+    -- a label given by the user
+    name TEXT NOT NULL DEFAULT '',
+    creation TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_usage TEXT,
+    FOREIGN KEY(user) REFERENCES users(rowid)
+);
+
+-- challenges of pending registrations (user is set) and logins (user is NULL)
+CREATE TABLE webauthn_challenges (
+    rowid INTEGER PRIMARY KEY,
+    challenge TEXT NOT NULL UNIQUE,
+    user INTEGER,
+    creation TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(user) REFERENCES users(rowid)
+);
@@ -0,0 +1,12 @@
+-- Discord accounts that are linked to users (via OAuth2)
+CREATE TABLE discord_accounts (
+    rowid INTEGER PRIMARY KEY,
+    discord_id TEXT NOT NULL UNIQUE,
+    user INTEGER,
+
+    -- the Discord handle, updated at every login
+    username TEXT NOT NULL DEFAULT '',
+    creation TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_login TEXT,
+    FOREIGN KEY(user) REFERENCES users(rowid)
+);
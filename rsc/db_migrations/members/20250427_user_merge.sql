@@ -0,0 +1,5 @@
+-- the token that proves the ownership of a user which shall be merged into another user
+ALTER TABLE users ADD COLUMN merge_token BLOB;
+
+-- when the merge token was created (valid for ten minutes)
+ALTER TABLE users ADD COLUMN merge_token_creation TEXT;
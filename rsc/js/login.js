@@ -6,16 +6,23 @@ function disable_all_elements(disable) {
         document.getElementById("WithPasswordId").disabled = true;
         document.getElementById("WithPasswordPassword").disabled = true;
         document.getElementById("WithPasswordButton").disabled = true;
+        document.getElementById("WithPasswordForgotButton").disabled = true;
     } else {
         document.getElementById("WithPasswordId").disabled = disable;
         document.getElementById("WithPasswordPassword").disabled = disable;
         document.getElementById("WithPasswordButton").disabled = disable;
+        document.getElementById("WithPasswordForgotButton").disabled = disable;
     }
 
+    document.getElementById("WithPasskeyButton").disabled = disable;
+
     document.getElementById("WithEmailEmail").disabled = disable;
     document.getElementById("WithEmailButton").disabled = disable;
 
     document.getElementById("WithSteamButton").disabled = disable;
+
+    let btn_discord = document.getElementById("WithDiscordButton");
+    if (btn_discord) btn_discord.disabled = disable;
     
     busy_spinner(disable)
 }
@@ -32,11 +39,16 @@ document.addEventListener('DOMContentLoaded', function () {
         document.getElementById("WithPasswordId").classList.add(class_name);
         document.getElementById("WithPasswordPassword").classList.add(class_name);
         document.getElementById("WithPasswordButton").classList.add(class_name);
+        document.getElementById("WithPasswordForgotButton").classList.add(class_name);
+        document.getElementById("WithPasskeyButton").classList.add(class_name);
 
         document.getElementById("WithEmailEmail").classList.add(class_name);
         document.getElementById("WithEmailButton").classList.add(class_name);
 
         document.getElementById("WithSteamButton").classList.add(class_name);
+
+        let btn_discord = document.getElementById("WithDiscordButton");
+        if (btn_discord) btn_discord.classList.add(class_name);
     }
 
     function rm_classes(class_name) {
@@ -46,11 +58,16 @@ document.addEventListener('DOMContentLoaded', function () {
         document.getElementById("WithPasswordId").classList.remove(class_name);
         document.getElementById("WithPasswordPassword").classList.remove(class_name);
         document.getElementById("WithPasswordButton").classList.remove(class_name);
+        document.getElementById("WithPasswordForgotButton").classList.remove(class_name);
+        document.getElementById("WithPasskeyButton").classList.remove(class_name);
 
         document.getElementById("WithEmailEmail").classList.remove(class_name);
         document.getElementById("WithEmailButton").classList.remove(class_name);
 
         document.getElementById("WithSteamButton").classList.remove(class_name);
+
+        let btn_discord = document.getElementById("WithDiscordButton");
+        if (btn_discord) btn_discord.classList.remove(class_name);
     }
 
     function switch_log_reg_handler() {
@@ -60,12 +77,16 @@ document.addEventListener('DOMContentLoaded', function () {
             document.getElementById("WithPasswordId").disabled = true;
             document.getElementById("WithPasswordPassword").disabled = true;
             document.getElementById("WithPasswordButton").disabled = true;
+            document.getElementById("WithPasswordForgotButton").disabled = true;
+            document.getElementById("WithPasskeyButton").disabled = true;
         } else {
             add_classes("SwitchedToLogin");
             rm_classes("SwitchedToRegister");
             document.getElementById("WithPasswordId").disabled = false;
             document.getElementById("WithPasswordPassword").disabled = false;
             document.getElementById("WithPasswordButton").disabled = false;
+            document.getElementById("WithPasswordForgotButton").disabled = false;
+            document.getElementById("WithPasskeyButton").disabled = false;
         }
     }
 
@@ -85,6 +106,10 @@ document.addEventListener('DOMContentLoaded', function () {
         let request_data = {};
         request_data.identification = document.getElementById("WithPasswordId").value;
         request_data.password = document.getElementById("WithPasswordPassword").value;
+        let totp_code = document.getElementById("WithPasswordTotp").value;
+        if (totp_code.length > 0) {
+            request_data.totp = totp_code;
+        }
 
         // call api
         api_v0_post("login/password", request_data, password_login_api_callback)
@@ -95,12 +120,58 @@ function password_login_api_callback(status, data) {
     if (status == 200) {
         append_message_success("OK", "Successful login");
         window.location.href = "/";
+    } else if (status == 401) {
+        document.getElementById("WithPasswordTotp").style.display = "block";
+        append_message_error(data.summary, "Please enter the code from your authenticator app");
+        disable_all_elements(false);
     } else {
         append_message_error("ERROR", "Login failed");
         disable_all_elements(false);
     }
 }
 
+// With Passkey button handling
+document.addEventListener('DOMContentLoaded', function () {
+    let btn = document.getElementById("WithPasskeyButton");
+    btn.addEventListener("click", function() {
+        if (!window.PublicKeyCredential) {
+            append_message_error("Passkey Unavailable", "This browser does not support passkeys");
+            return;
+        }
+        disable_all_elements(true);
+        api_v0_post("login/webauthn/challenge", {}, passkey_challenge_api_callback)
+    })
+})
+
+function passkey_challenge_api_callback(status, data) {
+    if (status != 200) {
+        append_message_error(data.summary, data.description);
+        disable_all_elements(false);
+        return;
+    }
+
+    let options = {
+        challenge: base64url_decode(data.challenge),
+        rpId: data.rp_id,
+        userVerification: "required",
+        timeout: 300000,
+    };
+    navigator.credentials.get({publicKey: options})
+        .then(credential => {
+            let request_data = {
+                credential_id: credential.id,
+                client_data_json: base64url_encode(credential.response.clientDataJSON),
+                authenticator_data: base64url_encode(credential.response.authenticatorData),
+                signature: base64url_encode(credential.response.signature),
+            };
+            api_v0_post("login/webauthn", request_data, password_login_api_callback)
+        })
+        .catch(error => {
+            append_message_error("Passkey Login Failed", error.message);
+            disable_all_elements(false);
+        });
+}
+
 // With Email button handling
 document.addEventListener('DOMContentLoaded', function () {
     let btn = document.getElementById("WithEmailButton");
@@ -153,4 +224,50 @@ document.addEventListener('DOMContentLoaded', function () {
             + "&openid.return_to=" + steam_return_url;
         window.location.replace(steam_forward_url);
     })
-})
\ No newline at end of file
+})
+
+// With Discord button handling (only available, when Discord login is configured)
+document.addEventListener('DOMContentLoaded', function () {
+    let btn = document.getElementById("WithDiscordButton");
+    if (!btn) return;
+    btn.addEventListener("click", function() {
+        let switch_log_reg = document.getElementById("SwitchLoginRegister").getElementsByTagName("input")[0];
+
+        // disable UI
+        disable_all_elements(true);
+
+        // forward to discord (via league server, to keep the OAuth2 state)
+        if (switch_log_reg.checked) {
+            window.location.href = window.location.origin + "/html/login_discord/create";
+        } else {
+            window.location.href = window.location.origin + "/html/login_discord/login";
+        }
+    })
+})
+
+// Forgot Password button handling
+document.addEventListener('DOMContentLoaded', function () {
+    let btn = document.getElementById("WithPasswordForgotButton");
+    btn.addEventListener("click", function() {
+        let email = document.getElementById("WithPasswordId").value;
+
+        // verify email address
+        if (!is_valid_email(email)) {
+            append_message_error("Invalid Email", "Please enter the email address of your account!");
+            return;
+        }
+
+        // call api
+        disable_all_elements(true);
+        api_v0_post("login/password/forgot", {email: email}, password_forgot_api_callback)
+    })
+})
+
+function password_forgot_api_callback(status, data) {
+    if (status == 200) {
+        append_message_success("OK", "An email with a password reset link was sent<br><small>(No link is sent if previous link is still active, or email is not verified)</small>");
+    } else {
+        append_message_error(data.summary, data.description);
+    }
+    disable_all_elements(false);
+}
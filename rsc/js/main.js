@@ -79,6 +79,21 @@ function api_v0_post(endpoint, tx_data, callback, callback_data) {
         })
 }
 
+/** Encode an ArrayBuffer as base64url string without padding (as used by WebAuthn) */
+function base64url_encode(buffer) {
+    let binary = "";
+    for (let byte of new Uint8Array(buffer)) {
+        binary += String.fromCharCode(byte);
+    }
+    return btoa(binary).replace(/\+/g, "-").replace(/\//g, "_").replace(/=+$/, "");
+}
+
+/** Decode a base64url string into an Uint8Array */
+function base64url_decode(text) {
+    let binary = atob(text.replace(/-/g, "+").replace(/_/g, "/"));
+    return Uint8Array.from(binary, c => c.charCodeAt(0));
+}
+
 function is_valid_email(email) {
     return email.match(
         /^(([^<>()[\]\\.,;:\s@\"]+(\.[^<>()[\]\\.,;:\s@\"]+)*)|(\".+\"))@((\[[0-9]{1,3}\.[0-9]{1,3}\.[0-9]{1,3}\.[0-9]{1,3}\])|(([a-zA-Z\-0-9]+\.)+[a-zA-Z]{2,}))$/
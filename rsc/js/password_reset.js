@@ -0,0 +1,40 @@
+// Set Password button handling
+document.addEventListener('DOMContentLoaded', function () {
+    let btn = document.getElementById("ResetButton");
+    btn.addEventListener("click", function() {
+        let password = document.getElementById("ResetPassword").value;
+        let password_repeat = document.getElementById("ResetPasswordRepeat").value;
+
+        // verify password
+        if (password.length == 0) {
+            append_message_error("Invalid Password", "Please enter a new password!");
+            return;
+        }
+        if (password != password_repeat) {
+            append_message_error("Invalid Password", "The repeated password does not match!");
+            return;
+        }
+
+        // prepare request data
+        let request_data = {};
+        request_data.email_account = parseInt(document.getElementById("ResetEmailAccount").value);
+        request_data.token = document.getElementById("ResetToken").value;
+        request_data.password = password;
+
+        // call api
+        btn.disabled = true;
+        busy_spinner(true);
+        api_v0_post("login/password/reset", request_data, password_reset_api_callback)
+    })
+})
+
+function password_reset_api_callback(status, data) {
+    busy_spinner(false);
+    if (status == 200) {
+        append_message_success("OK", "New password set, please login again");
+        window.location.href = "/html/login";
+    } else {
+        append_message_error(data.summary, data.description);
+        document.getElementById("ResetButton").disabled = false;
+    }
+}
@@ -0,0 +1,77 @@
+function disable_all_elements(disable) {
+    if (disable) {
+        document.body.style.cursor = "wait";
+    } else {
+        document.body.style.cursor = "default";
+    }
+    busy_spinner(disable);
+}
+
+function handler_button_file(session_id) {
+    let drivers = [];
+    for (let checkbox of document.getElementsByName("ProtestDriver")) {
+        if (checkbox.checked) {
+            drivers.push({driver_guid: checkbox.value});
+        }
+    }
+    let lap = document.getElementById("ProtestLap").value;
+    let evidence = document.getElementById("ProtestEvidence").value.split("\n").map(s => s.trim()).filter(s => s.length > 0);
+    let tx_data = {
+        session: session_id,
+        lap: lap ? parseInt(lap) : null,
+        description: document.getElementById("ProtestDescription").value,
+        drivers: drivers,
+        evidence: evidence,
+    };
+    api_v0("POST", "protests", tx_data, handler_button_file_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_file_callback(status, data) {
+    if (status == 201) {
+        window.location.href = "/html/protests/" + data.id;
+    } else {
+        disable_all_elements(false);
+        append_message_error(data.summary, data.description);
+    }
+}
+
+function handler_button_comment(protest_id) {
+    let tx_data = { text: document.getElementById("ProtestComment").value };
+    api_v0("POST", "protests/" + protest_id + "/comments", tx_data, handler_button_reload_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_resolve(protest_id) {
+    let penalty_type = document.getElementById("ResolutionPenaltyType").value;
+    let tx_data = {
+        resolution: document.getElementById("ResolutionText").value,
+        penalty: null,
+    };
+    if (penalty_type) {
+        tx_data.penalty = {
+            driver_guid: document.getElementById("ResolutionDriver").value,
+            penalty_type: penalty_type,
+            value: parseInt(document.getElementById("ResolutionValue").value),
+        };
+    }
+    api_v0("POST", "protests/" + protest_id + "/resolution", tx_data, handler_button_reload_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_reload_callback(status, data) {
+    if (status == 200) {
+        location.reload();
+    } else {
+        disable_all_elements(false);
+        append_message_error(data.summary, data.description);
+    }
+}
+
+function handler_button_decide(penalty_id, accept) {
+    let comment = prompt("Comment on the decision");
+    if (comment !== null) {
+        api_v0("POST", "penalties/" + penalty_id + "/decision", {accept: accept, comment: comment}, handler_button_reload_callback);
+        disable_all_elements(true);
+    }
+}
@@ -0,0 +1,55 @@
+function disable_all_elements(disable) {
+    if (disable) {
+        document.body.style.cursor = "wait";
+    } else {
+        document.body.style.cursor = "default";
+    }
+    busy_spinner(disable);
+}
+
+function handler_button_register(event_id) {
+    let car = document.getElementById("RegistrationCar").value;
+    let tx_data = { car: car ? parseInt(car) : null };
+    api_v0("POST", "schedules/" + event_id + "/registration", tx_data, handler_button_registration_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_unregister(event_id) {
+    api_v0("DELETE", "schedules/" + event_id + "/registration", {}, handler_button_registration_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_registration_callback(status, data) {
+    if (status == 200) {
+        location.reload();
+    } else if (status == 400 || status == 401 || status == 404 || status == 409 || status == 500) {
+        disable_all_elements(false);
+        append_message_error(data.summary, data.description);
+    } else {
+        disable_all_elements(false);
+        append_message_error("Unexpected Error", data);
+    }
+}
+
+function handler_button_calendar_create() {
+    api_v0("POST", "user/calendar", {}, handler_button_calendar_create_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_calendar_create_callback(status, data) {
+    disable_all_elements(false);
+    if (status == 200) {
+        let inp_url = document.getElementById("CalendarUrl");
+        inp_url.value = data.url;
+        inp_url.hidden = false;
+        inp_url.select();
+        append_message_success("Calendar Link", "Copy the link now, it cannot be shown again!");
+    } else {
+        append_message_error(data.summary, data.description);
+    }
+}
+
+function handler_button_calendar_revoke() {
+    api_v0("DELETE", "user/calendar", {}, handler_button_registration_callback);
+    disable_all_elements(true);
+}
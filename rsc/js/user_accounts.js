@@ -32,7 +32,11 @@ function tabSelectByIndex(active_index) {
         document.getElementById("AccountTypeButtonPassword"),
         document.getElementById("AccountTypeButtonEmail"),
         document.getElementById("AccountTypeButtonSteam"),
+        document.getElementById("AccountTypeButtonPasskey"),
         document.getElementById("AccountTypeButtonDiscord"),
+        document.getElementById("AccountTypeButtonSessions"),
+        document.getElementById("AccountTypeButtonMerge"),
+        document.getElementById("AccountTypeButtonData"),
     ];
 
     // definition of tabs
@@ -40,7 +44,11 @@ function tabSelectByIndex(active_index) {
         document.getElementById("AccountTabPassword"),
         document.getElementById("AccountTabEmail"),
         document.getElementById("AccountTabSteam"),
+        document.getElementById("AccountTabPasskey"),
         document.getElementById("AccountTabDiscord"),
+        document.getElementById("AccountTabSessions"),
+        document.getElementById("AccountTabMerge"),
+        document.getElementById("AccountTabData"),
     ]
 
     // activation of buttons
@@ -66,8 +74,7 @@ function tabSelectByIndex(active_index) {
 
 
 document.addEventListener('DOMContentLoaded', function () {
-    var live_input = document.getElementById("AccountTabPassword");
-    liveinput_init("AccountTabPassword", prepare_password_save)
+    liveinput_init("PasswordChange", prepare_password_save)
 })
 
 function prepare_password_save(input_elements) {
@@ -141,4 +148,223 @@ function handler_button_email_callback(status, data) {
         disable_all_elements(false);
         append_message_error("Unexpected Error", data);
     }
-}
\ No newline at end of file
+}
+function handler_button_delete_steam(id) {
+    let tx_data= { id: id };
+    api_v0("DELETE", "user/account/steam", tx_data, handler_button_steam_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_steam_callback(status, data) {
+    if (status == 204) {
+        location.reload();
+    } else {
+        disable_all_elements(false);
+        append_message_error(data.summary, data.description);
+    }
+}
+
+function handler_button_totp_enroll() {
+    api_v0("POST", "user/totp", {}, handler_button_totp_enroll_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_totp_enroll_callback(status, data) {
+    disable_all_elements(false);
+    if (status == 200) {
+        let e_uri = document.getElementById("TotpUri");
+        e_uri.href = data.uri;
+        e_uri.innerText = data.uri;
+        document.getElementById("TotpSecret").innerText = data.secret;
+        for (let e_row of document.querySelectorAll("#Totp tr.TotpEnrollment")) {
+            e_row.style.display = "table-row";
+        }
+    } else {
+        append_message_error(data.summary, data.description);
+    }
+}
+
+function handler_button_totp_confirm() {
+    let tx_data = { code: document.getElementById("TotpCode").value };
+    api_v0("PUT", "user/totp", tx_data, handler_button_totp_codes_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_totp_verify() {
+    let tx_data = { code: document.getElementById("TotpCode").value };
+    api_v0("POST", "user/totp/verify", tx_data, handler_button_totp_reload_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_totp_recovery_codes() {
+    let tx_data = { code: document.getElementById("TotpCode").value };
+    api_v0("POST", "user/totp/recovery_codes", tx_data, handler_button_totp_codes_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_totp_codes_callback(status, data) {
+    disable_all_elements(false);
+    if (status == 200) {
+        let e_row = document.getElementById("TotpRecoveryCodes");
+        e_row.getElementsByTagName("code")[0].innerText = data.recovery_codes.join("\n");
+        e_row.style.display = "table-row";
+        for (let e_enroll of document.querySelectorAll("#Totp tr.TotpEnrollment")) {
+            e_enroll.style.display = "none";
+        }
+    } else {
+        append_message_error(data.summary, data.description);
+    }
+}
+
+function handler_button_totp_disable() {
+    let tx_data = { code: document.getElementById("TotpCode").value };
+    api_v0("DELETE", "user/totp", tx_data, handler_button_totp_reload_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_totp_reload_callback(status, data) {
+    if (status == 200) {
+        location.reload();
+    } else {
+        disable_all_elements(false);
+        append_message_error(data.summary, data.description);
+    }
+}
+
+function handler_button_add_passkey() {
+    if (!window.PublicKeyCredential) {
+        append_message_error("Passkey Unavailable", "This browser does not support passkeys");
+        return;
+    }
+    api_v0("POST", "user/webauthn/challenge", {}, handler_button_add_passkey_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_add_passkey_callback(status, data) {
+    if (status != 200) {
+        disable_all_elements(false);
+        append_message_error(data.summary, data.description);
+        return;
+    }
+
+    let options = {
+        challenge: base64url_decode(data.challenge),
+        rp: {id: data.rp_id, name: data.rp_name},
+        user: {id: base64url_decode(data.user_id), name: data.user_name, displayName: data.user_name},
+        pubKeyCredParams: [{type: "public-key", alg: -7}],
+        excludeCredentials: data.exclude_credentials.map(id => ({type: "public-key", id: base64url_decode(id)})),
+        authenticatorSelection: {residentKey: "required", userVerification: "required"},
+        attestation: "none",
+        timeout: 300000,
+    };
+    navigator.credentials.create({publicKey: options})
+        .then(credential => {
+            let tx_data = {
+                name: document.getElementById("AddPasskeyName").value,
+                client_data_json: base64url_encode(credential.response.clientDataJSON),
+                attestation_object: base64url_encode(credential.response.attestationObject),
+            };
+            api_v0("POST", "user/webauthn", tx_data, handler_button_passkey_callback);
+        })
+        .catch(error => {
+            disable_all_elements(false);
+            append_message_error("Passkey Registration Failed", error.message);
+        });
+}
+
+function handler_button_delete_passkey(id) {
+    api_v0("DELETE", "user/webauthn/" + id, {}, handler_button_passkey_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_passkey_callback(status, data) {
+    if (status == 200 || status == 201) {
+        location.reload();
+    } else {
+        disable_all_elements(false);
+        append_message_error(data.summary, data.description);
+    }
+}
+
+function handler_button_delete_session(id) {
+    api_v0("DELETE", "user/session/" + id, {}, handler_button_session_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_delete_other_sessions() {
+    api_v0("DELETE", "user/sessions", {}, handler_button_session_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_session_callback(status, data) {
+    if (status == 200) {
+        location.reload();
+    } else {
+        disable_all_elements(false);
+        append_message_error(data.summary, data.description);
+    }
+}
+
+function handler_button_merge_code() {
+    api_v0("POST", "user/merge/code", {}, handler_button_merge_code_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_merge_code_callback(status, data) {
+    disable_all_elements(false);
+    if (status == 200) {
+        let row = document.getElementById("MergeCode");
+        row.getElementsByTagName("code")[0].textContent = "User-ID: " + data.user_id + "\nCode: " + data.code;
+        row.style.display = "table-row";
+        append_message_success("Merge Code Created", "Login with the other user and enter the code within " + data.valid_minutes + " minutes");
+    } else {
+        append_message_error(data.summary, data.description);
+    }
+}
+
+function handler_button_merge() {
+    let user_id = parseInt(document.getElementById("MergeUserId").value);
+    let code = document.getElementById("MergeUserCode").value;
+    if (isNaN(user_id) || code == "") {
+        append_message_error("Invalid Input", "Please enter the User-ID and the merge code of the other user");
+        return;
+    }
+    if (!confirm("Merge user " + user_id + " into this user? This cannot be undone.")) {
+        return;
+    }
+    api_v0("POST", "user/merge", {user_id: user_id, code: code}, handler_button_merge_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_merge_callback(status, data) {
+    if (status == 200) {
+        location.reload();
+    } else {
+        disable_all_elements(false);
+        append_message_error(data.summary, data.description);
+    }
+}
+
+function handler_button_delete_user() {
+    if (!confirm("Delete this user? All personal data is removed, results are kept anonymously.")) {
+        return;
+    }
+    let tx_data = {};
+    for (let [key, id] of [["password", "DeleteUserPassword"], ["totp", "DeleteUserTotp"], ["name", "DeleteUserName"]]) {
+        let e_input = document.getElementById(id);
+        if (e_input) {
+            tx_data[key] = e_input.value;
+        }
+    }
+    api_v0("DELETE", "user", tx_data, handler_button_delete_user_callback);
+    disable_all_elements(true);
+}
+
+function handler_button_delete_user_callback(status, data) {
+    if (status == 200) {
+        window.location.href = "/";
+    } else {
+        disable_all_elements(false);
+        append_message_error(data.summary, data.description);
+    }
+}
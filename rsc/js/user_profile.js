@@ -8,3 +8,20 @@ function username_prepare_save(input_elements) {
         api_data:{name: input_elements[0].value},
     };
 }
+
+function handler_button_appeal(penalty_id) {
+    let text = prompt("Reason for your appeal");
+    if (text) {
+        api_v0("POST", "penalties/" + penalty_id + "/appeal", {text: text}, handler_button_appeal_callback);
+        busy_spinner(true);
+    }
+}
+
+function handler_button_appeal_callback(status, data) {
+    busy_spinner(false);
+    if (status == 200) {
+        location.reload();
+    } else {
+        append_message_error(data.summary, data.description);
+    }
+}
@@ -0,0 +1,17 @@
+function handler_button_promote(user_id) {
+    let tx_data = {
+        level: document.getElementById("PromotionLevel" + user_id).value,
+        authority: document.getElementById("PromotionAuthority" + user_id).value,
+    };
+    api_v0("PUT", "user/" + user_id + "/promotion", tx_data, handler_button_promote_callback);
+    busy_spinner(true);
+}
+
+function handler_button_promote_callback(status, data) {
+    busy_spinner(false);
+    if (status == 200) {
+        append_message_success("Promotion", "Changed to " + data.label);
+    } else {
+        append_message_error(data.summary, data.description);
+    }
+}
@@ -55,6 +55,16 @@ impl AppState {
     }
 
 
+    /// App state with in-memory databases (for unit tests)
+    #[cfg(test)]
+    pub async fn new_in_memory(config: Config) -> Self {
+        AppState {
+            database_dir: PathBuf::new(),
+            config,
+            database: db2::DatabaseManager::new_in_memory().await,
+        }
+    }
+
     /// Returns a RustlsConfig object, or panics
     pub async fn get_rustls_config(&self) -> RustlsConfig {
 
@@ -1,6 +1,7 @@
 use std::error::Error;
 use std::path::PathBuf;
 use serde::Deserialize;
+use sslo_lib::db::members::users::PromotionLevel;
 use sslo_lib::error::SsloError;
 
 #[derive(Deserialize, Clone)]
@@ -14,6 +15,18 @@ pub struct Config {
 
     /// Configuration for sending emails
     pub smtp: Smtp,
+
+    /// Lobbies that are trusted to request user information from the league
+    #[serde(default)]
+    pub lobbies: Vec<Lobby>,
+
+    /// Two-factor authentication with authenticator apps (disabled if not configured)
+    #[serde(default)]
+    pub totp: Option<Totp>,
+
+    /// Login with Discord accounts (disabled if not configured)
+    #[serde(default)]
+    pub discord: Option<Discord>,
 }
 
 
@@ -31,6 +44,28 @@ impl Config {
         })?;
         Ok(config)
     }
+
+    /// A minimal configuration for unit tests
+    #[cfg(test)]
+    pub fn new_for_test() -> Self {
+        toml::from_str(r#"
+            [general]
+            database_dir="."
+            days_recent_activity=70
+
+            [http]
+            port_http=8080
+            port_https=8443
+            tls_cert="tls/cert.pem"
+            tls_key="tls/key.pem"
+
+            [smtp]
+            email="sslo@myprovider.tld"
+            host="smtp.myprovider.tld"
+            username="my_stmp_username"
+            password="my_smtp_password"
+        "#).unwrap()
+    }
 }
 
 
@@ -49,8 +84,24 @@ pub struct General {
     pub days_recent_activity: u16,
 
     /// Manually define a single user as Server Admin, by user-id
-    /// This is intended to be used only temporarily until
+    /// This is intended to be used only temporarily until a regular administrator is promoted.
+    /// The root user has all permissions, independent of the promotion level.
+    /// When TOTP is required for a promotion level, the root user must enable TOTP as well.
     pub root_user_id: Option<i64>,
+
+    /// The amount of days after which unused logins (login cookies) expire
+    /// A value of 0 disables the expiry.
+    #[serde(default = "General::default_days_login_expiry")]
+    pub days_login_expiry: u16,
+
+    /// The public base URL of the league (eg. https://league.mydomain.com)
+    /// This is used for links in emails, as WebAuthn relying party and for the Discord redirect URI.
+    /// Password reset emails, passkeys and Discord logins are disabled without it.
+    pub public_url: Option<String>,
+}
+
+impl General {
+    fn default_days_login_expiry() -> u16 { 90 }
 }
 
 
@@ -88,3 +139,61 @@ pub struct Smtp {
     /// The password for login
     pub password: String,
 }
+
+
+#[derive(Deserialize, Clone)]
+/// A lobby that is allowed to use the federation API
+/// Requests must be signed with the shared secret (see sslo_lib::federation)
+pub struct Lobby {
+
+    /// The name that the lobby uses to identify itself
+    pub name: String,
+
+    /// The shared secret (must be identical to the secret in the lobby config)
+    pub secret: String,
+}
+
+
+#[derive(Deserialize, Clone)]
+/// Configuration for time-based one-time passwords (TOTP)
+pub struct Totp {
+
+    /// The server key to encrypt the TOTP secrets in the database (use a long random string)
+    /// Changing this key invalidates all enrolled authenticators
+    pub key: String,
+
+    /// Users with this or a higher promotion level must login with TOTP (or verify the login afterwards) to use their promotion
+    /// This includes the root user (who has all promotion levels).
+    pub required_level: Option<PromotionLevel>,
+}
+
+
+#[derive(Deserialize, Clone)]
+/// A Discord OAuth2 application (https://discord.com/developers/applications)
+/// The redirect URI <public_url>/html/login_discord_verify must be registered at the application
+pub struct Discord {
+
+    /// The client id of the application
+    pub client_id: String,
+
+    /// The client secret of the application
+    pub client_secret: String,
+
+    /// The URL where users authorize the league (only needs to be changed for testing)
+    #[serde(default = "Discord::default_authorize_url")]
+    pub authorize_url: String,
+
+    /// The URL to exchange an authorization code into an access token
+    #[serde(default = "Discord::default_token_url")]
+    pub token_url: String,
+
+    /// The base URL of the Discord REST API
+    #[serde(default = "Discord::default_api_url")]
+    pub api_url: String,
+}
+
+impl Discord {
+    fn default_authorize_url() -> String { "https://discord.com/oauth2/authorize".to_string() }
+    fn default_token_url() -> String { "https://discord.com/api/oauth2/token".to_string() }
+    fn default_api_url() -> String { "https://discord.com/api/v10".to_string() }
+}
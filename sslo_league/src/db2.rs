@@ -1,24 +1,43 @@
+pub mod content;
+pub mod results;
+pub mod series;
+
 use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use sqlx::{Connection, SqliteConnection, SqlitePool};
 use sslo_lib::error::SsloError;
-use crate::db2::members::{MembersDbData, MembersDbInterface};
+use sslo_lib::db::members::{MembersDbData, MembersDbInterface};
+use sslo_lib::db::members::users::UserItem;
+use content::{ContentDbData, ContentDbInterface};
+use results::{ResultsDbData, ResultsDbInterface};
+use series::{SeriesDbData, SeriesDbInterface};
 
-pub mod members;
 
 struct DatabaseManagerData {
     db_members: Arc<RwLock<MembersDbData>>,
+    db_content: Arc<RwLock<ContentDbData>>,
+    db_results: Arc<RwLock<ResultsDbData>>,
+    db_series: Arc<RwLock<SeriesDbData>>,
 }
 
 impl DatabaseManagerData {
-    pub async fn new(database_directory: &Path) -> Result<Arc<RwLock<Self>>, SsloError> {
+    /// When database_directory is None, all databases are generated in memory
+    pub async fn new(database_directory: Option<&Path>) -> Result<Arc<RwLock<Self>>, SsloError> {
 
         // set up tables
-        let db_members = MembersDbData::new(Some(database_directory.join("members.db").as_path())).await?;
+        let db_path = |file_name: &str| database_directory.map(|d| d.join(file_name));
+        let db_members = MembersDbData::new(db_path("members.db").as_deref()).await?;
+        let db_content = ContentDbData::new(db_path("content.db").as_deref()).await?;
+        let db_results = ResultsDbData::new(db_path("results.db").as_deref()).await?;
+        let db_series = SeriesDbData::new(db_path("series.db").as_deref()).await?;
 
         // create the manager
         Ok(Arc::new(RwLock::new( Self {
            db_members,
+           db_content,
+           db_results,
+           db_series,
         })))
     }
 }
@@ -28,11 +47,233 @@ pub struct DatabaseManager(Arc<RwLock<DatabaseManagerData>>);
 
 impl DatabaseManager {
     pub async fn new(database_directory: &Path) -> Result<Self, SsloError> {
-        let data = DatabaseManagerData::new(database_directory).await?;
+        let data = DatabaseManagerData::new(Some(database_directory)).await?;
         Ok(Self(data))
     }
 
+    /// Create all databases in memory
+    #[cfg(test)]
+    pub async fn new_in_memory() -> Self {
+        Self(DatabaseManagerData::new(None).await.unwrap())
+    }
+
     pub async fn db_members(&self) -> MembersDbInterface {
         MembersDbInterface::new(self.0.read().await.db_members.clone())
     }
-}
\ No newline at end of file
+
+    pub async fn db_content(&self) -> ContentDbInterface {
+        ContentDbInterface::new(self.0.read().await.db_content.clone())
+    }
+
+    pub async fn db_results(&self) -> ResultsDbInterface {
+        ResultsDbInterface::new(self.0.read().await.db_results.clone())
+    }
+
+    pub async fn db_series(&self) -> SeriesDbInterface {
+        SeriesDbInterface::new(self.0.read().await.db_series.clone())
+    }
+
+    /// Merge a user into another user across all databases
+    /// The results and series databases are attached to the members database,
+    /// so that all changes are done within a single transaction.
+    pub async fn merge_users(&self, from: &UserItem, into: &UserItem) -> Result<(), SsloError> {
+        let from_id = from.id().await;
+        let into_id = into.id().await;
+        if from_id == into_id {
+            return Err(SsloError::GeneralError(format!("cannot merge {} into itself", from.display().await)));
+        }
+        let db_members = self.db_members().await;
+        let db_results = self.db_results().await;
+        let db_series = self.db_series().await;
+        let pool_results = db_results.pool().await;
+        let pool_series = db_series.pool().await;
+
+        {   // hold the connections of the attached databases, so no other task can access them meanwhile
+            let mut conn_results = pool_results.acquire().await?;
+            let mut conn_series = pool_series.acquire().await?;
+
+            // the locking modes are restored on every path, also when the merge failed
+            let locking_mode_results = release_locks(&mut conn_results).await?;
+            let res = match release_locks(&mut conn_series).await {
+                Ok(locking_mode_series) => {
+                    let res = Self::merge_users_attached(&db_members, &pool_results, &pool_series, from, into).await;
+                    let restored = restore_locks(&mut conn_series, &locking_mode_series).await;
+                    res.and(restored)
+                },
+                Err(e) => Err(e),
+            };
+            let restored = restore_locks(&mut conn_results, &locking_mode_results).await;
+            res.and(restored)?;
+        }
+
+        // update cached items
+        db_members.reload_caches().await?;
+        db_results.reload_caches().await?;
+        log::info!("merged user id={} into {}", from_id, into.display().await);
+        Ok(())
+    }
+
+    /// Attach the results and series databases to a connection of the members database and merge within a single transaction
+    /// The databases are detached afterwards, also when the merge failed.
+    async fn merge_users_attached(db_members: &MembersDbInterface, pool_results: &SqlitePool, pool_series: &SqlitePool,
+                                  from: &UserItem, into: &UserItem) -> Result<(), SsloError> {
+        let from_id = from.id().await;
+        let into_id = into.id().await;
+        let mut conn = db_members.pool().await.acquire().await?;
+        let res = async {
+            sqlx::query("ATTACH DATABASE $1 AS results;").bind(sslo_lib::db::attach_uri(pool_results)).execute(&mut *conn).await?;
+            sqlx::query("ATTACH DATABASE $1 AS series;").bind(sslo_lib::db::attach_uri(pool_series)).execute(&mut *conn).await?;
+            let mut tx = conn.begin().await?;
+            SeriesDbInterface::merge_users_within(&mut tx, from_id, into_id).await?;
+            ResultsDbInterface::merge_users_within(&mut tx, from_id, into_id).await?;
+            db_members.merge_users_within(&mut tx, from, into).await?;
+            tx.commit().await?;
+            Ok::<(), SsloError>(())
+        }.await;
+        for query in ["DETACH DATABASE results;", "DETACH DATABASE series;"] {
+            if let Err(e) = sqlx::query(query).execute(&mut *conn).await {
+                log::error!("{}: {}", query, e);
+            }
+        }
+        res
+    }
+
+    /// Remove all personal data of a user across all databases
+    /// Results are kept, but the driver identity is replaced by an anonymous name.
+    /// The members database is changed last, so that repeating a failed anonymization completes it.
+    pub async fn anonymize_user(&self, user: &UserItem) -> Result<(), SsloError> {
+        let user_id = user.id().await;
+        let name = sslo_lib::db::members::users::anonymous_name(user_id);
+        self.db_series().await.anonymize_user(user_id).await?;
+        self.db_results().await.anonymize_user(user_id, &name).await?;
+        self.db_members().await.anonymize_user(user).await
+    }
+}
+
+/// Release the file locks of a connection (exclusive locking mode keeps them until the connection is closed)
+/// This allows to attach the database to another connection.
+/// Returns the previous locking mode, which must be restored afterwards.
+async fn release_locks(conn: &mut SqliteConnection) -> Result<String, SsloError> {
+    let (locking_mode,): (String,) = sqlx::query_as("PRAGMA locking_mode;").fetch_one(&mut *conn).await?;
+    sqlx::query("PRAGMA locking_mode = NORMAL;").execute(&mut *conn).await?;
+    sqlx::query("SELECT COUNT(*) FROM sqlite_master;").execute(&mut *conn).await?;  // locks are released at the next access
+    Ok(locking_mode)
+}
+
+/// Restore the locking mode that was returned by release_locks()
+async fn restore_locks(conn: &mut SqliteConnection, locking_mode: &str) -> Result<(), SsloError> {
+    let query = match locking_mode.to_lowercase().as_str() {
+        "exclusive" => "PRAGMA locking_mode = EXCLUSIVE;",
+        _ => "PRAGMA locking_mode = NORMAL;",
+    };
+    sqlx::query(query).execute(&mut *conn).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use content::Simulator;
+    use results::SessionType;
+    use results::penalties::PenaltyType;
+    use results::sessions::ResultRow;
+    use sslo_lib::db::DbRow;
+
+    async fn merge_users(db: DatabaseManager) {
+        let tbl_usr = db.db_members().await.tbl_users().await;
+        let from = tbl_usr.create_new_user().await.unwrap();
+        let into = tbl_usr.create_new_user().await.unwrap();
+        let from_id = from.id().await;
+        let into_id = into.id().await;
+
+        // results
+        let db_results = db.db_results().await;
+        let tbl_ses = db_results.tbl_sessions().await;
+        let tbl_pen = db_results.tbl_penalties().await;
+        let tbl_pro = db_results.tbl_protests().await;
+        let session = tbl_ses.create_session(Simulator::AssettoCorsa, SessionType::Race, "Server".to_string(),
+                                             "race.json".to_string(), "monza".to_string(), None).await.unwrap();
+        let session_id = session.id().await;
+        let mut result = ResultRow::new(0);
+        result.user = Some(from_id);
+        session.add_result(result).await.unwrap();
+        let penalty = tbl_pen.create_penalty(session_id, Some(from_id), "S7".to_string(), PenaltyType::LicensePoints, 3,
+                                             "".to_string(), 2).await.unwrap();
+        let protest = tbl_pro.create_protest(session_id, None, from_id, "blocking".to_string(),
+                                             vec![(Some(99), "S99".to_string())], Vec::new()).await.unwrap();
+
+        // series
+        let db_series = db.db_series().await;
+        let tbl_evt = db_series.tbl_events().await;
+        let event1 = tbl_evt.create_event("Monza".to_string(), Simulator::AssettoCorsa).await.unwrap();
+        let event2 = tbl_evt.create_event("Spa".to_string(), Simulator::AssettoCorsa).await.unwrap();
+        event1.register(from_id, Some(1)).await.unwrap();
+        event1.register(into_id, Some(2)).await.unwrap();
+        event2.register(from_id, None).await.unwrap();
+        db_series.tbl_ical_tokens().await.create(from_id).await.unwrap();
+
+        assert!(db.merge_users(&into, &into).await.is_err());
+        db.merge_users(&from, &into).await.unwrap();
+
+        // results and cached items are moved
+        assert_eq!(session.results().await[0].user, Some(into_id));
+        assert_eq!(penalty.user().await, Some(into_id));
+        assert_eq!(protest.reporter().await, into_id);
+        assert_eq!(tbl_pen.license_points(into_id).await, 3);
+        assert_eq!(tbl_pen.license_points(from_id).await, 0);
+        assert!(tbl_pro.items_by_reporter(from_id).await.is_empty());
+
+        // double registrations are resolved in favor of the target user
+        assert_eq!(event1.registrations().await.len(), 1);
+        assert_eq!(event1.registrations().await[0].car, Some(2));
+        assert!(event2.registration_status(into_id).await.is_some());
+        assert!(event2.registration_status(from_id).await.is_none());
+        assert!(db_series.tbl_ical_tokens().await.creation(from_id).await.is_none());
+
+        // merged user is gone
+        assert!(tbl_usr.user_by_id(from_id).await.is_none());
+
+        // the databases are usable after detaching
+        assert!(tbl_ses.create_session(Simulator::AssettoCorsa, SessionType::Race, "Server".to_string(),
+                                       "race2.json".to_string(), "spa".to_string(), None).await.is_some());
+        assert!(tbl_evt.create_event("Imola".to_string(), Simulator::AssettoCorsa).await.is_some());
+    }
+
+    #[test(tokio::test)]
+    async fn merge_users_in_memory() {
+        merge_users(DatabaseManager::new_in_memory().await).await;
+    }
+
+    #[test(tokio::test)]
+    async fn merge_users_in_files() {
+        let dir = std::env::temp_dir().join(format!("sslo_db2_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        merge_users(DatabaseManager::new(&dir).await.unwrap()).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test(tokio::test)]
+    async fn merge_users_failed() {
+        let dir = std::env::temp_dir().join(format!("sslo_db2_test_failed_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = DatabaseManager::new(&dir).await.unwrap();
+        let tbl_usr = db.db_members().await.tbl_users().await;
+        let from = tbl_usr.create_new_user().await.unwrap();
+        let into = tbl_usr.create_new_user().await.unwrap();
+        let pool_results = db.db_results().await.pool().await;
+        let pool_series = db.db_series().await.pool().await;
+
+        // let the merge fail within the transaction
+        sqlx::query("DROP TABLE ical_tokens;").execute(&pool_series).await.unwrap();
+        assert!(db.merge_users(&from, &into).await.is_err());
+        assert!(tbl_usr.user_by_id(from.id().await).await.is_some());
+
+        // the locking modes are restored
+        for pool in [&pool_results, &pool_series] {
+            let (locking_mode,): (String,) = sqlx::query_as("PRAGMA locking_mode;").fetch_one(pool).await.unwrap();
+            assert_eq!(locking_mode, "exclusive");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,181 @@
+pub mod tracks;
+pub mod track_layouts;
+pub mod cars;
+pub mod car_classes;
+
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use sslo_lib::db::{TableData, TableInterface};
+use sslo_lib::error::SsloError;
+use tracks::{TrackItem, TrackTable};
+use track_layouts::{TrackLayoutItem, TrackLayoutTable};
+use cars::{CarItem, CarTable};
+use car_classes::{CarClassItem, CarClassTable};
+
+/// The supported racing simulators
+#[derive(PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(sqlx::Type)]
+#[derive(Debug)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[repr(u32)]
+pub enum Simulator {
+    AssettoCorsa = 1,
+    AssettoCorsaCompetizione = 2,
+    RFactor2 = 3,
+    LeMansUltimate = 4,
+    Automobilista2 = 5,
+}
+
+impl Simulator {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Simulator::AssettoCorsa => "Assetto Corsa",
+            Simulator::AssettoCorsaCompetizione => "Assetto Corsa Competizione",
+            Simulator::RFactor2 => "rFactor 2",
+            Simulator::LeMansUltimate => "Le Mans Ultimate",
+            Simulator::Automobilista2 => "Automobilista 2",
+        }
+    }
+}
+
+/// The content database (tracks, cars, etc.)
+pub struct ContentDbData {
+    tbl_tracks: Arc<RwLock<TableData<TrackItem>>>,
+    tbl_track_layouts: Arc<RwLock<TableData<TrackLayoutItem>>>,
+    tbl_cars: Arc<RwLock<TableData<CarItem>>>,
+    tbl_car_classes: Arc<RwLock<TableData<CarClassItem>>>,
+}
+
+impl ContentDbData {
+    /// When db_path is None, the pool is generated in memory
+    pub(super) async fn new(db_path: Option<&Path>) -> Result<Arc<RwLock<Self>>, SsloError> {
+
+        // set up db
+        let pool = sslo_lib::db::get_pool(db_path);
+        sqlx::migrate!("../rsc/db_migrations/league_content").run(&pool).await?;
+
+        // create data object
+        Ok(Arc::new_cyclic(|me| {
+            RwLock::new(Self {
+                tbl_tracks: TableData::new(pool.clone(), me.clone()),
+                tbl_track_layouts: TableData::new(pool.clone(), me.clone()),
+                tbl_cars: TableData::new(pool.clone(), me.clone()),
+                tbl_car_classes: TableData::new(pool.clone(), me.clone()),
+            })
+        }))
+    }
+}
+
+pub struct ContentDbInterface(Arc<RwLock<ContentDbData>>);
+
+impl ContentDbInterface {
+
+    pub(super) fn new(data: Arc<RwLock<ContentDbData>>) -> Self {
+        Self(data)
+    }
+
+    pub async fn tbl_tracks(&self) -> TrackTable {
+        let data = self.0.read().await;
+        TrackTable::new(TableInterface::new(data.tbl_tracks.clone()))
+    }
+
+    pub async fn tbl_track_layouts(&self) -> TrackLayoutTable {
+        let data = self.0.read().await;
+        TrackLayoutTable::new(TableInterface::new(data.tbl_track_layouts.clone()))
+    }
+
+    pub async fn tbl_cars(&self) -> CarTable {
+        let data = self.0.read().await;
+        CarTable::new(TableInterface::new(data.tbl_cars.clone()))
+    }
+
+    pub async fn tbl_car_classes(&self) -> CarClassTable {
+        let data = self.0.read().await;
+        CarClassTable::new(TableInterface::new(data.tbl_car_classes.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    async fn get_db() -> ContentDbInterface {
+        let data = ContentDbData::new(None).await.unwrap();
+        ContentDbInterface::new(data)
+    }
+
+    #[test(tokio::test)]
+    async fn tracks_and_layouts() {
+        let db = get_db().await;
+        let tbl_trk = db.tbl_tracks().await;
+
+        // create track with layouts
+        let track = tbl_trk.create_track("Nordschleife".to_string()).await.unwrap();
+        let layout1 = track.create_layout("Full".to_string()).await.unwrap();
+        let layout2 = track.create_layout("Short".to_string()).await.unwrap();
+        layout1.set_sim_id(Simulator::AssettoCorsa, "ks_nordschleife".to_string()).await.unwrap();
+        layout1.set_sim_id(Simulator::AssettoCorsaCompetizione, "nurburgring_24h".to_string()).await.unwrap();
+        layout1.set_sim_id(Simulator::AssettoCorsa, "ks_nordschleife_touristenfahrten".to_string()).await.unwrap();
+        assert_eq!(track.layouts().await.len(), 2);
+        assert_eq!(layout1.sim_ids().await.len(), 2);
+        assert_eq!(layout1.sim_ids().await.into_iter().find(|row| row.simulator == Simulator::AssettoCorsa).unwrap().identifier,
+                   "ks_nordschleife_touristenfahrten");
+        assert!(layout2.sim_ids().await.is_empty());
+
+        // find layout by sim id
+        let tbl_lay = db.tbl_track_layouts().await;
+        let layout = tbl_lay.item_by_sim_id(Simulator::AssettoCorsaCompetizione, "nurburgring_24h").await.unwrap();
+        assert_eq!(layout.id().await, layout1.id().await);
+        assert_eq!(layout.track().await.unwrap().name().await, "Nordschleife");
+
+        // delete track (including layouts)
+        let layout_id = layout1.id().await;
+        tbl_trk.delete_track(track).await.unwrap();
+        assert!(tbl_trk.item_by_id(1).await.is_none());
+        assert!(tbl_lay.item_by_id(layout_id).await.is_none());
+        assert!(tbl_lay.item_by_sim_id(Simulator::AssettoCorsaCompetizione, "nurburgring_24h").await.is_none());
+    }
+
+    #[test(tokio::test)]
+    async fn cars_and_classes() {
+        let db = get_db().await;
+        let tbl_car = db.tbl_cars().await;
+        let tbl_cls = db.tbl_car_classes().await;
+
+        // create cars in class
+        let gt3 = tbl_cls.create_class("GT3".to_string()).await.unwrap();
+        let car1 = tbl_car.create_car("Porsche 911 GT3 R".to_string()).await.unwrap();
+        let car2 = tbl_car.create_car("BMW M4 GT3".to_string()).await.unwrap();
+        car1.set_car_class(Some(&gt3)).await.unwrap();
+        car2.set_car_class(Some(&gt3)).await.unwrap();
+        car1.set_sim_id(Simulator::AssettoCorsaCompetizione, "porsche_992_gt3_r".to_string()).await.unwrap();
+        assert_eq!(gt3.cars().await.len(), 2);
+        assert_eq!(car1.car_class().await.unwrap().name().await, "GT3");
+        let car = tbl_car.item_by_sim_id(Simulator::AssettoCorsaCompetizione, "porsche_992_gt3_r").await.unwrap();
+        assert_eq!(car.id().await, car1.id().await);
+
+        // BoP history
+        assert!(car1.bop().await.is_none());
+        car1.set_bop(10.0, 2.0, Some(3)).await.unwrap();
+        car1.set_bop(15.0, 0.0, None).await.unwrap();
+        let bop = car1.bop().await.unwrap();
+        assert_eq!(bop.ballast, 15.0);
+        assert_eq!(bop.user, None);
+        let history = car1.bop_history().await;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].ballast, 10.0);
+        assert_eq!(history[1].user, Some(3));
+
+        // deleting a class keeps the cars
+        tbl_cls.delete_class(gt3).await.unwrap();
+        assert!(car1.car_class().await.is_none());
+        assert_eq!(tbl_car.items_all().await.len(), 2);
+
+        // delete car
+        tbl_car.delete_car(car1).await.unwrap();
+        assert!(tbl_car.item_by_sim_id(Simulator::AssettoCorsaCompetizione, "porsche_992_gt3_r").await.is_none());
+        assert_eq!(tbl_car.items_all().await.len(), 1);
+    }
+}
@@ -0,0 +1,174 @@
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use sslo_lib::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use sslo_lib::error::SsloError;
+use super::{ContentDbData, ContentDbInterface};
+use super::cars::CarItem;
+
+sslo_lib::db_row! {
+    tablename: "car_classes",
+    pub struct DbDataRow {
+        name: String = String::new(),
+    }
+    display: |row| format!("car_classes(id={};name={})", row.rowid, row.name),
+    sanitize: |row| {
+        row.name = row.name.trim().to_string();
+    },
+}
+
+/// This abstracts data access to shared items
+#[derive(Clone)]
+pub struct CarClassItem(Arc<RwLock<ItemData<DbDataRow, ContentDbData>>>);
+
+impl ItemInterface for CarClassItem {
+    type Row = DbDataRow;
+    type Db = ContentDbData;
+    type Extra = ();
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, ContentDbData>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, ContentDbData>>> {
+        &self.0
+    }
+}
+
+impl CarClassItem {
+
+    /// Returns a string, that can be used in log messages
+    pub async fn display(&self) -> String {
+        self.0.read().await.display()
+    }
+
+    pub async fn id(&self) -> i64 { self.0.read().await.row.rowid }
+
+    pub async fn name(&self) -> String { self.0.read().await.row.name.clone() }
+
+    pub async fn html_name(&self) -> String {
+        let mut html = String::new();
+        html_escape::encode_safe_to_string(&self.0.read().await.row.name, &mut html);
+        html
+    }
+
+    pub async fn set_name(&self, name: String) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.name = name;
+        data.store().await
+    }
+
+    /// All cars that belong to this class
+    pub async fn cars(&self) -> Vec<CarItem> {
+        let data = self.0.read().await;
+        let db_content = match data.db.upgrade() {
+            Some(db_data) => ContentDbInterface::new(db_data),
+            None => {
+                log::error!("cannot upgrade weak pointer for {}", data.display());
+                return Vec::new();
+            }
+        };
+        drop(data);
+        db_content.tbl_cars().await.items_by_class(self).await
+    }
+}
+
+pub struct CarClassTable(TableInterface<CarClassItem>);
+
+impl CarClassTable {
+    pub(super) fn new(tbl: TableInterface<CarClassItem>) -> Self { Self(tbl) }
+
+    /// Create a new car class
+    pub async fn create_class(&self, name: String) -> Option<CarClassItem> {
+        let mut row = DbDataRow::new(0);
+        row.name = name;
+        match self.0.create_item(row).await {
+            Ok(item) => {
+                log::info!("new car class created: {}", item.display().await);
+                Some(item)
+            },
+            Err(e) => {
+                log::error!("Could not create new car class: {}", e);
+                None
+            }
+        }
+    }
+
+    pub async fn item_by_id(&self, id: i64) -> Option<CarClassItem> {
+        self.0.item_by_id(id).await
+    }
+
+    /// All car classes (sorted by name)
+    pub async fn items_all(&self) -> Vec<CarClassItem> {
+        let mut items: Vec<(String, CarClassItem)> = Vec::new();
+        for item in self.0.items_all().await.into_iter() {
+            items.push((item.name().await.to_lowercase(), item));
+        }
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        items.into_iter().map(|(_, item)| item).collect()
+    }
+
+    /// Delete a car class
+    /// The cars of this class are not deleted, but become unassigned
+    pub async fn delete_class(&self, car_class: CarClassItem) -> Result<(), SsloError> {
+        let class_display = car_class.display().await;
+        for car in car_class.cars().await.into_iter() {
+            car.set_car_class(None).await?;
+        }
+        self.0.delete_item(car_class).await?;
+        log::info!("deleted {}", class_display);
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use sqlx::SqlitePool;
+    use super::*;
+
+    async fn get_pool() -> SqlitePool {
+        let pool = sslo_lib::db::get_pool(None);
+        sqlx::migrate!("../rsc/db_migrations/league_content").run(&pool).await.unwrap();
+        pool
+    }
+
+    mod row {
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn new_defaults() {
+            let row = DbDataRow::new(33);
+            assert_eq!(row.rowid, 33);
+            assert_eq!(row.name, String::new());
+        }
+
+        /// Testing load and store (insert+update)
+        #[test(tokio::test)]
+        async fn load_store() {
+            let pool = get_pool().await;
+
+            // store (insert)
+            let mut row = DbDataRow::new(0);
+            row.name = " GT3 ".to_string();
+            row.store(&pool).await.unwrap();
+
+            // load (sanitized)
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.name, "GT3");
+
+            // store (update)
+            let mut row = DbDataRow::new(1);
+            row.name = "GT4".to_string();
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.name, "GT4");
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use tokio::sync::RwLock;
+use sslo_lib::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use sslo_lib::error::SsloError;
+use super::{ContentDbData, ContentDbInterface, Simulator};
+use super::car_classes::CarClassItem;
+
+sslo_lib::db_row! {
+    tablename: "cars",
+    pub struct DbDataRow {
+        name: String = String::new(),
+        manufacturer: String = String::new(),
+        car_class: Option<i64> = None,
+    }
+    display: |row| format!("cars(id={};name={})", row.rowid, row.name),
+    sanitize: |row| {
+        row.name = row.name.trim().to_string();
+        row.manufacturer = row.manufacturer.trim().to_string();
+    },
+}
+
+sslo_lib::db_row! {
+    tablename: "car_sim_ids",
+    /// The identifier of a car within a certain simulator
+    pub struct SimIdRow {
+        pub car: i64 = 0,
+        pub simulator: Simulator = Simulator::AssettoCorsa,
+        pub identifier: String = String::new(),
+    }
+    sanitize: |row| {
+        row.identifier = row.identifier.trim().to_string();
+    },
+}
+
+impl SimIdRow {
+    async fn fetch_by_identifier(pool: &SqlitePool, simulator: Simulator, identifier: &str) -> Result<Option<Self>, SsloError> {
+        let row = sqlx::query_as::<sqlx::Sqlite, Self>(
+            "SELECT rowid,* FROM car_sim_ids WHERE simulator = $1 AND identifier = $2 LIMIT 1;")
+            .bind(simulator)
+            .bind(identifier)
+            .fetch_optional(pool)
+            .await?;
+        Ok(row)
+    }
+}
+
+sslo_lib::db_row! {
+    tablename: "car_bop",
+    /// A Balance-of-Performance setting of a car, valid since a certain time
+    pub struct BopRow {
+        pub car: i64 = 0,
+        pub ballast: f64 = 0.0,
+        pub restrictor: f64 = 0.0,
+        pub since: DateTime<Utc> = Utc::now(),
+        pub user: Option<i64> = None,
+    }
+    sanitize: |row| {
+        row.restrictor = row.restrictor.clamp(0.0, 100.0);
+    },
+}
+
+/// This abstracts data access to shared items
+#[derive(Clone)]
+pub struct CarItem(Arc<RwLock<ItemData<DbDataRow, ContentDbData>>>);
+
+impl ItemInterface for CarItem {
+    type Row = DbDataRow;
+    type Db = ContentDbData;
+    type Extra = ();
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, ContentDbData>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, ContentDbData>>> {
+        &self.0
+    }
+}
+
+impl CarItem {
+
+    /// Returns a string, that can be used in log messages
+    pub async fn display(&self) -> String {
+        self.0.read().await.display()
+    }
+
+    pub async fn id(&self) -> i64 { self.0.read().await.row.rowid }
+
+    pub async fn name(&self) -> String { self.0.read().await.row.name.clone() }
+
+    pub async fn html_name(&self) -> String {
+        let mut html = String::new();
+        html_escape::encode_safe_to_string(&self.0.read().await.row.name, &mut html);
+        html
+    }
+
+    pub async fn set_name(&self, name: String) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.name = name;
+        data.store().await
+    }
+
+    pub async fn manufacturer(&self) -> String { self.0.read().await.row.manufacturer.clone() }
+
+    pub async fn set_manufacturer(&self, manufacturer: String) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.manufacturer = manufacturer;
+        data.store().await
+    }
+
+    /// The class this car belongs to
+    pub async fn car_class(&self) -> Option<CarClassItem> {
+        let data = self.0.read().await;
+        let class_id = data.row.car_class?;
+        let db_content = match data.db.upgrade() {
+            Some(db_data) => ContentDbInterface::new(db_data),
+            None => {
+                log::error!("cannot upgrade weak pointer for {}", data.display());
+                return None;
+            }
+        };
+        drop(data);
+        db_content.tbl_car_classes().await.item_by_id(class_id).await
+    }
+
+    pub async fn set_car_class(&self, car_class: Option<&CarClassItem>) -> Result<(), SsloError> {
+        let class_id = match car_class {
+            Some(car_class) => Some(car_class.id().await),
+            None => None,
+        };
+        let mut data = self.0.write().await;
+        data.row.car_class = class_id;
+        data.store().await
+    }
+
+    /// All identifiers of this car within the simulators
+    pub async fn sim_ids(&self) -> Vec<SimIdRow> {
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Vec::new(),
+        };
+        match SimIdRow::fetch_all_by(pool, "car", data.row.rowid, 100).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to read simulator ids for {}: {}", data.display(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Set the identifier of this car within a simulator
+    /// An already existing identifier for the same simulator is replaced
+    pub async fn set_sim_id(&self, simulator: Simulator, identifier: String) -> Result<(), SsloError> {
+        let mut row = match self.sim_ids().await.into_iter().find(|row| row.simulator == simulator) {
+            Some(row) => row,
+            None => {
+                let mut row = SimIdRow::new(0);
+                row.car = self.id().await;
+                row.simulator = simulator;
+                row
+            }
+        };
+        row.identifier = identifier;
+        let data = self.0.read().await;
+        match data.pool.as_ref() {
+            Some(pool) => row.store(pool).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Remove the identifier of this car within a simulator
+    pub async fn remove_sim_id(&self, simulator: Simulator) -> Result<(), SsloError> {
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Ok(()),
+        };
+        for mut row in SimIdRow::fetch_all_by(pool, "car", data.row.rowid, 100).await? {
+            if row.simulator == simulator {
+                row.delete(pool).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The history of Balance-of-Performance settings (latest first)
+    pub async fn bop_history(&self) -> Vec<BopRow> {
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Vec::new(),
+        };
+        match BopRow::fetch_all_by(pool, "car", data.row.rowid, 1000).await {
+            Ok(mut rows) => {
+                rows.sort_by(|a, b| b.since.cmp(&a.since).then(b.rowid().cmp(&a.rowid())));
+                rows
+            },
+            Err(e) => {
+                log::error!("failed to read BoP for {}: {}", data.display(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// The currently valid Balance-of-Performance setting
+    pub async fn bop(&self) -> Option<BopRow> {
+        self.bop_history().await.into_iter().next()
+    }
+
+    /// Set new Balance-of-Performance values
+    /// The previous values are kept in the history
+    pub async fn set_bop(&self, ballast: f64, restrictor: f64, user_id: Option<i64>) -> Result<(), SsloError> {
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Ok(()),
+        };
+        let mut row = BopRow::new(0);
+        row.car = data.row.rowid;
+        row.ballast = ballast;
+        row.restrictor = restrictor;
+        row.since = Utc::now();
+        row.user = user_id;
+        row.store(pool).await?;
+        log::info!("new BoP for {}: ballast={}kg, restrictor={}%", data.display(), ballast, restrictor);
+        Ok(())
+    }
+}
+
+pub struct CarTable(TableInterface<CarItem>);
+
+impl CarTable {
+    pub(super) fn new(tbl: TableInterface<CarItem>) -> Self { Self(tbl) }
+
+    /// Create a new car
+    pub async fn create_car(&self, name: String) -> Option<CarItem> {
+        let mut row = DbDataRow::new(0);
+        row.name = name;
+        match self.0.create_item(row).await {
+            Ok(item) => {
+                log::info!("new car created: {}", item.display().await);
+                Some(item)
+            },
+            Err(e) => {
+                log::error!("Could not create new car: {}", e);
+                None
+            }
+        }
+    }
+
+    pub async fn item_by_id(&self, id: i64) -> Option<CarItem> {
+        self.0.item_by_id(id).await
+    }
+
+    /// All cars (sorted by name)
+    pub async fn items_all(&self) -> Vec<CarItem> {
+        let mut items: Vec<(String, CarItem)> = Vec::new();
+        for item in self.0.items_all().await.into_iter() {
+            items.push((item.name().await.to_lowercase(), item));
+        }
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        items.into_iter().map(|(_, item)| item).collect()
+    }
+
+    /// All cars of a certain class
+    pub async fn items_by_class(&self, car_class: &CarClassItem) -> Vec<CarItem> {
+        self.0.items_by_column("car_class", car_class.id().await).await
+    }
+
+    /// Find the car that is identified within a simulator
+    pub async fn item_by_sim_id(&self, simulator: Simulator, identifier: &str) -> Option<CarItem> {
+        let pool = self.0.pool().await;
+        match SimIdRow::fetch_by_identifier(&pool, simulator, identifier).await {
+            Ok(Some(row)) => self.item_by_id(row.car).await,
+            Ok(None) => None,
+            Err(e) => {
+                log::error!("failed to find car for {:?} '{}': {}", simulator, identifier, e);
+                None
+            }
+        }
+    }
+
+    /// Delete a car, including its simulator identifiers and BoP history
+    pub async fn delete_car(&self, car: CarItem) -> Result<(), SsloError> {
+        let car_display = car.display().await;
+        let pool = self.0.pool().await;
+        let car_id = car.id().await;
+        for mut row in SimIdRow::fetch_all_by(&pool, "car", car_id, 100).await? {
+            row.delete(&pool).await?;
+        }
+        for mut row in BopRow::fetch_all_by(&pool, "car", car_id, 1000).await? {
+            row.delete(&pool).await?;
+        }
+        self.0.delete_item(car).await?;
+        log::info!("deleted {}", car_display);
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get_pool() -> SqlitePool {
+        let pool = sslo_lib::db::get_pool(None);
+        sqlx::migrate!("../rsc/db_migrations/league_content").run(&pool).await.unwrap();
+        pool
+    }
+
+    mod row {
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn new_defaults() {
+            let row = DbDataRow::new(33);
+            assert_eq!(row.rowid, 33);
+            assert_eq!(row.name, String::new());
+            assert_eq!(row.manufacturer, String::new());
+            assert_eq!(row.car_class, None);
+        }
+
+        /// Testing load and store (insert+update)
+        #[test(tokio::test)]
+        async fn load_store() {
+            let pool = get_pool().await;
+
+            // generate some test data
+            let query = sqlx::query("INSERT INTO car_classes (rowid,name) VALUES (44,'GT3');");
+            query.execute(&pool).await.unwrap();
+
+            // store (insert)
+            let mut row = DbDataRow::new(0);
+            row.name = " 911 GT3 R ".to_string();
+            row.manufacturer = " Porsche ".to_string();
+            row.car_class = Some(44);
+            row.store(&pool).await.unwrap();
+
+            // load (sanitized)
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.name, "911 GT3 R");
+            assert_eq!(row.manufacturer, "Porsche");
+            assert_eq!(row.car_class, Some(44));
+
+            // store (update)
+            let mut row = DbDataRow::new(1);
+            row.name = "M4 GT3".to_string();
+            row.manufacturer = "BMW".to_string();
+            row.car_class = None;
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.name, "M4 GT3");
+            assert_eq!(row.manufacturer, "BMW");
+            assert_eq!(row.car_class, None);
+        }
+
+        /// Testing load and store of simulator identifiers
+        #[test(tokio::test)]
+        async fn sim_id_load_store() {
+            let pool = get_pool().await;
+            sqlx::query("INSERT INTO cars (rowid,name) VALUES (44,'Foo');").execute(&pool).await.unwrap();
+
+            // store (insert)
+            let mut row = SimIdRow::new(0);
+            row.car = 44;
+            row.simulator = Simulator::AssettoCorsaCompetizione;
+            row.identifier = " porsche_991ii_gt3_r ".to_string();
+            row.store(&pool).await.unwrap();
+
+            // load (sanitized)
+            let row = SimIdRow::fetch_by_identifier(&pool, Simulator::AssettoCorsaCompetizione, "porsche_991ii_gt3_r").await.unwrap().unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.car, 44);
+            assert!(SimIdRow::fetch_by_identifier(&pool, Simulator::AssettoCorsa, "porsche_991ii_gt3_r").await.unwrap().is_none());
+
+            // store (update)
+            let mut row = SimIdRow::new(1);
+            row.car = 44;
+            row.simulator = Simulator::Automobilista2;
+            row.identifier = "Porsche_911_GT3_R".to_string();
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = SimIdRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.car, 44);
+            assert_eq!(row.simulator, Simulator::Automobilista2);
+            assert_eq!(row.identifier, "Porsche_911_GT3_R");
+        }
+
+        /// Testing load and store of Balance-of-Performance settings
+        #[test(tokio::test)]
+        async fn bop_load_store() {
+            let pool = get_pool().await;
+            sqlx::query("INSERT INTO cars (rowid,name) VALUES (44,'Foo');").execute(&pool).await.unwrap();
+            let dt1: DateTime<Utc> = DateTime::parse_from_rfc3339("1001-01-01T01:01:01.1111+01:00").unwrap().into();
+
+            // store (insert, sanitized)
+            let mut row = BopRow::new(0);
+            row.car = 44;
+            row.ballast = 15.0;
+            row.restrictor = 120.0;
+            row.since = dt1;
+            row.user = Some(3);
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = BopRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.car, 44);
+            assert_eq!(row.ballast, 15.0);
+            assert_eq!(row.restrictor, 100.0);
+            assert_eq!(row.since, dt1);
+            assert_eq!(row.user, Some(3));
+        }
+    }
+}
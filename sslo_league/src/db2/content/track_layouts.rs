@@ -0,0 +1,362 @@
+use std::sync::Arc;
+use sqlx::SqlitePool;
+use tokio::sync::RwLock;
+use sslo_lib::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use sslo_lib::error::SsloError;
+use super::{ContentDbData, ContentDbInterface, Simulator};
+use super::tracks::TrackItem;
+
+sslo_lib::db_row! {
+    tablename: "track_layouts",
+    pub struct DbDataRow {
+        track: i64 = 0,
+        name: String = String::new(),
+        length: Option<f64> = None,
+        pit_lane_length: Option<f64> = None,
+        pit_speed_limit: Option<f64> = None,
+        pit_boxes: Option<i64> = None,
+    }
+    display: |row| format!("track_layouts(id={};track-id={};name={})", row.rowid, row.track, row.name),
+    sanitize: |row| {
+        row.name = row.name.trim().to_string();
+    },
+}
+
+sslo_lib::db_row! {
+    tablename: "track_layout_sim_ids",
+    /// The identifier of a layout within a certain simulator
+    pub struct SimIdRow {
+        pub layout: i64 = 0,
+        pub simulator: Simulator = Simulator::AssettoCorsa,
+        pub identifier: String = String::new(),
+    }
+    sanitize: |row| {
+        row.identifier = row.identifier.trim().to_string();
+    },
+}
+
+impl SimIdRow {
+    async fn fetch_by_identifier(pool: &SqlitePool, simulator: Simulator, identifier: &str) -> Result<Option<Self>, SsloError> {
+        let row = sqlx::query_as::<sqlx::Sqlite, Self>(
+            "SELECT rowid,* FROM track_layout_sim_ids WHERE simulator = $1 AND identifier = $2 LIMIT 1;")
+            .bind(simulator)
+            .bind(identifier)
+            .fetch_optional(pool)
+            .await?;
+        Ok(row)
+    }
+}
+
+/// This abstracts data access to shared items
+#[derive(Clone)]
+pub struct TrackLayoutItem(Arc<RwLock<ItemData<DbDataRow, ContentDbData>>>);
+
+impl ItemInterface for TrackLayoutItem {
+    type Row = DbDataRow;
+    type Db = ContentDbData;
+    type Extra = ();
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, ContentDbData>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, ContentDbData>>> {
+        &self.0
+    }
+}
+
+impl TrackLayoutItem {
+
+    /// Returns a string, that can be used in log messages
+    pub async fn display(&self) -> String {
+        self.0.read().await.display()
+    }
+
+    pub async fn id(&self) -> i64 { self.0.read().await.row.rowid }
+
+    pub async fn name(&self) -> String { self.0.read().await.row.name.clone() }
+
+    pub async fn html_name(&self) -> String {
+        let mut html = String::new();
+        html_escape::encode_safe_to_string(&self.0.read().await.row.name, &mut html);
+        html
+    }
+
+    pub async fn set_name(&self, name: String) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.name = name;
+        data.store().await
+    }
+
+    /// track length in meters
+    pub async fn length(&self) -> Option<f64> { self.0.read().await.row.length }
+
+    pub async fn set_length(&self, length: Option<f64>) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.length = length;
+        data.store().await
+    }
+
+    /// length of the pit-lane in meters
+    pub async fn pit_lane_length(&self) -> Option<f64> { self.0.read().await.row.pit_lane_length }
+
+    pub async fn set_pit_lane_length(&self, pit_lane_length: Option<f64>) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.pit_lane_length = pit_lane_length;
+        data.store().await
+    }
+
+    /// speed limit in the pit-lane in km/h
+    pub async fn pit_speed_limit(&self) -> Option<f64> { self.0.read().await.row.pit_speed_limit }
+
+    pub async fn set_pit_speed_limit(&self, pit_speed_limit: Option<f64>) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.pit_speed_limit = pit_speed_limit;
+        data.store().await
+    }
+
+    /// amount of pit boxes
+    pub async fn pit_boxes(&self) -> Option<i64> { self.0.read().await.row.pit_boxes }
+
+    pub async fn set_pit_boxes(&self, pit_boxes: Option<i64>) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.pit_boxes = pit_boxes;
+        data.store().await
+    }
+
+    /// The track this layout belongs to
+    pub async fn track(&self) -> Option<TrackItem> {
+        let data = self.0.read().await;
+        let db_content = match data.db.upgrade() {
+            Some(db_data) => ContentDbInterface::new(db_data),
+            None => {
+                log::error!("cannot upgrade weak pointer for {}", data.display());
+                return None;
+            }
+        };
+        let track_id = data.row.track;
+        drop(data);
+        db_content.tbl_tracks().await.item_by_id(track_id).await
+    }
+
+    /// All identifiers of this layout within the simulators
+    pub async fn sim_ids(&self) -> Vec<SimIdRow> {
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Vec::new(),
+        };
+        match SimIdRow::fetch_all_by(pool, "layout", data.row.rowid, 100).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to read simulator ids for {}: {}", data.display(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Set the identifier of this layout within a simulator
+    /// An already existing identifier for the same simulator is replaced
+    pub async fn set_sim_id(&self, simulator: Simulator, identifier: String) -> Result<(), SsloError> {
+        let mut row = match self.sim_ids().await.into_iter().find(|row| row.simulator == simulator) {
+            Some(row) => row,
+            None => {
+                let mut row = SimIdRow::new(0);
+                row.layout = self.id().await;
+                row.simulator = simulator;
+                row
+            }
+        };
+        row.identifier = identifier;
+        let data = self.0.read().await;
+        match data.pool.as_ref() {
+            Some(pool) => row.store(pool).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Remove the identifier of this layout within a simulator
+    pub async fn remove_sim_id(&self, simulator: Simulator) -> Result<(), SsloError> {
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Ok(()),
+        };
+        for mut row in SimIdRow::fetch_all_by(pool, "layout", data.row.rowid, 100).await? {
+            if row.simulator == simulator {
+                row.delete(pool).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct TrackLayoutTable(TableInterface<TrackLayoutItem>);
+
+impl TrackLayoutTable {
+    pub(super) fn new(tbl: TableInterface<TrackLayoutItem>) -> Self { Self(tbl) }
+
+    pub(super) async fn create_layout(&self, track: &TrackItem, name: String) -> Option<TrackLayoutItem> {
+        let mut row = DbDataRow::new(0);
+        row.track = track.id().await;
+        row.name = name;
+        match self.0.create_item(row).await {
+            Ok(item) => {
+                log::info!("new track layout created: {}", item.display().await);
+                Some(item)
+            },
+            Err(e) => {
+                log::error!("Could not create new layout for {}: {}", track.display().await, e);
+                None
+            }
+        }
+    }
+
+    pub async fn item_by_id(&self, id: i64) -> Option<TrackLayoutItem> {
+        self.0.item_by_id(id).await
+    }
+
+    /// All layouts of a certain track
+    pub async fn items_by_track(&self, track: &TrackItem) -> Vec<TrackLayoutItem> {
+        self.0.items_by_column("track", track.id().await).await
+    }
+
+    /// Find the layout that is identified within a simulator
+    pub async fn item_by_sim_id(&self, simulator: Simulator, identifier: &str) -> Option<TrackLayoutItem> {
+        let pool = self.0.pool().await;
+        match SimIdRow::fetch_by_identifier(&pool, simulator, identifier).await {
+            Ok(Some(row)) => self.item_by_id(row.layout).await,
+            Ok(None) => None,
+            Err(e) => {
+                log::error!("failed to find layout for {:?} '{}': {}", simulator, identifier, e);
+                None
+            }
+        }
+    }
+
+    /// Delete a layout, including its simulator identifiers
+    pub async fn delete_layout(&self, layout: TrackLayoutItem) -> Result<(), SsloError> {
+        let layout_display = layout.display().await;
+        let pool = self.0.pool().await;
+        for mut row in SimIdRow::fetch_all_by(&pool, "layout", layout.id().await, 100).await? {
+            row.delete(&pool).await?;
+        }
+        self.0.delete_item(layout).await?;
+        log::info!("deleted {}", layout_display);
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get_pool() -> SqlitePool {
+        let pool = sslo_lib::db::get_pool(None);
+        sqlx::migrate!("../rsc/db_migrations/league_content").run(&pool).await.unwrap();
+        pool
+    }
+
+    mod row {
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn new_defaults() {
+            let row = DbDataRow::new(33);
+            assert_eq!(row.rowid, 33);
+            assert_eq!(row.track, 0);
+            assert_eq!(row.name, String::new());
+            assert_eq!(row.length, None);
+            assert_eq!(row.pit_lane_length, None);
+            assert_eq!(row.pit_speed_limit, None);
+            assert_eq!(row.pit_boxes, None);
+        }
+
+        /// Testing load and store (insert+update)
+        #[test(tokio::test)]
+        async fn load_store() {
+            let pool = get_pool().await;
+
+            // generate some test data
+            let query = sqlx::query("INSERT INTO tracks (rowid,name) VALUES (44,'Foo');");
+            query.execute(&pool).await.unwrap();
+            let query = sqlx::query("INSERT INTO tracks (rowid,name) VALUES (46,'Bar');");
+            query.execute(&pool).await.unwrap();
+
+            // store (insert)
+            let mut row = DbDataRow::new(0);
+            row.track = 44;
+            row.name = " GP ".to_string();
+            row.length = Some(5793.0);
+            row.pit_boxes = Some(30);
+            row.store(&pool).await.unwrap();
+
+            // load (sanitized)
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.track, 44);
+            assert_eq!(row.name, "GP");
+            assert_eq!(row.length, Some(5793.0));
+            assert_eq!(row.pit_lane_length, None);
+            assert_eq!(row.pit_speed_limit, None);
+            assert_eq!(row.pit_boxes, Some(30));
+
+            // store (update)
+            let mut row = DbDataRow::new(1);
+            row.track = 46;
+            row.name = "Junior".to_string();
+            row.pit_lane_length = Some(420.5);
+            row.pit_speed_limit = Some(60.0);
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.track, 46);
+            assert_eq!(row.name, "Junior");
+            assert_eq!(row.length, None);
+            assert_eq!(row.pit_lane_length, Some(420.5));
+            assert_eq!(row.pit_speed_limit, Some(60.0));
+            assert_eq!(row.pit_boxes, None);
+        }
+
+        /// Testing load and store of simulator identifiers
+        #[test(tokio::test)]
+        async fn sim_id_load_store() {
+            let pool = get_pool().await;
+            sqlx::query("INSERT INTO tracks (rowid,name) VALUES (44,'Foo');").execute(&pool).await.unwrap();
+            sqlx::query("INSERT INTO track_layouts (rowid,track,name) VALUES (45,44,'Foo');").execute(&pool).await.unwrap();
+
+            // store (insert)
+            let mut row = SimIdRow::new(0);
+            row.layout = 45;
+            row.simulator = Simulator::AssettoCorsaCompetizione;
+            row.identifier = " monza ".to_string();
+            row.store(&pool).await.unwrap();
+
+            // load (sanitized)
+            let row = SimIdRow::fetch_by_identifier(&pool, Simulator::AssettoCorsaCompetizione, "monza").await.unwrap().unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.layout, 45);
+            assert!(SimIdRow::fetch_by_identifier(&pool, Simulator::AssettoCorsa, "monza").await.unwrap().is_none());
+
+            // store (update)
+            let mut row = SimIdRow::new(1);
+            row.layout = 45;
+            row.simulator = Simulator::RFactor2;
+            row.identifier = "Monza".to_string();
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = SimIdRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.layout, 45);
+            assert_eq!(row.simulator, Simulator::RFactor2);
+            assert_eq!(row.identifier, "Monza");
+        }
+    }
+}
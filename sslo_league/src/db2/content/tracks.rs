@@ -0,0 +1,203 @@
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use sslo_lib::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use sslo_lib::error::SsloError;
+use super::{ContentDbData, ContentDbInterface};
+use super::track_layouts::TrackLayoutItem;
+
+sslo_lib::db_row! {
+    tablename: "tracks",
+    pub struct DbDataRow {
+        name: String = String::new(),
+        country: String = String::new(),
+    }
+    display: |row| format!("tracks(id={};name={})", row.rowid, row.name),
+    sanitize: |row| {
+        row.name = row.name.trim().to_string();
+        row.country = row.country.trim().to_uppercase();
+    },
+}
+
+/// This abstracts data access to shared items
+#[derive(Clone)]
+pub struct TrackItem(Arc<RwLock<ItemData<DbDataRow, ContentDbData>>>);
+
+impl ItemInterface for TrackItem {
+    type Row = DbDataRow;
+    type Db = ContentDbData;
+    type Extra = ();
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, ContentDbData>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, ContentDbData>>> {
+        &self.0
+    }
+}
+
+impl TrackItem {
+
+    /// Returns a string, that can be used in log messages
+    pub async fn display(&self) -> String {
+        self.0.read().await.display()
+    }
+
+    pub async fn id(&self) -> i64 { self.0.read().await.row.rowid }
+
+    pub async fn name(&self) -> String { self.0.read().await.row.name.clone() }
+
+    pub async fn html_name(&self) -> String {
+        let mut html = String::new();
+        html_escape::encode_safe_to_string(&self.0.read().await.row.name, &mut html);
+        html
+    }
+
+    pub async fn set_name(&self, name: String) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.name = name;
+        data.store().await
+    }
+
+    /// ISO 3166-1 alpha-2 country code
+    pub async fn country(&self) -> String { self.0.read().await.row.country.clone() }
+
+    pub async fn set_country(&self, country: String) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.country = country;
+        data.store().await
+    }
+
+    async fn db_content(&self) -> Option<ContentDbInterface> {
+        let data = self.0.read().await;
+        match data.db.upgrade() {
+            Some(db_data) => Some(ContentDbInterface::new(db_data)),
+            None => {
+                log::error!("cannot upgrade weak pointer for {}", data.display());
+                None
+            }
+        }
+    }
+
+    /// All layouts of this track
+    pub async fn layouts(&self) -> Vec<TrackLayoutItem> {
+        match self.db_content().await {
+            Some(db_content) => db_content.tbl_track_layouts().await.items_by_track(self).await,
+            None => Vec::new(),
+        }
+    }
+
+    /// Add a new layout to this track
+    pub async fn create_layout(&self, name: String) -> Option<TrackLayoutItem> {
+        let db_content = self.db_content().await?;
+        db_content.tbl_track_layouts().await.create_layout(self, name).await
+    }
+}
+
+pub struct TrackTable(TableInterface<TrackItem>);
+
+impl TrackTable {
+    pub(super) fn new(tbl: TableInterface<TrackItem>) -> Self { Self(tbl) }
+
+    /// Create a new track
+    pub async fn create_track(&self, name: String) -> Option<TrackItem> {
+        let mut row = DbDataRow::new(0);
+        row.name = name;
+        match self.0.create_item(row).await {
+            Ok(item) => {
+                log::info!("new track created: {}", item.display().await);
+                Some(item)
+            },
+            Err(e) => {
+                log::error!("Could not create new track: {}", e);
+                None
+            }
+        }
+    }
+
+    pub async fn item_by_id(&self, id: i64) -> Option<TrackItem> {
+        self.0.item_by_id(id).await
+    }
+
+    /// All tracks (sorted by name)
+    pub async fn items_all(&self) -> Vec<TrackItem> {
+        let mut items: Vec<(String, TrackItem)> = Vec::new();
+        for item in self.0.items_all().await.into_iter() {
+            items.push((item.name().await.to_lowercase(), item));
+        }
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        items.into_iter().map(|(_, item)| item).collect()
+    }
+
+    /// Delete a track, including all of its layouts
+    pub async fn delete_track(&self, track: TrackItem) -> Result<(), SsloError> {
+        let track_display = track.display().await;
+        if let Some(db_content) = track.db_content().await {
+            let tbl_lay = db_content.tbl_track_layouts().await;
+            for layout in track.layouts().await.into_iter() {
+                tbl_lay.delete_layout(layout).await?;
+            }
+        }
+        self.0.delete_item(track).await?;
+        log::info!("deleted {}", track_display);
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use sqlx::SqlitePool;
+    use super::*;
+
+    async fn get_pool() -> SqlitePool {
+        let pool = sslo_lib::db::get_pool(None);
+        sqlx::migrate!("../rsc/db_migrations/league_content").run(&pool).await.unwrap();
+        pool
+    }
+
+    mod row {
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn new_defaults() {
+            let row = DbDataRow::new(33);
+            assert_eq!(row.rowid, 33);
+            assert_eq!(row.name, String::new());
+            assert_eq!(row.country, String::new());
+        }
+
+        /// Testing load and store (insert+update)
+        #[test(tokio::test)]
+        async fn load_store() {
+            let pool = get_pool().await;
+
+            // store (insert)
+            let mut row = DbDataRow::new(0);
+            row.name = " Nordschleife ".to_string();
+            row.country = "de".to_string();
+            row.store(&pool).await.unwrap();
+
+            // load (sanitized)
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.name, "Nordschleife");
+            assert_eq!(row.country, "DE");
+
+            // store (update)
+            let mut row = DbDataRow::new(1);
+            row.name = "Monza".to_string();
+            row.country = "IT".to_string();
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.name, "Monza");
+            assert_eq!(row.country, "IT");
+        }
+    }
+}
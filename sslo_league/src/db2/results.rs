@@ -0,0 +1,347 @@
+pub mod penalties;
+pub mod protests;
+pub mod ratings;
+pub mod sessions;
+
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use sslo_lib::db::{TableData, TableInterface};
+use sslo_lib::error::SsloError;
+use sqlx::{SqliteConnection, SqlitePool};
+use penalties::{PenaltyItem, PenaltyTable};
+use protests::{ProtestItem, ProtestTable};
+use ratings::RatingTable;
+use sessions::{SessionItem, SessionTable};
+
+/// The type of a session
+#[derive(PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(sqlx::Type)]
+#[derive(Debug)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[repr(u32)]
+pub enum SessionType {
+    Practice = 1,
+    Qualifying = 2,
+    Race = 3,
+}
+
+impl SessionType {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SessionType::Practice => "Practice",
+            SessionType::Qualifying => "Qualifying",
+            SessionType::Race => "Race",
+        }
+    }
+}
+
+/// The results database (imported sessions, laps, etc.)
+pub struct ResultsDbData {
+    pool: SqlitePool,
+    tbl_sessions: Arc<RwLock<TableData<SessionItem>>>,
+    tbl_penalties: Arc<RwLock<TableData<PenaltyItem>>>,
+    tbl_protests: Arc<RwLock<TableData<ProtestItem>>>,
+}
+
+impl ResultsDbData {
+    /// When db_path is None, the pool is generated in memory
+    pub(super) async fn new(db_path: Option<&Path>) -> Result<Arc<RwLock<Self>>, SsloError> {
+
+        // set up db
+        let pool = sslo_lib::db::get_pool(db_path);
+        sqlx::migrate!("../rsc/db_migrations/league_results").run(&pool).await?;
+
+        // create data object
+        Ok(Arc::new_cyclic(|me| {
+            RwLock::new(Self {
+                pool: pool.clone(),
+                tbl_sessions: TableData::new(pool.clone(), me.clone()),
+                tbl_penalties: TableData::new(pool.clone(), me.clone()),
+                tbl_protests: TableData::new(pool.clone(), me.clone()),
+            })
+        }))
+    }
+}
+
+pub struct ResultsDbInterface(Arc<RwLock<ResultsDbData>>);
+
+impl ResultsDbInterface {
+
+    pub(super) fn new(data: Arc<RwLock<ResultsDbData>>) -> Self {
+        Self(data)
+    }
+
+    pub async fn tbl_sessions(&self) -> SessionTable {
+        let data = self.0.read().await;
+        SessionTable::new(TableInterface::new(data.tbl_sessions.clone()))
+    }
+
+    pub async fn tbl_penalties(&self) -> PenaltyTable {
+        let data = self.0.read().await;
+        PenaltyTable::new(TableInterface::new(data.tbl_penalties.clone()))
+    }
+
+    pub async fn tbl_protests(&self) -> ProtestTable {
+        let data = self.0.read().await;
+        ProtestTable::new(TableInterface::new(data.tbl_protests.clone()))
+    }
+
+    pub async fn tbl_ratings(&self) -> RatingTable {
+        RatingTable::new(self.0.read().await.pool.clone())
+    }
+
+    pub(crate) async fn pool(&self) -> SqlitePool {
+        self.0.read().await.pool.clone()
+    }
+
+    /// Move all results, penalties and protests of a user to another user
+    /// The statements are executed within a transaction, where the results database is attached as 'results'.
+    /// The ratings of the merged user are removed, they need to be recomputed.
+    pub(super) async fn merge_users_within(tx: &mut SqliteConnection, from_id: i64, into_id: i64) -> Result<(), SsloError> {
+        for query in [
+            "UPDATE results.session_results SET user = $2 WHERE user = $1;",
+            "UPDATE results.session_laps SET user = $2 WHERE user = $1;",
+            "UPDATE results.session_penalties SET user = $2 WHERE user = $1;",
+            "UPDATE results.session_incidents SET user = $2 WHERE user = $1;",
+            "UPDATE results.session_incidents SET other_user = $2 WHERE other_user = $1;",
+            "UPDATE results.steward_penalties SET user = $2 WHERE user = $1;",
+            "UPDATE results.steward_penalties SET issued_by = $2 WHERE issued_by = $1;",
+            "UPDATE results.steward_penalty_audit SET user = $2 WHERE user = $1;",
+            "UPDATE results.protests SET reporter = $2 WHERE reporter = $1;",
+            "UPDATE results.protests SET resolved_by = $2 WHERE resolved_by = $1;",
+            "UPDATE results.protest_drivers SET user = $2 WHERE user = $1;",
+            "UPDATE results.protest_comments SET user = $2 WHERE user = $1;",
+            "DELETE FROM results.rating_history WHERE user = $1;",
+            "DELETE FROM results.ratings WHERE user = $1;",
+        ] {
+            sqlx::query(query).bind(from_id).bind(into_id).execute(&mut *tx).await?;
+        }
+        Ok(())
+    }
+
+    /// Reload the cached penalties and protests (after changes that bypassed the items)
+    pub(super) async fn reload_caches(&self) -> Result<(), SsloError> {
+        let data = self.0.read().await;
+        TableInterface::new(data.tbl_penalties.clone()).reload_cache().await?;
+        TableInterface::new(data.tbl_protests.clone()).reload_cache().await?;
+        Ok(())
+    }
+
+    /// Replace the driver identity of a user in all results (within a single transaction)
+    /// The results are kept, but the driver name is replaced and the simulator identifiers are removed.
+    /// Texts and evidence links that were written by the user are cleared.
+    pub async fn anonymize_user(&self, user_id: i64, name: &str) -> Result<(), SsloError> {
+        let data = self.0.read().await;
+        let mut tx = data.pool.begin().await?;
+        for query in [
+            "UPDATE session_results SET driver_name = $2, driver_guid = '' WHERE user = $1;",
+            "UPDATE session_laps SET driver_guid = '' WHERE user = $1;",
+            "UPDATE session_penalties SET driver_guid = '' WHERE user = $1;",
+            "UPDATE session_incidents SET driver_guid = '' WHERE user = $1;",
+            "UPDATE session_incidents SET other_driver_guid = NULL WHERE other_user = $1;",
+            "UPDATE steward_penalties SET driver_guid = '' WHERE user = $1;",
+            "UPDATE protest_drivers SET driver_guid = '' WHERE user = $1;",
+            "UPDATE steward_penalties SET appeal_text = '' WHERE user = $1;",
+            "UPDATE protests SET description = '' WHERE reporter = $1;",
+            "DELETE FROM protest_evidence WHERE protest IN (SELECT rowid FROM protests WHERE reporter = $1);",
+            "UPDATE protest_comments SET text = '' WHERE user = $1;",
+        ] {
+            sqlx::query(query).bind(user_id).bind(name).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+
+        // update cached items
+        TableInterface::new(data.tbl_penalties.clone()).reload_cache().await?;
+        TableInterface::new(data.tbl_protests.clone()).reload_cache().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use crate::db2::content::Simulator;
+    use penalties::{AppealState, PenaltyType};
+    use protests::ProtestState;
+    use sessions::{LapRow, ResultRow};
+    use sslo_lib::db::DbRow;
+
+    async fn get_db() -> ResultsDbInterface {
+        let data = ResultsDbData::new(None).await.unwrap();
+        ResultsDbInterface::new(data)
+    }
+
+    #[test(tokio::test)]
+    async fn sessions() {
+        let db = get_db().await;
+        let tbl_ses = db.tbl_sessions().await;
+
+        // create session
+        let session = tbl_ses.create_session(Simulator::AssettoCorsaCompetizione, SessionType::Race,
+                                             "Server".to_string(), "250414_203000_R.json".to_string(),
+                                             "monza".to_string(), None).await.unwrap();
+        let mut result = ResultRow::new(0);
+        result.position = 1;
+        result.user = Some(7);
+        session.add_result(result).await.unwrap();
+        let mut lap = LapRow::new(0);
+        lap.lap_time = 108_123;
+        session.add_lap(lap).await.unwrap();
+        assert_eq!(session.results().await.len(), 1);
+        assert_eq!(session.results().await[0].session, session.id().await);
+        assert_eq!(session.laps().await[0].lap_time, 108_123);
+
+        // file is identified
+        assert!(tbl_ses.item_by_file(Simulator::AssettoCorsaCompetizione, "Server", "250414_203000_R.json").await.is_some());
+        assert!(tbl_ses.item_by_file(Simulator::AssettoCorsa, "Server", "250414_203000_R.json").await.is_none());
+        assert!(tbl_ses.create_session(Simulator::AssettoCorsaCompetizione, SessionType::Race,
+                                       "Server".to_string(), "250414_203000_R.json".to_string(),
+                                       "monza".to_string(), None).await.is_none());
+
+        // delete session
+        tbl_ses.delete_session(session).await.unwrap();
+        assert!(tbl_ses.item_by_file(Simulator::AssettoCorsaCompetizione, "Server", "250414_203000_R.json").await.is_none());
+    }
+
+    #[test(tokio::test)]
+    async fn penalties() {
+        let db = get_db().await;
+        let tbl_pen = db.tbl_penalties().await;
+        let tbl_ses = db.tbl_sessions().await;
+        for file_name in ["race1.json", "race2.json"] {
+            tbl_ses.create_session(Simulator::AssettoCorsa, SessionType::Race, "Server".to_string(),
+                                   file_name.to_string(), "monza".to_string(), None).await.unwrap();
+        }
+
+        // issue penalties
+        let penalty = tbl_pen.create_penalty(1, Some(7), "S76561".to_string(), PenaltyType::Time, 5,
+                                             " unsafe rejoin ".to_string(), 2).await.unwrap();
+        assert_eq!(penalty.reason().await, "unsafe rejoin");
+        assert!(penalty.concerns(Some(7), "").await);
+        assert!(!penalty.concerns(Some(8), "S76561").await);
+        tbl_pen.create_penalty(1, None, "S12345".to_string(), PenaltyType::LicensePoints, 2, "".to_string(), 2).await.unwrap();
+        let license = tbl_pen.create_penalty(2, Some(7), "S76561".to_string(), PenaltyType::LicensePoints, 3,
+                                             "".to_string(), 2).await.unwrap();
+        assert_eq!(tbl_pen.items_by_session(1).await.len(), 2);
+        assert_eq!(tbl_pen.items_by_user(7).await.len(), 2);
+        assert_eq!(tbl_pen.license_points(7).await, 3);
+
+        // amend and appeal
+        penalty.amend(2, 10, "unsafe rejoin, caused collision".to_string()).await.unwrap();
+        assert_eq!(penalty.value().await, 10);
+        license.appeal(7, "no contact".to_string()).await.unwrap();
+        assert_eq!(tbl_pen.items_appeal_pending().await.len(), 1);
+        license.decide_appeal(2, true, "video evidence".to_string()).await.unwrap();
+        assert_eq!(license.appeal_state().await, AppealState::Accepted);
+        assert!(license.revoked().await);
+        assert_eq!(tbl_pen.license_points(7).await, 0);
+        assert!(tbl_pen.items_appeal_pending().await.is_empty());
+
+        // audit trail
+        let trail = license.audit_trail().await;
+        assert_eq!(trail.len(), 3);
+        assert_eq!(trail[1].user, 7);
+        assert!(trail[2].action.starts_with("appeal accepted"));
+        assert_eq!(penalty.audit_trail().await.len(), 2);
+
+        // penalties are deleted together with the session
+        tbl_ses.delete_session(tbl_ses.item_by_id(2).await.unwrap()).await.unwrap();
+        assert!(tbl_pen.items_by_session(2).await.is_empty());
+        assert!(tbl_pen.item_by_id(license.id().await).await.is_none());
+    }
+
+    #[test(tokio::test)]
+    async fn protests() {
+        let db = get_db().await;
+        let tbl_ses = db.tbl_sessions().await;
+        let tbl_pro = db.tbl_protests().await;
+        let tbl_pen = db.tbl_penalties().await;
+        let session = tbl_ses.create_session(Simulator::AssettoCorsa, SessionType::Race, "Server".to_string(),
+                                             "race.json".to_string(), "monza".to_string(), None).await.unwrap();
+        let session_id = session.id().await;
+
+        // file protests
+        let protest = tbl_pro.create_protest(session_id, Some(3), 7, " divebomb in T1 ".to_string(),
+                                             vec![(Some(8), "S8".to_string()), (None, "S9".to_string())],
+                                             vec!["https://example.com/replay".to_string()]).await.unwrap();
+        assert_eq!(protest.description().await, "divebomb in T1");
+        assert_eq!(protest.state().await, ProtestState::Open);
+        assert!(protest.involves(8).await);
+        assert!(!protest.involves(9).await);
+        assert_eq!(protest.evidence().await, vec!["https://example.com/replay".to_string()]);
+        let other = tbl_pro.create_protest(session_id, None, 8, "blocking".to_string(),
+                                           vec![(Some(7), "S7".to_string())], Vec::new()).await.unwrap();
+        assert_eq!(tbl_pro.items_open().await.len(), 2);
+        assert_eq!(tbl_pro.items_by_reporter(7).await.len(), 1);
+
+        // review
+        protest.add_comment(2, "checking replay".to_string()).await.unwrap();
+        protest.add_comment(7, "see minute 12".to_string()).await.unwrap();
+        assert_eq!(protest.comments().await.len(), 2);
+        assert_eq!(protest.comments().await[1].user, 7);
+
+        // resolve
+        let penalty = tbl_pen.create_penalty(session_id, Some(8), "S8".to_string(), PenaltyType::Time, 5,
+                                             "causing a collision".to_string(), 2).await.unwrap();
+        protest.resolve(2, "penalty for car 8".to_string(), Some(penalty.id().await)).await.unwrap();
+        assert_eq!(protest.state().await, ProtestState::Penalized);
+        other.resolve(2, "racing incident".to_string(), None).await.unwrap();
+        assert_eq!(other.state().await, ProtestState::NoFurtherAction);
+        assert_eq!(other.resolved_by().await, Some(2));
+        assert!(tbl_pro.items_open().await.is_empty());
+
+        // protests are deleted together with the session
+        tbl_ses.delete_session(session).await.unwrap();
+        assert!(tbl_pro.items_by_session(session_id).await.is_empty());
+    }
+
+    #[test(tokio::test)]
+    async fn anonymize_user() {
+        let db = get_db().await;
+        let tbl_ses = db.tbl_sessions().await;
+        let tbl_pen = db.tbl_penalties().await;
+        let session = tbl_ses.create_session(Simulator::AssettoCorsa, SessionType::Race, "Server".to_string(),
+                                             "race.json".to_string(), "monza".to_string(), None).await.unwrap();
+        let session_id = session.id().await;
+        for (position, user, name) in [(1, 7, "Jane Doe"), (2, 8, "John Doe")] {
+            let mut result = ResultRow::new(0);
+            result.position = position;
+            result.user = Some(user);
+            result.driver_name = name.to_string();
+            result.driver_guid = format!("S{}", user);
+            session.add_result(result).await.unwrap();
+        }
+        let penalty = tbl_pen.create_penalty(session_id, Some(7), "S7".to_string(), PenaltyType::LicensePoints, 3,
+                                             "".to_string(), 2).await.unwrap();
+        penalty.appeal(7, "I was not involved".to_string()).await.unwrap();
+        let tbl_pro = db.tbl_protests().await;
+        let protest = tbl_pro.create_protest(session_id, None, 7, "John blocked me".to_string(),
+                                             vec![(Some(8), "S8".to_string())],
+                                             vec!["https://video.tld/onboard".to_string()]).await.unwrap();
+        protest.add_comment(7, "see my onboard".to_string()).await.unwrap();
+        protest.add_comment(2, "under investigation".to_string()).await.unwrap();
+
+        // results are kept, but without identity
+        db.anonymize_user(7, "Deleted-User-7").await.unwrap();
+        let results = session.results().await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].user, Some(7));
+        assert_eq!(results[0].driver_name, "Deleted-User-7");
+        assert_eq!(results[0].driver_guid, "");
+        assert_eq!(results[1].driver_name, "John Doe");
+        assert_eq!(results[1].driver_guid, "S8");
+        assert_eq!(penalty.driver_guid().await, "");
+        assert_eq!(tbl_ses.results_by_user(7).await.len(), 1);
+
+        // texts and evidence of the user are removed
+        assert_eq!(penalty.appeal_text().await, "");
+        assert_eq!(protest.description().await, "");
+        assert!(protest.evidence().await.is_empty());
+        let comments = protest.comments().await;
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].text, "");
+        assert_eq!(comments[1].text, "under investigation");
+    }
+}
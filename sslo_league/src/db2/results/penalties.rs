@@ -0,0 +1,444 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use sslo_lib::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use sslo_lib::error::SsloError;
+use super::ResultsDbData;
+
+/// The kind of a steward penalty (the meaning of the value depends on the type)
+#[derive(PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(sqlx::Type)]
+#[derive(Debug)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[repr(u32)]
+pub enum PenaltyType {
+    /// seconds that are added to the total race time
+    Time = 1,
+
+    /// positions that the driver is moved back in the classification
+    PositionDrop = 2,
+
+    /// championship points that are deducted
+    PointsDeduction = 3,
+
+    /// amount of following races the driver is not allowed to register for
+    RaceBan = 4,
+
+    /// license points (accumulated in the driver profile)
+    LicensePoints = 5,
+}
+
+impl PenaltyType {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PenaltyType::Time => "Time Penalty",
+            PenaltyType::PositionDrop => "Position Drop",
+            PenaltyType::PointsDeduction => "Points Deduction",
+            PenaltyType::RaceBan => "Race Ban",
+            PenaltyType::LicensePoints => "License Points",
+        }
+    }
+
+    /// Human readable penalty including the value
+    pub fn describe(&self, value: i64) -> String {
+        match self {
+            PenaltyType::Time => format!("{}s time penalty", value),
+            PenaltyType::PositionDrop => format!("{} position drop", value),
+            PenaltyType::PointsDeduction => format!("{} points deduction", value),
+            PenaltyType::RaceBan => format!("{} race ban", value),
+            PenaltyType::LicensePoints => format!("{} license points", value),
+        }
+    }
+}
+
+/// The state of an appeal against a penalty
+#[derive(PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(sqlx::Type)]
+#[derive(Debug)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[repr(u32)]
+pub enum AppealState {
+    None = 0,
+    Pending = 1,
+    Rejected = 2,
+    Accepted = 3,
+}
+
+impl AppealState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AppealState::None => "-",
+            AppealState::Pending => "Pending",
+            AppealState::Rejected => "Rejected",
+            AppealState::Accepted => "Accepted",
+        }
+    }
+}
+
+sslo_lib::db_row! {
+    tablename: "steward_penalties",
+    pub struct DbDataRow {
+        session: i64 = 0,
+        user: Option<i64> = None,
+        driver_guid: String = String::new(),
+        penalty_type: PenaltyType = PenaltyType::Time,
+        value: i64 = 0,
+        reason: String = String::new(),
+        issued_by: i64 = 0,
+        issued: DateTime<Utc> = Utc::now(),
+        appeal_state: AppealState = AppealState::None,
+        appeal_text: String = String::new(),
+        revoked: bool = false,
+    }
+    display: |row| format!("steward_penalties(id={};session={};{:?}={})", row.rowid, row.session, row.penalty_type, row.value),
+    sanitize: |row| {
+        row.value = row.value.max(0);
+        row.reason = row.reason.trim().to_string();
+    },
+}
+
+sslo_lib::db_row! {
+    tablename: "steward_penalty_audit",
+    /// A change of a steward penalty
+    pub struct AuditRow {
+        pub penalty: i64 = 0,
+        pub user: i64 = 0,
+        pub date: DateTime<Utc> = Utc::now(),
+        pub action: String = String::new(),
+    }
+}
+
+/// This abstracts data access to shared items
+#[derive(Clone)]
+pub struct PenaltyItem(Arc<RwLock<ItemData<DbDataRow, ResultsDbData>>>);
+
+impl ItemInterface for PenaltyItem {
+    type Row = DbDataRow;
+    type Db = ResultsDbData;
+    type Extra = ();
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, ResultsDbData>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, ResultsDbData>>> {
+        &self.0
+    }
+}
+
+impl PenaltyItem {
+
+    /// Returns a string, that can be used in log messages
+    pub async fn display(&self) -> String {
+        self.0.read().await.display()
+    }
+
+    pub async fn id(&self) -> i64 { self.0.read().await.row.rowid }
+    pub async fn session(&self) -> i64 { self.0.read().await.row.session }
+    pub async fn user(&self) -> Option<i64> { self.0.read().await.row.user }
+    pub async fn driver_guid(&self) -> String { self.0.read().await.row.driver_guid.clone() }
+    pub async fn penalty_type(&self) -> PenaltyType { self.0.read().await.row.penalty_type }
+    pub async fn value(&self) -> i64 { self.0.read().await.row.value }
+    pub async fn reason(&self) -> String { self.0.read().await.row.reason.clone() }
+    pub async fn issued_by(&self) -> i64 { self.0.read().await.row.issued_by }
+    pub async fn issued(&self) -> DateTime<Utc> { self.0.read().await.row.issued }
+    pub async fn appeal_state(&self) -> AppealState { self.0.read().await.row.appeal_state }
+    pub async fn appeal_text(&self) -> String { self.0.read().await.row.appeal_text.clone() }
+    pub async fn revoked(&self) -> bool { self.0.read().await.row.revoked }
+
+    /// Human readable penalty including the value
+    pub async fn describe(&self) -> String {
+        let data = self.0.read().await;
+        data.row.penalty_type.describe(data.row.value)
+    }
+
+    /// Check if the penalty belongs to a certain driver (by user, or by simulator guid if the user is not known)
+    pub async fn concerns(&self, user: Option<i64>, driver_guid: &str) -> bool {
+        let data = self.0.read().await;
+        match (data.row.user, user) {
+            (Some(a), Some(b)) => a == b,
+            _ => !data.row.driver_guid.is_empty() && data.row.driver_guid == driver_guid,
+        }
+    }
+
+    /// Append an entry to the audit trail
+    async fn audit(&self, user_id: i64, action: String) -> Result<(), SsloError> {
+        let data = self.0.read().await;
+        let mut row = AuditRow::new(0);
+        row.penalty = data.row.rowid;
+        row.user = user_id;
+        row.date = Utc::now();
+        row.action = action;
+        log::info!("user {} changed {}: {}", user_id, data.display(), row.action);
+        match data.pool.as_ref() {
+            Some(pool) => row.store(pool).await,
+            None => Ok(()),
+        }
+    }
+
+    /// The audit trail (oldest first)
+    pub async fn audit_trail(&self) -> Vec<AuditRow> {
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Vec::new(),
+        };
+        match AuditRow::fetch_all_by(pool, "penalty", data.row.rowid, 10000).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to read audit trail of {}: {}", data.display(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Change value and reason (by a steward)
+    pub async fn amend(&self, user_id: i64, value: i64, reason: String) -> Result<(), SsloError> {
+        let action = {
+            let mut data = self.0.write().await;
+            let previous = format!("'{}' ({})", data.row.penalty_type.describe(data.row.value), data.row.reason);
+            data.row.value = value;
+            data.row.reason = reason;
+            data.store().await?;
+            format!("amended from {} to '{}' ({})", previous, data.row.penalty_type.describe(data.row.value), data.row.reason)
+        };
+        self.audit(user_id, action).await
+    }
+
+    /// Revoke the penalty (by a steward), it is kept for the audit trail
+    pub async fn revoke(&self, user_id: i64, comment: String) -> Result<(), SsloError> {
+        {
+            let mut data = self.0.write().await;
+            data.row.revoked = true;
+            data.store().await?;
+        }
+        self.audit(user_id, format!("revoked: {}", comment)).await
+    }
+
+    /// Appeal against the penalty (by the penalized driver)
+    pub async fn appeal(&self, user_id: i64, text: String) -> Result<(), SsloError> {
+        {
+            let mut data = self.0.write().await;
+            data.row.appeal_state = AppealState::Pending;
+            data.row.appeal_text = text.clone();
+            data.store().await?;
+        }
+        self.audit(user_id, format!("appealed: {}", text)).await
+    }
+
+    /// Decide about a pending appeal (by a steward), an accepted appeal revokes the penalty
+    pub async fn decide_appeal(&self, user_id: i64, accept: bool, comment: String) -> Result<(), SsloError> {
+        {
+            let mut data = self.0.write().await;
+            if accept {
+                data.row.appeal_state = AppealState::Accepted;
+                data.row.revoked = true;
+            } else {
+                data.row.appeal_state = AppealState::Rejected;
+            }
+            data.store().await?;
+        }
+        let action = match accept {
+            true => format!("appeal accepted: {}", comment),
+            false => format!("appeal rejected: {}", comment),
+        };
+        self.audit(user_id, action).await
+    }
+}
+
+pub struct PenaltyTable(TableInterface<PenaltyItem>);
+
+impl PenaltyTable {
+    pub(super) fn new(tbl: TableInterface<PenaltyItem>) -> Self { Self(tbl) }
+
+    /// Pronounce a new penalty
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_penalty(&self,
+                                session: i64,
+                                user: Option<i64>,
+                                driver_guid: String,
+                                penalty_type: PenaltyType,
+                                value: i64,
+                                reason: String,
+                                issued_by: i64,
+    ) -> Result<PenaltyItem, SsloError> {
+        let mut row = DbDataRow::new(0);
+        row.session = session;
+        row.user = user;
+        row.driver_guid = driver_guid;
+        row.penalty_type = penalty_type;
+        row.value = value;
+        row.reason = reason;
+        row.issued_by = issued_by;
+        row.issued = Utc::now();
+        let item = self.0.create_item(row).await?;
+        let action = format!("issued {} ({})", item.describe().await, item.reason().await);
+        item.audit(issued_by, action).await?;
+        Ok(item)
+    }
+
+    pub async fn item_by_id(&self, id: i64) -> Option<PenaltyItem> {
+        self.0.item_by_id(id).await
+    }
+
+    async fn items_by_query(&self, sql: &str, value: i64) -> Vec<PenaltyItem> {
+        let pool = self.0.pool().await;
+        match sqlx::query_as::<sqlx::Sqlite, DbDataRow>(sql)
+            .bind(value)
+            .fetch_all(&pool)
+            .await {
+            Ok(rows) => self.0.items_by_rows(rows).await,
+            Err(e) => {
+                log::error!("{}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// All penalties of a session (including revoked ones)
+    pub async fn items_by_session(&self, session_id: i64) -> Vec<PenaltyItem> {
+        self.items_by_query("SELECT rowid,* FROM steward_penalties WHERE session = $1 ORDER BY rowid;", session_id).await
+    }
+
+    /// All penalties of a user, latest first (including revoked ones)
+    pub async fn items_by_user(&self, user_id: i64) -> Vec<PenaltyItem> {
+        self.items_by_query("SELECT rowid,* FROM steward_penalties WHERE user = $1 ORDER BY rowid DESC;", user_id).await
+    }
+
+    /// All penalties with pending appeals
+    pub async fn items_appeal_pending(&self) -> Vec<PenaltyItem> {
+        self.items_by_query("SELECT rowid,* FROM steward_penalties WHERE appeal_state = $1 ORDER BY rowid;",
+                            AppealState::Pending as i64).await
+    }
+
+    /// The sum of all license points of a user (revoked penalties are not counted)
+    pub async fn license_points(&self, user_id: i64) -> i64 {
+        let mut points = 0;
+        for penalty in self.items_by_user(user_id).await.iter() {
+            if !penalty.revoked().await && penalty.penalty_type().await == PenaltyType::LicensePoints {
+                points += penalty.value().await;
+            }
+        }
+        points
+    }
+
+    /// Delete a penalty including its audit trail (only intended for deleting whole sessions)
+    pub async fn delete_penalty(&self, penalty: PenaltyItem) -> Result<(), SsloError> {
+        let pool = self.0.pool().await;
+        for mut row in penalty.audit_trail().await.into_iter() {
+            row.delete(&pool).await?;
+        }
+        self.0.delete_item(penalty).await
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use crate::db2::content::Simulator;
+    use super::super::{ResultsDbData, ResultsDbInterface, SessionType};
+
+    /// A results database with one session
+    async fn get_db() -> ResultsDbInterface {
+        let data = ResultsDbData::new(None).await.unwrap();
+        let db = ResultsDbInterface::new(data);
+        db.tbl_sessions().await.create_session(Simulator::AssettoCorsa, SessionType::Race, "Server".to_string(),
+                                               "race.json".to_string(), "monza".to_string(), None).await.unwrap();
+        db
+    }
+
+    async fn create_penalty(tbl: &PenaltyTable) -> PenaltyItem {
+        tbl.create_penalty(1, Some(7), "S7".to_string(), PenaltyType::LicensePoints, 3, "collision".to_string(), 2).await.unwrap()
+    }
+
+    #[test(tokio::test)]
+    async fn new_penalty() {
+        let tbl = get_db().await.tbl_penalties().await;
+        let penalty = tbl.create_penalty(1, Some(7), "S7".to_string(), PenaltyType::Time, -5, " collision ".to_string(), 2).await.unwrap();
+        assert_eq!(penalty.value().await, 0);
+        assert_eq!(penalty.reason().await, "collision");
+        assert_eq!(penalty.appeal_state().await, AppealState::None);
+        assert!(!penalty.revoked().await);
+        let trail = penalty.audit_trail().await;
+        assert_eq!(trail.len(), 1);
+        assert_eq!(trail[0].user, 2);
+        assert_eq!(trail[0].action, "issued 0s time penalty (collision)");
+    }
+
+    #[test(tokio::test)]
+    async fn appeal_rejected() {
+        let tbl = get_db().await.tbl_penalties().await;
+        let penalty = create_penalty(&tbl).await;
+
+        // None -> Pending
+        penalty.appeal(7, "no contact".to_string()).await.unwrap();
+        assert_eq!(penalty.appeal_state().await, AppealState::Pending);
+        assert_eq!(penalty.appeal_text().await, "no contact");
+        assert_eq!(tbl.items_appeal_pending().await.len(), 1);
+
+        // Pending -> Rejected (the penalty stays valid)
+        penalty.decide_appeal(2, false, "contact visible".to_string()).await.unwrap();
+        assert_eq!(penalty.appeal_state().await, AppealState::Rejected);
+        assert!(!penalty.revoked().await);
+        assert!(tbl.items_appeal_pending().await.is_empty());
+        assert_eq!(tbl.license_points(7).await, 3);
+        assert_eq!(penalty.audit_trail().await[2].action, "appeal rejected: contact visible");
+    }
+
+    #[test(tokio::test)]
+    async fn appeal_accepted() {
+        let tbl = get_db().await.tbl_penalties().await;
+        let penalty = create_penalty(&tbl).await;
+
+        // Pending -> Accepted (the penalty is revoked)
+        penalty.appeal(7, "no contact".to_string()).await.unwrap();
+        penalty.decide_appeal(2, true, "no contact visible".to_string()).await.unwrap();
+        assert_eq!(penalty.appeal_state().await, AppealState::Accepted);
+        assert!(penalty.revoked().await);
+        assert_eq!(tbl.license_points(7).await, 0);
+        assert_eq!(penalty.audit_trail().await[2].action, "appeal accepted: no contact visible");
+    }
+
+    #[test(tokio::test)]
+    async fn amend_and_revoke() {
+        let tbl = get_db().await.tbl_penalties().await;
+        let penalty = create_penalty(&tbl).await;
+
+        // amend
+        penalty.amend(2, 5, " collision, repeated ".to_string()).await.unwrap();
+        assert_eq!(penalty.value().await, 5);
+        assert_eq!(penalty.reason().await, "collision, repeated");
+        assert_eq!(tbl.license_points(7).await, 5);
+
+        // revoke (without appeal)
+        penalty.revoke(4, "wrong driver".to_string()).await.unwrap();
+        assert!(penalty.revoked().await);
+        assert_eq!(penalty.appeal_state().await, AppealState::None);
+        assert_eq!(tbl.license_points(7).await, 0);
+        assert_eq!(tbl.items_by_user(7).await.len(), 1);
+
+        // audit trail
+        let trail = penalty.audit_trail().await;
+        assert_eq!(trail.len(), 3);
+        assert_eq!(trail[1].action, "amended from '3 license points' (collision) to '5 license points' (collision, repeated)");
+        assert_eq!(trail[2].user, 4);
+        assert_eq!(trail[2].action, "revoked: wrong driver");
+    }
+
+    #[test(tokio::test)]
+    async fn delete_penalty() {
+        let db = get_db().await;
+        let tbl = db.tbl_penalties().await;
+        let penalty = create_penalty(&tbl).await;
+        penalty.appeal(7, "no contact".to_string()).await.unwrap();
+        let penalty_id = penalty.id().await;
+
+        // the audit trail is deleted as well
+        tbl.delete_penalty(penalty).await.unwrap();
+        assert!(tbl.item_by_id(penalty_id).await.is_none());
+        let audit = AuditRow::fetch_all_by(&db.pool().await, "penalty", penalty_id, 100).await.unwrap();
+        assert!(audit.is_empty());
+    }
+}
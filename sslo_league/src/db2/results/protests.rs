@@ -0,0 +1,404 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use sslo_lib::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use sslo_lib::error::SsloError;
+use super::ResultsDbData;
+
+/// The processing state of a protest
+#[derive(PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(sqlx::Type)]
+#[derive(Debug)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[repr(u32)]
+pub enum ProtestState {
+
+    /// waiting for a decision of the stewards
+    Open = 1,
+
+    /// resolved into a penalty
+    Penalized = 2,
+
+    /// resolved without consequences
+    NoFurtherAction = 3,
+}
+
+impl ProtestState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProtestState::Open => "Open",
+            ProtestState::Penalized => "Penalized",
+            ProtestState::NoFurtherAction => "No Further Action",
+        }
+    }
+}
+
+sslo_lib::db_row! {
+    tablename: "protests",
+    pub struct DbDataRow {
+        session: i64 = 0,
+        lap: Option<i64> = None,
+        reporter: i64 = 0,
+        description: String = String::new(),
+        filed: DateTime<Utc> = Utc::now(),
+        state: ProtestState = ProtestState::Open,
+        resolution: String = String::new(),
+        resolved_by: Option<i64> = None,
+        resolved: Option<DateTime<Utc>> = None,
+        penalty: Option<i64> = None,
+    }
+    display: |row| format!("protests(id={};session={};reporter={})", row.rowid, row.session, row.reporter),
+    sanitize: |row| {
+        row.description = row.description.trim().to_string();
+        row.resolution = row.resolution.trim().to_string();
+    },
+}
+
+sslo_lib::db_row! {
+    tablename: "protest_drivers",
+    /// A driver that is involved in a protest (identified by user, or by simulator guid if the user is not known)
+    pub struct DriverRow {
+        pub protest: i64 = 0,
+        pub user: Option<i64> = None,
+        pub driver_guid: String = String::new(),
+    }
+}
+
+sslo_lib::db_row! {
+    tablename: "protest_evidence",
+    /// A link to evidence of a protest
+    pub struct EvidenceRow {
+        pub protest: i64 = 0,
+        pub url: String = String::new(),
+    }
+}
+
+sslo_lib::db_row! {
+    tablename: "protest_comments",
+    /// A comment on a protest
+    pub struct CommentRow {
+        pub protest: i64 = 0,
+        pub user: i64 = 0,
+        pub date: DateTime<Utc> = Utc::now(),
+        pub text: String = String::new(),
+    }
+}
+
+/// This abstracts data access to shared items
+#[derive(Clone)]
+pub struct ProtestItem(Arc<RwLock<ItemData<DbDataRow, ResultsDbData>>>);
+
+impl ItemInterface for ProtestItem {
+    type Row = DbDataRow;
+    type Db = ResultsDbData;
+    type Extra = ();
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, ResultsDbData>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, ResultsDbData>>> {
+        &self.0
+    }
+}
+
+impl ProtestItem {
+
+    /// Returns a string, that can be used in log messages
+    pub async fn display(&self) -> String {
+        self.0.read().await.display()
+    }
+
+    pub async fn id(&self) -> i64 { self.0.read().await.row.rowid }
+    pub async fn session(&self) -> i64 { self.0.read().await.row.session }
+    pub async fn lap(&self) -> Option<i64> { self.0.read().await.row.lap }
+    pub async fn reporter(&self) -> i64 { self.0.read().await.row.reporter }
+    pub async fn description(&self) -> String { self.0.read().await.row.description.clone() }
+    pub async fn filed(&self) -> DateTime<Utc> { self.0.read().await.row.filed }
+    pub async fn state(&self) -> ProtestState { self.0.read().await.row.state }
+    pub async fn resolution(&self) -> String { self.0.read().await.row.resolution.clone() }
+    pub async fn resolved_by(&self) -> Option<i64> { self.0.read().await.row.resolved_by }
+    pub async fn resolved(&self) -> Option<DateTime<Utc>> { self.0.read().await.row.resolved }
+
+    /// The penalty that was pronounced when resolving the protest
+    pub async fn penalty(&self) -> Option<i64> { self.0.read().await.row.penalty }
+
+    /// Read all rows of a sub-table that belong to this protest
+    async fn fetch_rows<R: DbRow>(&self) -> Vec<R> {
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Vec::new(),
+        };
+        match R::fetch_all_by(pool, "protest", data.row.rowid, 10000).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to read {} for {}: {}", R::TABLENAME, data.display(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Store a new row of a sub-table
+    async fn store_row<R: DbRow>(&self, mut row: R) -> Result<(), SsloError> {
+        let data = self.0.read().await;
+        match data.pool.as_ref() {
+            Some(pool) => row.store(pool).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Delete all rows of a sub-table that belong to this protest
+    async fn delete_rows<R: DbRow>(&self) -> Result<(), SsloError> {
+        let rows: Vec<R> = self.fetch_rows().await;
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Ok(()),
+        };
+        for mut row in rows.into_iter() {
+            row.delete(pool).await?;
+        }
+        Ok(())
+    }
+
+    /// The involved drivers
+    pub async fn drivers(&self) -> Vec<DriverRow> {
+        self.fetch_rows().await
+    }
+
+    /// Check if a user is involved in the protest
+    pub async fn involves(&self, user_id: i64) -> bool {
+        self.drivers().await.iter().any(|d| d.user == Some(user_id))
+    }
+
+    /// Links to evidence
+    pub async fn evidence(&self) -> Vec<String> {
+        self.fetch_rows::<EvidenceRow>().await.into_iter().map(|r| r.url).collect()
+    }
+
+    /// All comments, oldest first
+    pub async fn comments(&self) -> Vec<CommentRow> {
+        self.fetch_rows().await
+    }
+
+    pub async fn add_comment(&self, user_id: i64, text: String) -> Result<(), SsloError> {
+        let mut row = CommentRow::new(0);
+        row.protest = self.id().await;
+        row.user = user_id;
+        row.date = Utc::now();
+        row.text = text.trim().to_string();
+        self.store_row(row).await
+    }
+
+    /// Close the protest (by a steward), optionally with a pronounced penalty
+    pub async fn resolve(&self, user_id: i64, resolution: String, penalty: Option<i64>) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.state = match penalty {
+            Some(_) => ProtestState::Penalized,
+            None => ProtestState::NoFurtherAction,
+        };
+        data.row.resolution = resolution;
+        data.row.resolved_by = Some(user_id);
+        data.row.resolved = Some(Utc::now());
+        data.row.penalty = penalty;
+        data.store().await?;
+        log::info!("user {} resolved {} ({})", user_id, data.display(), data.row.state.label());
+        Ok(())
+    }
+}
+
+pub struct ProtestTable(TableInterface<ProtestItem>);
+
+impl ProtestTable {
+    pub(super) fn new(tbl: TableInterface<ProtestItem>) -> Self { Self(tbl) }
+
+    /// File a new protest
+    pub async fn create_protest(&self,
+                                session: i64,
+                                lap: Option<i64>,
+                                reporter: i64,
+                                description: String,
+                                drivers: Vec<(Option<i64>, String)>,
+                                evidence: Vec<String>,
+    ) -> Result<ProtestItem, SsloError> {
+        let mut row = DbDataRow::new(0);
+        row.session = session;
+        row.lap = lap;
+        row.reporter = reporter;
+        row.description = description;
+        row.filed = Utc::now();
+        let item = self.0.create_item(row).await?;
+        let protest_id = item.id().await;
+        for (user, driver_guid) in drivers.into_iter() {
+            let mut row = DriverRow::new(0);
+            row.protest = protest_id;
+            row.user = user;
+            row.driver_guid = driver_guid;
+            item.store_row(row).await?;
+        }
+        for url in evidence.into_iter() {
+            let mut row = EvidenceRow::new(0);
+            row.protest = protest_id;
+            row.url = url.trim().to_string();
+            item.store_row(row).await?;
+        }
+        log::info!("user {} filed {}", reporter, item.display().await);
+        Ok(item)
+    }
+
+    pub async fn item_by_id(&self, id: i64) -> Option<ProtestItem> {
+        self.0.item_by_id(id).await
+    }
+
+    async fn items_by_query(&self, sql: &str, value: i64) -> Vec<ProtestItem> {
+        let pool = self.0.pool().await;
+        match sqlx::query_as::<sqlx::Sqlite, DbDataRow>(sql)
+            .bind(value)
+            .fetch_all(&pool)
+            .await {
+            Ok(rows) => self.0.items_by_rows(rows).await,
+            Err(e) => {
+                log::error!("{}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// The review queue of the stewards (oldest first)
+    pub async fn items_open(&self) -> Vec<ProtestItem> {
+        self.items_by_query("SELECT rowid,* FROM protests WHERE state = $1 ORDER BY filed, rowid;",
+                            ProtestState::Open as i64).await
+    }
+
+    /// All protests of a session
+    pub async fn items_by_session(&self, session_id: i64) -> Vec<ProtestItem> {
+        self.items_by_query("SELECT rowid,* FROM protests WHERE session = $1 ORDER BY rowid;", session_id).await
+    }
+
+    /// All protests that were filed by a user, latest first
+    pub async fn items_by_reporter(&self, user_id: i64) -> Vec<ProtestItem> {
+        self.items_by_query("SELECT rowid,* FROM protests WHERE reporter = $1 ORDER BY rowid DESC;", user_id).await
+    }
+
+    /// All protests a user is involved in as driver (the involvement rows)
+    pub async fn drivers_by_user(&self, user_id: i64) -> Vec<DriverRow> {
+        self.rows_by_user(user_id).await
+    }
+
+    /// All comments of a user (over all protests)
+    pub async fn comments_by_user(&self, user_id: i64) -> Vec<CommentRow> {
+        self.rows_by_user(user_id).await
+    }
+
+    async fn rows_by_user<R: DbRow>(&self, user_id: i64) -> Vec<R> {
+        let pool = self.0.pool().await;
+        match R::fetch_all_by(&pool, "user", user_id, 100000).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to read {} of user {}: {}", R::TABLENAME, user_id, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Delete a protest including drivers, evidence and comments
+    pub async fn delete_protest(&self, protest: ProtestItem) -> Result<(), SsloError> {
+        protest.delete_rows::<DriverRow>().await?;
+        protest.delete_rows::<EvidenceRow>().await?;
+        protest.delete_rows::<CommentRow>().await?;
+        self.0.delete_item(protest).await
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use crate::db2::content::Simulator;
+    use super::super::{ResultsDbData, ResultsDbInterface, SessionType};
+    use super::super::penalties::PenaltyType;
+
+    /// A results database with one session
+    async fn get_db() -> ResultsDbInterface {
+        let data = ResultsDbData::new(None).await.unwrap();
+        let db = ResultsDbInterface::new(data);
+        db.tbl_sessions().await.create_session(Simulator::AssettoCorsa, SessionType::Race, "Server".to_string(),
+                                               "race.json".to_string(), "monza".to_string(), None).await.unwrap();
+        db
+    }
+
+    async fn create_protest(tbl: &ProtestTable) -> ProtestItem {
+        tbl.create_protest(1, Some(3), 7, "divebomb".to_string(), vec![(Some(8), "S8".to_string())],
+                           vec![" https://video.tld/onboard ".to_string()]).await.unwrap()
+    }
+
+    #[test(tokio::test)]
+    async fn new_protest() {
+        let tbl = get_db().await.tbl_protests().await;
+        let protest = create_protest(&tbl).await;
+        assert_eq!(protest.state().await, ProtestState::Open);
+        assert_eq!(protest.resolved().await, None);
+        assert_eq!(protest.resolved_by().await, None);
+        assert_eq!(protest.penalty().await, None);
+        assert_eq!(protest.evidence().await, vec!["https://video.tld/onboard".to_string()]);
+        assert!(protest.involves(8).await);
+        assert!(!protest.involves(7).await);
+        assert_eq!(tbl.items_open().await.len(), 1);
+    }
+
+    #[test(tokio::test)]
+    async fn resolve_penalized() {
+        let db = get_db().await;
+        let tbl = db.tbl_protests().await;
+        let protest = create_protest(&tbl).await;
+        let penalty = db.tbl_penalties().await.create_penalty(1, Some(8), "S8".to_string(), PenaltyType::Time, 5,
+                                                              "divebomb".to_string(), 2).await.unwrap();
+
+        // Open -> Penalized
+        protest.resolve(2, " penalty for car 8 ".to_string(), Some(penalty.id().await)).await.unwrap();
+        assert_eq!(protest.state().await, ProtestState::Penalized);
+        assert_eq!(protest.resolution().await, "penalty for car 8");
+        assert_eq!(protest.resolved_by().await, Some(2));
+        assert!(protest.resolved().await.is_some());
+        assert_eq!(protest.penalty().await, Some(penalty.id().await));
+        assert!(tbl.items_open().await.is_empty());
+    }
+
+    #[test(tokio::test)]
+    async fn resolve_no_further_action() {
+        let tbl = get_db().await.tbl_protests().await;
+        let protest = create_protest(&tbl).await;
+
+        // Open -> NoFurtherAction
+        protest.resolve(2, "racing incident".to_string(), None).await.unwrap();
+        assert_eq!(protest.state().await, ProtestState::NoFurtherAction);
+        assert_eq!(protest.resolved_by().await, Some(2));
+        assert_eq!(protest.penalty().await, None);
+        assert!(tbl.items_open().await.is_empty());
+
+        // the resolution is persistent
+        tbl.0.reload_cache().await.unwrap();
+        let protest = tbl.item_by_id(protest.id().await).await.unwrap();
+        assert_eq!(protest.state().await, ProtestState::NoFurtherAction);
+        assert_eq!(protest.resolution().await, "racing incident");
+    }
+
+    #[test(tokio::test)]
+    async fn delete_protest() {
+        let db = get_db().await;
+        let tbl = db.tbl_protests().await;
+        let protest = create_protest(&tbl).await;
+        protest.add_comment(2, "checking replay".to_string()).await.unwrap();
+        let protest_id = protest.id().await;
+
+        // drivers, evidence and comments are deleted as well
+        tbl.delete_protest(protest).await.unwrap();
+        assert!(tbl.item_by_id(protest_id).await.is_none());
+        let pool = db.pool().await;
+        assert!(DriverRow::fetch_all_by(&pool, "protest", protest_id, 100).await.unwrap().is_empty());
+        assert!(EvidenceRow::fetch_all_by(&pool, "protest", protest_id, 100).await.unwrap().is_empty());
+        assert!(CommentRow::fetch_all_by(&pool, "protest", protest_id, 100).await.unwrap().is_empty());
+    }
+}
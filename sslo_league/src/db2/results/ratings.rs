@@ -0,0 +1,170 @@
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use sslo_lib::db::DbRow;
+use sslo_lib::error::SsloError;
+
+sslo_lib::db_row! {
+    tablename: "ratings",
+    /// The current rating of a driver (overall, or within a car class)
+    /// car_class is zero for the overall rating
+    pub struct RatingRow {
+        pub user: i64 = 0,
+        pub car_class: i64 = 0,
+        pub rating: f64 = 1500.0,
+        pub deviation: f64 = 350.0,
+        pub volatility: f64 = 0.06,
+        pub races: i64 = 0,
+        pub updated: DateTime<Utc> = Utc::now(),
+    }
+}
+
+sslo_lib::db_row! {
+    tablename: "rating_history",
+    /// The rating of a driver after a race
+    pub struct RatingHistoryRow {
+        pub user: i64 = 0,
+        pub car_class: i64 = 0,
+        pub session: i64 = 0,
+        pub rating: f64 = 1500.0,
+        pub deviation: f64 = 350.0,
+        pub volatility: f64 = 0.06,
+        pub date: DateTime<Utc> = Utc::now(),
+    }
+}
+
+/// Access to the driver ratings
+/// Ratings are derived data, which are completely replaced on every re-computation (so they are not cached).
+pub struct RatingTable(SqlitePool);
+
+impl RatingTable {
+    pub(super) fn new(pool: SqlitePool) -> Self { Self(pool) }
+
+    /// All ratings of a car class (zero for overall), sorted by descending rating
+    pub async fn ratings(&self, car_class: i64) -> Vec<RatingRow> {
+        let mut rows = match RatingRow::fetch_all_by(&self.0, "car_class", car_class, 10000).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to read ratings: {}", e);
+                return Vec::new();
+            }
+        };
+        rows.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+        rows
+    }
+
+    /// The current ratings of a user (overall and per car class)
+    pub async fn ratings_by_user(&self, user_id: i64) -> Vec<RatingRow> {
+        match RatingRow::fetch_all_by(&self.0, "user", user_id, 1000).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to read ratings of user {}: {}", user_id, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// The rating history of a user within a car class (zero for overall), oldest first
+    pub async fn history(&self, user_id: i64, car_class: i64) -> Vec<RatingHistoryRow> {
+        match RatingHistoryRow::fetch_all_by(&self.0, "user", user_id, 10000).await {
+            Ok(rows) => rows.into_iter().filter(|r| r.car_class == car_class).collect(),
+            Err(e) => {
+                log::error!("failed to read rating history of user {}: {}", user_id, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Replace all ratings and the complete history
+    pub async fn replace_all(&self, ratings: Vec<RatingRow>, history: Vec<RatingHistoryRow>) -> Result<(), SsloError> {
+        sqlx::query("DELETE FROM rating_history;").execute(&self.0).await?;
+        sqlx::query("DELETE FROM ratings;").execute(&self.0).await?;
+        for mut row in ratings.into_iter() {
+            row.set_rowid(0);
+            row.store(&self.0).await?;
+        }
+        for mut row in history.into_iter() {
+            row.set_rowid(0);
+            row.store(&self.0).await?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use crate::db2::content::Simulator;
+    use super::super::{ResultsDbData, ResultsDbInterface, SessionType};
+
+    /// The rating table of a results database with two sessions
+    async fn get_table() -> RatingTable {
+        let data = ResultsDbData::new(None).await.unwrap();
+        let db = ResultsDbInterface::new(data);
+        let tbl_ses = db.tbl_sessions().await;
+        for file_name in ["race1.json", "race2.json"] {
+            tbl_ses.create_session(Simulator::AssettoCorsa, SessionType::Race, "Server".to_string(),
+                                   file_name.to_string(), "monza".to_string(), None).await.unwrap();
+        }
+        db.tbl_ratings().await
+    }
+
+    fn rating(user: i64, car_class: i64, rating: f64) -> RatingRow {
+        let mut row = RatingRow::new(0);
+        row.user = user;
+        row.car_class = car_class;
+        row.rating = rating;
+        row
+    }
+
+    fn history(user: i64, car_class: i64, session: i64, rating: f64) -> RatingHistoryRow {
+        let mut row = RatingHistoryRow::new(0);
+        row.user = user;
+        row.car_class = car_class;
+        row.session = session;
+        row.rating = rating;
+        row
+    }
+
+    #[test(tokio::test)]
+    async fn ratings() {
+        let tbl = get_table().await;
+        tbl.replace_all(vec![rating(7, 0, 1450.0), rating(8, 0, 1620.0), rating(7, 3, 1510.0)],
+                        vec![history(7, 0, 1, 1480.0), history(7, 3, 1, 1510.0), history(7, 0, 2, 1450.0)]).await.unwrap();
+
+        // sorted by descending rating, filtered by car class
+        let overall = tbl.ratings(0).await;
+        assert_eq!(overall.len(), 2);
+        assert_eq!(overall[0].user, 8);
+        assert_eq!(overall[1].user, 7);
+        assert_eq!(tbl.ratings(3).await.len(), 1);
+        assert_eq!(tbl.ratings_by_user(7).await.len(), 2);
+
+        // history of a car class
+        let hist = tbl.history(7, 0).await;
+        assert_eq!(hist.len(), 2);
+        assert_eq!(hist[0].session, 1);
+        assert_eq!(hist[1].rating, 1450.0);
+        assert_eq!(tbl.history(7, 3).await.len(), 1);
+        assert!(tbl.history(8, 0).await.is_empty());
+    }
+
+    #[test(tokio::test)]
+    async fn replace_all() {
+        let tbl = get_table().await;
+        tbl.replace_all(vec![rating(7, 0, 1450.0), rating(8, 0, 1620.0)],
+                        vec![history(7, 0, 1, 1450.0)]).await.unwrap();
+
+        // previous ratings are removed, row ids are assigned anew
+        let mut row = rating(9, 0, 1500.0);
+        row.set_rowid(77);
+        tbl.replace_all(vec![row], Vec::new()).await.unwrap();
+        let overall = tbl.ratings(0).await;
+        assert_eq!(overall.len(), 1);
+        assert_eq!(overall[0].user, 9);
+        assert_ne!(overall[0].rowid, 77);
+        assert!(tbl.ratings_by_user(7).await.is_empty());
+        assert!(tbl.history(7, 0).await.is_empty());
+    }
+}
@@ -0,0 +1,482 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use sslo_lib::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use sslo_lib::error::SsloError;
+use crate::db2::content::Simulator;
+use super::{ResultsDbData, ResultsDbInterface, SessionType};
+
+sslo_lib::db_row! {
+    tablename: "sessions",
+    pub struct DbDataRow {
+        simulator: Simulator = Simulator::AssettoCorsa,
+        session_type: SessionType = SessionType::Practice,
+        server_name: String = String::new(),
+        file_name: String = String::new(),
+        track_identifier: String = String::new(),
+        track_layout: Option<i64> = None,
+        wet: bool = false,
+        started: Option<DateTime<Utc>> = None,
+        imported: DateTime<Utc> = Utc::now(),
+    }
+    display: |row| format!("sessions(id={};type={:?};file={})", row.rowid, row.session_type, row.file_name),
+}
+
+sslo_lib::db_row! {
+    tablename: "session_results",
+    /// The final classification of a driver in a session
+    pub struct ResultRow {
+        pub session: i64 = 0,
+        pub position: i64 = 0,
+        pub user: Option<i64> = None,
+        pub driver_name: String = String::new(),
+        pub driver_guid: String = String::new(),
+        pub car: Option<i64> = None,
+        pub car_identifier: String = String::new(),
+        pub car_number: i64 = 0,
+        pub team: String = String::new(),
+        pub lap_count: i64 = 0,
+        pub best_lap: Option<i64> = None,
+        pub total_time: Option<i64> = None,
+        pub incidents: i64 = 0,
+    }
+}
+
+sslo_lib::db_row! {
+    tablename: "session_laps",
+    /// A single lap that was driven in a session
+    pub struct LapRow {
+        pub session: i64 = 0,
+        pub user: Option<i64> = None,
+        pub driver_guid: String = String::new(),
+        pub car_number: i64 = 0,
+        pub lap_number: i64 = 0,
+        pub lap_time: i64 = 0,
+        pub sectors: String = String::new(),
+        pub valid: bool = true,
+    }
+}
+
+sslo_lib::db_row! {
+    tablename: "session_penalties",
+    /// A penalty that was issued by the simulator server
+    pub struct PenaltyRow {
+        pub session: i64 = 0,
+        pub user: Option<i64> = None,
+        pub driver_guid: String = String::new(),
+        pub car_number: i64 = 0,
+        pub reason: String = String::new(),
+        pub penalty: String = String::new(),
+        pub penalty_value: i64 = 0,
+        pub violation_lap: i64 = 0,
+        pub cleared_lap: Option<i64> = None,
+        pub post_race: bool = false,
+    }
+}
+
+sslo_lib::db_row! {
+    tablename: "session_incidents",
+    /// An incident (e.g. collision) that was reported by the simulator server
+    pub struct IncidentRow {
+        pub session: i64 = 0,
+        pub user: Option<i64> = None,
+        pub driver_guid: String = String::new(),
+        pub car_number: i64 = 0,
+        pub other_user: Option<i64> = None,
+        pub other_driver_guid: Option<String> = None,
+        pub incident_type: String = String::new(),
+        pub impact_speed: Option<f64> = None,
+        pub lap: Option<i64> = None,
+    }
+}
+
+/// This abstracts data access to shared items
+#[derive(Clone)]
+pub struct SessionItem(Arc<RwLock<ItemData<DbDataRow, ResultsDbData>>>);
+
+impl ItemInterface for SessionItem {
+    type Row = DbDataRow;
+    type Db = ResultsDbData;
+    type Extra = ();
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, ResultsDbData>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, ResultsDbData>>> {
+        &self.0
+    }
+}
+
+impl SessionItem {
+
+    /// Returns a string, that can be used in log messages
+    pub async fn display(&self) -> String {
+        self.0.read().await.display()
+    }
+
+    pub async fn id(&self) -> i64 { self.0.read().await.row.rowid }
+    pub async fn session_type(&self) -> SessionType { self.0.read().await.row.session_type }
+    pub async fn server_name(&self) -> String { self.0.read().await.row.server_name.clone() }
+    pub async fn track_identifier(&self) -> String { self.0.read().await.row.track_identifier.clone() }
+    pub async fn started(&self) -> Option<DateTime<Utc>> { self.0.read().await.row.started }
+    pub async fn imported(&self) -> DateTime<Utc> { self.0.read().await.row.imported }
+    pub async fn wet(&self) -> bool { self.0.read().await.row.wet }
+
+    /// The rowid of the track layout in the content database
+    pub async fn track_layout(&self) -> Option<i64> { self.0.read().await.row.track_layout }
+
+    pub async fn set_track_layout(&self, layout_id: Option<i64>) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.track_layout = layout_id;
+        data.store().await
+    }
+
+    pub async fn set_wet(&self, wet: bool) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.wet = wet;
+        data.store().await
+    }
+
+    /// Read all rows of a sub-table that belong to this session
+    async fn fetch_rows<R: DbRow>(&self) -> Vec<R> {
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Vec::new(),
+        };
+        match R::fetch_all_by(pool, "session", data.row.rowid, 10000).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to read {} for {}: {}", R::TABLENAME, data.display(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Store a new row of a sub-table
+    async fn store_row<R: DbRow>(&self, mut row: R) -> Result<(), SsloError> {
+        let data = self.0.read().await;
+        match data.pool.as_ref() {
+            Some(pool) => row.store(pool).await,
+            None => Ok(()),
+        }
+    }
+
+    /// The classification (sorted by position)
+    pub async fn results(&self) -> Vec<ResultRow> {
+        let mut rows: Vec<ResultRow> = self.fetch_rows().await;
+        rows.sort_by_key(|r| r.position);
+        rows
+    }
+
+    pub async fn add_result(&self, mut row: ResultRow) -> Result<(), SsloError> {
+        row.session = self.id().await;
+        self.store_row(row).await
+    }
+
+    pub async fn laps(&self) -> Vec<LapRow> {
+        self.fetch_rows().await
+    }
+
+    pub async fn add_lap(&self, mut row: LapRow) -> Result<(), SsloError> {
+        row.session = self.id().await;
+        self.store_row(row).await
+    }
+
+    pub async fn penalties(&self) -> Vec<PenaltyRow> {
+        self.fetch_rows().await
+    }
+
+    pub async fn add_penalty(&self, mut row: PenaltyRow) -> Result<(), SsloError> {
+        row.session = self.id().await;
+        self.store_row(row).await
+    }
+
+    pub async fn incidents(&self) -> Vec<IncidentRow> {
+        self.fetch_rows().await
+    }
+
+    pub async fn add_incident(&self, mut row: IncidentRow) -> Result<(), SsloError> {
+        row.session = self.id().await;
+        self.store_row(row).await
+    }
+}
+
+pub struct SessionTable(TableInterface<SessionItem>);
+
+impl SessionTable {
+    pub(super) fn new(tbl: TableInterface<SessionItem>) -> Self { Self(tbl) }
+
+    /// Create a new session
+    /// Returns None, if the session already exists (or on database errors)
+    pub async fn create_session(&self,
+                                simulator: Simulator,
+                                session_type: SessionType,
+                                server_name: String,
+                                file_name: String,
+                                track_identifier: String,
+                                started: Option<DateTime<Utc>>,
+    ) -> Option<SessionItem> {
+        let mut row = DbDataRow::new(0);
+        row.simulator = simulator;
+        row.session_type = session_type;
+        row.server_name = server_name;
+        row.file_name = file_name;
+        row.track_identifier = track_identifier;
+        row.started = started;
+        row.imported = Utc::now();
+        match self.0.create_item(row).await {
+            Ok(item) => {
+                log::info!("new session created: {}", item.display().await);
+                Some(item)
+            },
+            Err(e) => {
+                log::error!("Could not create new session: {}", e);
+                None
+            }
+        }
+    }
+
+    pub async fn item_by_id(&self, id: i64) -> Option<SessionItem> {
+        self.0.item_by_id(id).await
+    }
+
+    /// Find a session by the file that it was imported from
+    pub async fn item_by_file(&self, simulator: Simulator, server_name: &str, file_name: &str) -> Option<SessionItem> {
+        let pool = self.0.pool().await;
+        match sqlx::query_as::<sqlx::Sqlite, DbDataRow>(
+            "SELECT rowid,* FROM sessions WHERE simulator = $1 AND server_name = $2 AND file_name = $3 LIMIT 1;")
+            .bind(simulator)
+            .bind(server_name)
+            .bind(file_name)
+            .fetch_optional(&pool)
+            .await {
+            Ok(Some(row)) => Some(self.0.item_by_row(row).await),
+            Ok(None) => None,
+            Err(e) => {
+                log::error!("{}", e);
+                None
+            }
+        }
+    }
+
+    /// All sessions of a certain type, in chronological order
+    pub async fn items_by_type(&self, session_type: SessionType) -> Vec<SessionItem> {
+        let pool = self.0.pool().await;
+        match sqlx::query_as::<sqlx::Sqlite, DbDataRow>(
+            "SELECT rowid,* FROM sessions WHERE session_type = $1 ORDER BY COALESCE(started, imported), rowid;")
+            .bind(session_type)
+            .fetch_all(&pool)
+            .await {
+            Ok(rows) => self.0.items_by_rows(rows).await,
+            Err(e) => {
+                log::error!("{}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// All classifications of a user (over all sessions)
+    pub async fn results_by_user(&self, user_id: i64) -> Vec<ResultRow> {
+        self.rows_by_user(user_id).await
+    }
+
+    /// All penalties of a user that were issued by the simulator servers
+    pub async fn penalties_by_user(&self, user_id: i64) -> Vec<PenaltyRow> {
+        self.rows_by_user(user_id).await
+    }
+
+    /// All laps of a user
+    pub async fn laps_by_user(&self, user_id: i64) -> Vec<LapRow> {
+        self.rows_by_user(user_id).await
+    }
+
+    /// All incidents a user was involved in (as driver or as other driver)
+    pub async fn incidents_by_user(&self, user_id: i64) -> Vec<IncidentRow> {
+        let pool = self.0.pool().await;
+        match sqlx::query_as::<sqlx::Sqlite, IncidentRow>("SELECT rowid,* FROM session_incidents WHERE user = $1 OR other_user = $1 ORDER BY rowid;")
+            .bind(user_id)
+            .fetch_all(&pool)
+            .await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to read incidents of user {}: {}", user_id, e);
+                Vec::new()
+            }
+        }
+    }
+
+    async fn rows_by_user<R: DbRow>(&self, user_id: i64) -> Vec<R> {
+        let pool = self.0.pool().await;
+        match R::fetch_all_by(&pool, "user", user_id, 100000).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to read {} of user {}: {}", R::TABLENAME, user_id, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Delete a session, including all results, laps, penalties (also steward penalties) and incidents
+    pub async fn delete_session(&self, session: SessionItem) -> Result<(), SsloError> {
+        let session_display = session.display().await;
+        let pool = self.0.pool().await;
+        for mut row in session.fetch_rows::<ResultRow>().await {
+            row.delete(&pool).await?;
+        }
+        for mut row in session.fetch_rows::<LapRow>().await {
+            row.delete(&pool).await?;
+        }
+        for mut row in session.fetch_rows::<PenaltyRow>().await {
+            row.delete(&pool).await?;
+        }
+        for mut row in session.fetch_rows::<IncidentRow>().await {
+            row.delete(&pool).await?;
+        }
+        if let Some(db_data) = self.0.db().await.upgrade() {
+            let db = ResultsDbInterface::new(db_data);
+            let tbl_pro = db.tbl_protests().await;
+            for protest in tbl_pro.items_by_session(session.id().await).await {
+                tbl_pro.delete_protest(protest).await?;
+            }
+            let tbl_pen = db.tbl_penalties().await;
+            for penalty in tbl_pen.items_by_session(session.id().await).await {
+                tbl_pen.delete_penalty(penalty).await?;
+            }
+        }
+        self.0.delete_item(session).await?;
+        log::info!("deleted {}", session_display);
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    async fn get_db() -> ResultsDbInterface {
+        let data = ResultsDbData::new(None).await.unwrap();
+        ResultsDbInterface::new(data)
+    }
+
+    async fn create_session(tbl: &SessionTable, session_type: SessionType, file_name: &str, started: Option<DateTime<Utc>>) -> SessionItem {
+        tbl.create_session(Simulator::AssettoCorsa, session_type, "Server".to_string(), file_name.to_string(),
+                           "monza".to_string(), started).await.unwrap()
+    }
+
+    mod row {
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn new_defaults() {
+            let row = DbDataRow::new(33);
+            assert_eq!(row.rowid, 33);
+            assert_eq!(row.simulator, Simulator::AssettoCorsa);
+            assert_eq!(row.session_type, SessionType::Practice);
+            assert_eq!(row.track_layout, None);
+            assert!(!row.wet);
+            assert_eq!(row.started, None);
+        }
+
+        /// Testing load and store (insert+update)
+        #[test(tokio::test)]
+        async fn load_store() {
+            let pool = get_db().await.pool().await;
+            let dt1: DateTime<Utc> = DateTime::parse_from_rfc3339("1001-01-01T01:01:01.1111+01:00").unwrap().into();
+            let dt2: DateTime<Utc> = DateTime::parse_from_rfc3339("2002-02-02T02:02:02.2222+02:00").unwrap().into();
+
+            // store (insert)
+            let mut row = DbDataRow::new(0);
+            row.simulator = Simulator::RFactor2;
+            row.session_type = SessionType::Race;
+            row.server_name = "Server".to_string();
+            row.file_name = "race.xml".to_string();
+            row.track_identifier = "monza".to_string();
+            row.started = Some(dt1);
+            row.imported = dt2;
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.simulator, Simulator::RFactor2);
+            assert_eq!(row.session_type, SessionType::Race);
+            assert_eq!(row.server_name, "Server");
+            assert_eq!(row.file_name, "race.xml");
+            assert_eq!(row.track_identifier, "monza");
+            assert_eq!(row.track_layout, None);
+            assert!(!row.wet);
+            assert_eq!(row.started, Some(dt1));
+            assert_eq!(row.imported, dt2);
+
+            // store (update)
+            row.track_layout = Some(4);
+            row.wet = true;
+            row.started = None;
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.track_layout, Some(4));
+            assert!(row.wet);
+            assert_eq!(row.started, None);
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn items_by_type() {
+        let tbl = get_db().await.tbl_sessions().await;
+        let dt1: DateTime<Utc> = DateTime::parse_from_rfc3339("2025-04-14T20:00:00+00:00").unwrap().into();
+        let dt2: DateTime<Utc> = DateTime::parse_from_rfc3339("2025-04-15T20:00:00+00:00").unwrap().into();
+        create_session(&tbl, SessionType::Race, "race2.json", Some(dt2)).await;
+        create_session(&tbl, SessionType::Qualifying, "qualifying.json", Some(dt1)).await;
+        create_session(&tbl, SessionType::Race, "race1.json", Some(dt1)).await;
+
+        // chronological order
+        let races = tbl.items_by_type(SessionType::Race).await;
+        assert_eq!(races.len(), 2);
+        assert_eq!(races[0].started().await, Some(dt1));
+        assert_eq!(races[1].started().await, Some(dt2));
+        assert_eq!(tbl.items_by_type(SessionType::Qualifying).await.len(), 1);
+        assert!(tbl.items_by_type(SessionType::Practice).await.is_empty());
+    }
+
+    #[test(tokio::test)]
+    async fn rows_by_user() {
+        let tbl = get_db().await.tbl_sessions().await;
+        let session = create_session(&tbl, SessionType::Race, "race.json", None).await;
+        for (position, user) in [(1, Some(7)), (2, Some(8)), (3, None)] {
+            let mut row = ResultRow::new(0);
+            row.position = position;
+            row.user = user;
+            session.add_result(row).await.unwrap();
+            let mut row = LapRow::new(0);
+            row.user = user;
+            session.add_lap(row).await.unwrap();
+        }
+        let mut row = IncidentRow::new(0);
+        row.user = Some(7);
+        row.other_user = Some(8);
+        session.add_incident(row).await.unwrap();
+        let mut row = IncidentRow::new(0);
+        row.user = Some(8);
+        session.add_incident(row).await.unwrap();
+
+        // rows of a user
+        assert_eq!(tbl.results_by_user(7).await.len(), 1);
+        assert_eq!(tbl.results_by_user(7).await[0].session, session.id().await);
+        assert_eq!(tbl.laps_by_user(8).await.len(), 1);
+        assert!(tbl.penalties_by_user(7).await.is_empty());
+
+        // incidents as driver or as other driver
+        assert_eq!(tbl.incidents_by_user(7).await.len(), 1);
+        assert_eq!(tbl.incidents_by_user(8).await.len(), 2);
+        assert!(tbl.incidents_by_user(9).await.is_empty());
+    }
+}
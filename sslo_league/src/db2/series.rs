@@ -0,0 +1,250 @@
+pub mod championships;
+pub mod events;
+pub mod ical_tokens;
+
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use sslo_lib::db::{TableData, TableInterface};
+use sslo_lib::error::SsloError;
+use sqlx::{SqliteConnection, SqlitePool};
+use championships::{ChampionshipItem, ChampionshipTable};
+use events::{EventItem, EventTable};
+use ical_tokens::IcalTokenTable;
+
+/// The series database (championships, scheduled events, etc.)
+pub struct SeriesDbData {
+    pool: SqlitePool,
+    tbl_championships: Arc<RwLock<TableData<ChampionshipItem>>>,
+    tbl_events: Arc<RwLock<TableData<EventItem>>>,
+}
+
+impl SeriesDbData {
+    /// When db_path is None, the pool is generated in memory
+    pub(super) async fn new(db_path: Option<&Path>) -> Result<Arc<RwLock<Self>>, SsloError> {
+
+        // set up db
+        let pool = sslo_lib::db::get_pool(db_path);
+        sqlx::migrate!("../rsc/db_migrations/league_series").run(&pool).await?;
+
+        // create data object
+        Ok(Arc::new_cyclic(|me| {
+            RwLock::new(Self {
+                pool: pool.clone(),
+                tbl_championships: TableData::new(pool.clone(), me.clone()),
+                tbl_events: TableData::new(pool.clone(), me.clone()),
+            })
+        }))
+    }
+}
+
+pub struct SeriesDbInterface(Arc<RwLock<SeriesDbData>>);
+
+impl SeriesDbInterface {
+
+    pub(super) fn new(data: Arc<RwLock<SeriesDbData>>) -> Self {
+        Self(data)
+    }
+
+    pub async fn tbl_championships(&self) -> ChampionshipTable {
+        let data = self.0.read().await;
+        ChampionshipTable::new(TableInterface::new(data.tbl_championships.clone()))
+    }
+
+    pub async fn tbl_events(&self) -> EventTable {
+        let data = self.0.read().await;
+        EventTable::new(TableInterface::new(data.tbl_events.clone()))
+    }
+
+    pub async fn tbl_ical_tokens(&self) -> IcalTokenTable {
+        let data = self.0.read().await;
+        IcalTokenTable::new(data.pool.clone())
+    }
+
+    pub(super) async fn pool(&self) -> SqlitePool {
+        self.0.read().await.pool.clone()
+    }
+
+    /// Move all event registrations of a user to another user
+    /// The statements are executed within a transaction, where the series database is attached as 'series'.
+    /// When both users are registered for the same event, the registration of the target user is kept.
+    pub(super) async fn merge_users_within(tx: &mut SqliteConnection, from_id: i64, into_id: i64) -> Result<(), SsloError> {
+        for query in [
+            "DELETE FROM series.event_registrations WHERE user = $1 AND event IN (SELECT event FROM series.event_registrations WHERE user = $2);",
+            "UPDATE series.event_registrations SET user = $2 WHERE user = $1;",
+            "DELETE FROM series.ical_tokens WHERE user = $1;",
+        ] {
+            sqlx::query(query).bind(from_id).bind(into_id).execute(&mut *tx).await?;
+        }
+        Ok(())
+    }
+
+    /// Remove all event registrations and the calendar feed of a user (within a single transaction)
+    pub async fn anonymize_user(&self, user_id: i64) -> Result<(), SsloError> {
+        let data = self.0.read().await;
+        let mut tx = data.pool.begin().await?;
+        for query in [
+            "DELETE FROM event_registrations WHERE user = $1;",
+            "DELETE FROM ical_tokens WHERE user = $1;",
+        ] {
+            sqlx::query(query).bind(user_id).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use chrono::{Duration, Utc};
+    use sslo_lib::db::DbRow;
+    use crate::db2::content::Simulator;
+    use crate::db2::results::SessionType;
+    use events::{RegistrationStatus, SessionSchedule};
+
+    async fn get_db() -> SeriesDbInterface {
+        let data = SeriesDbData::new(None).await.unwrap();
+        SeriesDbInterface::new(data)
+    }
+
+    #[test(tokio::test)]
+    async fn championships() {
+        let db = get_db().await;
+        let tbl_chp = db.tbl_championships().await;
+
+        // create championship
+        let championship = tbl_chp.create_championship(" GT3 Cup ".to_string()).await.unwrap();
+        assert_eq!(championship.name().await, "GT3 Cup");
+        championship.set_drop_worst(1).await.unwrap();
+        championship.set_bonus_points(1, 2).await.unwrap();
+        assert_eq!(championship.points_fastest_lap().await, 2);
+
+        // points and classes are replaced
+        championship.set_points_table(&[10, 8, 6]).await.unwrap();
+        championship.set_points_table(&[25, 18]).await.unwrap();
+        assert_eq!(championship.points_table().await, vec![25, 18]);
+        championship.set_car_classes(&[3, 4, 3]).await.unwrap();
+        assert_eq!(championship.car_classes().await, vec![3, 4]);
+
+        // rounds are renumbered after deletion
+        let round1 = championship.create_round("Monza".to_string()).await.unwrap();
+        let mut round2 = championship.create_round("Spa".to_string()).await.unwrap();
+        let round3 = championship.create_round("Imola".to_string()).await.unwrap();
+        assert_eq!(round3.round_number, 3);
+        round2.race_session = Some(7);
+        championship.update_round(round2).await.unwrap();
+        championship.delete_round(round1.rowid()).await.unwrap();
+        let rounds = championship.rounds().await;
+        assert_eq!(rounds.len(), 2);
+        assert_eq!(rounds[0].name, "Spa");
+        assert_eq!(rounds[0].round_number, 1);
+        assert_eq!(rounds[0].race_session, Some(7));
+        assert_eq!(tbl_chp.round_by_id(round3.rowid()).await.unwrap().round_number, 2);
+
+        // delete
+        let id = championship.id().await;
+        tbl_chp.delete_championship(championship).await.unwrap();
+        assert!(tbl_chp.item_by_id(id).await.is_none());
+        assert!(tbl_chp.round_by_id(round3.rowid()).await.is_none());
+    }
+
+    #[test(tokio::test)]
+    async fn events() {
+        let db = get_db().await;
+        let tbl_evt = db.tbl_events().await;
+        let now = Utc::now();
+
+        // create event
+        let event = tbl_evt.create_event(" Monza Sprint ".to_string(), Simulator::AssettoCorsa).await.unwrap();
+        assert_eq!(event.name().await, "Monza Sprint");
+        assert!(event.start().await.is_none());
+        let race = SessionSchedule { start: now + Duration::days(2), duration_minutes: 60 };
+        let qualifying = SessionSchedule { start: now + Duration::days(1), duration_minutes: 15 };
+        event.set_session_schedule(SessionType::Race, Some(race)).await.unwrap();
+        event.set_session_schedule(SessionType::Qualifying, Some(qualifying)).await.unwrap();
+        assert_eq!(event.session_schedule(SessionType::Race).await, Some(race));
+        assert_eq!(event.session_schedule(SessionType::Practice).await, None);
+        assert_eq!(event.start().await, Some(qualifying.start));
+        assert_eq!(event.end().await, Some(race.end()));
+
+        // registration window closes at the first session
+        assert!(event.is_registration_open(now).await);
+        assert!(!event.is_registration_open(qualifying.start).await);
+        event.set_registration_window(Some(now + Duration::hours(1)), None).await.unwrap();
+        assert!(!event.is_registration_open(now).await);
+
+        // waitlist
+        event.set_max_grid(2).await.unwrap();
+        assert_eq!(event.register(11, None).await.unwrap(), RegistrationStatus::Grid);
+        assert_eq!(event.register(12, Some(5)).await.unwrap(), RegistrationStatus::Grid);
+        assert_eq!(event.register(13, None).await.unwrap(), RegistrationStatus::Waitlist(1));
+        assert_eq!(event.register(14, None).await.unwrap(), RegistrationStatus::Waitlist(2));
+        assert_eq!(event.register(13, Some(7)).await.unwrap(), RegistrationStatus::Waitlist(1));
+        assert_eq!(event.registrations().await.len(), 4);
+        event.unregister(11).await.unwrap();
+        assert!(event.unregister(11).await.is_err());
+        assert_eq!(event.registration_status(13).await, Some(RegistrationStatus::Grid));
+        assert_eq!(event.registration_status(14).await, Some(RegistrationStatus::Waitlist(1)));
+        assert_eq!(event.registration_status(11).await, None);
+        event.set_max_grid(0).await.unwrap();
+        assert_eq!(event.registration_status(14).await, Some(RegistrationStatus::Grid));
+
+        // sorting by start
+        let unscheduled = tbl_evt.create_event("Unscheduled".to_string(), Simulator::AssettoCorsa).await.unwrap();
+        let early = tbl_evt.create_event("Early".to_string(), Simulator::AssettoCorsa).await.unwrap();
+        early.set_session_schedule(SessionType::Race, Some(SessionSchedule { start: now, duration_minutes: 30 })).await.unwrap();
+        let mut names = Vec::new();
+        for event in tbl_evt.items_all().await.iter() {
+            names.push(event.name().await);
+        }
+        assert_eq!(names, vec!["Early", "Monza Sprint", "Unscheduled"]);
+
+        // delete
+        let id = event.id().await;
+        tbl_evt.delete_event(event).await.unwrap();
+        assert!(tbl_evt.item_by_id(id).await.is_none());
+        assert!(tbl_evt.item_by_id(unscheduled.id().await).await.is_some());
+    }
+
+    #[test(tokio::test)]
+    async fn ical_tokens() {
+        let db = get_db().await;
+        let tbl_ict = db.tbl_ical_tokens().await;
+
+        let token1 = tbl_ict.create(3).await.unwrap();
+        assert_eq!(tbl_ict.user_by_token(&token1).await, Some(3));
+        assert!(tbl_ict.user_by_token(&format!("{}0", token1)).await.is_none());
+        assert!(tbl_ict.user_by_token("invalid").await.is_none());
+        assert!(tbl_ict.creation(3).await.is_some());
+
+        // a new token revokes the old one
+        let token2 = tbl_ict.create(3).await.unwrap();
+        assert!(tbl_ict.user_by_token(&token1).await.is_none());
+        assert_eq!(tbl_ict.user_by_token(&token2).await, Some(3));
+
+        // revoke
+        assert!(tbl_ict.revoke(3).await.unwrap());
+        assert!(!tbl_ict.revoke(3).await.unwrap());
+        assert!(tbl_ict.user_by_token(&token2).await.is_none());
+        assert!(tbl_ict.creation(3).await.is_none());
+    }
+
+    #[test(tokio::test)]
+    async fn anonymize_user() {
+        let db = get_db().await;
+        let tbl_evt = db.tbl_events().await;
+        let event = tbl_evt.create_event("Monza".to_string(), Simulator::AssettoCorsa).await.unwrap();
+        event.register(3, Some(1)).await.unwrap();
+        event.register(4, Some(2)).await.unwrap();
+        db.tbl_ical_tokens().await.create(3).await.unwrap();
+        assert_eq!(tbl_evt.registrations_by_user(3).await.len(), 1);
+
+        db.anonymize_user(3).await.unwrap();
+        assert!(event.registration_status(3).await.is_none());
+        assert!(event.registration_status(4).await.is_some());
+        assert!(tbl_evt.registrations_by_user(3).await.is_empty());
+        assert!(db.tbl_ical_tokens().await.creation(3).await.is_none());
+    }
+}
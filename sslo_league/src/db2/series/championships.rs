@@ -0,0 +1,318 @@
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use sslo_lib::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use sslo_lib::error::SsloError;
+use super::SeriesDbData;
+
+sslo_lib::db_row! {
+    tablename: "championships",
+    pub struct DbDataRow {
+        name: String = String::new(),
+        description: String = String::new(),
+        drop_worst: i64 = 0,
+        points_pole: i64 = 0,
+        points_fastest_lap: i64 = 0,
+    }
+    display: |row| format!("championships(id={};name={})", row.rowid, row.name),
+    sanitize: |row| {
+        row.name = row.name.trim().to_string();
+        row.drop_worst = row.drop_worst.max(0);
+    },
+}
+
+sslo_lib::db_row! {
+    tablename: "championship_car_classes",
+    /// A car class that is allowed in a championship
+    pub struct CarClassRow {
+        pub championship: i64 = 0,
+        pub car_class: i64 = 0,
+    }
+}
+
+sslo_lib::db_row! {
+    tablename: "championship_points",
+    /// The points for a finishing position
+    pub struct PointsRow {
+        pub championship: i64 = 0,
+        pub position: i64 = 0,
+        pub points: i64 = 0,
+    }
+}
+
+sslo_lib::db_row! {
+    tablename: "championship_rounds",
+    /// A round of a championship with the sessions that are scored
+    pub struct RoundRow {
+        pub championship: i64 = 0,
+        pub round_number: i64 = 0,
+        pub name: String = String::new(),
+        pub race_session: Option<i64> = None,
+        pub qualifying_session: Option<i64> = None,
+    }
+    sanitize: |row| {
+        row.name = row.name.trim().to_string();
+    },
+}
+
+/// This abstracts data access to shared items
+#[derive(Clone)]
+pub struct ChampionshipItem(Arc<RwLock<ItemData<DbDataRow, SeriesDbData>>>);
+
+impl ItemInterface for ChampionshipItem {
+    type Row = DbDataRow;
+    type Db = SeriesDbData;
+    type Extra = ();
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, SeriesDbData>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, SeriesDbData>>> {
+        &self.0
+    }
+}
+
+impl ChampionshipItem {
+
+    /// Returns a string, that can be used in log messages
+    pub async fn display(&self) -> String {
+        self.0.read().await.display()
+    }
+
+    pub async fn id(&self) -> i64 { self.0.read().await.row.rowid }
+
+    pub async fn name(&self) -> String { self.0.read().await.row.name.clone() }
+
+    pub async fn html_name(&self) -> String {
+        let mut html = String::new();
+        html_escape::encode_safe_to_string(&self.0.read().await.row.name, &mut html);
+        html
+    }
+
+    pub async fn set_name(&self, name: String) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.name = name;
+        data.store().await
+    }
+
+    pub async fn description(&self) -> String { self.0.read().await.row.description.clone() }
+
+    pub async fn set_description(&self, description: String) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.description = description;
+        data.store().await
+    }
+
+    /// Amount of worst round results that are not counted for the standings
+    pub async fn drop_worst(&self) -> i64 { self.0.read().await.row.drop_worst }
+
+    pub async fn set_drop_worst(&self, drop_worst: i64) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.drop_worst = drop_worst;
+        data.store().await
+    }
+
+    /// Extra points for the best qualifying position
+    pub async fn points_pole(&self) -> i64 { self.0.read().await.row.points_pole }
+
+    /// Extra points for the fastest race lap
+    pub async fn points_fastest_lap(&self) -> i64 { self.0.read().await.row.points_fastest_lap }
+
+    pub async fn set_bonus_points(&self, points_pole: i64, points_fastest_lap: i64) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.points_pole = points_pole;
+        data.row.points_fastest_lap = points_fastest_lap;
+        data.store().await
+    }
+
+    /// Read all rows of a sub-table that belong to this championship
+    async fn fetch_rows<R: DbRow>(&self) -> Vec<R> {
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Vec::new(),
+        };
+        match R::fetch_all_by(pool, "championship", data.row.rowid, 1000).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to read {} for {}: {}", R::TABLENAME, data.display(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Replace all rows of a sub-table that belong to this championship
+    async fn replace_rows<R: DbRow>(&self, rows: Vec<R>) -> Result<(), SsloError> {
+        let old_rows: Vec<R> = self.fetch_rows().await;
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Ok(()),
+        };
+        for mut row in old_rows.into_iter() {
+            row.delete(pool).await?;
+        }
+        for mut row in rows.into_iter() {
+            row.store(pool).await?;
+        }
+        Ok(())
+    }
+
+    /// IDs of the car classes that are allowed (all classes are allowed if empty)
+    pub async fn car_classes(&self) -> Vec<i64> {
+        self.fetch_rows::<CarClassRow>().await.into_iter().map(|r| r.car_class).collect()
+    }
+
+    pub async fn set_car_classes(&self, car_classes: &[i64]) -> Result<(), SsloError> {
+        let id = self.id().await;
+        let mut rows = Vec::new();
+        for car_class in car_classes.iter() {
+            if rows.iter().any(|r: &CarClassRow| r.car_class == *car_class) {
+                continue;
+            }
+            let mut row = CarClassRow::new(0);
+            row.championship = id;
+            row.car_class = *car_class;
+            rows.push(row);
+        }
+        self.replace_rows(rows).await
+    }
+
+    /// The points for the finishing positions (the first element is for the winner)
+    pub async fn points_table(&self) -> Vec<i64> {
+        let mut rows: Vec<PointsRow> = self.fetch_rows().await;
+        rows.sort_by_key(|r| r.position);
+        rows.into_iter().map(|r| r.points).collect()
+    }
+
+    pub async fn set_points_table(&self, points: &[i64]) -> Result<(), SsloError> {
+        let id = self.id().await;
+        let rows = points.iter().enumerate().map(|(index, points)| {
+            let mut row = PointsRow::new(0);
+            row.championship = id;
+            row.position = index as i64 + 1;
+            row.points = *points;
+            row
+        }).collect();
+        self.replace_rows(rows).await
+    }
+
+    /// All rounds (sorted by round number)
+    pub async fn rounds(&self) -> Vec<RoundRow> {
+        let mut rows: Vec<RoundRow> = self.fetch_rows().await;
+        rows.sort_by_key(|r| r.round_number);
+        rows
+    }
+
+    /// Append a new round
+    pub async fn create_round(&self, name: String) -> Result<RoundRow, SsloError> {
+        let round_number = self.rounds().await.last().map(|r| r.round_number).unwrap_or(0) + 1;
+        let data = self.0.read().await;
+        let mut row = RoundRow::new(0);
+        row.championship = data.row.rowid;
+        row.round_number = round_number;
+        row.name = name;
+        if let Some(pool) = data.pool.as_ref() {
+            row.store(pool).await?;
+        }
+        log::info!("new round {} for {}", round_number, data.display());
+        Ok(row)
+    }
+
+    /// Store a changed round
+    pub async fn update_round(&self, mut round: RoundRow) -> Result<(), SsloError> {
+        let data = self.0.read().await;
+        if round.championship != data.row.rowid {
+            return Err(SsloError::DatabaseIdNotFound(RoundRow::TABLENAME, "rowid", round.rowid()));
+        }
+        match data.pool.as_ref() {
+            Some(pool) => round.store(pool).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Delete a round (the following rounds are renumbered)
+    pub async fn delete_round(&self, round_id: i64) -> Result<(), SsloError> {
+        let rounds = self.rounds().await;
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Ok(()),
+        };
+        let mut round_number = 1;
+        let mut found = false;
+        for mut round in rounds.into_iter() {
+            if round.rowid() == round_id {
+                round.delete(pool).await?;
+                found = true;
+            } else {
+                if round.round_number != round_number {
+                    round.round_number = round_number;
+                    round.store(pool).await?;
+                }
+                round_number += 1;
+            }
+        }
+        if !found {
+            return Err(SsloError::DatabaseIdNotFound(RoundRow::TABLENAME, "rowid", round_id));
+        }
+        Ok(())
+    }
+}
+
+pub struct ChampionshipTable(TableInterface<ChampionshipItem>);
+
+impl ChampionshipTable {
+    pub(super) fn new(tbl: TableInterface<ChampionshipItem>) -> Self { Self(tbl) }
+
+    /// Create a new championship
+    pub async fn create_championship(&self, name: String) -> Option<ChampionshipItem> {
+        let mut row = DbDataRow::new(0);
+        row.name = name;
+        match self.0.create_item(row).await {
+            Ok(item) => {
+                log::info!("new championship created: {}", item.display().await);
+                Some(item)
+            },
+            Err(e) => {
+                log::error!("Could not create new championship: {}", e);
+                None
+            }
+        }
+    }
+
+    pub async fn item_by_id(&self, id: i64) -> Option<ChampionshipItem> {
+        self.0.item_by_id(id).await
+    }
+
+    /// All championships (latest first)
+    pub async fn items_all(&self) -> Vec<ChampionshipItem> {
+        let mut items = self.0.items_all().await;
+        items.reverse();
+        items
+    }
+
+    /// Find a round by its ID
+    pub async fn round_by_id(&self, round_id: i64) -> Option<RoundRow> {
+        let pool = self.0.pool().await;
+        let mut row = RoundRow::new(round_id);
+        match row.load(&pool).await {
+            Ok(_) => Some(row),
+            Err(e) => {
+                log::warn!("{}", e);
+                None
+            }
+        }
+    }
+
+    /// Delete a championship, including its rounds, points and car classes
+    pub async fn delete_championship(&self, championship: ChampionshipItem) -> Result<(), SsloError> {
+        let championship_display = championship.display().await;
+        championship.replace_rows::<CarClassRow>(Vec::new()).await?;
+        championship.replace_rows::<PointsRow>(Vec::new()).await?;
+        championship.replace_rows::<RoundRow>(Vec::new()).await?;
+        self.0.delete_item(championship).await?;
+        log::info!("deleted {}", championship_display);
+        Ok(())
+    }
+}
@@ -0,0 +1,411 @@
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::RwLock;
+use sslo_lib::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use sslo_lib::error::SsloError;
+use crate::db2::content::Simulator;
+use crate::db2::results::SessionType;
+use super::SeriesDbData;
+
+sslo_lib::db_row! {
+    tablename: "events",
+    pub struct DbDataRow {
+        name: String = String::new(),
+        description: String = String::new(),
+        simulator: Simulator = Simulator::AssettoCorsaCompetizione,
+        track_layout: Option<i64> = None,
+        server: String = String::new(),
+        practice_start: Option<DateTime<Utc>> = None,
+        practice_duration: i64 = 0,
+        qualifying_start: Option<DateTime<Utc>> = None,
+        qualifying_duration: i64 = 0,
+        race_start: Option<DateTime<Utc>> = None,
+        race_duration: i64 = 0,
+        registration_opens: Option<DateTime<Utc>> = None,
+        registration_closes: Option<DateTime<Utc>> = None,
+        max_grid: i64 = 0,
+    }
+    display: |row| format!("events(id={};name={})", row.rowid, row.name),
+    sanitize: |row| {
+        row.name = row.name.trim().to_string();
+        row.server = row.server.trim().to_string();
+        row.practice_duration = row.practice_duration.max(0);
+        row.qualifying_duration = row.qualifying_duration.max(0);
+        row.race_duration = row.race_duration.max(0);
+        row.max_grid = row.max_grid.max(0);
+    },
+}
+
+sslo_lib::db_row! {
+    tablename: "event_car_classes",
+    /// A car class that is allowed at an event
+    pub struct CarClassRow {
+        pub event: i64 = 0,
+        pub car_class: i64 = 0,
+    }
+}
+
+sslo_lib::db_row! {
+    tablename: "event_registrations",
+    /// The sign-up of a driver for an event
+    pub struct RegistrationRow {
+        pub event: i64 = 0,
+        pub user: i64 = 0,
+        pub car: Option<i64> = None,
+        pub registered: DateTime<Utc> = Utc::now(),
+    }
+}
+
+/// The planned timing of a session
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SessionSchedule {
+    pub start: DateTime<Utc>,
+    pub duration_minutes: i64,
+}
+
+impl SessionSchedule {
+    pub fn end(&self) -> DateTime<Utc> {
+        self.start + Duration::minutes(self.duration_minutes)
+    }
+}
+
+/// Where a registered driver is placed
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RegistrationStatus {
+    Grid,
+
+    /// position on the waitlist (starting at 1)
+    Waitlist(usize),
+}
+
+impl RegistrationStatus {
+    pub fn label(&self) -> String {
+        match self {
+            RegistrationStatus::Grid => "Grid".to_string(),
+            RegistrationStatus::Waitlist(position) => format!("Waitlist #{}", position),
+        }
+    }
+}
+
+/// This abstracts data access to shared items
+#[derive(Clone)]
+pub struct EventItem(Arc<RwLock<ItemData<DbDataRow, SeriesDbData>>>);
+
+impl ItemInterface for EventItem {
+    type Row = DbDataRow;
+    type Db = SeriesDbData;
+    type Extra = ();
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, SeriesDbData>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, SeriesDbData>>> {
+        &self.0
+    }
+}
+
+impl EventItem {
+
+    /// Returns a string, that can be used in log messages
+    pub async fn display(&self) -> String {
+        self.0.read().await.display()
+    }
+
+    pub async fn id(&self) -> i64 { self.0.read().await.row.rowid }
+
+    pub async fn name(&self) -> String { self.0.read().await.row.name.clone() }
+
+    pub async fn html_name(&self) -> String {
+        let mut html = String::new();
+        html_escape::encode_safe_to_string(&self.0.read().await.row.name, &mut html);
+        html
+    }
+
+    pub async fn set_name(&self, name: String) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.name = name;
+        data.store().await
+    }
+
+    pub async fn description(&self) -> String { self.0.read().await.row.description.clone() }
+
+    pub async fn set_description(&self, description: String) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.description = description;
+        data.store().await
+    }
+
+    pub async fn simulator(&self) -> Simulator { self.0.read().await.row.simulator }
+
+    pub async fn set_simulator(&self, simulator: Simulator) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.simulator = simulator;
+        data.store().await
+    }
+
+    /// ID of the track layout in the content database
+    pub async fn track_layout(&self) -> Option<i64> { self.0.read().await.row.track_layout }
+
+    pub async fn set_track_layout(&self, track_layout: Option<i64>) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.track_layout = track_layout;
+        data.store().await
+    }
+
+    /// Name of the simulator server that hosts the event
+    pub async fn server(&self) -> String { self.0.read().await.row.server.clone() }
+
+    pub async fn set_server(&self, server: String) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.server = server;
+        data.store().await
+    }
+
+    /// The planned timing of a session (None if the session is not held)
+    pub async fn session_schedule(&self, session_type: SessionType) -> Option<SessionSchedule> {
+        let data = self.0.read().await;
+        let (start, duration_minutes) = match session_type {
+            SessionType::Practice => (data.row.practice_start, data.row.practice_duration),
+            SessionType::Qualifying => (data.row.qualifying_start, data.row.qualifying_duration),
+            SessionType::Race => (data.row.race_start, data.row.race_duration),
+        };
+        start.map(|start| SessionSchedule { start, duration_minutes })
+    }
+
+    pub async fn set_session_schedule(&self, session_type: SessionType, schedule: Option<SessionSchedule>) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        let start = schedule.map(|s| s.start);
+        let duration_minutes = schedule.map(|s| s.duration_minutes).unwrap_or(0);
+        match session_type {
+            SessionType::Practice => (data.row.practice_start, data.row.practice_duration) = (start, duration_minutes),
+            SessionType::Qualifying => (data.row.qualifying_start, data.row.qualifying_duration) = (start, duration_minutes),
+            SessionType::Race => (data.row.race_start, data.row.race_duration) = (start, duration_minutes),
+        }
+        data.store().await
+    }
+
+    /// The begin of the first scheduled session
+    pub async fn start(&self) -> Option<DateTime<Utc>> {
+        let data = self.0.read().await;
+        [data.row.practice_start, data.row.qualifying_start, data.row.race_start].into_iter().flatten().min()
+    }
+
+    /// The end of the last scheduled session
+    pub async fn end(&self) -> Option<DateTime<Utc>> {
+        let mut end = None;
+        for session_type in [SessionType::Practice, SessionType::Qualifying, SessionType::Race] {
+            if let Some(schedule) = self.session_schedule(session_type).await {
+                end = end.max(Some(schedule.end()));
+            }
+        }
+        end
+    }
+
+    /// When the registration opens and closes
+    pub async fn registration_window(&self) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+        let data = self.0.read().await;
+        (data.row.registration_opens, data.row.registration_closes)
+    }
+
+    pub async fn set_registration_window(&self, opens: Option<DateTime<Utc>>, closes: Option<DateTime<Utc>>) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.registration_opens = opens;
+        data.row.registration_closes = closes;
+        data.store().await
+    }
+
+    /// Check if drivers can sign up (registration closes at the first session, if not defined otherwise)
+    pub async fn is_registration_open(&self, now: DateTime<Utc>) -> bool {
+        let (opens, closes) = self.registration_window().await;
+        let closes = match closes {
+            Some(closes) => Some(closes),
+            None => self.start().await,
+        };
+        opens.is_none_or(|opens| opens <= now) && closes.is_none_or(|closes| now < closes)
+    }
+
+    /// Maximum amount of drivers on the grid (0 for unlimited)
+    pub async fn max_grid(&self) -> i64 { self.0.read().await.row.max_grid }
+
+    pub async fn set_max_grid(&self, max_grid: i64) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.max_grid = max_grid;
+        data.store().await
+    }
+
+    /// Read all rows of a sub-table that belong to this event
+    async fn fetch_rows<R: DbRow>(&self) -> Vec<R> {
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Vec::new(),
+        };
+        match R::fetch_all_by(pool, "event", data.row.rowid, 10000).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to read {} for {}: {}", R::TABLENAME, data.display(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Replace all rows of a sub-table that belong to this event
+    async fn replace_rows<R: DbRow>(&self, rows: Vec<R>) -> Result<(), SsloError> {
+        let old_rows: Vec<R> = self.fetch_rows().await;
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Ok(()),
+        };
+        for mut row in old_rows.into_iter() {
+            row.delete(pool).await?;
+        }
+        for mut row in rows.into_iter() {
+            row.store(pool).await?;
+        }
+        Ok(())
+    }
+
+    /// IDs of the car classes that are allowed (all classes are allowed if empty)
+    pub async fn car_classes(&self) -> Vec<i64> {
+        self.fetch_rows::<CarClassRow>().await.into_iter().map(|r| r.car_class).collect()
+    }
+
+    pub async fn set_car_classes(&self, car_classes: &[i64]) -> Result<(), SsloError> {
+        let id = self.id().await;
+        let mut rows = Vec::new();
+        for car_class in car_classes.iter() {
+            if rows.iter().any(|r: &CarClassRow| r.car_class == *car_class) {
+                continue;
+            }
+            let mut row = CarClassRow::new(0);
+            row.event = id;
+            row.car_class = *car_class;
+            rows.push(row);
+        }
+        self.replace_rows(rows).await
+    }
+
+    /// All registrations in the order of sign-up
+    pub async fn registrations(&self) -> Vec<RegistrationRow> {
+        let mut rows: Vec<RegistrationRow> = self.fetch_rows().await;
+        rows.sort_by(|a, b| a.registered.cmp(&b.registered).then(a.rowid().cmp(&b.rowid())));
+        rows
+    }
+
+    /// The registrations that are on the grid and the ones on the waitlist
+    pub async fn grid_and_waitlist(&self) -> (Vec<RegistrationRow>, Vec<RegistrationRow>) {
+        let mut grid = self.registrations().await;
+        let max_grid = self.max_grid().await as usize;
+        let waitlist = match max_grid {
+            0 => Vec::new(),
+            _ => grid.split_off(max_grid.min(grid.len())),
+        };
+        (grid, waitlist)
+    }
+
+    /// Where a user is placed (None if not registered)
+    pub async fn registration_status(&self, user_id: i64) -> Option<RegistrationStatus> {
+        let (grid, waitlist) = self.grid_and_waitlist().await;
+        if grid.iter().any(|r| r.user == user_id) {
+            return Some(RegistrationStatus::Grid);
+        }
+        waitlist.iter().position(|r| r.user == user_id).map(|index| RegistrationStatus::Waitlist(index + 1))
+    }
+
+    /// Sign up a user (the car may be changed by registering again)
+    pub async fn register(&self, user_id: i64, car: Option<i64>) -> Result<RegistrationStatus, SsloError> {
+        let mut row = self.registrations().await.into_iter()
+            .find(|r| r.user == user_id)
+            .unwrap_or_else(|| RegistrationRow::new(0));
+        {
+            let data = self.0.read().await;
+            row.event = data.row.rowid;
+            row.user = user_id;
+            row.car = car;
+            if let Some(pool) = data.pool.as_ref() {
+                row.store(pool).await?;
+            }
+            log::info!("user {} registered for {}", user_id, data.display());
+        }
+        self.registration_status(user_id).await
+            .ok_or(SsloError::DatabaseIdNotFound(RegistrationRow::TABLENAME, "user", user_id))
+    }
+
+    /// Withdraw the registration of a user (the first driver on the waitlist moves up)
+    pub async fn unregister(&self, user_id: i64) -> Result<(), SsloError> {
+        let mut row = match self.registrations().await.into_iter().find(|r| r.user == user_id) {
+            Some(row) => row,
+            None => return Err(SsloError::DatabaseIdNotFound(RegistrationRow::TABLENAME, "user", user_id)),
+        };
+        let data = self.0.read().await;
+        if let Some(pool) = data.pool.as_ref() {
+            row.delete(pool).await?;
+        }
+        log::info!("user {} unregistered from {}", user_id, data.display());
+        Ok(())
+    }
+}
+
+pub struct EventTable(TableInterface<EventItem>);
+
+impl EventTable {
+    pub(super) fn new(tbl: TableInterface<EventItem>) -> Self { Self(tbl) }
+
+    /// Create a new event
+    pub async fn create_event(&self, name: String, simulator: Simulator) -> Option<EventItem> {
+        let mut row = DbDataRow::new(0);
+        row.name = name;
+        row.simulator = simulator;
+        match self.0.create_item(row).await {
+            Ok(item) => {
+                log::info!("new event created: {}", item.display().await);
+                Some(item)
+            },
+            Err(e) => {
+                log::error!("Could not create new event: {}", e);
+                None
+            }
+        }
+    }
+
+    pub async fn item_by_id(&self, id: i64) -> Option<EventItem> {
+        self.0.item_by_id(id).await
+    }
+
+    /// All events, sorted by start (unscheduled events last)
+    pub async fn items_all(&self) -> Vec<EventItem> {
+        let mut events = Vec::new();
+        for item in self.0.items_all().await.into_iter() {
+            events.push((item.start().await, item));
+        }
+        events.sort_by(|(a, _), (b, _)| match (a, b) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (a, b) => b.cmp(a),
+        });
+        events.into_iter().map(|(_, item)| item).collect()
+    }
+
+    /// All registrations of a user (over all events)
+    pub async fn registrations_by_user(&self, user_id: i64) -> Vec<RegistrationRow> {
+        let pool = self.0.pool().await;
+        match RegistrationRow::fetch_all_by(&pool, "user", user_id, 100000).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to read registrations of user {}: {}", user_id, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Delete an event, including its registrations
+    pub async fn delete_event(&self, event: EventItem) -> Result<(), SsloError> {
+        let event_display = event.display().await;
+        event.replace_rows::<CarClassRow>(Vec::new()).await?;
+        event.replace_rows::<RegistrationRow>(Vec::new()).await?;
+        self.0.delete_item(event).await?;
+        log::info!("deleted {}", event_display);
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use sslo_lib::db::DbRow;
+use sslo_lib::error::SsloError;
+use sslo_lib::token::{Token, TokenType};
+
+sslo_lib::db_row! {
+    tablename: "ical_tokens",
+    /// The secret token of a personal calendar feed (only the encrypted token is stored)
+    pub struct IcalTokenRow {
+        pub user: i64 = 0,
+        pub token: String = String::new(),
+        pub creation: DateTime<Utc> = Utc::now(),
+    }
+}
+
+/// Access to the tokens of the personal calendar feeds
+/// A feed token has the format '<rowid>-<decrypted token>' and is only known directly after creation.
+pub struct IcalTokenTable(SqlitePool);
+
+impl IcalTokenTable {
+    pub(super) fn new(pool: SqlitePool) -> Self { Self(pool) }
+
+    async fn row_by_user(&self, user_id: i64) -> Option<IcalTokenRow> {
+        match IcalTokenRow::fetch_one_by(&self.0, "user", user_id).await {
+            Ok(row) => Some(row),
+            Err(e) if e.is_db_not_found_type() => None,
+            Err(e) => {
+                log::error!("failed to read calendar token of user {}: {}", user_id, e);
+                None
+            }
+        }
+    }
+
+    /// When the current token of a user was created (None if the user has no token)
+    pub async fn creation(&self, user_id: i64) -> Option<DateTime<Utc>> {
+        self.row_by_user(user_id).await.map(|row| row.creation)
+    }
+
+    /// Create a new token for a user (an existing token is revoked)
+    pub async fn create(&self, user_id: i64) -> Result<String, SsloError> {
+        self.revoke(user_id).await?;
+        let token = Token::generate(TokenType::Quick)
+            .map_err(|e| SsloError::GeneralError(format!("failed to generate calendar token: {}", e)))?;
+        let mut row = IcalTokenRow::new(0);
+        row.user = user_id;
+        row.token = token.encrypted;
+        row.creation = Utc::now();
+        row.store(&self.0).await?;
+        log::info!("new calendar token for user {}", user_id);
+        Ok(format!("{}-{}", row.rowid(), token.decrypted))
+    }
+
+    /// Revoke the token of a user (returns false if the user had no token)
+    pub async fn revoke(&self, user_id: i64) -> Result<bool, SsloError> {
+        match self.row_by_user(user_id).await {
+            Some(mut row) => {
+                row.delete(&self.0).await?;
+                log::info!("revoked calendar token of user {}", user_id);
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+
+    /// Verify a feed token and return the user ID
+    pub async fn user_by_token(&self, feed_token: &str) -> Option<i64> {
+        let (rowid, decrypted) = feed_token.split_once('-')?;
+        let mut row = IcalTokenRow::new(rowid.parse::<i64>().ok()?);
+        if let Err(e) = row.load(&self.0).await {
+            log::warn!("{}", e);
+            return None;
+        }
+        match Token::new(decrypted.to_string(), row.token.clone()).verify() {
+            true => Some(row.user),
+            false => {
+                log::warn!("failed to verify calendar token for {}", row.display());
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use serde::Deserialize;
+use sslo_lib::error::SsloError;
+use crate::config::Discord;
+
+/// The OAuth2 scope that is requested (only the identity of the user)
+pub const SCOPE: &str = "identify";
+
+/// A Discord user as returned by the users/@me endpoint
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct DiscordUser {
+    pub id: String,
+    pub username: String,
+    pub global_name: Option<String>,
+}
+
+impl DiscordUser {
+
+    /// The name that is shown in Discord
+    pub fn display_name(&self) -> &str {
+        self.global_name.as_deref().unwrap_or(&self.username)
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+fn request_failed(url: &str, description: impl std::fmt::Display) -> SsloError {
+    SsloError::OAuthRequestFailed(url.to_string(), description.to_string())
+}
+
+/// The URL where the user shall be forwarded to, to authorize the league
+pub fn authorize_url(cfg: &Discord, redirect_uri: &str, state: &str) -> Result<String, SsloError> {
+    let url = reqwest::Url::parse_with_params(&cfg.authorize_url, &[
+        ("response_type", "code"),
+        ("client_id", cfg.client_id.as_str()),
+        ("scope", SCOPE),
+        ("state", state),
+        ("redirect_uri", redirect_uri),
+        ("prompt", "none"),
+    ]).map_err(|e| request_failed(&cfg.authorize_url, e))?;
+    Ok(url.to_string())
+}
+
+/// Exchange an authorization code into an access token
+pub async fn exchange_code(cfg: &Discord, code: &str, redirect_uri: &str) -> Result<String, SsloError> {
+    let response = reqwest::Client::new()
+        .post(&cfg.token_url)
+        .basic_auth(&cfg.client_id, Some(&cfg.client_secret))
+        .form(&[("grant_type", "authorization_code"), ("code", code), ("redirect_uri", redirect_uri)])
+        .send()
+        .await
+        .map_err(|e| request_failed(&cfg.token_url, e))?;
+    if !response.status().is_success() {
+        return Err(request_failed(&cfg.token_url, format!("status {}", response.status())));
+    }
+    let token: TokenResponse = response.json().await.map_err(|e| request_failed(&cfg.token_url, e))?;
+    Ok(token.access_token)
+}
+
+/// Request the user that authorized an access token
+pub async fn fetch_user(cfg: &Discord, access_token: &str) -> Result<DiscordUser, SsloError> {
+    let url = format!("{}/users/@me", cfg.api_url);
+    let response = reqwest::Client::new()
+        .get(&url)
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .map_err(|e| request_failed(&url, e))?;
+    if !response.status().is_success() {
+        return Err(request_failed(&url, format!("status {}", response.status())));
+    }
+    response.json().await.map_err(|e| request_failed(&url, e))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use axum::{routing, Form, Json, Router};
+    use axum::http::{HeaderMap, StatusCode};
+    use std::collections::HashMap;
+
+    const REDIRECT_URI: &str = "https://league.tld/html/login_discord_verify";
+
+    /// A local server that behaves like the Discord OAuth2 endpoints
+    async fn mock_server() -> Discord {
+        let app = Router::new()
+            .route("/oauth2/token", routing::post(|headers: HeaderMap, Form(form): Form<HashMap<String, String>>| async move {
+                // "client:secret" in basic auth
+                let authorized = headers.get("authorization").and_then(|h| h.to_str().ok()) == Some("Basic Y2xpZW50OnNlY3JldA==");
+                if !authorized || form.get("code").map(String::as_str) != Some("valid_code")
+                    || form.get("redirect_uri").map(String::as_str) != Some(REDIRECT_URI) {
+                    return Err(StatusCode::BAD_REQUEST);
+                }
+                Ok(Json(serde_json::json!({"access_token": "token123", "token_type": "Bearer", "expires_in": 604800, "scope": "identify"})))
+            }))
+            .route("/api/users/@me", routing::get(|headers: HeaderMap| async move {
+                if headers.get("authorization").and_then(|h| h.to_str().ok()) != Some("Bearer token123") {
+                    return Err(StatusCode::UNAUTHORIZED);
+                }
+                Ok(Json(serde_json::json!({"id": "80351110224678912", "username": "nelly", "global_name": "Nelly", "discriminator": "0"})))
+            }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        Discord {
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            authorize_url: format!("http://{}/oauth2/authorize", address),
+            token_url: format!("http://{}/oauth2/token", address),
+            api_url: format!("http://{}/api", address),
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn authorization() {
+        let cfg = mock_server().await;
+        let url = authorize_url(&cfg, REDIRECT_URI, "abc").unwrap();
+        assert!(url.starts_with(&cfg.authorize_url));
+        assert!(url.contains("client_id=client"));
+        assert!(url.contains("state=abc"));
+        assert!(url.contains("redirect_uri=https%3A%2F%2Fleague.tld%2Fhtml%2Flogin_discord_verify"));
+    }
+
+    #[test(tokio::test)]
+    async fn code_flow() {
+        let cfg = mock_server().await;
+        let token = exchange_code(&cfg, "valid_code", REDIRECT_URI).await.unwrap();
+        let user = fetch_user(&cfg, &token).await.unwrap();
+        assert_eq!(user.id, "80351110224678912");
+        assert_eq!(user.username, "nelly");
+        assert_eq!(user.display_name(), "Nelly");
+
+        // invalid requests
+        assert!(exchange_code(&cfg, "invalid_code", REDIRECT_URI).await.is_err());
+        assert!(exchange_code(&cfg, "valid_code", "https://other.tld/").await.is_err());
+        assert!(fetch_user(&cfg, "invalid_token").await.is_err());
+        let mut cfg_wrong_secret = cfg.clone();
+        cfg_wrong_secret.client_secret = "wrong".to_string();
+        assert!(exchange_code(&cfg_wrong_secret, "valid_code", REDIRECT_URI).await.is_err());
+    }
+}
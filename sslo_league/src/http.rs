@@ -4,11 +4,12 @@ use axum::handler::HandlerWithoutStateExt;
 use axum::http::{StatusCode, Uri};
 use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum::{routing, Router};
-use sslo_lib::http_routes::static_resources;
+use sslo_lib::http::route_handler_static_resources;
 use sslo_lib::http::FrontendMessage;
 use crate::app_state::AppState;
 
 mod routes_html;
+mod routes_ical;
 mod routes_rest_v0;
 mod http_user;
 
@@ -135,6 +136,7 @@ impl HtmlTemplate {
         html += "                  <a href=\"/html/ranking\">Driver Ranking</a>";
         html += "                  <a href=\"/html/schedules\">Scheduled Races</a>";
         html += "                  <a href=\"/html/championships\">Championships</a>";
+        html += "                  <a href=\"/html/protests\">Protests</a>";
         html += "              </div>";
         html += "          </div>";
         html += "          <div class=\"NavbarDropdown\">";
@@ -143,7 +145,7 @@ impl HtmlTemplate {
         html += "                  <a href=\"/html/users\">Users</a>";
         html += "                  <a href=\"/html/tracks\">Tracks</a>";
         html += "                  <a href=\"/html/cars\">Cars</a>";
-        html += "                  <a href=\"/html/cars\">Car Classes</a>";
+        html += "                  <a href=\"/html/car_classes\">Car Classes</a>";
         html += "              </div>";
         html += "          </div>";
         if self.http_user.is_logged_in() {
@@ -207,7 +209,7 @@ impl HtmlTemplate {
 
 pub fn create_router(app_state: AppState) -> Router {
     let router = Router::new()
-        .route("/rsc/*filepath", routing::get(static_resources::route_handler))
+        .route("/rsc/*filepath", routing::get(route_handler_static_resources))
 
         .route("/", routing::get(routes_html::home::handler))
 
@@ -218,16 +220,89 @@ pub fn create_router(app_state: AppState) -> Router {
         .route("/html/login_steam_create", routing::get(routes_html::login::handler_steam_create))
         .route("/html/login_steam_existing", routing::get(routes_html::login::handler_steam_existing))
         .route("/html/login_steam_assign", routing::get(routes_html::login::handler_steam_assign))
+        .route("/html/login_discord/:action", routing::get(routes_html::login::handler_discord))
+        .route("/html/login_discord_verify", routing::get(routes_html::login::handler_discord_verify))
+        .route("/html/login_password_reset/:email_account_id/:token", routing::get(routes_html::login::handler_password_reset))
         .route("/html/logout", routing::get(routes_html::login::handler_logout))
 
         .route("/html/user_profile", routing::get(routes_html::user::handler_profile))
         .route("/html/user/accounts", routing::get(routes_html::user::accounts::handler))
+        .route("/html/users", routing::get(routes_html::users::handler))
+        .route("/html/users/:user_id", routing::get(routes_html::users::handler_user))
+        .route("/html/tracks", routing::get(routes_html::tracks::handler))
+        .route("/html/tracks/:track_id", routing::get(routes_html::tracks::handler_track))
+        .route("/html/cars", routing::get(routes_html::cars::handler))
+        .route("/html/cars/:car_id", routing::get(routes_html::cars::handler_car))
+        .route("/html/car_classes", routing::get(routes_html::cars::handler_classes))
+        .route("/html/car_classes/:class_id", routing::get(routes_html::cars::handler_class))
+        .route("/html/ranking", routing::get(routes_html::ranking::handler))
+        .route("/html/championships", routing::get(routes_html::championships::handler))
+        .route("/html/championships/:championship_id", routing::get(routes_html::championships::handler_championship))
+        .route("/html/protests", routing::get(routes_html::protests::handler))
+        .route("/html/protests/:protest_id", routing::get(routes_html::protests::handler_protest))
+        .route("/html/schedules", routing::get(routes_html::schedules::handler))
+        .route("/html/schedules/:event_id", routing::get(routes_html::schedules::handler_event))
+
+        .route("/ical/schedule.ics", routing::get(routes_ical::handler_schedule))
+        .route("/ical/user/:file_name", routing::get(routes_ical::handler_user))
 
         .route("/api/v0/login/password", routing::post(routes_rest_v0::login_password::handler))
+        .route("/api/v0/login/password/forgot", routing::post(routes_rest_v0::login_password::handler_forgot))
+        .route("/api/v0/login/password/reset", routing::post(routes_rest_v0::login_password::handler_reset))
+        .route("/api/v0/login/webauthn", routing::post(routes_rest_v0::login_webauthn::handler))
+        .route("/api/v0/login/webauthn/challenge", routing::post(routes_rest_v0::login_webauthn::handler_challenge))
         .route("/api/v0/user/set_password", routing::post(routes_rest_v0::user::handler_set_password))
         .route("/api/v0/user/set_name", routing::post(routes_rest_v0::user::handler_set_name))
+        .route("/api/v0/user", routing::delete(routes_rest_v0::user::data::handler_delete))
+        .route("/api/v0/user/data", routing::get(routes_rest_v0::user::data::handler_export))
         .route("/api/v0/user/account/email", routing::put(routes_rest_v0::user::account::email_put))
         .route("/api/v0/user/account/email", routing::delete(routes_rest_v0::user::account::email_delete))
+        .route("/api/v0/user/account/steam", routing::delete(routes_rest_v0::user::account::steam_delete))
+        .route("/api/v0/user/sessions", routing::delete(routes_rest_v0::user::session::handler_delete_others))
+        .route("/api/v0/user/session/:session_id", routing::delete(routes_rest_v0::user::session::handler_delete))
+        .route("/api/v0/user/totp", routing::post(routes_rest_v0::user::totp::handler_enroll).put(routes_rest_v0::user::totp::handler_confirm).delete(routes_rest_v0::user::totp::handler_disable))
+        .route("/api/v0/user/totp/recovery_codes", routing::post(routes_rest_v0::user::totp::handler_recovery_codes))
+        .route("/api/v0/user/totp/verify", routing::post(routes_rest_v0::user::totp::handler_verify))
+        .route("/api/v0/user/webauthn", routing::post(routes_rest_v0::user::webauthn::handler_register))
+        .route("/api/v0/user/webauthn/challenge", routing::post(routes_rest_v0::user::webauthn::handler_challenge))
+        .route("/api/v0/user/webauthn/:credential_id", routing::delete(routes_rest_v0::user::webauthn::handler_delete))
+        .route("/api/v0/user/calendar", routing::post(routes_rest_v0::user::calendar::handler_create).delete(routes_rest_v0::user::calendar::handler_revoke))
+        .route("/api/v0/user/merge", routing::post(routes_rest_v0::user::merge::handler_merge))
+        .route("/api/v0/user/merge/code", routing::post(routes_rest_v0::user::merge::handler_code))
+        .route("/api/v0/user/:user_id/merge", routing::post(routes_rest_v0::user::merge::handler_admin_merge))
+        .route("/api/v0/user/:user_id/promotion", routing::get(routes_rest_v0::user::promotion::handler_get).put(routes_rest_v0::user::promotion::handler_set))
+        .route("/api/v0/tracks", routing::get(routes_rest_v0::tracks::handler_list).post(routes_rest_v0::tracks::handler_create))
+        .route("/api/v0/tracks/:track_id", routing::get(routes_rest_v0::tracks::handler_get).put(routes_rest_v0::tracks::handler_update).delete(routes_rest_v0::tracks::handler_delete))
+        .route("/api/v0/tracks/:track_id/layouts", routing::post(routes_rest_v0::tracks::handler_layout_create))
+        .route("/api/v0/tracks/layouts/:layout_id", routing::put(routes_rest_v0::tracks::handler_layout_update).delete(routes_rest_v0::tracks::handler_layout_delete))
+        .route("/api/v0/cars", routing::get(routes_rest_v0::cars::handler_list).post(routes_rest_v0::cars::handler_create))
+        .route("/api/v0/cars/:car_id", routing::get(routes_rest_v0::cars::handler_get).put(routes_rest_v0::cars::handler_update).delete(routes_rest_v0::cars::handler_delete))
+        .route("/api/v0/cars/:car_id/bop", routing::post(routes_rest_v0::cars::handler_bop))
+        .route("/api/v0/car_classes", routing::get(routes_rest_v0::cars::handler_class_list).post(routes_rest_v0::cars::handler_class_create))
+        .route("/api/v0/car_classes/:class_id", routing::get(routes_rest_v0::cars::handler_class_get).put(routes_rest_v0::cars::handler_class_update).delete(routes_rest_v0::cars::handler_class_delete))
+        .route("/api/v0/results/import/:simulator/:file_name", routing::post(routes_rest_v0::results::handler_import))
+        .route("/api/v0/ranking", routing::get(routes_rest_v0::ranking::handler_list))
+        .route("/api/v0/ranking/user/:user_id", routing::get(routes_rest_v0::ranking::handler_user))
+        .route("/api/v0/ranking/recompute", routing::post(routes_rest_v0::ranking::handler_recompute))
+        .route("/api/v0/championships", routing::get(routes_rest_v0::championships::handler_list).post(routes_rest_v0::championships::handler_create))
+        .route("/api/v0/championships/:championship_id", routing::get(routes_rest_v0::championships::handler_get).put(routes_rest_v0::championships::handler_update).delete(routes_rest_v0::championships::handler_delete))
+        .route("/api/v0/championships/:championship_id/standings", routing::get(routes_rest_v0::championships::handler_standings))
+        .route("/api/v0/championships/:championship_id/rounds", routing::post(routes_rest_v0::championships::handler_round_create))
+        .route("/api/v0/championships/rounds/:round_id", routing::put(routes_rest_v0::championships::handler_round_update).delete(routes_rest_v0::championships::handler_round_delete))
+        .route("/api/v0/penalties", routing::get(routes_rest_v0::penalties::handler_list).post(routes_rest_v0::penalties::handler_create))
+        .route("/api/v0/penalties/:penalty_id", routing::get(routes_rest_v0::penalties::handler_get).put(routes_rest_v0::penalties::handler_amend))
+        .route("/api/v0/penalties/:penalty_id/revoke", routing::post(routes_rest_v0::penalties::handler_revoke))
+        .route("/api/v0/penalties/:penalty_id/appeal", routing::post(routes_rest_v0::penalties::handler_appeal))
+        .route("/api/v0/penalties/:penalty_id/decision", routing::post(routes_rest_v0::penalties::handler_decision))
+        .route("/api/v0/protests", routing::get(routes_rest_v0::protests::handler_list).post(routes_rest_v0::protests::handler_create))
+        .route("/api/v0/protests/:protest_id", routing::get(routes_rest_v0::protests::handler_get))
+        .route("/api/v0/protests/:protest_id/comments", routing::post(routes_rest_v0::protests::handler_comment))
+        .route("/api/v0/protests/:protest_id/resolution", routing::post(routes_rest_v0::protests::handler_resolution))
+        .route("/api/v0/schedules", routing::get(routes_rest_v0::schedules::handler_list).post(routes_rest_v0::schedules::handler_create))
+        .route("/api/v0/schedules/:event_id", routing::get(routes_rest_v0::schedules::handler_get).put(routes_rest_v0::schedules::handler_update).delete(routes_rest_v0::schedules::handler_delete))
+        .route("/api/v0/schedules/:event_id/registration", routing::post(routes_rest_v0::schedules::handler_register).delete(routes_rest_v0::schedules::handler_unregister))
+        .route("/api/v0/federation/user/id/:user_id", routing::get(routes_rest_v0::federation::handler_user_by_id))
+        .route("/api/v0/federation/user/steam/:steam_id", routing::get(routes_rest_v0::federation::handler_user_by_steam_id))
 
         .with_state(app_state);
     router
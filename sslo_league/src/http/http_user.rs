@@ -1,17 +1,27 @@
+use std::marker::PhantomData;
 use axum::extract::{FromRef, FromRequestParts};
-use axum::http::header;
+use axum::http::{header, StatusCode};
 use axum::http::request::Parts;
+use axum::response::{IntoResponse, Response};
 use chrono::Utc;
 use crate::app_state::AppState;
-use crate::db2::members::users::Promotion;
-use super::super::db2::members::users::UserItem;
-use super::super::db2::members::cookie_logins::CookieLoginItem;
+use crate::http::HtmlTemplate;
+use crate::http::routes_rest_v0::GeneralError;
+use sslo_lib::db::members::users::PromotionLevel;
+use sslo_lib::db::members::users::UserItem;
+use sslo_lib::db::members::cookie_logins::CookieLoginItem;
 
 /// Representing the current user of the http service
 pub struct HttpUser {
     pub user: UserItem,
     pub cookie_login: Option<CookieLoginItem>,
     pub user_agent: String,
+
+    /// the user is configured as root user (server admin, above all promotion levels)
+    pub is_root: bool,
+
+    /// promotion levels at or above this are not granted, because the current login is not verified by TOTP
+    pub promotion_limit: Option<PromotionLevel>,
 }
 
 
@@ -24,12 +34,35 @@ impl HttpUser {
             user: tbl_usr.user_dummy().await,
             cookie_login: None,
             user_agent: "".to_string(),
+            is_root: false,
+            promotion_limit: None,
         }
     }
 
     pub fn is_logged_in(&self) -> bool {
         self.cookie_login.is_some()
     }
+
+    /// Check if the user is logged in and has at least a certain promotion level
+    /// This is always true for the root user, except for levels that require TOTP.
+    pub async fn has_promotion(&self, level: PromotionLevel) -> bool {
+        if !self.is_logged_in() {
+            return false;
+        }
+        if self.promotion_limit.as_ref().is_some_and(|limit| level >= *limit) {
+            return false;
+        }
+        if self.is_root {
+            return true;
+        }
+        self.user.promotion().await.level >= level
+    }
+
+    /// The user is root and may use the root permissions (above all promotion levels)
+    /// When TOTP is required for any promotion level, the root user needs it as well.
+    pub fn has_root_permissions(&self) -> bool {
+        self.is_logged_in() && self.is_root && self.promotion_limit.is_none()
+    }
 }
 
 
@@ -70,10 +103,17 @@ where
                         cl_user.set_last_login(Utc::now()).await;
 
                         // create http user
+                        let is_root = app_state.config.general.root_user_id == Some(cl_user.id().await);
+                        let promotion_limit = match app_state.config.totp.as_ref().and_then(|t| t.required_level.clone()) {
+                            Some(level) if cl.totp_verified().await.is_none() || cl_user.totp_enabled().await.raw().is_none() => Some(level),
+                            _ => None,
+                        };
                         let http_user = HttpUser {
                             user: cl_user,
                             cookie_login: Some(cl),
                             user_agent,
+                            is_root,
+                            promotion_limit,
                         };
                         return Ok(Self(http_user));
                     }
@@ -87,7 +127,318 @@ where
             user: tbl_usr.user_dummy().await,
             cookie_login: None,
             user_agent,
+            is_root: false,
+            promotion_limit: None,
+        };
+        Ok(Self(http_user))
+    }
+}
+
+
+/// Reject a request for an insufficient user (JSON for the REST API, an HTML page otherwise)
+async fn reject(parts: &Parts, http_user: HttpUser, status_code: StatusCode, description: &str) -> Response {
+    if parts.uri.path().starts_with("/api/") {
+        return GeneralError::new(status_code, description.to_string()).into_response();
+    }
+    let mut html = HtmlTemplate::new(http_user);
+    html.message_error(description.to_string());
+    let mut response = html.into_response().await;
+    *response.status_mut() = status_code;
+    response
+}
+
+
+/// Extractor that only accepts logged in users
+pub struct RequireLogin(pub HttpUser);
+
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for RequireLogin
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let http_user = match HttpUserExtractor::from_request_parts(parts, state).await {
+            Ok(HttpUserExtractor(http_user)) => http_user,
+            Err(_) => HttpUser::new_anonymous(AppState::from_ref(state)).await,
         };
+        if !http_user.is_logged_in() {
+            return Err(reject(parts, http_user, StatusCode::UNAUTHORIZED, "No user logged in").await);
+        }
         Ok(Self(http_user))
     }
 }
+
+
+/// A promotion level that can be required by RequirePromotion
+pub trait RequiredLevel {
+    const LEVEL: PromotionLevel;
+}
+
+/// Marker types for the promotion levels
+#[allow(dead_code)]
+pub mod levels {
+    use sslo_lib::db::members::users::PromotionLevel;
+    use super::RequiredLevel;
+
+    pub struct Steward;
+    impl RequiredLevel for Steward { const LEVEL: PromotionLevel = PromotionLevel::Steward; }
+
+    pub struct Marshal;
+    impl RequiredLevel for Marshal { const LEVEL: PromotionLevel = PromotionLevel::Marshal; }
+
+    pub struct Officer;
+    impl RequiredLevel for Officer { const LEVEL: PromotionLevel = PromotionLevel::Officer; }
+
+    pub struct Commissar;
+    impl RequiredLevel for Commissar { const LEVEL: PromotionLevel = PromotionLevel::Commissar; }
+
+    pub struct Director;
+    impl RequiredLevel for Director { const LEVEL: PromotionLevel = PromotionLevel::Director; }
+
+    pub struct Admin;
+    impl RequiredLevel for Admin { const LEVEL: PromotionLevel = PromotionLevel::Admin; }
+}
+
+
+/// Extractor that only accepts logged in users with at least a certain promotion level (or the root user)
+pub struct RequirePromotion<L: RequiredLevel>(pub HttpUser, pub PhantomData<L>);
+
+
+#[axum::async_trait]
+impl<S, L> FromRequestParts<S> for RequirePromotion<L>
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+    L: RequiredLevel + Send,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let RequireLogin(http_user) = RequireLogin::from_request_parts(parts, state).await?;
+        if !http_user.has_promotion(L::LEVEL).await {
+            log::warn!("deny request from {} to {}, because {:?} is required", http_user.user.display().await, parts.uri.path(), L::LEVEL);
+            let description = match http_user.promotion_limit.as_ref().is_some_and(|limit| L::LEVEL >= *limit) {
+                true => "Two-factor authentication (TOTP) required for this promotion",
+                false => "Insufficient promotion",
+            };
+            return Err(reject(parts, http_user, StatusCode::FORBIDDEN, description).await);
+        }
+        Ok(Self(http_user, PhantomData))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use axum::http::Request;
+    use sslo_lib::db::members::users::{Promotion, PromotionAuthority};
+    use axum::extract::State;
+    use axum::Json;
+    use crate::config::{Config, Totp};
+    use crate::http::routes_rest_v0::user::totp;
+
+    const TOTP_KEY: &str = "some_totp_test_key";
+
+    /// App state with user 1 as root and user 2 as Officer
+    async fn get_app_state(totp_required_level: Option<PromotionLevel>) -> AppState {
+        let mut config = Config::new_for_test();
+        config.general.root_user_id = Some(1);
+        config.totp = Some(Totp { key: TOTP_KEY.to_string(), required_level: totp_required_level });
+        let app_state = AppState::new_in_memory(config).await;
+        let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+        tbl_usr.create_new_user().await.unwrap();
+        let mut officer = tbl_usr.create_new_user().await.unwrap();
+        officer.grant_promotion(Promotion::new(PromotionLevel::Officer, PromotionAuthority::Executing), None).await.unwrap();
+        app_state
+    }
+
+    /// Enable TOTP for a user, returns the recovery codes
+    async fn enable_totp(app_state: &AppState, user_id: i64) -> Vec<String> {
+        let mut user = app_state.database.db_members().await.tbl_users().await.user_by_id(user_id).await.unwrap();
+        let secret = user.totp_enroll(TOTP_KEY).await.unwrap();
+        let code = sslo_lib::totp::code(&secret, sslo_lib::totp::time_step(Utc::now().timestamp()));
+        user.totp_confirm(TOTP_KEY, &code).await.unwrap().unwrap()
+    }
+
+    /// Create a new login of a user and return the cookie
+    /// A login with TOTP (password login) is marked as verified,
+    /// other logins (Steam, Discord, email, passkeys) are not.
+    async fn login(app_state: &AppState, user_id: i64, totp_verified: bool) -> String {
+        let db_members = app_state.database.db_members().await;
+        let user = db_members.tbl_users().await.user_by_id(user_id).await.unwrap();
+        let cookie_login = db_members.tbl_cookie_logins().await.create_new_cookie(&user).await.unwrap();
+        if totp_verified {
+            cookie_login.set_totp_verified().await.unwrap();
+        }
+        cookie_login.get_cookie().await.unwrap()
+    }
+
+    /// Request parts with the login cookie of a new login without TOTP (anonymous when user_id is None)
+    async fn request_parts(app_state: &AppState, path: &str, user_id: Option<i64>) -> Parts {
+        let mut request = Request::builder().uri(path);
+        if let Some(user_id) = user_id {
+            request = request.header(header::COOKIE, login(app_state, user_id, false).await);
+        }
+        request.body(()).unwrap().into_parts().0
+    }
+
+    async fn http_user_by_cookie(app_state: &AppState, cookie: &str) -> HttpUser {
+        let mut parts = Request::builder().uri("/").header(header::COOKIE, cookie).body(()).unwrap().into_parts().0;
+        HttpUserExtractor::from_request_parts(&mut parts, app_state).await.unwrap().0
+    }
+
+    async fn http_user(app_state: &AppState, user_id: Option<i64>) -> HttpUser {
+        let mut parts = request_parts(app_state, "/", user_id).await;
+        HttpUserExtractor::from_request_parts(&mut parts, app_state).await.unwrap().0
+    }
+
+    #[test(tokio::test)]
+    async fn is_root() {
+        let app_state = get_app_state(None).await;
+        assert!(!http_user(&app_state, None).await.is_root);
+        assert!(http_user(&app_state, Some(1)).await.is_root);
+        assert!(!http_user(&app_state, Some(2)).await.is_root);
+    }
+
+    #[test(tokio::test)]
+    async fn has_promotion_without_totp_requirement() {
+        let app_state = get_app_state(None).await;
+
+        let anonymous = http_user(&app_state, None).await;
+        assert!(!anonymous.has_promotion(PromotionLevel::None).await);
+        assert!(!anonymous.has_root_permissions());
+
+        let root = http_user(&app_state, Some(1)).await;
+        assert!(root.promotion_limit.is_none());
+        assert!(root.has_promotion(PromotionLevel::Admin).await);
+        assert!(root.has_root_permissions());
+
+        let officer = http_user(&app_state, Some(2)).await;
+        assert!(officer.promotion_limit.is_none());
+        assert!(officer.has_promotion(PromotionLevel::Officer).await);
+        assert!(!officer.has_promotion(PromotionLevel::Commissar).await);
+        assert!(!officer.has_root_permissions());
+    }
+
+    #[test(tokio::test)]
+    async fn has_promotion_with_totp_requirement() {
+        let app_state = get_app_state(Some(PromotionLevel::Officer)).await;
+
+        // without TOTP, levels at or above the required level are not granted (also not to root)
+        let root = http_user(&app_state, Some(1)).await;
+        assert_eq!(root.promotion_limit, Some(PromotionLevel::Officer));
+        assert!(root.has_promotion(PromotionLevel::Marshal).await);
+        assert!(!root.has_promotion(PromotionLevel::Officer).await);
+        assert!(!root.has_promotion(PromotionLevel::Admin).await);
+        assert!(!root.has_root_permissions());
+
+        let officer = http_user(&app_state, Some(2)).await;
+        assert_eq!(officer.promotion_limit, Some(PromotionLevel::Officer));
+        assert!(officer.has_promotion(PromotionLevel::Marshal).await);
+        assert!(!officer.has_promotion(PromotionLevel::Officer).await);
+
+        // with TOTP enabled, only logins that were verified with a code are granted
+        enable_totp(&app_state, 1).await;
+        enable_totp(&app_state, 2).await;
+
+        let root = http_user_by_cookie(&app_state, &login(&app_state, 1, true).await).await;
+        assert!(root.promotion_limit.is_none());
+        assert!(root.has_promotion(PromotionLevel::Admin).await);
+        assert!(root.has_root_permissions());
+
+        let officer = http_user_by_cookie(&app_state, &login(&app_state, 2, true).await).await;
+        assert!(officer.promotion_limit.is_none());
+        assert!(officer.has_promotion(PromotionLevel::Officer).await);
+        assert!(!officer.has_promotion(PromotionLevel::Commissar).await);
+    }
+
+    #[test(tokio::test)]
+    async fn has_promotion_without_totp_login() {
+        let app_state = get_app_state(Some(PromotionLevel::Officer)).await;
+        enable_totp(&app_state, 1).await;
+        let recovery_codes = enable_totp(&app_state, 2).await;
+
+        // logins without second factor (Steam, Discord, email, passkeys) are limited, although TOTP is enabled
+        let root = http_user(&app_state, Some(1)).await;
+        assert_eq!(root.promotion_limit, Some(PromotionLevel::Officer));
+        assert!(!root.has_root_permissions());
+        let cookie = login(&app_state, 2, false).await;
+        let officer = http_user_by_cookie(&app_state, &cookie).await;
+        assert_eq!(officer.promotion_limit, Some(PromotionLevel::Officer));
+        assert!(!officer.has_promotion(PromotionLevel::Officer).await);
+
+        // verify the login afterwards
+        let input = serde_json::from_value(serde_json::json!({"code": "invalid"})).unwrap();
+        let response = totp::handler_verify(State(app_state.clone()), RequireLogin(officer), Json(input)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let officer = http_user_by_cookie(&app_state, &cookie).await;
+        assert_eq!(officer.promotion_limit, Some(PromotionLevel::Officer));
+        let input = serde_json::from_value(serde_json::json!({"code": recovery_codes[0]})).unwrap();
+        let response = totp::handler_verify(State(app_state.clone()), RequireLogin(officer), Json(input)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let officer = http_user_by_cookie(&app_state, &cookie).await;
+        assert!(officer.promotion_limit.is_none());
+        assert!(officer.has_promotion(PromotionLevel::Officer).await);
+
+        // disabling TOTP resets the verification of all logins
+        let input = serde_json::from_value(serde_json::json!({"code": recovery_codes[1]})).unwrap();
+        let response = totp::handler_disable(State(app_state.clone()), RequireLogin(officer), Json(input)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let officer = http_user_by_cookie(&app_state, &cookie).await;
+        assert_eq!(officer.promotion_limit, Some(PromotionLevel::Officer));
+    }
+
+    #[test(tokio::test)]
+    async fn require_login() {
+        let app_state = get_app_state(None).await;
+
+        // anonymous
+        let mut parts = request_parts(&app_state, "/api/v0/user/account", None).await;
+        let response = RequireLogin::from_request_parts(&mut parts, &app_state).await.err().unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let mut parts = request_parts(&app_state, "/html/user", None).await;
+        let response = RequireLogin::from_request_parts(&mut parts, &app_state).await.err().unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // logged in
+        let mut parts = request_parts(&app_state, "/api/v0/user/account", Some(2)).await;
+        let RequireLogin(http_user) = RequireLogin::from_request_parts(&mut parts, &app_state).await.ok().unwrap();
+        assert_eq!(http_user.user.id().await, 2);
+    }
+
+    #[test(tokio::test)]
+    async fn require_promotion() {
+        let app_state = get_app_state(Some(PromotionLevel::Director)).await;
+
+        // anonymous
+        let mut parts = request_parts(&app_state, "/api/v0/users", None).await;
+        let response = RequirePromotion::<levels::Officer>::from_request_parts(&mut parts, &app_state).await.err().unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // insufficient level
+        let mut parts = request_parts(&app_state, "/api/v0/users", Some(2)).await;
+        let response = RequirePromotion::<levels::Commissar>::from_request_parts(&mut parts, &app_state).await.err().unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let mut parts = request_parts(&app_state, "/html/users", Some(2)).await;
+        let response = RequirePromotion::<levels::Commissar>::from_request_parts(&mut parts, &app_state).await.err().unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // sufficient level
+        let mut parts = request_parts(&app_state, "/api/v0/users", Some(2)).await;
+        let RequirePromotion(http_user, _) = RequirePromotion::<levels::Officer>::from_request_parts(&mut parts, &app_state).await.ok().unwrap();
+        assert_eq!(http_user.user.id().await, 2);
+
+        // root without TOTP is limited as well
+        let mut parts = request_parts(&app_state, "/api/v0/users", Some(1)).await;
+        assert!(RequirePromotion::<levels::Commissar>::from_request_parts(&mut parts, &app_state).await.is_ok());
+        let mut parts = request_parts(&app_state, "/api/v0/users", Some(1)).await;
+        let response = RequirePromotion::<levels::Director>::from_request_parts(&mut parts, &app_state).await.err().unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -1,3 +1,10 @@
+pub mod cars;
+pub mod championships;
 pub mod home;
 pub mod login;
+pub mod protests;
+pub mod ranking;
+pub mod schedules;
+pub mod tracks;
 pub mod user;
+pub mod users;
@@ -0,0 +1,146 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use sslo_lib::optional_date::OptionalDateTime;
+use crate::app_state::AppState;
+use crate::db2::content::cars::CarItem;
+use crate::http::HtmlTemplate;
+use crate::http::http_user::HttpUserExtractor;
+
+async fn html_car_row(car: &CarItem) -> String {
+    let mut html = String::new();
+    html += "<tr><td><a href=\"/html/cars/";
+    html += &car.id().await.to_string();
+    html += "\">";
+    html += &car.html_name().await;
+    html += "</a></td><td>";
+    html += &html_escape::encode_safe(&car.manufacturer().await);
+    html += "</td><td>";
+    if let Some(bop) = car.bop().await {
+        html += &format!("{}kg / {}%", bop.ballast, bop.restrictor);
+    }
+    html += "</td></tr>";
+    html
+}
+
+/// List all cars
+pub async fn handler(State(app_state): State<AppState>,
+                     HttpUserExtractor(http_user): HttpUserExtractor,
+) -> Result<Response, StatusCode> {
+    let tbl_car = app_state.database.db_content().await.tbl_cars().await;
+
+    let mut html = HtmlTemplate::new(http_user);
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<table><tr><th>Car</th><th>Manufacturer</th><th>Class</th><th>BoP</th></tr>");
+    for car in tbl_car.items_all().await.iter() {
+        let car_class = match car.car_class().await {
+            Some(car_class) => format!("<a href=\"/html/car_classes/{}\">{}</a>", car_class.id().await, car_class.html_name().await),
+            None => "".to_string(),
+        };
+        html.push_body("<tr><td><a href=\"/html/cars/");
+        html.push_body(&car.id().await.to_string());
+        html.push_body("\">");
+        html.push_body(&car.html_name().await);
+        html.push_body("</a></td><td>");
+        html.push_body(&html_escape::encode_safe(&car.manufacturer().await));
+        html.push_body("</td><td>");
+        html.push_body(&car_class);
+        html.push_body("</td><td>");
+        if let Some(bop) = car.bop().await {
+            html.push_body(&format!("{}kg / {}%", bop.ballast, bop.restrictor));
+        }
+        html.push_body("</td></tr>");
+    }
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    Ok(html.into_response().await)
+}
+
+/// Show a single car with its BoP history
+pub async fn handler_car(State(app_state): State<AppState>,
+                         HttpUserExtractor(http_user): HttpUserExtractor,
+                         Path(car_id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    let tbl_car = app_state.database.db_content().await.tbl_cars().await;
+    let car = tbl_car.item_by_id(car_id).await.ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut html = HtmlTemplate::new(http_user);
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h1>");
+    html.push_body(&car.html_name().await);
+    html.push_body("</h1>");
+    html.push_body("<table><tr><th>Manufacturer</th><td>");
+    html.push_body(&html_escape::encode_safe(&car.manufacturer().await));
+    html.push_body("</td></tr><tr><th>Class</th><td>");
+    if let Some(car_class) = car.car_class().await {
+        html.push_body(&format!("<a href=\"/html/car_classes/{}\">{}</a>", car_class.id().await, car_class.html_name().await));
+    }
+    html.push_body("</td></tr>");
+    for sim_id in car.sim_ids().await.iter() {
+        html.push_body("<tr><th>");
+        html.push_body(sim_id.simulator.label());
+        html.push_body("</th><td>");
+        html.push_body(&html_escape::encode_safe(&sim_id.identifier));
+        html.push_body("</td></tr>");
+    }
+    html.push_body("</table></div>");
+
+    html.push_body("<div class=\"BgBox\"><h2>Balance of Performance</h2>");
+    html.push_body("<table><tr><th>Since</th><th>Ballast</th><th>Restrictor</th></tr>");
+    for bop in car.bop_history().await.iter() {
+        html.push_body("<tr><td>");
+        html.push_body(&OptionalDateTime::new(Some(bop.since)).html_label_full());
+        html.push_body(&format!("</td><td>{} kg</td><td>{} %</td></tr>", bop.ballast, bop.restrictor));
+    }
+    html.push_body("</table></div>");
+
+    Ok(html.into_response().await)
+}
+
+/// List all car classes
+pub async fn handler_classes(State(app_state): State<AppState>,
+                             HttpUserExtractor(http_user): HttpUserExtractor,
+) -> Result<Response, StatusCode> {
+    let tbl_cls = app_state.database.db_content().await.tbl_car_classes().await;
+
+    let mut html = HtmlTemplate::new(http_user);
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<table><tr><th>Class</th><th>Cars</th></tr>");
+    for car_class in tbl_cls.items_all().await.iter() {
+        html.push_body("<tr><td><a href=\"/html/car_classes/");
+        html.push_body(&car_class.id().await.to_string());
+        html.push_body("\">");
+        html.push_body(&car_class.html_name().await);
+        html.push_body("</a></td><td>");
+        html.push_body(&car_class.cars().await.len().to_string());
+        html.push_body("</td></tr>");
+    }
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    Ok(html.into_response().await)
+}
+
+/// Show a single car class with its cars
+pub async fn handler_class(State(app_state): State<AppState>,
+                           HttpUserExtractor(http_user): HttpUserExtractor,
+                           Path(class_id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    let tbl_cls = app_state.database.db_content().await.tbl_car_classes().await;
+    let car_class = tbl_cls.item_by_id(class_id).await.ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut html = HtmlTemplate::new(http_user);
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h1>");
+    html.push_body(&car_class.html_name().await);
+    html.push_body("</h1>");
+    html.push_body("<table><tr><th>Car</th><th>Manufacturer</th><th>BoP</th></tr>");
+    for car in car_class.cars().await.iter() {
+        html.push_body(&html_car_row(car).await);
+    }
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    Ok(html.into_response().await)
+}
@@ -0,0 +1,120 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use crate::app_state::AppState;
+use crate::http::HtmlTemplate;
+use crate::http::http_user::HttpUserExtractor;
+use crate::standings;
+
+/// Format the points of a round for a standings table
+fn html_round_points(points: &Option<i64>, dropped: bool) -> String {
+    match (points, dropped) {
+        (Some(points), true) => format!("<td><s>{}</s></td>", points),
+        (Some(points), false) => format!("<td>{}</td>", points),
+        (None, _) => "<td>-</td>".to_string(),
+    }
+}
+
+/// List of all championships
+pub async fn handler(State(app_state): State<AppState>,
+                     HttpUserExtractor(http_user): HttpUserExtractor,
+) -> Result<Response, StatusCode> {
+    let tbl_chp = app_state.database.db_series().await.tbl_championships().await;
+
+    let mut html = HtmlTemplate::new(http_user);
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h1>Championships</h1>");
+    html.push_body("<table><tr><th>Championship</th><th>Rounds</th></tr>");
+    for championship in tbl_chp.items_all().await.iter() {
+        html.push_body("<tr><td><a href=\"/html/championships/");
+        html.push_body(&championship.id().await.to_string());
+        html.push_body("\">");
+        html.push_body(&championship.html_name().await);
+        html.push_body("</a></td><td>");
+        html.push_body(&championship.rounds().await.len().to_string());
+        html.push_body("</td></tr>");
+    }
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    Ok(html.into_response().await)
+}
+
+/// Rounds and standings of a single championship
+pub async fn handler_championship(State(app_state): State<AppState>,
+                                  HttpUserExtractor(http_user): HttpUserExtractor,
+                                  Path(championship_id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    let tbl_chp = app_state.database.db_series().await.tbl_championships().await;
+    let championship = tbl_chp.item_by_id(championship_id).await.ok_or(StatusCode::NOT_FOUND)?;
+    let rounds = championship.rounds().await;
+    let standings = standings::standings(&app_state.database, &championship).await;
+
+    let mut html = HtmlTemplate::new(http_user);
+
+    // overview
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h1>");
+    html.push_body(&championship.html_name().await);
+    html.push_body("</h1>");
+    html.push_body("<p>");
+    html.push_body(&html_escape::encode_safe(&championship.description().await));
+    html.push_body("</p>");
+    html.push_body("<table>");
+    let points: Vec<String> = championship.points_table().await.iter().map(|p| p.to_string()).collect();
+    html.push_body(&format!("<tr><th>Points</th><td>{}</td></tr>", points.join(", ")));
+    html.push_body(&format!("<tr><th>Pole Position</th><td>{}</td></tr>", championship.points_pole().await));
+    html.push_body(&format!("<tr><th>Fastest Lap</th><td>{}</td></tr>", championship.points_fastest_lap().await));
+    html.push_body(&format!("<tr><th>Dropped Results</th><td>{}</td></tr>", championship.drop_worst().await));
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    // rounds
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h2>Rounds</h2>");
+    html.push_body("<table><tr><th>Round</th><th>Name</th></tr>");
+    for round in rounds.iter() {
+        html.push_body(&format!("<tr><td>{}</td><td>", round.round_number));
+        html.push_body(&html_escape::encode_safe(&round.name));
+        html.push_body("</td></tr>");
+    }
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    // driver standings
+    let round_headers: String = rounds.iter().map(|r| format!("<th>R{}</th>", r.round_number)).collect();
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h2>Driver Standings</h2>");
+    html.push_body(&format!("<table><tr><th>Pos</th><th>Driver</th><th>Team</th>{}<th>Points</th></tr>", round_headers));
+    for (index, driver) in standings.drivers.iter().enumerate() {
+        html.push_body(&format!("<tr><td>{}</td><td>", index + 1));
+        html.push_body(&html_escape::encode_safe(&driver.name));
+        html.push_body("</td><td>");
+        html.push_body(&html_escape::encode_safe(&driver.team));
+        html.push_body("</td>");
+        for (points, dropped) in driver.round_points.iter().zip(driver.dropped.iter()) {
+            html.push_body(&html_round_points(points, *dropped));
+        }
+        html.push_body(&format!("<td>{}</td></tr>", driver.points));
+    }
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    // team standings
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h2>Team Standings</h2>");
+    html.push_body(&format!("<table><tr><th>Pos</th><th>Team</th>{}<th>Points</th></tr>", round_headers));
+    for (index, team) in standings.teams.iter().enumerate() {
+        html.push_body(&format!("<tr><td>{}</td><td>", index + 1));
+        html.push_body(&html_escape::encode_safe(&team.team));
+        html.push_body("</td>");
+        for (points, dropped) in team.round_points.iter().zip(team.dropped.iter()) {
+            html.push_body(&html_round_points(points, *dropped));
+        }
+        html.push_body(&format!("<td>{}</td></tr>", team.points));
+    }
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    Ok(html.into_response().await)
+}
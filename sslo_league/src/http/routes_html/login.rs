@@ -1,17 +1,22 @@
-use axum::extract::{OriginalUri, Path, State};
+use axum::extract::{OriginalUri, Path, Query, State};
 use axum::http;
-use axum::http::header::{REFRESH, SET_COOKIE};
+use axum::http::header::{COOKIE, LOCATION, REFRESH, SET_COOKIE};
+use axum::http::HeaderMap;
 use axum::http::StatusCode;
-use axum::response::Response;
-use rand::RngCore;
+use axum::response::{IntoResponse, Response};
+use rand::{Rng, RngCore};
+use serde::Deserialize;
 use crate::app_state::AppState;
-use crate::db2::members::email_accounts::EmailAccountItem;
-use crate::db2::members::steam_accounts::SteamAccountItem;
-use crate::db2::members::users::UserItem;
+use sslo_lib::db::members::discord_accounts::DiscordAccountItem;
+use sslo_lib::db::members::email_accounts::EmailAccountItem;
+use sslo_lib::db::members::steam_accounts::SteamAccountItem;
+use sslo_lib::db::members::users::UserItem;
+use crate::discord::DiscordUser;
 use crate::http::HtmlTemplate;
 use crate::http::http_user::{HttpUser, HttpUserExtractor};
 
-pub async fn handler(HttpUserExtractor(http_user): HttpUserExtractor,
+pub async fn handler(State(app_state): State<AppState>,
+                     HttpUserExtractor(http_user): HttpUserExtractor,
                      OriginalUri(uri): OriginalUri,
 ) -> Result<Response, StatusCode> {
 
@@ -34,7 +39,13 @@ pub async fn handler(HttpUserExtractor(http_user): HttpUserExtractor,
     html.push_body("<div class=\"HrLine\">with Password</div>");
     html.push_body("<input id=\"WithPasswordId\" type=\"text\" placeholder=\"Email or User-ID\"/>");
     html.push_body("<input id=\"WithPasswordPassword\" type=\"password\" placeholder=\"Password\"/>");
+    html.push_body("<input id=\"WithPasswordTotp\" type=\"text\" placeholder=\"Authenticator or Recovery Code\" autocomplete=\"one-time-code\"/>");
     html.push_body("<button id=\"WithPasswordButton\" type=\"button\">Login with Password</button>");
+    html.push_body("<button id=\"WithPasswordForgotButton\" type=\"button\">Forgot Password</button>");
+
+    // passkey
+    html.push_body("<div class=\"HrLine\">with Passkey</div>");
+    html.push_body("<button id=\"WithPasskeyButton\" type=\"button\">Login with Passkey</button>");
 
     // email
     html.push_body("<div class=\"HrLine\">with Email</div>");
@@ -61,6 +72,12 @@ pub async fn handler(HttpUserExtractor(http_user): HttpUserExtractor,
         html.push_body("<span>Steam Login Unavailable</span>");
     }
 
+    // discord
+    if app_state.config.discord.is_some() && app_state.config.general.public_url.is_some() {
+        html.push_body("<div class=\"HrLine\">with Discord</div>");
+        html.push_body("<button id=\"WithDiscordButton\" type=\"button\">Login via Discord Account</button>");
+    }
+
     html.push_body("</div>");
     Ok(html.into_response().await)
 }
@@ -234,6 +251,24 @@ pub async fn handler_email_verify(State(app_state): State<AppState>,
     Ok(response)
 }
 
+/// Form to set a new password from a password reset link
+pub async fn handler_password_reset(HttpUserExtractor(http_user): HttpUserExtractor,
+                                    Path((email_account_id, token)): Path<(i64, String)>,
+) -> Result<Response, StatusCode> {
+    let mut html = HtmlTemplate::new(http_user);
+    html.include_css("/rsc/css/login.css");
+    html.include_js("/rsc/js/password_reset.js");
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<div class=\"HrLine\">Set New Password</div>");
+    html.push_body(&format!("<input type=\"hidden\" id=\"ResetEmailAccount\" value=\"{}\">", email_account_id));
+    html.push_body(&format!("<input type=\"hidden\" id=\"ResetToken\" value=\"{}\">", html_escape::encode_safe(&token)));
+    html.push_body("<input id=\"ResetPassword\" type=\"password\" placeholder=\"New Password\"/>");
+    html.push_body("<input id=\"ResetPasswordRepeat\" type=\"password\" placeholder=\"Repeat New Password\"/>");
+    html.push_body("<button id=\"ResetButton\" type=\"button\">Set Password</button>");
+    html.push_body("</div>");
+    Ok(html.into_response().await)
+}
+
 pub async fn get_steam_account(app_state: AppState, uri: http::uri::Uri) -> Option<SteamAccountItem> {
     let mut steam_account : Option<SteamAccountItem> = None;
     let db_members = app_state.database.db_members().await;
@@ -403,6 +438,201 @@ pub async fn handler_steam_assign(State(app_state): State<AppState>,
     Ok(html.into_response().await)
 }
 
+/// name of the cookie that carries the OAuth2 state during a Discord login
+const DISCORD_STATE_COOKIE: &str = "discord_oauth_state";
+
+#[derive(Deserialize)]
+pub struct DiscordVerifyQuery {
+    code: Option<String>,
+    state: Option<String>,
+}
+
+/// The redirect URI of the Discord login, derived from general.public_url
+fn discord_redirect_uri(app_state: &AppState) -> Option<String> {
+    match app_state.config.general.public_url.as_ref() {
+        Some(public_url) => Some(format!("{}/html/login_discord_verify", public_url.trim_end_matches('/'))),
+        None => {
+            log::error!("Cannot login with Discord, because general.public_url is not configured");
+            None
+        }
+    }
+}
+
+/// Forward to Discord for authorization
+/// The action (login, create or assign) is remembered together with the OAuth2 state in a short-lived cookie
+pub async fn handler_discord(State(app_state): State<AppState>,
+                             Path(action): Path<String>,
+) -> Result<Response, StatusCode> {
+    let cfg = app_state.config.discord.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    if !["login", "create", "assign"].contains(&action.as_str()) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let redirect_uri = discord_redirect_uri(&app_state).ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let state = format!("{:032x}", rand::thread_rng().gen::<u128>());
+    let url = match crate::discord::authorize_url(cfg, &redirect_uri, &state) {
+        Ok(url) => url,
+        Err(e) => {
+            log::error!("{}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let cookie = format!("{}={}.{}; HttpOnly; Secure; SameSite=Lax; Max-Age=600; Path=/html/login_discord_verify",
+                         DISCORD_STATE_COOKIE, action, state);
+    let mut response = StatusCode::SEE_OTHER.into_response();
+    response.headers_mut().insert(SET_COOKIE, cookie.parse().unwrap());
+    response.headers_mut().insert(LOCATION, url.parse().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?);
+    Ok(response)
+}
+
+/// returns the action that was remembered in the state cookie, if the state matches
+fn discord_state_action(headers: &HeaderMap, state: &str) -> Option<String> {
+    for cookie_header in headers.get_all(COOKIE) {
+        let cookie_string = cookie_header.to_str().ok()?;
+        for cookie in cookie_string.split(';') {
+            if let Some((name, value)) = cookie.trim().split_once('=') {
+                if name == DISCORD_STATE_COOKIE {
+                    let (action, cookie_state) = value.split_once('.')?;
+                    if cookie_state == state {
+                        return Some(action.to_string());
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Request the Discord user that authorized the code
+async fn get_discord_user(app_state: &AppState, code: &str) -> Option<DiscordUser> {
+    let cfg = app_state.config.discord.as_ref()?;
+    let redirect_uri = discord_redirect_uri(app_state)?;
+    let discord_user = match crate::discord::exchange_code(cfg, code, &redirect_uri).await {
+        Ok(token) => crate::discord::fetch_user(cfg, &token).await,
+        Err(e) => Err(e),
+    };
+    match discord_user {
+        Ok(discord_user) => Some(discord_user),
+        Err(e) => {
+            log::warn!("Discord verification failed: {}", e);
+            None
+        }
+    }
+}
+
+/// Get the Discord account of a verified Discord user (a new account is only created if allowed)
+async fn get_discord_account(app_state: &AppState, discord_user: &DiscordUser, allow_new_item_creation: bool) -> Option<DiscordAccountItem> {
+    let tbl_discord = app_state.database.db_members().await.tbl_discord_accounts().await;
+    let discord_account = match tbl_discord.item_by_discord_id(&discord_user.id, allow_new_item_creation).await {
+        Some(discord_account) => discord_account,
+        None => {
+            log::warn!("No Discord account for Discord user {}", discord_user.id);
+            return None;
+        }
+    };
+    if let Err(e) = discord_account.set_username(discord_user.display_name().to_string()).await {
+        log::error!("Could not update username of {}: {}", discord_account.display().await, e);
+    }
+    Some(discord_account)
+}
+
+/// Callback from Discord after authorization
+pub async fn handler_discord_verify(State(app_state): State<AppState>,
+                                    HttpUserExtractor(http_user): HttpUserExtractor,
+                                    headers: HeaderMap,
+                                    Query(query): Query<DiscordVerifyQuery>,
+) -> Result<Response, StatusCode> {
+    let mut html = HtmlTemplate::new(http_user);
+    let clear_state_cookie = format!("{}=; HttpOnly; Secure; SameSite=Lax; Max-Age=0; Path=/html/login_discord_verify",
+                                     DISCORD_STATE_COOKIE);
+
+    // verify state
+    let action = match (&query.code, &query.state) {
+        (Some(_), Some(state)) => discord_state_action(&headers, state),
+        _ => None,
+    };
+    let discord_user = match (action.as_ref(), query.code.as_ref()) {
+        (Some(_), Some(code)) => get_discord_user(&app_state, code).await,
+        _ => {
+            log::warn!("Deny Discord login with invalid OAuth2 state");
+            None
+        }
+    };
+
+    // only verified Discord users that create or assign an account are stored
+    let discord_account = match (action.as_deref(), discord_user.as_ref()) {
+        (Some(action), Some(discord_user)) => {
+            let allow_new_item_creation = action == "create" || (action == "assign" && html.http_user.is_logged_in());
+            get_discord_account(&app_state, discord_user, allow_new_item_creation).await
+        },
+        _ => None,
+    };
+
+    let mut user: Option<UserItem> = None;
+    let is_assign = action.as_deref() == Some("assign");
+    let mut assigned = false;
+    if let (Some(action), Some(discord_account)) = (action, discord_account) {
+        match action.as_str() {
+            "assign" if html.http_user.is_logged_in() => {
+                match discord_account.set_user(&html.http_user.user).await {
+                    Ok(_) => assigned = true,
+                    Err(e) => {
+                        log::error!("Assigning {} to {} failed: {}",
+                            &html.http_user.user.display().await,
+                            discord_account.display().await, e);
+                    }
+                }
+            },
+            "create" if discord_account.has_user().await => {
+                log::warn!("Deny creating new user for {}, because user already exists.", discord_account.display().await);
+            },
+            "login" if !discord_account.has_user().await => {
+                log::warn!("Deny logging into {}, because no user is assigned.", discord_account.display().await);
+            },
+            "create" | "login" => {
+                user = discord_account.user().await;  // this generates a new user for "create"
+                if user.is_none() {
+                    log::error!("Could not get user for {}", discord_account.display().await);
+                }
+            },
+            _ => log::warn!("Deny assigning {} without login", discord_account.display().await),
+        }
+        if let Err(e) = discord_account.set_last_login(chrono::Utc::now()).await {
+            log::error!("Could not set last login of {}: {}", discord_account.display().await, e);
+        }
+    }
+
+    // create login cookie
+    let mut cookie: Option<String> = None;
+    if let Some(some_user) = user.as_ref() {
+        let tbl_cookie = app_state.database.db_members().await.tbl_cookie_logins().await;
+        if let Some(login_cookie_item) = tbl_cookie.create_new_cookie(some_user).await {
+            cookie = login_cookie_item.get_cookie().await;
+        }
+    }
+
+    // user info
+    if is_assign {
+        if assigned {
+            html.message_success("Discord account assigned".to_string());
+        } else {
+            html.message_error("Deny assigning invalid Discord account".to_string());
+        }
+    } else if cookie.is_none() {
+        html.message_error("Login failed!".to_string());
+    }
+
+    // done
+    let mut response = html.into_response().await;
+    response.headers_mut().append(SET_COOKIE, clear_state_cookie.parse().unwrap());
+    if let Some(cookie) = cookie {
+        response.headers_mut().append(SET_COOKIE, cookie.parse().unwrap());
+        response.headers_mut().insert(REFRESH, "0; url=/".parse().unwrap());
+    }
+    Ok(response)
+}
+
 pub async fn handler_logout(State(app_state): State<AppState>,
                             HttpUserExtractor(mut http_user): HttpUserExtractor) -> Result<Response, StatusCode> {
 
@@ -0,0 +1,258 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use serde::Deserialize;
+use sslo_lib::db::members::users::PromotionLevel;
+use sslo_lib::optional_date::OptionalDateTime;
+use crate::app_state::AppState;
+use crate::db2::results::penalties::PenaltyType;
+use crate::db2::results::protests::{ProtestItem, ProtestState};
+use crate::db2::results::sessions::SessionItem;
+use crate::db2::results::SessionType;
+use crate::http::HtmlTemplate;
+use crate::http::http_user::RequireLogin;
+
+#[derive(Deserialize)]
+pub struct ProtestQuery {
+    session: Option<i64>,
+}
+
+/// Date, server and track of a session
+async fn html_session_label(session: &SessionItem) -> String {
+    format!("{} {} {}",
+            OptionalDateTime::new(Some(session.started().await.unwrap_or(session.imported().await))).html_label_full(),
+            html_escape::encode_safe(&session.server_name().await),
+            html_escape::encode_safe(&session.track_identifier().await))
+}
+
+/// A table of protests
+async fn html_protest_table(app_state: &AppState, protests: &[ProtestItem]) -> String {
+    let tbl_ses = app_state.database.db_results().await.tbl_sessions().await;
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+    let mut html = String::new();
+    html += "<table><tr><th>Filed</th><th>Session</th><th>Reporter</th><th>State</th></tr>";
+    for protest in protests.iter() {
+        html += "<tr><td><a href=\"/html/protests/";
+        html += &protest.id().await.to_string();
+        html += "\">";
+        html += &OptionalDateTime::new(Some(protest.filed().await)).html_label_full();
+        html += "</a></td><td>";
+        if let Some(session) = tbl_ses.item_by_id(protest.session().await).await {
+            html += &html_session_label(&session).await;
+        }
+        html += "</td><td>";
+        if let Some(user) = tbl_usr.user_by_id(protest.reporter().await).await {
+            html += &user.html_name().await;
+        }
+        html += "</td><td>";
+        html += protest.state().await.label();
+        html += "</td></tr>";
+    }
+    html += "</table>";
+    html
+}
+
+/// Filing of protests, own protests and the review queue of the stewards
+pub async fn handler(State(app_state): State<AppState>,
+                     RequireLogin(http_user): RequireLogin,
+                     Query(query): Query<ProtestQuery>,
+) -> Result<Response, StatusCode> {
+    let mut html = HtmlTemplate::new(http_user);
+    html.include_js("/rsc/js/protests.js");
+    let user_id = html.http_user.user.id().await;
+    let tbl_ses = app_state.database.db_results().await.tbl_sessions().await;
+    let tbl_pro = app_state.database.db_results().await.tbl_protests().await;
+
+    // file a protest
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h1>File a Protest</h1>");
+    match query.session {
+        Some(session_id) => {
+            let session = tbl_ses.item_by_id(session_id).await.ok_or(StatusCode::NOT_FOUND)?;
+            html.push_body(&format!("<p>{}</p>", html_session_label(&session).await));
+            html.push_body("<table id=\"ProtestForm\">");
+            html.push_body("<tr><th>Involved Drivers</th><td>");
+            for result in session.results().await.iter() {
+                if result.user == Some(user_id) {
+                    continue;
+                }
+                html.push_body(&format!("<label class=\"NoBr\"><input type=\"checkbox\" name=\"ProtestDriver\" value=\"{}\"> {}</label><br>",
+                                        html_escape::encode_double_quoted_attribute(&result.driver_guid),
+                                        html_escape::encode_safe(&result.driver_name)));
+            }
+            html.push_body("</td></tr>");
+            html.push_body("<tr><th>Lap</th><td><input type=\"number\" id=\"ProtestLap\" min=\"1\"></td></tr>");
+            html.push_body("<tr><th>Description</th><td><textarea id=\"ProtestDescription\"></textarea></td></tr>");
+            html.push_body("<tr><th>Evidence</th><td><textarea id=\"ProtestEvidence\" placeholder=\"one link per line\"></textarea></td></tr>");
+            html.push_body("</table>");
+            html.push_body(&format!("<button onclick=\"handler_button_file({})\">File Protest</button>", session_id));
+        },
+        None => {
+            let mut sessions = tbl_ses.items_by_type(SessionType::Race).await;
+            sessions.reverse();
+            sessions.truncate(20);
+            html.push_body("<form method=\"get\"><select name=\"session\">");
+            for session in sessions.iter() {
+                html.push_body(&format!("<option value=\"{}\">{}</option>", session.id().await, html_session_label(session).await));
+            }
+            html.push_body("</select> <button type=\"submit\">Select Session</button></form>");
+        },
+    }
+    html.push_body("</div>");
+
+    // own protests
+    let own_protests = tbl_pro.items_by_reporter(user_id).await;
+    if !own_protests.is_empty() {
+        html.push_body("<div class=\"BgBox\">");
+        html.push_body("<h2>My Protests</h2>");
+        html.push_body(&html_protest_table(&app_state, &own_protests).await);
+        html.push_body("</div>");
+    }
+
+    // review queue
+    if html.http_user.has_promotion(PromotionLevel::Steward).await {
+        html.push_body("<div class=\"BgBox\">");
+        html.push_body("<h2>Review Queue</h2>");
+        html.push_body(&html_protest_table(&app_state, &tbl_pro.items_open().await).await);
+        html.push_body("</div>");
+    }
+
+    // pending appeals against penalties
+    if html.http_user.has_promotion(PromotionLevel::Commissar).await {
+        let tbl_pen = app_state.database.db_results().await.tbl_penalties().await;
+        let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+        html.push_body("<div class=\"BgBox\">");
+        html.push_body("<h2>Pending Appeals</h2>");
+        html.push_body("<table><tr><th>Issued</th><th>Driver</th><th>Penalty</th><th>Appeal</th><th></th></tr>");
+        for penalty in tbl_pen.items_appeal_pending().await.iter() {
+            html.push_body("<tr><td>");
+            html.push_body(&OptionalDateTime::new(Some(penalty.issued().await)).html_label_full());
+            html.push_body("</td><td>");
+            match penalty.user().await {
+                Some(user_id) => if let Some(user) = tbl_usr.user_by_id(user_id).await {
+                    html.push_body(&user.html_name().await);
+                },
+                None => html.push_body(&html_escape::encode_safe(&penalty.driver_guid().await)),
+            }
+            html.push_body("</td><td>");
+            html.push_body(&penalty.describe().await);
+            html.push_body("</td><td>");
+            html.push_body(&html_escape::encode_safe(&penalty.appeal_text().await));
+            html.push_body("</td><td>");
+            let penalty_id = penalty.id().await;
+            html.push_body(&format!("<button onclick=\"handler_button_decide({}, true)\">Accept</button> ", penalty_id));
+            html.push_body(&format!("<button onclick=\"handler_button_decide({}, false)\">Reject</button>", penalty_id));
+            html.push_body("</td></tr>");
+        }
+        html.push_body("</table>");
+        html.push_body("</div>");
+    }
+
+    Ok(html.into_response().await)
+}
+
+/// Details of a single protest with comments and the resolution
+pub async fn handler_protest(State(app_state): State<AppState>,
+                             RequireLogin(http_user): RequireLogin,
+                             Path(protest_id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    let tbl_pro = app_state.database.db_results().await.tbl_protests().await;
+    let protest = tbl_pro.item_by_id(protest_id).await.ok_or(StatusCode::NOT_FOUND)?;
+    let user_id = http_user.user.id().await;
+    let is_steward = http_user.has_promotion(PromotionLevel::Steward).await;
+    let is_reporter = protest.reporter().await == user_id;
+    if !is_steward && !is_reporter && !protest.involves(user_id).await {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+    let session = app_state.database.db_results().await.tbl_sessions().await
+        .item_by_id(protest.session().await).await.ok_or(StatusCode::NOT_FOUND)?;
+    let results = session.results().await;
+    let driver_name = |driver_guid: &str| -> String {
+        match results.iter().find(|r| r.driver_guid == driver_guid) {
+            Some(result) => html_escape::encode_safe(&result.driver_name).to_string(),
+            None => html_escape::encode_safe(driver_guid).to_string(),
+        }
+    };
+
+    let mut html = HtmlTemplate::new(http_user);
+    html.include_js("/rsc/js/protests.js");
+
+    // overview
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body(&format!("<h1>Protest #{}</h1>", protest_id));
+    html.push_body("<table>");
+    html.push_body(&format!("<tr><th>Session</th><td>{}</td></tr>", html_session_label(&session).await));
+    if let Some(lap) = protest.lap().await {
+        html.push_body(&format!("<tr><th>Lap</th><td>{}</td></tr>", lap));
+    }
+    html.push_body("<tr><th>Reporter</th><td>");
+    if let Some(user) = tbl_usr.user_by_id(protest.reporter().await).await {
+        html.push_body(&user.html_name().await);
+    }
+    html.push_body("</td></tr>");
+    html.push_body(&format!("<tr><th>Filed</th><td>{}</td></tr>", OptionalDateTime::new(Some(protest.filed().await)).html_label_full()));
+    let drivers: Vec<String> = protest.drivers().await.iter().map(|d| driver_name(&d.driver_guid)).collect();
+    html.push_body(&format!("<tr><th>Involved Drivers</th><td>{}</td></tr>", drivers.join(", ")));
+    html.push_body("<tr><th>Evidence</th><td>");
+    for url in protest.evidence().await.iter() {
+        let url = html_escape::encode_double_quoted_attribute(url);
+        html.push_body(&format!("<a href=\"{}\" rel=\"nofollow noopener\" target=\"_blank\">{}</a><br>", url, url));
+    }
+    html.push_body("</td></tr>");
+    html.push_body(&format!("<tr><th>Description</th><td>{}</td></tr>", html_escape::encode_safe(&protest.description().await)));
+    html.push_body(&format!("<tr><th>State</th><td>{}</td></tr>", protest.state().await.label()));
+    if protest.state().await != ProtestState::Open {
+        html.push_body(&format!("<tr><th>Resolution</th><td>{}</td></tr>", html_escape::encode_safe(&protest.resolution().await)));
+    }
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    // comments
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h2>Comments</h2>");
+    for comment in protest.comments().await.iter() {
+        html.push_body("<p><strong>");
+        if let Some(user) = tbl_usr.user_by_id(comment.user).await {
+            html.push_body(&user.html_name().await);
+        }
+        html.push_body("</strong> ");
+        html.push_body(&OptionalDateTime::new(Some(comment.date)).html_label_full());
+        html.push_body("<br>");
+        html.push_body(&html_escape::encode_safe(&comment.text));
+        html.push_body("</p>");
+    }
+    if is_steward || is_reporter {
+        html.push_body("<textarea id=\"ProtestComment\"></textarea><br>");
+        html.push_body(&format!("<button onclick=\"handler_button_comment({})\">Comment</button>", protest_id));
+    }
+    html.push_body("</div>");
+
+    // resolution
+    if is_steward && protest.state().await == ProtestState::Open {
+        html.push_body("<div class=\"BgBox\">");
+        html.push_body("<h2>Resolution</h2>");
+        html.push_body("<table>");
+        html.push_body("<tr><th>Decision</th><td><select id=\"ResolutionPenaltyType\">");
+        html.push_body("<option value=\"\">No Further Action</option>");
+        for penalty_type in [PenaltyType::Time, PenaltyType::PositionDrop, PenaltyType::PointsDeduction,
+                             PenaltyType::RaceBan, PenaltyType::LicensePoints] {
+            html.push_body(&format!("<option value=\"{:?}\">{}</option>", penalty_type, penalty_type.label()));
+        }
+        html.push_body("</select></td></tr>");
+        html.push_body("<tr><th>Driver</th><td><select id=\"ResolutionDriver\">");
+        for driver in protest.drivers().await.iter() {
+            html.push_body(&format!("<option value=\"{}\">{}</option>",
+                                    html_escape::encode_double_quoted_attribute(&driver.driver_guid),
+                                    driver_name(&driver.driver_guid)));
+        }
+        html.push_body("</select></td></tr>");
+        html.push_body("<tr><th>Value</th><td><input type=\"number\" id=\"ResolutionValue\" min=\"0\" value=\"0\"></td></tr>");
+        html.push_body("<tr><th>Reason</th><td><textarea id=\"ResolutionText\"></textarea></td></tr>");
+        html.push_body("</table>");
+        html.push_body(&format!("<button onclick=\"handler_button_resolve({})\">Resolve</button>", protest_id));
+        html.push_body("</div>");
+    }
+
+    Ok(html.into_response().await)
+}
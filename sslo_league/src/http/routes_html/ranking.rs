@@ -0,0 +1,58 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use serde::Deserialize;
+use crate::app_state::AppState;
+use crate::http::HtmlTemplate;
+use crate::http::http_user::HttpUserExtractor;
+use crate::ranking;
+
+#[derive(Deserialize)]
+pub struct RankingQuery {
+    car_class: Option<i64>,
+}
+
+/// The driver ranking, overall or within a car class
+pub async fn handler(State(app_state): State<AppState>,
+                     HttpUserExtractor(http_user): HttpUserExtractor,
+                     Query(query): Query<RankingQuery>,
+) -> Result<Response, StatusCode> {
+    let car_class = query.car_class.unwrap_or(0);
+    let tbl_cls = app_state.database.db_content().await.tbl_car_classes().await;
+
+    let mut html = HtmlTemplate::new(http_user);
+
+    // car class selection
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<a href=\"/html/ranking\">Overall</a>");
+    for cls in tbl_cls.items_all().await.iter() {
+        html.push_body(" | <a href=\"/html/ranking?car_class=");
+        html.push_body(&cls.id().await.to_string());
+        html.push_body("\">");
+        html.push_body(&cls.html_name().await);
+        html.push_body("</a>");
+    }
+    html.push_body("</div>");
+
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h1>");
+    match car_class {
+        0 => html.push_body("Driver Ranking"),
+        id => {
+            let cls = tbl_cls.item_by_id(id).await.ok_or(StatusCode::NOT_FOUND)?;
+            html.push_body("Driver Ranking ");
+            html.push_body(&cls.html_name().await);
+        }
+    }
+    html.push_body("</h1>");
+    html.push_body("<table><tr><th>Pos</th><th>Driver</th><th>Rating</th><th>Deviation</th><th>Races</th></tr>");
+    for (index, (user, row)) in ranking::ranking(&app_state.database, car_class).await.iter().enumerate() {
+        html.push_body(&format!("<tr><td>{}</td><td>", index + 1));
+        html.push_body(&user.html_name().await);
+        html.push_body(&format!("</td><td>{:.0}</td><td>&plusmn;{:.0}</td><td>{}</td></tr>", row.rating, row.deviation, row.races));
+    }
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    Ok(html.into_response().await)
+}
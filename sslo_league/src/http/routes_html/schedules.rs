@@ -0,0 +1,216 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use chrono::Utc;
+use sslo_lib::optional_date::OptionalDateTime;
+use crate::app_state::AppState;
+use crate::db2::results::SessionType;
+use crate::db2::series::events::EventItem;
+use crate::http::HtmlTemplate;
+use crate::http::http_user::HttpUserExtractor;
+
+/// The track and layout name of an event
+async fn html_track(app_state: &AppState, event: &EventItem) -> String {
+    let layout = match event.track_layout().await {
+        Some(layout_id) => app_state.database.db_content().await.tbl_track_layouts().await.item_by_id(layout_id).await,
+        None => None,
+    };
+    match layout {
+        Some(layout) => match layout.track().await {
+            Some(track) => format!("{} {}", track.html_name().await, layout.html_name().await),
+            None => layout.html_name().await,
+        },
+        None => "-".to_string(),
+    }
+}
+
+/// List of upcoming and past events
+pub async fn handler(State(app_state): State<AppState>,
+                     HttpUserExtractor(http_user): HttpUserExtractor,
+) -> Result<Response, StatusCode> {
+    let tbl_evt = app_state.database.db_series().await.tbl_events().await;
+    let now = Utc::now();
+
+    let mut upcoming = Vec::new();
+    let mut past = Vec::new();
+    for event in tbl_evt.items_all().await.into_iter() {
+        match event.end().await {
+            Some(end) if end < now => past.push(event),
+            _ => upcoming.push(event),
+        }
+    }
+    past.reverse();
+
+    let mut html = HtmlTemplate::new(http_user);
+    html.include_js("/rsc/js/schedules.js");
+    for (title, events) in [("Scheduled Races", upcoming), ("Past Races", past)] {
+        html.push_body("<div class=\"BgBox\">");
+        html.push_body(&format!("<h1>{}</h1>", title));
+        html.push_body("<table><tr><th>Start</th><th>Event</th><th>Simulator</th><th>Track</th><th>Drivers</th><th>Registration</th></tr>");
+        for event in events.iter() {
+            html.push_body("<tr><td>");
+            html.push_body(&OptionalDateTime::new(event.start().await).html_label_full());
+            html.push_body("</td><td><a href=\"/html/schedules/");
+            html.push_body(&event.id().await.to_string());
+            html.push_body("\">");
+            html.push_body(&event.html_name().await);
+            html.push_body("</a></td><td>");
+            html.push_body(event.simulator().await.label());
+            html.push_body("</td><td>");
+            html.push_body(&html_track(&app_state, event).await);
+            html.push_body("</td><td>");
+            let registrations = event.registrations().await.len();
+            match event.max_grid().await {
+                0 => html.push_body(&registrations.to_string()),
+                max_grid => html.push_body(&format!("{}/{}", registrations, max_grid)),
+            }
+            html.push_body("</td><td>");
+            html.push_body(if event.is_registration_open(now).await { "open" } else { "closed" });
+            html.push_body("</td></tr>");
+        }
+        html.push_body("</table>");
+        html.push_body("</div>");
+    }
+
+    // calendar subscription
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h2>Calendar</h2>");
+    html.push_body("<p>Subscribe to all scheduled races: <a href=\"/ical/schedule.ics\">schedule.ics</a></p>");
+    if html.http_user.is_logged_in() {
+        let tbl_ict = app_state.database.db_series().await.tbl_ical_tokens().await;
+        match tbl_ict.creation(html.http_user.user.id().await).await {
+            Some(creation) => {
+                html.push_body("<p>Your personal calendar link (only your registered races) was created ");
+                html.push_body(&OptionalDateTime::new(Some(creation)).html_label_full());
+                html.push_body("</p>");
+                html.push_body("<button onclick=\"handler_button_calendar_create()\">New Link</button> ");
+                html.push_body("<button onclick=\"handler_button_calendar_revoke()\">Revoke Link</button>");
+            },
+            None => {
+                html.push_body("<p>Create a personal calendar link with only your registered races.</p>");
+                html.push_body("<button onclick=\"handler_button_calendar_create()\">Create Link</button>");
+            }
+        }
+        html.push_body("<p><input type=\"text\" id=\"CalendarUrl\" readonly hidden></p>");
+    }
+    html.push_body("</div>");
+
+    Ok(html.into_response().await)
+}
+
+/// Details of a single event with the grid and the waitlist
+pub async fn handler_event(State(app_state): State<AppState>,
+                           HttpUserExtractor(http_user): HttpUserExtractor,
+                           Path(event_id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    let tbl_evt = app_state.database.db_series().await.tbl_events().await;
+    let event = tbl_evt.item_by_id(event_id).await.ok_or(StatusCode::NOT_FOUND)?;
+    let tbl_car = app_state.database.db_content().await.tbl_cars().await;
+    let tbl_cls = app_state.database.db_content().await.tbl_car_classes().await;
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+
+    let mut html = HtmlTemplate::new(http_user);
+    html.include_js("/rsc/js/schedules.js");
+
+    // overview
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h1>");
+    html.push_body(&event.html_name().await);
+    html.push_body("</h1>");
+    html.push_body("<p>");
+    html.push_body(&html_escape::encode_safe(&event.description().await));
+    html.push_body("</p>");
+    html.push_body("<table>");
+    html.push_body(&format!("<tr><th>Simulator</th><td>{}</td></tr>", event.simulator().await.label()));
+    html.push_body(&format!("<tr><th>Track</th><td>{}</td></tr>", html_track(&app_state, &event).await));
+    html.push_body(&format!("<tr><th>Server</th><td>{}</td></tr>", html_escape::encode_safe(&event.server().await)));
+    let mut class_names = Vec::new();
+    for class_id in event.car_classes().await.iter() {
+        if let Some(cls) = tbl_cls.item_by_id(*class_id).await {
+            class_names.push(cls.html_name().await);
+        }
+    }
+    if class_names.is_empty() {
+        class_names.push("all".to_string());
+    }
+    html.push_body(&format!("<tr><th>Car Classes</th><td>{}</td></tr>", class_names.join(", ")));
+    for session_type in [SessionType::Practice, SessionType::Qualifying, SessionType::Race] {
+        if let Some(schedule) = event.session_schedule(session_type).await {
+            html.push_body(&format!("<tr><th>{}</th><td>{} ({} min)</td></tr>",
+                                    session_type.label(),
+                                    OptionalDateTime::new(Some(schedule.start)).html_label_full(),
+                                    schedule.duration_minutes));
+        }
+    }
+    let (opens, closes) = event.registration_window().await;
+    if opens.is_some() {
+        html.push_body(&format!("<tr><th>Registration Opens</th><td>{}</td></tr>", OptionalDateTime::new(opens).html_label_full()));
+    }
+    if closes.is_some() {
+        html.push_body(&format!("<tr><th>Registration Closes</th><td>{}</td></tr>", OptionalDateTime::new(closes).html_label_full()));
+    }
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    // registration of the current user
+    if html.http_user.is_logged_in() {
+        let user_id = html.http_user.user.id().await;
+        html.push_body("<div class=\"BgBox\">");
+        html.push_body("<h2>Registration</h2>");
+        if let Some(status) = event.registration_status(user_id).await {
+            html.push_body(&format!("<p>You are registered: {}</p>", status.label()));
+            html.push_body(&format!("<button onclick=\"handler_button_unregister({})\">Withdraw</button>", event_id));
+        } else if event.is_registration_open(Utc::now()).await {
+            let allowed_classes = event.car_classes().await;
+            html.push_body("<select id=\"RegistrationCar\">");
+            for car in tbl_car.items_all().await.iter() {
+                if !allowed_classes.is_empty() {
+                    let car_class = match car.car_class().await {
+                        Some(car_class) => Some(car_class.id().await),
+                        None => None,
+                    };
+                    if !car_class.is_some_and(|c| allowed_classes.contains(&c)) {
+                        continue;
+                    }
+                }
+                html.push_body(&format!("<option value=\"{}\">{}</option>", car.id().await, car.html_name().await));
+            }
+            html.push_body("</select> ");
+            html.push_body(&format!("<button onclick=\"handler_button_register({})\">Sign Up</button>", event_id));
+        } else {
+            html.push_body("<p>Registration is closed.</p>");
+        }
+        html.push_body("</div>");
+    }
+
+    // grid and waitlist
+    let (grid, waitlist) = event.grid_and_waitlist().await;
+    for (title, registrations) in [("Grid", grid), ("Waitlist", waitlist)] {
+        if title == "Waitlist" && registrations.is_empty() {
+            continue;
+        }
+        html.push_body("<div class=\"BgBox\">");
+        html.push_body(&format!("<h2>{}</h2>", title));
+        html.push_body("<table><tr><th>#</th><th>Driver</th><th>Car</th><th>Registered</th></tr>");
+        for (index, registration) in registrations.iter().enumerate() {
+            html.push_body(&format!("<tr><td>{}</td><td>", index + 1));
+            if let Some(user) = tbl_usr.user_by_id(registration.user).await {
+                html.push_body(&user.html_name().await);
+            }
+            html.push_body("</td><td>");
+            if let Some(car) = match registration.car {
+                Some(car_id) => tbl_car.item_by_id(car_id).await,
+                None => None,
+            } {
+                html.push_body(&car.html_name().await);
+            }
+            html.push_body("</td><td>");
+            html.push_body(&OptionalDateTime::new(Some(registration.registered)).html_label_full());
+            html.push_body("</td></tr>");
+        }
+        html.push_body("</table>");
+        html.push_body("</div>");
+    }
+
+    Ok(html.into_response().await)
+}
@@ -0,0 +1,84 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use crate::app_state::AppState;
+use crate::http::HtmlTemplate;
+use crate::http::http_user::HttpUserExtractor;
+
+fn html_optional<T: std::fmt::Display>(value: Option<T>, unit: &str) -> String {
+    match value {
+        Some(v) => format!("{} {}", v, unit).trim().to_string(),
+        None => "-".to_string(),
+    }
+}
+
+/// List all tracks
+pub async fn handler(State(app_state): State<AppState>,
+                     HttpUserExtractor(http_user): HttpUserExtractor,
+) -> Result<Response, StatusCode> {
+    let tbl_trk = app_state.database.db_content().await.tbl_tracks().await;
+
+    let mut html = HtmlTemplate::new(http_user);
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<table><tr><th>Track</th><th>Country</th><th>Layouts</th></tr>");
+    for track in tbl_trk.items_all().await.iter() {
+        html.push_body("<tr><td><a href=\"/html/tracks/");
+        html.push_body(&track.id().await.to_string());
+        html.push_body("\">");
+        html.push_body(&track.html_name().await);
+        html.push_body("</a></td><td>");
+        html.push_body(&html_escape::encode_safe(&track.country().await));
+        html.push_body("</td><td>");
+        html.push_body(&track.layouts().await.len().to_string());
+        html.push_body("</td></tr>");
+    }
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    Ok(html.into_response().await)
+}
+
+/// Show a single track with its layouts
+pub async fn handler_track(State(app_state): State<AppState>,
+                           HttpUserExtractor(http_user): HttpUserExtractor,
+                           Path(track_id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    let tbl_trk = app_state.database.db_content().await.tbl_tracks().await;
+    let track = tbl_trk.item_by_id(track_id).await.ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut html = HtmlTemplate::new(http_user);
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h1>");
+    html.push_body(&track.html_name().await);
+    html.push_body("</h1>");
+    html.push_body("<table><tr><th>Country</th><td>");
+    html.push_body(&html_escape::encode_safe(&track.country().await));
+    html.push_body("</td></tr></table>");
+    html.push_body("</div>");
+
+    for layout in track.layouts().await.iter() {
+        html.push_body("<div class=\"BgBox\">");
+        html.push_body("<h2>");
+        html.push_body(&layout.html_name().await);
+        html.push_body("</h2><table>");
+        html.push_body("<tr><th>Length</th><td>");
+        html.push_body(&html_optional(layout.length().await, "m"));
+        html.push_body("</td></tr><tr><th>Pit-Lane Length</th><td>");
+        html.push_body(&html_optional(layout.pit_lane_length().await, "m"));
+        html.push_body("</td></tr><tr><th>Pit Speed Limit</th><td>");
+        html.push_body(&html_optional(layout.pit_speed_limit().await, "km/h"));
+        html.push_body("</td></tr><tr><th>Pit Boxes</th><td>");
+        html.push_body(&html_optional(layout.pit_boxes().await, ""));
+        html.push_body("</td></tr>");
+        for sim_id in layout.sim_ids().await.iter() {
+            html.push_body("<tr><th>");
+            html.push_body(sim_id.simulator.label());
+            html.push_body("</th><td>");
+            html.push_body(&html_escape::encode_safe(&sim_id.identifier));
+            html.push_body("</td></tr>");
+        }
+        html.push_body("</table></div>");
+    }
+
+    Ok(html.into_response().await)
+}
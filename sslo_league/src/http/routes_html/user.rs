@@ -5,6 +5,7 @@ use axum::http::StatusCode;
 use axum::response::Response;
 use sslo_lib::optional_date::OptionalDateTime;
 use crate::app_state::AppState;
+use crate::db2::results::penalties::AppealState;
 use crate::http::HtmlTemplate;
 use crate::http::http_user::HttpUserExtractor;
 
@@ -13,6 +14,7 @@ pub async fn handler_profile(State(app_state): State<AppState>,
                              HttpUserExtractor(http_user): HttpUserExtractor) -> Result<Response, StatusCode> {
 
     let tbl_eml = app_state.database.db_members().await.tbl_email_accounts().await;
+    let tbl_pen = app_state.database.db_results().await.tbl_penalties().await;
 
     let mut html = HtmlTemplate::new(http_user);
     html.include_css("/rsc/css/user.css");
@@ -34,7 +36,11 @@ pub async fn handler_profile(State(app_state): State<AppState>,
     html.push_body("</td></tr>");
 
     html.push_body("<tr><th>Promotion</th><td>");
-    html.push_body(html.http_user.user.promotion().await.label());
+    let promotion = html.http_user.user.promotion().await;
+    html.push_body(&format!("{} {}", promotion.symbol(), promotion.label()));
+    if html.http_user.is_root {
+        html.push_body(" (Root)");
+    }
     html.push_body("</td></tr>");
 
     html.push_body("<tr><th>Last Lap</th><td>");
@@ -53,7 +59,39 @@ pub async fn handler_profile(State(app_state): State<AppState>,
     }
     html.push_body("</td></tr>");
 
+    html.push_body("<tr><th>License Points</th><td>");
+    html.push_body(&format!("{}", tbl_pen.license_points(html.http_user.user.id().await).await));
+    html.push_body("</td></tr>");
+
     html.push_body("</table></div>");
 
+    // penalties
+    let penalties = tbl_pen.items_by_user(html.http_user.user.id().await).await;
+    if !penalties.is_empty() {
+        html.push_body("<div class=\"BgBox\"><table id=\"UserPenalties\">");
+        html.push_body("<tr><th>Issued</th><th>Session</th><th>Penalty</th><th>Reason</th><th>Appeal</th><th></th></tr>");
+        for penalty in penalties.iter() {
+            let penalty_id = penalty.id().await;
+            html.push_body("<tr><td>");
+            html.push_body(&OptionalDateTime::new(Some(penalty.issued().await)).html_label_full());
+            html.push_body(&format!("</td><td>{}</td><td>", penalty.session().await));
+            if penalty.revoked().await {
+                html.push_body(&format!("<s>{}</s>", penalty.describe().await));
+            } else {
+                html.push_body(&penalty.describe().await);
+            }
+            html.push_body("</td><td>");
+            html.push_body(&html_escape::encode_safe(&penalty.reason().await));
+            html.push_body("</td><td>");
+            html.push_body(penalty.appeal_state().await.label());
+            html.push_body("</td><td>");
+            if !penalty.revoked().await && penalty.appeal_state().await == AppealState::None {
+                html.push_body(&format!("<button onclick=\"handler_button_appeal({})\">Appeal</button>", penalty_id));
+            }
+            html.push_body("</td></tr>");
+        }
+        html.push_body("</table></div>");
+    }
+
     Ok(html.into_response().await)
 }
@@ -4,21 +4,19 @@ use axum::response::Response;
 use sslo_lib::optional_date::OptionalDateTime;
 use crate::app_state::AppState;
 use crate::http::HtmlTemplate;
-use crate::http::http_user::HttpUserExtractor;
+use crate::http::http_user::RequireLogin;
 
 pub async fn handler(State(app_state): State<AppState>,
-                     HttpUserExtractor(http_user): HttpUserExtractor,
+                     RequireLogin(http_user): RequireLogin,
                      OriginalUri(uri): OriginalUri,
 ) -> Result<Response, StatusCode> {
-
-    if !http_user.is_logged_in() {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
-
     // get tables
     let db_members = app_state.database.db_members().await;
     let tbl_eml = db_members.tbl_email_accounts().await;
     let tbl_steam = db_members.tbl_steam_accounts().await;
+    let tbl_wac = db_members.tbl_webauthn_credentials().await;
+    let tbl_discord = db_members.tbl_discord_accounts().await;
+    let tbl_cookie = db_members.tbl_cookie_logins().await;
 
     let mut html = HtmlTemplate::new(http_user);
     html.include_css("/rsc/css/user_accounts.css");
@@ -30,17 +28,51 @@ pub async fn handler(State(app_state): State<AppState>,
     html.push_body("<button id=\"AccountTypeButtonPassword\" onclick=\"tabSelectByIndex(0)\" class=\"ActiveButton\">SSLO Password</button>");
     html.push_body("<button id=\"AccountTypeButtonEmail\" onclick=\"tabSelectByIndex(1)\">Emails</button>");
     html.push_body("<button id=\"AccountTypeButtonSteam\" onclick=\"tabSelectByIndex(2)\">Steam</button>");
-    html.push_body("<button id=\"AccountTypeButtonDiscord\" onclick=\"tabSelectByIndex(3)\">Discord</button>");
+    html.push_body("<button id=\"AccountTypeButtonPasskey\" onclick=\"tabSelectByIndex(3)\">Passkeys</button>");
+    html.push_body("<button id=\"AccountTypeButtonDiscord\" onclick=\"tabSelectByIndex(4)\">Discord</button>");
+    html.push_body("<button id=\"AccountTypeButtonSessions\" onclick=\"tabSelectByIndex(5)\">Sessions</button>");
+    html.push_body("<button id=\"AccountTypeButtonMerge\" onclick=\"tabSelectByIndex(6)\">Merge</button>");
+    html.push_body("<button id=\"AccountTypeButtonData\" onclick=\"tabSelectByIndex(7)\">Data</button>");
     html.push_body("</div><hr>");
 
     // Tab password
-    html.push_body("<table id=\"AccountTabPassword\" class=\"LiveInput TabActive\">");
+    html.push_body("<div id=\"AccountTabPassword\" class=\"TabActive\">");
+    html.push_body("<table id=\"PasswordChange\" class=\"LiveInput\">");
     html.push_body("<tr><th>Current Password</th><td><input type=\"password\" name=\"PasswordCurrent\" placeholder=\"current password\"></td></tr>");
     html.push_body("<tr><th>New Password</th><td><input type=\"password\" name=\"PasswordNew1\" placeholder=\"new password\"></td></tr>");
     html.push_body("<tr><th>Repeat Password</th><td><input type=\"password\" name=\"PasswordNew2\" placeholder=\"repeat password\"></td></tr>");
     html.push_body("<tr><th></th><td><button title=\"Save\">&#128190; Save</button>");
     html.push_body("</table>");
 
+    // two-factor authentication
+    if app_state.config.totp.is_some() {
+        html.push_body("<h2>Two-Factor Authentication (TOTP)</h2>");
+        html.push_body("<table id=\"Totp\">");
+        let totp_enabled = html.http_user.user.totp_enabled().await;
+        if totp_enabled.raw().is_some() {
+            html.push_body(&format!("<tr><th>Enabled Since</th><td>{}</td></tr>", totp_enabled.html_label_full()));
+            html.push_body(&format!("<tr><th>Recovery Codes</th><td>{} left</td></tr>", html.http_user.user.totp_recovery_codes_left().await));
+            html.push_body("<tr><th>Code</th><td><input type=\"text\" id=\"TotpCode\" placeholder=\"authenticator or recovery code\" autocomplete=\"one-time-code\"></td></tr>");
+            if html.http_user.promotion_limit.is_some() {
+                html.push_body("<tr><td colspan=\"2\">Your promotion requires this login to be verified with a code.</td></tr>");
+                html.push_body("<tr><th></th><td><button onclick=\"handler_button_totp_verify()\">Verify Login</button></td></tr>");
+            }
+            html.push_body("<tr><th></th><td><button onclick=\"handler_button_totp_recovery_codes()\">Renew Recovery Codes</button> ");
+            html.push_body("<button onclick=\"handler_button_totp_disable()\">Disable</button></td></tr>");
+        } else {
+            if html.http_user.promotion_limit.is_some() {
+                html.push_body("<tr><td colspan=\"2\">Your promotion requires two-factor authentication.</td></tr>");
+            }
+            html.push_body("<tr><th></th><td><button onclick=\"handler_button_totp_enroll()\">Enable</button></td></tr>");
+            html.push_body("<tr class=\"TotpEnrollment\"><th>Secret</th><td><a id=\"TotpUri\"></a><br><code id=\"TotpSecret\"></code></td></tr>");
+            html.push_body("<tr class=\"TotpEnrollment\"><th>Code</th><td><input type=\"text\" id=\"TotpCode\" placeholder=\"code from authenticator\" autocomplete=\"one-time-code\"></td></tr>");
+            html.push_body("<tr class=\"TotpEnrollment\"><th></th><td><button onclick=\"handler_button_totp_confirm()\">Confirm</button></td></tr>");
+        }
+        html.push_body("<tr id=\"TotpRecoveryCodes\"><th>New Recovery Codes</th><td><code></code><br>Store these codes safely, each can be used once instead of a code from the authenticator.</td></tr>");
+        html.push_body("</table>");
+    }
+    html.push_body("</div>");
+
     // Tab Email
     html.push_body("<div id=\"AccountTabEmail\" class=\"TabInActive\">");
     html.push_body("<table><tr><th>Email</th><th>Verified Since</th><th>Info</th></tr>");
@@ -106,11 +138,109 @@ pub async fn handler(State(app_state): State<AppState>,
     }
     html.push_body("</div>");
 
+    // Tab Passkey
+    html.push_body("<div id=\"AccountTabPasskey\" class=\"TabInActive\">");
+    html.push_body("<table><tr><th>Name</th><th>Created At</th><th>Last Usage</th></tr>");
+    for wac in tbl_wac.items_by_user(&html.http_user.user).await.iter() {
+        html.push_body("<tr><td>");
+        html.push_body(&html_escape::encode_safe(&wac.name().await));
+        html.push_body("</td><td>");
+        html.push_body(&OptionalDateTime::new(Some(wac.creation().await)).html_label_full());
+        html.push_body("</td><td>");
+        html.push_body(&wac.last_usage().await.html_label_full());
+        html.push_body("</td><td>");
+        html.push_body(&format!("<button class=\"ButtonDelete\" onclick=\"handler_button_delete_passkey({})\" title=\"remove passkey\"></button>", wac.id().await));
+        html.push_body("</td></tr>");
+    }
+    html.push_body("<tr><td colspan=\"3\">");
+    html.push_body("<input type=\"text\" id=\"AddPasskeyName\" placeholder=\"Name of new passkey\"></td><td>");
+    html.push_body("<button title=\"Register passkey\" class=\"ButtonAdd\" onclick=\"handler_button_add_passkey()\"></button>");
+    html.push_body("</td></tr>");
+    html.push_body("</table>");
+    html.push_body("</div>");
+
     // Tab Discord
     html.push_body("<div id=\"AccountTabDiscord\" class=\"TabInActive\">");
-    html.push_body("Discord");
+    html.push_body("<table><tr><th>Discord User</th><th>Created At</th><th>Last Login</th></tr>");
+    for discord in tbl_discord.items_by_user(&html.http_user.user).await.iter() {
+        html.push_body("<tr><td>");
+        html.push_body(&html_escape::encode_safe(&discord.username().await));
+        html.push_body("</td><td>");
+        html.push_body(&OptionalDateTime::new(Some(discord.creation().await)).html_label_full());
+        html.push_body("</td><td>");
+        html.push_body(&discord.last_login().await.html_label_full());
+        html.push_body("</td></tr>");
+    }
+    html.push_body("</table>");
+    if app_state.config.discord.is_some() && app_state.config.general.public_url.is_some() {
+        html.push_body("<a href=\"/html/login_discord/assign\" target=\"_top\">Link Discord Account</a>");
+    }
     html.push_body("</div>");
 
+    // Tab Sessions
+    let current_session_id = match html.http_user.cookie_login.as_ref() {
+        Some(cookie_login) => Some(cookie_login.id().await),
+        None => None,
+    };
+    html.push_body("<div id=\"AccountTabSessions\" class=\"TabInActive\">");
+    html.push_body("<table><tr><th>Device</th><th>Created At</th><th>Last Usage</th></tr>");
+    for session in tbl_cookie.items_by_user(&html.http_user.user).await.iter() {
+        let session_id = session.id().await;
+        html.push_body("<tr><td>");
+        html.push_body(&html_escape::encode_safe(&session.last_useragent().await.unwrap_or_default()));
+        if Some(session_id) == current_session_id {
+            html.push_body(" <strong>(current)</strong>");
+        }
+        html.push_body("</td><td>");
+        html.push_body(&OptionalDateTime::new(Some(session.creation().await)).html_label_full());
+        html.push_body("</td><td>");
+        html.push_body(&OptionalDateTime::new(session.last_usage().await).html_label_full());
+        html.push_body("</td><td>");
+        html.push_body(&format!("<button class=\"ButtonDelete\" onclick=\"handler_button_delete_session({})\" title=\"log out this session\"></button>", session_id));
+        html.push_body("</td></tr>");
+    }
+    html.push_body("</table>");
+    html.push_body("<button onclick=\"handler_button_delete_other_sessions()\">Log Out Everywhere Else</button>");
+    html.push_body("</div>");
+
+    // Tab Merge
+    html.push_body("<div id=\"AccountTabMerge\" class=\"TabInActive\">");
+    html.push_body("<p>When you accidentally created a second user (e.g. by logging in with Steam and with Email), the users can be merged. ");
+    html.push_body("Create a merge code with the user that shall disappear, then login with the other user and enter the code there. ");
+    html.push_body("All login methods, results and registrations are moved, the name and password of the other user are kept.</p>");
+    html.push_body("<table>");
+    html.push_body("<tr><th>Merge this user</th><td><button onclick=\"handler_button_merge_code()\">Create Merge Code</button></td></tr>");
+    html.push_body("<tr id=\"MergeCode\"><th>Merge Code</th><td><code></code></td></tr>");
+    html.push_body("<tr><th>Merge other user</th><td>");
+    html.push_body("<input type=\"number\" id=\"MergeUserId\" placeholder=\"User-ID\">");
+    html.push_body("<input type=\"text\" id=\"MergeUserCode\" placeholder=\"Merge Code\">");
+    html.push_body("<button onclick=\"handler_button_merge()\">Merge Into This User</button>");
+    html.push_body("</td></tr>");
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    // Tab Data
+    html.push_body("<div id=\"AccountTabData\" class=\"TabInActive\">");
+    html.push_body("<p>All personal data that is stored about this user can be downloaded as JSON file.</p>");
+    html.push_body("<a href=\"/api/v0/user/data\" download>Download My Data</a>");
+    html.push_body("<p>Deleting this user removes all login methods, emails, sessions and registrations. ");
+    html.push_body("Results are kept for the league history, but the driver name is replaced by an anonymous name. ");
+    html.push_body("This cannot be undone.</p>");
+    html.push_body("<table id=\"DeleteUser\">");
+    let has_password = html.http_user.user.has_password().await;
+    let totp_enabled = html.http_user.user.totp_enabled().await.raw().is_some();
+    if has_password {
+        html.push_body("<tr><th>Password</th><td><input type=\"password\" id=\"DeleteUserPassword\" placeholder=\"current password\"></td></tr>");
+    }
+    if totp_enabled {
+        html.push_body("<tr><th>Code</th><td><input type=\"text\" id=\"DeleteUserTotp\" placeholder=\"authenticator or recovery code\" autocomplete=\"one-time-code\"></td></tr>");
+    }
+    if !has_password && !totp_enabled {
+        html.push_body("<tr><th>Name</th><td><input type=\"text\" id=\"DeleteUserName\" placeholder=\"enter your name to confirm\"></td></tr>");
+    }
+    html.push_body("<tr><th></th><td><button onclick=\"handler_button_delete_user()\">Delete This User</button></td></tr>");
+    html.push_body("</table>");
+    html.push_body("</div>");
 
     html.push_body("</div>");
     Ok(html.into_response().await)
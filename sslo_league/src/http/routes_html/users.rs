@@ -0,0 +1,106 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use sslo_lib::db::members::users::{Promotion, PromotionAuthority, PromotionLevel};
+use sslo_lib::optional_date::OptionalDateTime;
+use crate::app_state::AppState;
+use crate::http::HtmlTemplate;
+use crate::http::http_user::HttpUserExtractor;
+
+const LEVELS: [PromotionLevel; 7] = [PromotionLevel::None, PromotionLevel::Steward, PromotionLevel::Marshal,
+    PromotionLevel::Officer, PromotionLevel::Commissar, PromotionLevel::Director, PromotionLevel::Admin];
+
+/// A promotion badge with the label as tooltip
+fn html_badge(promotion: &Promotion) -> String {
+    format!("<span class=\"PromotionBadge\" title=\"{}\">{}</span>", promotion.label(), promotion.symbol())
+}
+
+/// List of all users with their promotion (Chiefs can change promotions)
+pub async fn handler(State(app_state): State<AppState>,
+                     HttpUserExtractor(http_user): HttpUserExtractor,
+) -> Result<Response, StatusCode> {
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+
+    let mut html = HtmlTemplate::new(http_user);
+    html.include_js("/rsc/js/users.js");
+    let own_id = html.http_user.user.id().await;
+    let is_root = html.http_user.has_root_permissions();
+    let own_promotion = match html.http_user.is_logged_in() {
+        true => html.http_user.user.promotion().await,
+        false => Promotion::new_lowest(),
+    };
+
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h1>Users</h1>");
+    html.push_body("<table><tr><th>Id</th><th>Name</th><th>Activity</th><th>Promotion</th><th></th></tr>");
+    for user in tbl_usr.users_all().await.iter() {
+        let user_id = user.id().await;
+        let promotion = user.promotion().await;
+        html.push_body(&format!("<tr><td>{}</td><td><a href=\"/html/users/{}\">{}</a></td><td>{}</td><td>{} {}</td><td>",
+                                user_id, user_id, user.html_name().await, user.activity().await.label(),
+                                html_badge(&promotion), promotion.label()));
+
+        // promotion editor (only for users that can be changed)
+        if is_root || (user_id != own_id && own_promotion.may_grant(&promotion, &Promotion::new_lowest())) {
+            html.push_body(&format!("<select id=\"PromotionLevel{}\">", user_id));
+            for level in LEVELS.iter().filter(|l| is_root || **l < own_promotion.level) {
+                let selected = if *level == promotion.level { " selected" } else { "" };
+                html.push_body(&format!("<option value=\"{:?}\"{}>{:?}</option>", level, selected, level));
+            }
+            html.push_body("</select> ");
+            html.push_body(&format!("<select id=\"PromotionAuthority{}\">", user_id));
+            for authority in [PromotionAuthority::Executing, PromotionAuthority::Chief].into_iter().filter(|a| is_root || *a < own_promotion.authority) {
+                let selected = if authority == promotion.authority { " selected" } else { "" };
+                html.push_body(&format!("<option value=\"{:?}\"{}>{:?}</option>", authority, selected, authority));
+            }
+            html.push_body("</select> ");
+            html.push_body(&format!("<button onclick=\"handler_button_promote({})\">Save</button>", user_id));
+        }
+        html.push_body("</td></tr>");
+    }
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    Ok(html.into_response().await)
+}
+
+/// A single user with the promotion history
+pub async fn handler_user(State(app_state): State<AppState>,
+                          HttpUserExtractor(http_user): HttpUserExtractor,
+                          Path(user_id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+    let user = tbl_usr.user_by_id(user_id).await.ok_or(StatusCode::NOT_FOUND)?;
+    let promotion = user.promotion().await;
+
+    let mut html = HtmlTemplate::new(http_user);
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body(&format!("<h1>{} {}</h1>", html_badge(&promotion), user.html_name().await));
+    html.push_body("<table>");
+    html.push_body(&format!("<tr><th>Activity</th><td>{}</td></tr>", user.activity().await.label()));
+    html.push_body(&format!("<tr><th>Promotion</th><td>{}</td></tr>", promotion.label()));
+    html.push_body(&format!("<tr><th>Last Lap</th><td>{}</td></tr>", user.last_lap().await.html_label_full()));
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    html.push_body("<div class=\"BgBox\">");
+    html.push_body("<h2>Promotion History</h2>");
+    html.push_body("<table><tr><th>Date</th><th>Promotion</th><th>Granted By</th></tr>");
+    for row in user.promotion_history().await.iter().rev() {
+        let row_promotion = Promotion::new(row.promotion_level.clone(), row.promotion_authority.clone());
+        html.push_body(&format!("<tr><td>{}</td><td>{} {}</td><td>",
+                                OptionalDateTime::new(Some(row.date)).html_label_full(),
+                                html_badge(&row_promotion), row_promotion.label()));
+        match row.granted_by {
+            Some(grantor_id) => if let Some(grantor) = tbl_usr.user_by_id(grantor_id).await {
+                html.push_body(&grantor.html_name().await);
+            },
+            None => html.push_body("Administration"),
+        }
+        html.push_body("</td></tr>");
+    }
+    html.push_body("</table>");
+    html.push_body("</div>");
+
+    Ok(html.into_response().await)
+}
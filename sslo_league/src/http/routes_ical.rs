@@ -0,0 +1,40 @@
+use axum::extract::{Host, Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use crate::app_state::AppState;
+use crate::ical;
+
+fn calendar_response(ics: String) -> Response {
+    ([(header::CONTENT_TYPE, "text/calendar; charset=utf-8")], ics).into_response()
+}
+
+/// Calendar of all scheduled events
+pub async fn handler_schedule(State(app_state): State<AppState>,
+                              Host(host): Host) -> Response {
+    let events = app_state.database.db_series().await.tbl_events().await.items_all().await;
+    calendar_response(ical::calendar(&app_state.database, &events, &host, "SSLO Scheduled Races").await)
+}
+
+/// Personal calendar with the events a user registered for
+/// The file name is the secret feed token with an '.ics' extension.
+pub async fn handler_user(State(app_state): State<AppState>,
+                          Host(host): Host,
+                          Path(file_name): Path<String>) -> Response {
+    let feed_token = match file_name.strip_suffix(".ics") {
+        Some(feed_token) => feed_token,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let tbl_ict = app_state.database.db_series().await.tbl_ical_tokens().await;
+    let user_id = match tbl_ict.user_by_token(feed_token).await {
+        Some(user_id) => user_id,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    let mut events = Vec::new();
+    for event in app_state.database.db_series().await.tbl_events().await.items_all().await.into_iter() {
+        if event.registration_status(user_id).await.is_some() {
+            events.push(event);
+        }
+    }
+    calendar_response(ical::calendar(&app_state.database, &events, &host, "SSLO My Races").await)
+}
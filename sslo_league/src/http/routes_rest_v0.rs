@@ -1,9 +1,18 @@
 use axum::http::StatusCode;
 use axum::Json;
-use axum::response::IntoResponse;
-use crate::http::http_user::HttpUser;
+use axum::response::{IntoResponse, Response};
 
+pub mod cars;
+pub mod championships;
+pub mod federation;
 pub mod login_password;
+pub mod login_webauthn;
+pub mod penalties;
+pub mod protests;
+pub mod ranking;
+pub mod results;
+pub mod schedules;
+pub mod tracks;
 pub mod user;
 
 
@@ -34,3 +43,9 @@ impl IntoResponse for GeneralError {
         (self.status_code, Json(json_data)).into_response()
     }
 }
+
+/// Log an internal error and create an according response
+pub(super) fn internal_error(e: impl std::fmt::Display) -> Response {
+    log::error!("{}", e);
+    GeneralError::new(StatusCode::INTERNAL_SERVER_ERROR, "Database operation failed".to_string()).into_response()
+}
@@ -0,0 +1,286 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::app_state::AppState;
+use crate::db2::content::car_classes::CarClassItem;
+use crate::db2::content::cars::CarItem;
+use crate::http::http_user::{levels, RequirePromotion};
+use super::{internal_error, GeneralError};
+use super::tracks::SimIdJson;
+use super::user::EmptyResponse;
+
+#[derive(Serialize, Clone)]
+pub struct BopJson {
+    ballast: f64,
+    restrictor: f64,
+    since: DateTime<Utc>,
+    user: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct CarJson {
+    id: i64,
+    name: String,
+    manufacturer: String,
+    car_class: Option<i64>,
+    sim_ids: Vec<SimIdJson>,
+    bop: Option<BopJson>,
+    bop_history: Vec<BopJson>,
+}
+
+#[derive(Serialize)]
+pub struct CarClassJson {
+    id: i64,
+    name: String,
+    cars: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct CarRequest {
+    name: Option<String>,
+    manufacturer: Option<String>,
+
+    /// zero removes the class assignment
+    car_class: Option<i64>,
+    sim_ids: Option<Vec<SimIdJson>>,
+}
+
+#[derive(Deserialize)]
+pub struct CarClassRequest {
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BopRequest {
+    ballast: f64,
+    restrictor: f64,
+}
+
+async fn car_json(car: &CarItem) -> CarJson {
+    let bop_history: Vec<BopJson> = car.bop_history().await.into_iter()
+        .map(|row| BopJson{ballast: row.ballast, restrictor: row.restrictor, since: row.since, user: row.user})
+        .collect();
+    let car_class = match car.car_class().await {
+        Some(car_class) => Some(car_class.id().await),
+        None => None,
+    };
+    CarJson {
+        id: car.id().await,
+        name: car.name().await,
+        manufacturer: car.manufacturer().await,
+        car_class,
+        sim_ids: car.sim_ids().await.into_iter()
+            .map(|row| SimIdJson{simulator: row.simulator, identifier: row.identifier})
+            .collect(),
+        bop: bop_history.first().cloned(),
+        bop_history,
+    }
+}
+
+async fn car_class_json(car_class: &CarClassItem) -> CarClassJson {
+    let mut cars = Vec::new();
+    for car in car_class.cars().await.iter() {
+        cars.push(car.id().await);
+    }
+    CarClassJson {
+        id: car_class.id().await,
+        name: car_class.name().await,
+        cars,
+    }
+}
+
+fn car_not_found() -> Response {
+    GeneralError::new(StatusCode::NOT_FOUND, "Car not found".to_string()).into_response()
+}
+
+fn car_class_not_found() -> Response {
+    GeneralError::new(StatusCode::NOT_FOUND, "Car class not found".to_string()).into_response()
+}
+
+async fn apply_car_request(app_state: &AppState, car: &CarItem, input: CarRequest) -> Result<(), Response> {
+    if let Some(name) = input.name {
+        car.set_name(name).await.map_err(internal_error)?;
+    }
+    if let Some(manufacturer) = input.manufacturer {
+        car.set_manufacturer(manufacturer).await.map_err(internal_error)?;
+    }
+    if let Some(class_id) = input.car_class {
+        if class_id == 0 {
+            car.set_car_class(None).await.map_err(internal_error)?;
+        } else {
+            let tbl_cls = app_state.database.db_content().await.tbl_car_classes().await;
+            let car_class = tbl_cls.item_by_id(class_id).await.ok_or_else(car_class_not_found)?;
+            car.set_car_class(Some(&car_class)).await.map_err(internal_error)?;
+        }
+    }
+    if let Some(sim_ids) = input.sim_ids {
+        for sim_id in sim_ids.into_iter() {
+            if sim_id.identifier.trim().is_empty() {
+                car.remove_sim_id(sim_id.simulator).await.map_err(internal_error)?;
+            } else {
+                car.set_sim_id(sim_id.simulator, sim_id.identifier).await.map_err(internal_error)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// List all cars
+pub async fn handler_list(State(app_state): State<AppState>) -> Response {
+    let tbl_car = app_state.database.db_content().await.tbl_cars().await;
+    let mut cars = Vec::new();
+    for car in tbl_car.items_all().await.iter() {
+        cars.push(car_json(car).await);
+    }
+    Json(cars).into_response()
+}
+
+/// Get a single car
+pub async fn handler_get(State(app_state): State<AppState>,
+                         Path(car_id): Path<i64>) -> Response {
+    let tbl_car = app_state.database.db_content().await.tbl_cars().await;
+    match tbl_car.item_by_id(car_id).await {
+        Some(car) => Json(car_json(&car).await).into_response(),
+        None => car_not_found(),
+    }
+}
+
+/// Create a new car
+pub async fn handler_create(State(app_state): State<AppState>,
+                            RequirePromotion(http_user, _): RequirePromotion<levels::Director>,
+                            Json(input): Json<CarRequest>) -> Response {
+    let name = match input.name.as_ref() {
+        Some(name) if !name.trim().is_empty() => name.clone(),
+        _ => return GeneralError::new(StatusCode::BAD_REQUEST, "Car name required".to_string()).into_response(),
+    };
+    let tbl_car = app_state.database.db_content().await.tbl_cars().await;
+    let car = match tbl_car.create_car(name).await {
+        Some(car) => car,
+        None => return internal_error("Could not create car"),
+    };
+    if let Err(response) = apply_car_request(&app_state, &car, input).await { return response; }
+    log::info!("{} created {}", http_user.user.display().await, car.display().await);
+    (StatusCode::CREATED, Json(car_json(&car).await)).into_response()
+}
+
+/// Modify a car
+pub async fn handler_update(State(app_state): State<AppState>,
+                            RequirePromotion(_, _): RequirePromotion<levels::Director>,
+                            Path(car_id): Path<i64>,
+                            Json(input): Json<CarRequest>) -> Response {
+    let tbl_car = app_state.database.db_content().await.tbl_cars().await;
+    let car = match tbl_car.item_by_id(car_id).await {
+        Some(car) => car,
+        None => return car_not_found(),
+    };
+    if let Err(response) = apply_car_request(&app_state, &car, input).await { return response; }
+    Json(car_json(&car).await).into_response()
+}
+
+/// Delete a car
+pub async fn handler_delete(State(app_state): State<AppState>,
+                            RequirePromotion(http_user, _): RequirePromotion<levels::Director>,
+                            Path(car_id): Path<i64>) -> Response {
+    let tbl_car = app_state.database.db_content().await.tbl_cars().await;
+    let car = match tbl_car.item_by_id(car_id).await {
+        Some(car) => car,
+        None => return car_not_found(),
+    };
+    log::info!("{} deletes {}", http_user.user.display().await, car.display().await);
+    match tbl_car.delete_car(car).await {
+        Ok(_) => Json(EmptyResponse{}).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Set new Balance-of-Performance values for a car
+pub async fn handler_bop(State(app_state): State<AppState>,
+                         RequirePromotion(http_user, _): RequirePromotion<levels::Director>,
+                         Path(car_id): Path<i64>,
+                         Json(input): Json<BopRequest>) -> Response {
+    if input.ballast.is_nan() || !(0.0..=100.0).contains(&input.restrictor) {
+        return GeneralError::new(StatusCode::BAD_REQUEST, "Invalid BoP values".to_string()).into_response();
+    }
+    let tbl_car = app_state.database.db_content().await.tbl_cars().await;
+    let car = match tbl_car.item_by_id(car_id).await {
+        Some(car) => car,
+        None => return car_not_found(),
+    };
+    match car.set_bop(input.ballast, input.restrictor, Some(http_user.user.id().await)).await {
+        Ok(_) => Json(car_json(&car).await).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// List all car classes
+pub async fn handler_class_list(State(app_state): State<AppState>) -> Response {
+    let tbl_cls = app_state.database.db_content().await.tbl_car_classes().await;
+    let mut classes = Vec::new();
+    for car_class in tbl_cls.items_all().await.iter() {
+        classes.push(car_class_json(car_class).await);
+    }
+    Json(classes).into_response()
+}
+
+/// Get a single car class
+pub async fn handler_class_get(State(app_state): State<AppState>,
+                               Path(class_id): Path<i64>) -> Response {
+    let tbl_cls = app_state.database.db_content().await.tbl_car_classes().await;
+    match tbl_cls.item_by_id(class_id).await {
+        Some(car_class) => Json(car_class_json(&car_class).await).into_response(),
+        None => car_class_not_found(),
+    }
+}
+
+/// Create a new car class
+pub async fn handler_class_create(State(app_state): State<AppState>,
+                                  RequirePromotion(http_user, _): RequirePromotion<levels::Director>,
+                                  Json(input): Json<CarClassRequest>) -> Response {
+    let name = match input.name {
+        Some(name) if !name.trim().is_empty() => name,
+        _ => return GeneralError::new(StatusCode::BAD_REQUEST, "Car class name required".to_string()).into_response(),
+    };
+    let tbl_cls = app_state.database.db_content().await.tbl_car_classes().await;
+    match tbl_cls.create_class(name).await {
+        Some(car_class) => {
+            log::info!("{} created {}", http_user.user.display().await, car_class.display().await);
+            (StatusCode::CREATED, Json(car_class_json(&car_class).await)).into_response()
+        },
+        None => internal_error("Could not create car class"),
+    }
+}
+
+/// Modify a car class
+pub async fn handler_class_update(State(app_state): State<AppState>,
+                                  RequirePromotion(_, _): RequirePromotion<levels::Director>,
+                                  Path(class_id): Path<i64>,
+                                  Json(input): Json<CarClassRequest>) -> Response {
+    let tbl_cls = app_state.database.db_content().await.tbl_car_classes().await;
+    let car_class = match tbl_cls.item_by_id(class_id).await {
+        Some(car_class) => car_class,
+        None => return car_class_not_found(),
+    };
+    if let Some(name) = input.name {
+        if let Err(e) = car_class.set_name(name).await { return internal_error(e); }
+    }
+    Json(car_class_json(&car_class).await).into_response()
+}
+
+/// Delete a car class (the cars are kept without class)
+pub async fn handler_class_delete(State(app_state): State<AppState>,
+                                  RequirePromotion(http_user, _): RequirePromotion<levels::Director>,
+                                  Path(class_id): Path<i64>) -> Response {
+    let tbl_cls = app_state.database.db_content().await.tbl_car_classes().await;
+    let car_class = match tbl_cls.item_by_id(class_id).await {
+        Some(car_class) => car_class,
+        None => return car_class_not_found(),
+    };
+    log::info!("{} deletes {}", http_user.user.display().await, car_class.display().await);
+    match tbl_cls.delete_class(car_class).await {
+        Ok(_) => Json(EmptyResponse{}).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
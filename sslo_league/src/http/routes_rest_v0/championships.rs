@@ -0,0 +1,332 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use sslo_lib::db::DbRow;
+use crate::app_state::AppState;
+use crate::db2::series::championships::{ChampionshipItem, RoundRow};
+use crate::http::http_user::{levels, RequirePromotion};
+use crate::standings;
+use super::{internal_error, GeneralError};
+use super::user::EmptyResponse;
+
+#[derive(Serialize)]
+pub struct RoundJson {
+    id: i64,
+    round_number: i64,
+    name: String,
+    race_session: Option<i64>,
+    qualifying_session: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ChampionshipJson {
+    id: i64,
+    name: String,
+    description: String,
+    drop_worst: i64,
+    points: Vec<i64>,
+    points_pole: i64,
+    points_fastest_lap: i64,
+    car_classes: Vec<i64>,
+    rounds: Vec<RoundJson>,
+}
+
+#[derive(Serialize)]
+pub struct DriverStandingJson {
+    position: usize,
+    user: Option<i64>,
+    driver_guid: String,
+    name: String,
+    team: String,
+    round_points: Vec<Option<i64>>,
+    dropped: Vec<bool>,
+    points: i64,
+}
+
+#[derive(Serialize)]
+pub struct TeamStandingJson {
+    position: usize,
+    team: String,
+    round_points: Vec<Option<i64>>,
+    dropped: Vec<bool>,
+    points: i64,
+}
+
+#[derive(Serialize)]
+pub struct StandingsJson {
+    drivers: Vec<DriverStandingJson>,
+    teams: Vec<TeamStandingJson>,
+}
+
+#[derive(Deserialize)]
+pub struct ChampionshipRequest {
+    name: Option<String>,
+    description: Option<String>,
+    drop_worst: Option<i64>,
+    points: Option<Vec<i64>>,
+    points_pole: Option<i64>,
+    points_fastest_lap: Option<i64>,
+    car_classes: Option<Vec<i64>>,
+}
+
+#[derive(Deserialize)]
+pub struct RoundRequest {
+    name: Option<String>,
+
+    /// zero removes the session assignment
+    race_session: Option<i64>,
+
+    /// zero removes the session assignment
+    qualifying_session: Option<i64>,
+}
+
+fn round_json(round: RoundRow) -> RoundJson {
+    RoundJson {
+        id: round.rowid(),
+        round_number: round.round_number,
+        name: round.name,
+        race_session: round.race_session,
+        qualifying_session: round.qualifying_session,
+    }
+}
+
+async fn championship_json(championship: &ChampionshipItem) -> ChampionshipJson {
+    ChampionshipJson {
+        id: championship.id().await,
+        name: championship.name().await,
+        description: championship.description().await,
+        drop_worst: championship.drop_worst().await,
+        points: championship.points_table().await,
+        points_pole: championship.points_pole().await,
+        points_fastest_lap: championship.points_fastest_lap().await,
+        car_classes: championship.car_classes().await,
+        rounds: championship.rounds().await.into_iter().map(round_json).collect(),
+    }
+}
+
+fn championship_not_found() -> Response {
+    GeneralError::new(StatusCode::NOT_FOUND, "Championship not found".to_string()).into_response()
+}
+
+fn round_not_found() -> Response {
+    GeneralError::new(StatusCode::NOT_FOUND, "Round not found".to_string()).into_response()
+}
+
+async fn apply_championship_request(app_state: &AppState, championship: &ChampionshipItem, input: ChampionshipRequest) -> Result<(), Response> {
+    if let Some(name) = input.name {
+        championship.set_name(name).await.map_err(internal_error)?;
+    }
+    if let Some(description) = input.description {
+        championship.set_description(description).await.map_err(internal_error)?;
+    }
+    if let Some(drop_worst) = input.drop_worst {
+        championship.set_drop_worst(drop_worst).await.map_err(internal_error)?;
+    }
+    if let Some(points) = input.points {
+        championship.set_points_table(&points).await.map_err(internal_error)?;
+    }
+    if input.points_pole.is_some() || input.points_fastest_lap.is_some() {
+        let points_pole = input.points_pole.unwrap_or(championship.points_pole().await);
+        let points_fastest_lap = input.points_fastest_lap.unwrap_or(championship.points_fastest_lap().await);
+        championship.set_bonus_points(points_pole, points_fastest_lap).await.map_err(internal_error)?;
+    }
+    if let Some(car_classes) = input.car_classes {
+        let tbl_cls = app_state.database.db_content().await.tbl_car_classes().await;
+        for class_id in car_classes.iter() {
+            if tbl_cls.item_by_id(*class_id).await.is_none() {
+                return Err(GeneralError::new(StatusCode::BAD_REQUEST, format!("Car class {} not found", class_id)).into_response());
+            }
+        }
+        championship.set_car_classes(&car_classes).await.map_err(internal_error)?;
+    }
+    Ok(())
+}
+
+/// Check that a requested session exists (zero means no session)
+async fn requested_session(app_state: &AppState, session_id: i64) -> Result<Option<i64>, Response> {
+    if session_id == 0 {
+        return Ok(None);
+    }
+    let tbl_ses = app_state.database.db_results().await.tbl_sessions().await;
+    match tbl_ses.item_by_id(session_id).await {
+        Some(_) => Ok(Some(session_id)),
+        None => Err(GeneralError::new(StatusCode::BAD_REQUEST, format!("Session {} not found", session_id)).into_response()),
+    }
+}
+
+async fn apply_round_request(app_state: &AppState, round: &mut RoundRow, input: RoundRequest) -> Result<(), Response> {
+    if let Some(name) = input.name {
+        round.name = name;
+    }
+    if let Some(race_session) = input.race_session {
+        round.race_session = requested_session(app_state, race_session).await?;
+    }
+    if let Some(qualifying_session) = input.qualifying_session {
+        round.qualifying_session = requested_session(app_state, qualifying_session).await?;
+    }
+    Ok(())
+}
+
+/// List all championships
+pub async fn handler_list(State(app_state): State<AppState>) -> Response {
+    let tbl_chp = app_state.database.db_series().await.tbl_championships().await;
+    let mut championships = Vec::new();
+    for championship in tbl_chp.items_all().await.iter() {
+        championships.push(championship_json(championship).await);
+    }
+    Json(championships).into_response()
+}
+
+/// Get a single championship
+pub async fn handler_get(State(app_state): State<AppState>,
+                         Path(championship_id): Path<i64>) -> Response {
+    let tbl_chp = app_state.database.db_series().await.tbl_championships().await;
+    match tbl_chp.item_by_id(championship_id).await {
+        Some(championship) => Json(championship_json(&championship).await).into_response(),
+        None => championship_not_found(),
+    }
+}
+
+/// Create a new championship
+pub async fn handler_create(State(app_state): State<AppState>,
+                            RequirePromotion(http_user, _): RequirePromotion<levels::Director>,
+                            Json(input): Json<ChampionshipRequest>) -> Response {
+    let name = match input.name.as_ref() {
+        Some(name) if !name.trim().is_empty() => name.clone(),
+        _ => return GeneralError::new(StatusCode::BAD_REQUEST, "Championship name required".to_string()).into_response(),
+    };
+    let tbl_chp = app_state.database.db_series().await.tbl_championships().await;
+    let championship = match tbl_chp.create_championship(name).await {
+        Some(championship) => championship,
+        None => return internal_error("Could not create championship"),
+    };
+    if let Err(response) = apply_championship_request(&app_state, &championship, input).await { return response; }
+    log::info!("{} created {}", http_user.user.display().await, championship.display().await);
+    (StatusCode::CREATED, Json(championship_json(&championship).await)).into_response()
+}
+
+/// Modify a championship
+pub async fn handler_update(State(app_state): State<AppState>,
+                            RequirePromotion(_, _): RequirePromotion<levels::Director>,
+                            Path(championship_id): Path<i64>,
+                            Json(input): Json<ChampionshipRequest>) -> Response {
+    let tbl_chp = app_state.database.db_series().await.tbl_championships().await;
+    let championship = match tbl_chp.item_by_id(championship_id).await {
+        Some(championship) => championship,
+        None => return championship_not_found(),
+    };
+    if let Err(response) = apply_championship_request(&app_state, &championship, input).await { return response; }
+    Json(championship_json(&championship).await).into_response()
+}
+
+/// Delete a championship (including all rounds)
+pub async fn handler_delete(State(app_state): State<AppState>,
+                            RequirePromotion(http_user, _): RequirePromotion<levels::Director>,
+                            Path(championship_id): Path<i64>) -> Response {
+    let tbl_chp = app_state.database.db_series().await.tbl_championships().await;
+    let championship = match tbl_chp.item_by_id(championship_id).await {
+        Some(championship) => championship,
+        None => return championship_not_found(),
+    };
+    log::info!("{} deletes {}", http_user.user.display().await, championship.display().await);
+    match tbl_chp.delete_championship(championship).await {
+        Ok(_) => Json(EmptyResponse{}).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// The driver and team standings of a championship
+pub async fn handler_standings(State(app_state): State<AppState>,
+                               Path(championship_id): Path<i64>) -> Response {
+    let tbl_chp = app_state.database.db_series().await.tbl_championships().await;
+    let championship = match tbl_chp.item_by_id(championship_id).await {
+        Some(championship) => championship,
+        None => return championship_not_found(),
+    };
+    let standings = standings::standings(&app_state.database, &championship).await;
+    Json(StandingsJson {
+        drivers: standings.drivers.into_iter().enumerate().map(|(index, d)| DriverStandingJson {
+            position: index + 1,
+            user: d.user,
+            driver_guid: d.driver_guid,
+            name: d.name,
+            team: d.team,
+            round_points: d.round_points,
+            dropped: d.dropped,
+            points: d.points,
+        }).collect(),
+        teams: standings.teams.into_iter().enumerate().map(|(index, t)| TeamStandingJson {
+            position: index + 1,
+            team: t.team,
+            round_points: t.round_points,
+            dropped: t.dropped,
+            points: t.points,
+        }).collect(),
+    }).into_response()
+}
+
+/// Append a new round to a championship
+pub async fn handler_round_create(State(app_state): State<AppState>,
+                                  RequirePromotion(_, _): RequirePromotion<levels::Director>,
+                                  Path(championship_id): Path<i64>,
+                                  Json(input): Json<RoundRequest>) -> Response {
+    let tbl_chp = app_state.database.db_series().await.tbl_championships().await;
+    let championship = match tbl_chp.item_by_id(championship_id).await {
+        Some(championship) => championship,
+        None => return championship_not_found(),
+    };
+    let mut round = match championship.create_round(String::new()).await {
+        Ok(round) => round,
+        Err(e) => return internal_error(e),
+    };
+    if let Err(response) = apply_round_request(&app_state, &mut round, input).await { return response; }
+    let round_id = round.rowid();
+    if let Err(e) = championship.update_round(round).await { return internal_error(e); }
+    match tbl_chp.round_by_id(round_id).await {
+        Some(round) => (StatusCode::CREATED, Json(round_json(round))).into_response(),
+        None => round_not_found(),
+    }
+}
+
+/// Modify a round
+pub async fn handler_round_update(State(app_state): State<AppState>,
+                                  RequirePromotion(_, _): RequirePromotion<levels::Director>,
+                                  Path(round_id): Path<i64>,
+                                  Json(input): Json<RoundRequest>) -> Response {
+    let tbl_chp = app_state.database.db_series().await.tbl_championships().await;
+    let mut round = match tbl_chp.round_by_id(round_id).await {
+        Some(round) => round,
+        None => return round_not_found(),
+    };
+    let championship = match tbl_chp.item_by_id(round.championship).await {
+        Some(championship) => championship,
+        None => return championship_not_found(),
+    };
+    if let Err(response) = apply_round_request(&app_state, &mut round, input).await { return response; }
+    match championship.update_round(round).await {
+        Ok(_) => Json(championship_json(&championship).await).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Delete a round
+pub async fn handler_round_delete(State(app_state): State<AppState>,
+                                  RequirePromotion(http_user, _): RequirePromotion<levels::Director>,
+                                  Path(round_id): Path<i64>) -> Response {
+    let tbl_chp = app_state.database.db_series().await.tbl_championships().await;
+    let round = match tbl_chp.round_by_id(round_id).await {
+        Some(round) => round,
+        None => return round_not_found(),
+    };
+    let championship = match tbl_chp.item_by_id(round.championship).await {
+        Some(championship) => championship,
+        None => return championship_not_found(),
+    };
+    log::info!("{} deletes round {} of {}", http_user.user.display().await, round.round_number, championship.display().await);
+    match championship.delete_round(round_id).await {
+        Ok(_) => Json(EmptyResponse{}).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
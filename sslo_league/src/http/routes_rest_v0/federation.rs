@@ -0,0 +1,102 @@
+use axum::extract::{OriginalUri, Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use sslo_lib::db::members::users::UserItem;
+use sslo_lib::federation::{FederatedUser, HEADER_LOBBY, HEADER_SIGNATURE, HEADER_TIMESTAMP};
+use crate::app_state::AppState;
+use super::GeneralError;
+
+/// Check the signature headers of a federation request
+/// Returns the name of the trusted lobby
+fn verify_lobby(app_state: &AppState, headers: &HeaderMap, path: &str) -> Result<String, GeneralError> {
+    let header = |name: &str| -> Option<String> {
+        headers.get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string())
+    };
+    let unauthorized = || GeneralError::new(StatusCode::UNAUTHORIZED, "Invalid lobby authentication".to_string());
+
+    // read headers
+    let lobby_name = header(HEADER_LOBBY).ok_or_else(unauthorized)?;
+    let timestamp: i64 = header(HEADER_TIMESTAMP)
+        .and_then(|t| t.parse().ok())
+        .ok_or_else(unauthorized)?;
+    let signature = header(HEADER_SIGNATURE).ok_or_else(unauthorized)?;
+
+    // find trusted lobby
+    let lobby = match app_state.config.lobbies.iter().find(|l| l.name == lobby_name) {
+        Some(lobby) => lobby,
+        None => {
+            log::warn!("deny federation request from unknown lobby '{}'", lobby_name);
+            return Err(unauthorized());
+        }
+    };
+
+    // verify
+    if !sslo_lib::federation::verify(&lobby.secret, &lobby.name, timestamp, path, &signature) {
+        return Err(unauthorized());
+    }
+    Ok(lobby_name)
+}
+
+async fn federated_user(user: &UserItem) -> FederatedUser {
+    let promotion = user.promotion().await;
+    FederatedUser {
+        id: user.id().await,
+        name: user.name().await,
+        promotion_level: promotion.level,
+        promotion_authority: promotion.authority,
+        last_lap: *user.last_lap().await.raw(),
+        last_login: *user.last_login().await.raw(),
+    }
+}
+
+/// Resolve a league user by user-id
+pub async fn handler_user_by_id(State(app_state): State<AppState>,
+                                OriginalUri(uri): OriginalUri,
+                                headers: HeaderMap,
+                                Path(user_id): Path<i64>,
+) -> Response {
+    let lobby = match verify_lobby(&app_state, &headers, uri.path()) {
+        Ok(lobby) => lobby,
+        Err(e) => return e.into_response(),
+    };
+
+    if user_id <= 0 {
+        return GeneralError::new(StatusCode::NOT_FOUND, "User not found".to_string()).into_response();
+    }
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+    match tbl_usr.user_by_id(user_id).await {
+        Some(user) => {
+            log::info!("lobby '{}' resolved {}", lobby, user.display().await);
+            Json(federated_user(&user).await).into_response()
+        },
+        None => GeneralError::new(StatusCode::NOT_FOUND, "User not found".to_string()).into_response(),
+    }
+}
+
+/// Resolve a league user by steam-id
+pub async fn handler_user_by_steam_id(State(app_state): State<AppState>,
+                                      OriginalUri(uri): OriginalUri,
+                                      headers: HeaderMap,
+                                      Path(steam_id): Path<String>,
+) -> Response {
+    let lobby = match verify_lobby(&app_state, &headers, uri.path()) {
+        Ok(lobby) => lobby,
+        Err(e) => return e.into_response(),
+    };
+
+    // find steam account (without creating new users in the league)
+    let tbl_stm = app_state.database.db_members().await.tbl_steam_accounts().await;
+    let steam_account = match tbl_stm.item_by_steam_id(&steam_id, false).await {
+        Some(steam_account) if steam_account.has_user().await => steam_account,
+        _ => return GeneralError::new(StatusCode::NOT_FOUND, "User not found".to_string()).into_response(),
+    };
+
+    match steam_account.user().await {
+        Some(user) => {
+            log::info!("lobby '{}' resolved {} from {}", lobby, user.display().await, steam_account.display().await);
+            Json(federated_user(&user).await).into_response()
+        },
+        None => GeneralError::new(StatusCode::NOT_FOUND, "User not found".to_string()).into_response(),
+    }
+}
@@ -7,14 +7,19 @@ use axum::response::IntoResponse;
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use crate::app_state::AppState;
-use crate::db2::members::users::UserItem;
+use sslo_lib::db::members::users::UserItem;
 use crate::http::http_user::HttpUserExtractor;
 use crate::http::routes_rest_v0::GeneralError;
+use crate::http::routes_rest_v0::user::EmptyResponse;
 
 #[derive(Deserialize)]
 pub struct RequestData {
     identification: String,
     password: String,
+
+    /// code from an authenticator app or a recovery code (only needed when TOTP is enabled)
+    #[serde(default)]
+    totp: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -54,14 +59,39 @@ pub async fn handler(State(app_state): State<AppState>,
 
     // verify password
     let mut cookie : Option<String> = None;
-    if let Some(user) = user {
+    if let Some(mut user) = user {
         if !user.verify_password(input.password, http_user.user_agent.clone()).await {
             log::warn!("Deny login of {} because password cannot be verified!", user.display().await);
             return response_failed;
         }
 
+        // verify second factor
+        let totp_enabled = user.totp_enabled().await.raw().is_some();
+        if totp_enabled {
+            let key = match app_state.config.totp.as_ref() {
+                Some(totp) => &totp.key,
+                None => {
+                    log::error!("Deny login of {} because TOTP is enabled but not configured!", user.display().await);
+                    return response_failed;
+                }
+            };
+            let code = match input.totp {
+                Some(code) => code,
+                None => return GeneralError::new(StatusCode::UNAUTHORIZED, "TOTP code required".to_string()).into_response(),
+            };
+            if !user.totp_verify(key, &code).await {
+                log::warn!("Deny login of {} because TOTP code cannot be verified!", user.display().await);
+                return response_failed;
+            }
+        }
+
         // create new token
         if let Some(login_cookie_item) = tbl_cookie.create_new_cookie(&user).await {
+            if totp_enabled {
+                if let Err(e) = login_cookie_item.set_totp_verified().await {
+                    log::error!("Could not mark {} as TOTP verified: {}", login_cookie_item.display().await, e);
+                }
+            }
             cookie = login_cookie_item.get_cookie().await;
         }
     }
@@ -73,3 +103,94 @@ pub async fn handler(State(app_state): State<AppState>,
     }
     response
 }
+
+#[derive(Deserialize)]
+pub struct ForgotRequest {
+    email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResetRequest {
+    email_account: i64,
+    token: String,
+    password: String,
+}
+
+/// Send a password reset link to a verified email account
+/// This always succeeds, to not reveal which email accounts exist
+pub async fn handler_forgot(State(app_state): State<AppState>,
+                            Json(input): Json<ForgotRequest>,
+) -> Response {
+
+    // artificial slowdown
+    let wait_ms: u64 = 1000u64 + u64::from(rand::thread_rng().next_u32()) / 0x200_000u64; // results in 1000..3048ms
+    tokio::time::sleep(std::time::Duration::from_millis(wait_ms)).await;
+
+    // links are only built from the configured url, never from request headers
+    let public_url = match app_state.config.general.public_url.as_ref() {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => {
+            log::error!("Cannot send password reset email, because general.public_url is not configured");
+            return Json(EmptyResponse{}).into_response();
+        }
+    };
+
+    let tbl_eml = app_state.database.db_members().await.tbl_email_accounts().await;
+    if let Some(eml) = tbl_eml.item_by_email(&input.email).await {
+        if let Some(token) = eml.create_reset_token().await {
+            let link = format!("{}/html/login_password_reset/{}/{}", public_url, eml.id().await, token);
+            let message = format!("Hello User,<br><br>please follow this link to set a new password for the SSLO League: <a href=\"{}\">{}</a>.<br>The link is valid for one hour.<br><br>Regards",
+                                  link, public_url);
+            if let Err(e) = crate::helpers::send_email(&app_state.config, &eml.email().await, "Password Reset", &message).await {
+                log::warn!("Could not send password reset email to '{}': {}", &input.email, e)
+            }
+        }
+    } else {
+        log::warn!("Deny password reset for unknown or unverified email '{}'", &input.email);
+    }
+
+    Json(EmptyResponse{}).into_response()
+}
+
+/// Set a new password with a token from a password reset email
+/// All existing logins of the user are invalidated
+pub async fn handler_reset(State(app_state): State<AppState>,
+                           Json(input): Json<ResetRequest>,
+) -> Response {
+
+    // artificial slowdown
+    let wait_ms: u64 = 1000u64 + u64::from(rand::thread_rng().next_u32()) / 0x200_000u64; // results in 1000..3048ms
+    tokio::time::sleep(std::time::Duration::from_millis(wait_ms)).await;
+
+    let response_failed = GeneralError::new(StatusCode::FORBIDDEN, "Invalid or outdated password reset link".to_string()).into_response();
+    if input.password.is_empty() {
+        return GeneralError::new(StatusCode::BAD_REQUEST, "Password required".to_string()).into_response();
+    }
+
+    // verify token
+    let tbl_eml = app_state.database.db_members().await.tbl_email_accounts().await;
+    let eml = match tbl_eml.item_by_id(input.email_account).await {
+        Some(eml) => eml,
+        None => return response_failed,
+    };
+    if !eml.consume_reset_token(input.token).await {
+        return response_failed;
+    }
+    let mut user = match eml.user().await {
+        Some(user) => user,
+        None => {
+            log::error!("Could not retrieve user from {}", eml.display().await);
+            return response_failed;
+        }
+    };
+
+    // set new password and logout everywhere
+    if !user.set_password(Some(input.password)).await {
+        return GeneralError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to set password".to_string()).into_response();
+    }
+    let tbl_cookie = app_state.database.db_members().await.tbl_cookie_logins().await;
+    tbl_cookie.delete_user_cookies(&user).await;
+    log::info!("password reset for {} via {}", user.display().await, eml.display().await);
+
+    Json(EmptyResponse{}).into_response()
+}
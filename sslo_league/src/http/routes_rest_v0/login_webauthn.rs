@@ -0,0 +1,99 @@
+use axum::extract::State;
+use axum::http::header::SET_COOKIE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use sslo_lib::webauthn::{base64url_decode, RelyingParty};
+use crate::app_state::AppState;
+use crate::http::routes_rest_v0::{internal_error, GeneralError};
+use crate::http::routes_rest_v0::user::EmptyResponse;
+
+#[derive(Serialize)]
+pub struct ChallengeResponse {
+    challenge: String,
+    rp_id: String,
+}
+
+/// The response of navigator.credentials.get() (all fields base64url encoded)
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    credential_id: String,
+    client_data_json: String,
+    authenticator_data: String,
+    signature: String,
+}
+
+/// The relying party of the league, derived from general.public_url (passkeys are disabled without it)
+pub(super) fn relying_party(app_state: &AppState) -> Result<RelyingParty, Response> {
+    match app_state.config.general.public_url.as_ref() {
+        Some(public_url) => Ok(RelyingParty::new(public_url)),
+        None => {
+            log::error!("Cannot use passkeys, because general.public_url is not configured");
+            Err(GeneralError::new(StatusCode::SERVICE_UNAVAILABLE, "Passkeys are not available".to_string()).into_response())
+        }
+    }
+}
+
+/// Create a challenge for a passkey login
+pub async fn handler_challenge(State(app_state): State<AppState>,
+) -> Response {
+    let rp = match relying_party(&app_state) {
+        Ok(rp) => rp,
+        Err(response) => return response,
+    };
+    let tbl_wac = app_state.database.db_members().await.tbl_webauthn_credentials().await;
+    match tbl_wac.create_challenge(None).await {
+        Ok(challenge) => Json(ChallengeResponse {
+            challenge,
+            rp_id: rp.id,
+        }).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Login with a signed challenge from a passkey
+pub async fn handler(State(app_state): State<AppState>,
+                     Json(input): Json<LoginRequest>,
+) -> Response {
+    let rp = match relying_party(&app_state) {
+        Ok(rp) => rp,
+        Err(response) => return response,
+    };
+    let response_failed = GeneralError::new(StatusCode::FORBIDDEN, "Login Failed!".to_string()).into_response();
+
+    // decode data
+    let (client_data_json, authenticator_data, signature) = match (base64url_decode(&input.client_data_json),
+                                                                   base64url_decode(&input.authenticator_data),
+                                                                   base64url_decode(&input.signature)) {
+        (Ok(c), Ok(a), Ok(s)) => (c, a, s),
+        _ => return GeneralError::new(StatusCode::BAD_REQUEST, "Invalid encoding".to_string()).into_response(),
+    };
+
+    // verify
+    let tbl_wac = app_state.database.db_members().await.tbl_webauthn_credentials().await;
+    let user = match tbl_wac.authenticate(&rp, &input.credential_id,
+                                          &client_data_json, &authenticator_data, &signature).await {
+        Some(user) => user,
+        None => return response_failed,
+    };
+
+    // create login cookie
+    let tbl_cookie = app_state.database.db_members().await.tbl_cookie_logins().await;
+    let cookie = match tbl_cookie.create_new_cookie(&user).await {
+        Some(item) => item.get_cookie().await,
+        None => None,
+    };
+    let cookie = match cookie {
+        Some(cookie) => cookie,
+        None => {
+            log::error!("Could not create login cookie for {}", user.display().await);
+            return response_failed;
+        }
+    };
+    log::info!("passkey login of {}", user.display().await);
+
+    let mut response = Json(EmptyResponse{}).into_response();
+    response.headers_mut().insert(SET_COOKIE, cookie.parse().unwrap());
+    response
+}
@@ -0,0 +1,245 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::app_state::AppState;
+use crate::db2::results::penalties::{AppealState, PenaltyItem, PenaltyType};
+use crate::db2::results::sessions::ResultRow;
+use crate::http::http_user::{levels, RequireLogin, RequirePromotion};
+use super::{internal_error, GeneralError};
+
+#[derive(Serialize)]
+pub struct AuditJson {
+    user: i64,
+    date: DateTime<Utc>,
+    action: String,
+}
+
+#[derive(Serialize)]
+pub struct PenaltyJson {
+    id: i64,
+    session: i64,
+    user: Option<i64>,
+    driver_guid: String,
+    penalty_type: PenaltyType,
+    value: i64,
+    reason: String,
+    issued_by: i64,
+    issued: DateTime<Utc>,
+    appeal_state: AppealState,
+    appeal_text: String,
+    revoked: bool,
+    audit: Vec<AuditJson>,
+}
+
+#[derive(Deserialize)]
+pub struct PenaltyQuery {
+    session: Option<i64>,
+    user: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct CreatePenaltyRequest {
+    session: i64,
+    user: Option<i64>,
+    driver_guid: Option<String>,
+    penalty_type: PenaltyType,
+    value: i64,
+    reason: String,
+}
+
+#[derive(Deserialize)]
+pub struct AmendPenaltyRequest {
+    value: Option<i64>,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CommentRequest {
+    comment: String,
+}
+
+#[derive(Deserialize)]
+pub struct AppealRequest {
+    text: String,
+}
+
+#[derive(Deserialize)]
+pub struct DecisionRequest {
+    accept: bool,
+    comment: String,
+}
+
+async fn penalty_json(penalty: &PenaltyItem) -> PenaltyJson {
+    PenaltyJson {
+        id: penalty.id().await,
+        session: penalty.session().await,
+        user: penalty.user().await,
+        driver_guid: penalty.driver_guid().await,
+        penalty_type: penalty.penalty_type().await,
+        value: penalty.value().await,
+        reason: penalty.reason().await,
+        issued_by: penalty.issued_by().await,
+        issued: penalty.issued().await,
+        appeal_state: penalty.appeal_state().await,
+        appeal_text: penalty.appeal_text().await,
+        revoked: penalty.revoked().await,
+        audit: penalty.audit_trail().await.into_iter()
+            .map(|row| AuditJson { user: row.user, date: row.date, action: row.action })
+            .collect(),
+    }
+}
+
+fn penalty_not_found() -> Response {
+    GeneralError::new(StatusCode::NOT_FOUND, "Penalty not found".to_string()).into_response()
+}
+
+async fn penalty_by_id(app_state: &AppState, penalty_id: i64) -> Result<PenaltyItem, Response> {
+    let tbl_pen = app_state.database.db_results().await.tbl_penalties().await;
+    tbl_pen.item_by_id(penalty_id).await.ok_or_else(penalty_not_found)
+}
+
+/// Find a driver in the results of a session (by user, or by simulator guid)
+pub(super) async fn session_driver(app_state: &AppState,
+                                   session_id: i64,
+                                   user: Option<i64>,
+                                   driver_guid: Option<&str>,
+) -> Result<ResultRow, Response> {
+    let session = match app_state.database.db_results().await.tbl_sessions().await.item_by_id(session_id).await {
+        Some(session) => session,
+        None => return Err(GeneralError::new(StatusCode::BAD_REQUEST, format!("Session {} not found", session_id)).into_response()),
+    };
+    let result = session.results().await.into_iter().find(|r| {
+        match (user, driver_guid) {
+            (Some(user_id), _) => r.user == Some(user_id),
+            (None, Some(driver_guid)) => r.driver_guid == driver_guid,
+            (None, None) => false,
+        }
+    });
+    result.ok_or_else(|| GeneralError::new(StatusCode::BAD_REQUEST, "Driver not found in session results".to_string()).into_response())
+}
+
+/// List penalties of a session or of a user
+pub async fn handler_list(State(app_state): State<AppState>,
+                          Query(query): Query<PenaltyQuery>) -> Response {
+    let tbl_pen = app_state.database.db_results().await.tbl_penalties().await;
+    let penalties = match (query.session, query.user) {
+        (Some(session_id), _) => tbl_pen.items_by_session(session_id).await,
+        (None, Some(user_id)) => tbl_pen.items_by_user(user_id).await,
+        (None, None) => return GeneralError::new(StatusCode::BAD_REQUEST, "Session or user required".to_string()).into_response(),
+    };
+    let mut list = Vec::new();
+    for penalty in penalties.iter() {
+        let user = penalty.user().await;
+        if query.user.is_none_or(|user_id| user == Some(user_id)) {
+            list.push(penalty_json(penalty).await);
+        }
+    }
+    Json(list).into_response()
+}
+
+/// Get a single penalty with its audit trail
+pub async fn handler_get(State(app_state): State<AppState>,
+                         Path(penalty_id): Path<i64>) -> Response {
+    match penalty_by_id(&app_state, penalty_id).await {
+        Ok(penalty) => Json(penalty_json(&penalty).await).into_response(),
+        Err(response) => response,
+    }
+}
+
+/// Pronounce a new penalty
+pub async fn handler_create(State(app_state): State<AppState>,
+                            RequirePromotion(http_user, _): RequirePromotion<levels::Commissar>,
+                            Json(input): Json<CreatePenaltyRequest>) -> Response {
+    let result = match session_driver(&app_state, input.session, input.user, input.driver_guid.as_deref()).await {
+        Ok(result) => result,
+        Err(response) => return response,
+    };
+
+    let tbl_pen = app_state.database.db_results().await.tbl_penalties().await;
+    match tbl_pen.create_penalty(input.session, result.user, result.driver_guid, input.penalty_type,
+                                 input.value, input.reason, http_user.user.id().await).await {
+        Ok(penalty) => {
+            log::info!("{} pronounced {}", http_user.user.display().await, penalty.display().await);
+            (StatusCode::CREATED, Json(penalty_json(&penalty).await)).into_response()
+        },
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Change value or reason of a penalty
+pub async fn handler_amend(State(app_state): State<AppState>,
+                           RequirePromotion(http_user, _): RequirePromotion<levels::Commissar>,
+                           Path(penalty_id): Path<i64>,
+                           Json(input): Json<AmendPenaltyRequest>) -> Response {
+    let penalty = match penalty_by_id(&app_state, penalty_id).await {
+        Ok(penalty) => penalty,
+        Err(response) => return response,
+    };
+    let value = input.value.unwrap_or(penalty.value().await);
+    let reason = input.reason.unwrap_or(penalty.reason().await);
+    match penalty.amend(http_user.user.id().await, value, reason).await {
+        Ok(_) => Json(penalty_json(&penalty).await).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Revoke a penalty (it is kept for the audit trail)
+pub async fn handler_revoke(State(app_state): State<AppState>,
+                            RequirePromotion(http_user, _): RequirePromotion<levels::Commissar>,
+                            Path(penalty_id): Path<i64>,
+                            Json(input): Json<CommentRequest>) -> Response {
+    let penalty = match penalty_by_id(&app_state, penalty_id).await {
+        Ok(penalty) => penalty,
+        Err(response) => return response,
+    };
+    if penalty.revoked().await {
+        return GeneralError::new(StatusCode::CONFLICT, "Penalty already revoked".to_string()).into_response();
+    }
+    match penalty.revoke(http_user.user.id().await, input.comment).await {
+        Ok(_) => Json(penalty_json(&penalty).await).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Appeal against a penalty (only by the penalized driver)
+pub async fn handler_appeal(State(app_state): State<AppState>,
+                            RequireLogin(http_user): RequireLogin,
+                            Path(penalty_id): Path<i64>,
+                            Json(input): Json<AppealRequest>) -> Response {
+    let penalty = match penalty_by_id(&app_state, penalty_id).await {
+        Ok(penalty) => penalty,
+        Err(response) => return response,
+    };
+    let user_id = http_user.user.id().await;
+    if penalty.user().await != Some(user_id) {
+        return GeneralError::new(StatusCode::FORBIDDEN, "Only the penalized driver can appeal".to_string()).into_response();
+    }
+    if penalty.revoked().await || penalty.appeal_state().await != AppealState::None {
+        return GeneralError::new(StatusCode::CONFLICT, "Penalty cannot be appealed".to_string()).into_response();
+    }
+    match penalty.appeal(user_id, input.text).await {
+        Ok(_) => Json(penalty_json(&penalty).await).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Decide about a pending appeal
+pub async fn handler_decision(State(app_state): State<AppState>,
+                              RequirePromotion(http_user, _): RequirePromotion<levels::Commissar>,
+                              Path(penalty_id): Path<i64>,
+                              Json(input): Json<DecisionRequest>) -> Response {
+    let penalty = match penalty_by_id(&app_state, penalty_id).await {
+        Ok(penalty) => penalty,
+        Err(response) => return response,
+    };
+    if penalty.appeal_state().await != AppealState::Pending {
+        return GeneralError::new(StatusCode::CONFLICT, "No pending appeal".to_string()).into_response();
+    }
+    match penalty.decide_appeal(http_user.user.id().await, input.accept, input.comment).await {
+        Ok(_) => Json(penalty_json(&penalty).await).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
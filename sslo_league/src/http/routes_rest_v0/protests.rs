@@ -0,0 +1,318 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sslo_lib::db::members::users::PromotionLevel;
+use crate::app_state::AppState;
+use crate::db2::results::penalties::PenaltyType;
+use crate::db2::results::protests::{ProtestItem, ProtestState};
+use crate::http::http_user::{levels, HttpUser, HttpUserExtractor, RequireLogin, RequirePromotion};
+use super::{internal_error, GeneralError};
+use super::penalties::session_driver;
+
+#[derive(Serialize, Deserialize)]
+pub struct ProtestDriverJson {
+    user: Option<i64>,
+    driver_guid: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ProtestCommentJson {
+    user: i64,
+    date: DateTime<Utc>,
+    text: String,
+}
+
+#[derive(Serialize)]
+pub struct ProtestJson {
+    id: i64,
+    session: i64,
+    lap: Option<i64>,
+    reporter: i64,
+    description: String,
+    filed: DateTime<Utc>,
+    drivers: Vec<ProtestDriverJson>,
+    evidence: Vec<String>,
+    comments: Vec<ProtestCommentJson>,
+    state: ProtestState,
+    resolution: String,
+    resolved_by: Option<i64>,
+    resolved: Option<DateTime<Utc>>,
+    penalty: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ProtestQuery {
+    session: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateProtestRequest {
+    session: i64,
+    lap: Option<i64>,
+    description: String,
+    drivers: Vec<ProtestDriverJson>,
+    #[serde(default)]
+    evidence: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CommentRequest {
+    text: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResolutionPenalty {
+    user: Option<i64>,
+    driver_guid: Option<String>,
+    penalty_type: PenaltyType,
+    value: i64,
+}
+
+#[derive(Deserialize)]
+pub struct ResolutionRequest {
+    resolution: String,
+
+    /// no further action, if not given
+    penalty: Option<ResolutionPenalty>,
+}
+
+async fn protest_json(protest: &ProtestItem) -> ProtestJson {
+    ProtestJson {
+        id: protest.id().await,
+        session: protest.session().await,
+        lap: protest.lap().await,
+        reporter: protest.reporter().await,
+        description: protest.description().await,
+        filed: protest.filed().await,
+        drivers: protest.drivers().await.into_iter()
+            .map(|row| ProtestDriverJson { user: row.user, driver_guid: Some(row.driver_guid) })
+            .collect(),
+        evidence: protest.evidence().await,
+        comments: protest.comments().await.into_iter()
+            .map(|row| ProtestCommentJson { user: row.user, date: row.date, text: row.text })
+            .collect(),
+        state: protest.state().await,
+        resolution: protest.resolution().await,
+        resolved_by: protest.resolved_by().await,
+        resolved: protest.resolved().await,
+        penalty: protest.penalty().await,
+    }
+}
+
+fn protest_not_found() -> Response {
+    GeneralError::new(StatusCode::NOT_FOUND, "Protest not found".to_string()).into_response()
+}
+
+async fn protest_by_id(app_state: &AppState, protest_id: i64) -> Result<ProtestItem, Response> {
+    let tbl_pro = app_state.database.db_results().await.tbl_protests().await;
+    tbl_pro.item_by_id(protest_id).await.ok_or_else(protest_not_found)
+}
+
+/// Stewards, the reporter and the involved drivers can access a protest
+async fn may_access(http_user: &HttpUser, protest: &ProtestItem) -> bool {
+    if !http_user.is_logged_in() {
+        return false;
+    }
+    let user_id = http_user.user.id().await;
+    http_user.has_promotion(PromotionLevel::Steward).await
+        || protest.reporter().await == user_id
+        || protest.involves(user_id).await
+}
+
+/// Send an email to all verified email addresses of some users (in background)
+async fn notify_users(app_state: &AppState, user_ids: Vec<i64>, subject: String, message: String) {
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+    let tbl_eml = app_state.database.db_members().await.tbl_email_accounts().await;
+    let mut receivers = Vec::new();
+    for user_id in user_ids.into_iter() {
+        let user = match tbl_usr.user_by_id(user_id).await {
+            Some(user) => user,
+            None => continue,
+        };
+        for eml in tbl_eml.items_by_user(&user).await {
+            let email = eml.email().await;
+            if eml.verified_since().await.raw().is_some() && !receivers.contains(&email) {
+                receivers.push(email);
+            }
+        }
+    }
+
+    let config = app_state.config.clone();
+    tokio::spawn(async move {
+        for receiver in receivers.iter() {
+            if let Err(e) = crate::helpers::send_email(&config, receiver, &subject, &message).await {
+                log::warn!("Could not send notification '{}' to '{}': {}", subject, receiver, e);
+            }
+        }
+    });
+}
+
+/// The review queue of the stewards, or all protests of a session
+pub async fn handler_list(State(app_state): State<AppState>,
+                          RequirePromotion(_, _): RequirePromotion<levels::Steward>,
+                          Query(query): Query<ProtestQuery>) -> Response {
+    let tbl_pro = app_state.database.db_results().await.tbl_protests().await;
+    let protests = match query.session {
+        Some(session_id) => tbl_pro.items_by_session(session_id).await,
+        None => tbl_pro.items_open().await,
+    };
+    let mut list = Vec::new();
+    for protest in protests.iter() {
+        list.push(protest_json(protest).await);
+    }
+    Json(list).into_response()
+}
+
+/// Get a single protest
+pub async fn handler_get(State(app_state): State<AppState>,
+                         HttpUserExtractor(http_user): HttpUserExtractor,
+                         Path(protest_id): Path<i64>) -> Response {
+    let protest = match protest_by_id(&app_state, protest_id).await {
+        Ok(protest) => protest,
+        Err(response) => return response,
+    };
+    if !may_access(&http_user, &protest).await {
+        return protest_not_found();
+    }
+    Json(protest_json(&protest).await).into_response()
+}
+
+/// Link to a protest for notification emails (derived from general.public_url)
+fn html_protest_link(app_state: &AppState, protest_id: i64) -> String {
+    match app_state.config.general.public_url.as_ref() {
+        Some(public_url) => format!("<a href=\"{url}/html/protests/{id}\">{url}/html/protests/{id}</a>",
+                                    url=public_url.trim_end_matches('/'), id=protest_id),
+        None => format!("#{}", protest_id),
+    }
+}
+
+/// File a new protest
+pub async fn handler_create(State(app_state): State<AppState>,
+                            RequireLogin(http_user): RequireLogin,
+                            Json(input): Json<CreateProtestRequest>) -> Response {
+    let user_id = http_user.user.id().await;
+
+    // check input
+    if input.description.trim().is_empty() {
+        return GeneralError::new(StatusCode::BAD_REQUEST, "Description required".to_string()).into_response();
+    }
+    if input.drivers.is_empty() {
+        return GeneralError::new(StatusCode::BAD_REQUEST, "No involved driver".to_string()).into_response();
+    }
+    if let Some(url) = input.evidence.iter().find(|url| !url.starts_with("https://") && !url.starts_with("http://")) {
+        return GeneralError::new(StatusCode::BAD_REQUEST, format!("Invalid evidence link '{}'", url)).into_response();
+    }
+    let mut drivers = Vec::new();
+    for driver in input.drivers.iter() {
+        let result = match session_driver(&app_state, input.session, driver.user, driver.driver_guid.as_deref()).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        if result.user == Some(user_id) {
+            return GeneralError::new(StatusCode::BAD_REQUEST, "Cannot protest against yourself".to_string()).into_response();
+        }
+        drivers.push((result.user, result.driver_guid));
+    }
+
+    let tbl_pro = app_state.database.db_results().await.tbl_protests().await;
+    let protest = match tbl_pro.create_protest(input.session, input.lap, user_id, input.description,
+                                               drivers, input.evidence).await {
+        Ok(protest) => protest,
+        Err(e) => return internal_error(e),
+    };
+    log::info!("{} filed {}", http_user.user.display().await, protest.display().await);
+
+    // inform stewards
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+    let mut stewards = Vec::new();
+    for steward in tbl_usr.users_by_promotion(PromotionLevel::Steward).await {
+        stewards.push(steward.id().await);
+    }
+    let message = format!("Hello Steward,\n\n{} filed a new protest: {}\n\nRegards",
+                          http_user.user.html_name().await, html_protest_link(&app_state, protest.id().await));
+    notify_users(&app_state, stewards, "New Protest".to_string(), message).await;
+
+    (StatusCode::CREATED, Json(protest_json(&protest).await)).into_response()
+}
+
+/// Comment on a protest (by stewards or the reporter)
+pub async fn handler_comment(State(app_state): State<AppState>,
+                             RequireLogin(http_user): RequireLogin,
+                             Path(protest_id): Path<i64>,
+                             Json(input): Json<CommentRequest>) -> Response {
+    let protest = match protest_by_id(&app_state, protest_id).await {
+        Ok(protest) => protest,
+        Err(response) => return response,
+    };
+    let user_id = http_user.user.id().await;
+    if !http_user.has_promotion(PromotionLevel::Steward).await && protest.reporter().await != user_id {
+        return GeneralError::new(StatusCode::FORBIDDEN, "Only stewards and the reporter can comment".to_string()).into_response();
+    }
+    if input.text.trim().is_empty() {
+        return GeneralError::new(StatusCode::BAD_REQUEST, "Empty comment".to_string()).into_response();
+    }
+    match protest.add_comment(user_id, input.text).await {
+        Ok(_) => Json(protest_json(&protest).await).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Close a protest with a penalty (requires Commissar) or with no further action (requires Steward)
+pub async fn handler_resolution(State(app_state): State<AppState>,
+                                RequirePromotion(http_user, _): RequirePromotion<levels::Steward>,
+                                Path(protest_id): Path<i64>,
+                                Json(input): Json<ResolutionRequest>) -> Response {
+    if input.penalty.is_some() && !http_user.has_promotion(PromotionLevel::Commissar).await {
+        return GeneralError::new(StatusCode::FORBIDDEN, "Insufficient promotion".to_string()).into_response();
+    }
+
+    let protest = match protest_by_id(&app_state, protest_id).await {
+        Ok(protest) => protest,
+        Err(response) => return response,
+    };
+    if protest.state().await != ProtestState::Open {
+        return GeneralError::new(StatusCode::CONFLICT, "Protest is already resolved".to_string()).into_response();
+    }
+    let user_id = http_user.user.id().await;
+
+    // pronounce penalty
+    let penalty_id = match input.penalty {
+        None => None,
+        Some(penalty) => {
+            let session_id = protest.session().await;
+            let result = match session_driver(&app_state, session_id, penalty.user, penalty.driver_guid.as_deref()).await {
+                Ok(result) => result,
+                Err(response) => return response,
+            };
+            let tbl_pen = app_state.database.db_results().await.tbl_penalties().await;
+            match tbl_pen.create_penalty(session_id, result.user, result.driver_guid, penalty.penalty_type,
+                                         penalty.value, input.resolution.clone(), user_id).await {
+                Ok(penalty) => {
+                    log::info!("{} pronounced {}", http_user.user.display().await, penalty.display().await);
+                    Some(penalty.id().await)
+                },
+                Err(e) => return internal_error(e),
+            }
+        },
+    };
+
+    if let Err(e) = protest.resolve(user_id, input.resolution, penalty_id).await {
+        return internal_error(e);
+    }
+
+    // inform reporter and involved drivers
+    let mut receivers = vec![protest.reporter().await];
+    for driver in protest.drivers().await.into_iter() {
+        if let Some(driver_user) = driver.user {
+            receivers.push(driver_user);
+        }
+    }
+    let message = format!("Hello Driver,\n\nthe stewards decided about a protest you are involved in ({}): {}\n\nRegards",
+                          protest.state().await.label(), html_protest_link(&app_state, protest_id));
+    notify_users(&app_state, receivers, "Protest Decision".to_string(), message).await;
+
+    Json(protest_json(&protest).await).into_response()
+}
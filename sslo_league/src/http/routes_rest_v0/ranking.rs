@@ -0,0 +1,109 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::app_state::AppState;
+use crate::db2::results::ratings::RatingRow;
+use crate::http::http_user::{levels, RequirePromotion};
+use crate::ranking;
+use super::{internal_error, GeneralError};
+use super::user::EmptyResponse;
+
+#[derive(Deserialize)]
+pub struct RankingQuery {
+
+    /// zero or none for the overall ranking
+    car_class: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct RatingJson {
+    car_class: i64,
+    rating: f64,
+    deviation: f64,
+    volatility: f64,
+    races: i64,
+    updated: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct RankingEntryJson {
+    position: usize,
+    user: i64,
+    name: String,
+    rating: RatingJson,
+}
+
+#[derive(Serialize)]
+pub struct RatingHistoryJson {
+    session: i64,
+    rating: f64,
+    deviation: f64,
+    date: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct UserRatingsJson {
+    user: i64,
+    ratings: Vec<RatingJson>,
+    history: Vec<RatingHistoryJson>,
+}
+
+fn rating_json(row: RatingRow) -> RatingJson {
+    RatingJson {
+        car_class: row.car_class,
+        rating: row.rating,
+        deviation: row.deviation,
+        volatility: row.volatility,
+        races: row.races,
+        updated: row.updated,
+    }
+}
+
+/// The driver ranking (only drivers with recent activity)
+pub async fn handler_list(State(app_state): State<AppState>,
+                          Query(query): Query<RankingQuery>,
+) -> Response {
+    let mut entries = Vec::new();
+    for (index, (user, row)) in ranking::ranking(&app_state.database, query.car_class.unwrap_or(0)).await.into_iter().enumerate() {
+        entries.push(RankingEntryJson {
+            position: index + 1,
+            user: user.id().await,
+            name: user.name().await,
+            rating: rating_json(row),
+        });
+    }
+    Json(entries).into_response()
+}
+
+/// All current ratings and the rating history of a user
+pub async fn handler_user(State(app_state): State<AppState>,
+                          Path(user_id): Path<i64>,
+                          Query(query): Query<RankingQuery>,
+) -> Response {
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+    if tbl_usr.user_by_id(user_id).await.is_none() {
+        return GeneralError::new(StatusCode::NOT_FOUND, format!("user {} not found", user_id)).into_response();
+    }
+
+    let tbl_rat = app_state.database.db_results().await.tbl_ratings().await;
+    Json(UserRatingsJson {
+        user: user_id,
+        ratings: tbl_rat.ratings_by_user(user_id).await.into_iter().map(rating_json).collect(),
+        history: tbl_rat.history(user_id, query.car_class.unwrap_or(0)).await.into_iter()
+            .map(|row| RatingHistoryJson{session: row.session, rating: row.rating, deviation: row.deviation, date: row.date})
+            .collect(),
+    }).into_response()
+}
+
+/// Recompute all ratings from the stored results (e.g. after car classes have been changed)
+pub async fn handler_recompute(State(app_state): State<AppState>,
+                               RequirePromotion(_, _): RequirePromotion<levels::Director>,
+) -> Response {
+    match ranking::recompute(&app_state.database).await {
+        Ok(_) => Json(EmptyResponse{}).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
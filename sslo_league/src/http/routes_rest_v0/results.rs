@@ -0,0 +1,53 @@
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use crate::app_state::AppState;
+use crate::http::http_user::{levels, RequirePromotion};
+use crate::importer;
+use crate::ranking;
+use super::GeneralError;
+
+#[derive(Serialize)]
+pub struct ImportResponse {
+    session_ids: Vec<i64>,
+}
+
+/// Import a result file (the file content is the request body)
+/// The simulator is given by its short name (ac, acc, rf2, lmu, ams2)
+pub async fn handler_import(State(app_state): State<AppState>,
+                            RequirePromotion(http_user, _): RequirePromotion<levels::Officer>,
+                            Path((simulator, file_name)): Path<(String, String)>,
+                            body: Bytes,
+) -> Response {
+    let result_importer = match importer::importer_by_name(&simulator) {
+        Some(result_importer) => result_importer,
+        None => return GeneralError::new(StatusCode::NOT_FOUND, format!("unknown simulator '{}'", simulator)).into_response(),
+    };
+    let imported = match result_importer.parse(&file_name, &body) {
+        Ok(imported) => imported,
+        Err(e) => {
+            log::warn!("{}", e);
+            return GeneralError::new(StatusCode::BAD_REQUEST, e.to_string()).into_response();
+        }
+    };
+    match importer::store_new(&app_state.database, &file_name, imported).await {
+        Ok(sessions) => {
+            let mut session_ids = Vec::new();
+            for session in sessions.iter() {
+                log::info!("{} imported {} ({})", http_user.user.display().await, session.display().await, result_importer.simulator().label());
+                session_ids.push(session.id().await);
+            }
+            if let Err(e) = ranking::recompute(&app_state.database).await {
+                log::error!("failed to recompute ratings: {}", e);
+            }
+            (StatusCode::CREATED, Json(ImportResponse{session_ids})).into_response()
+        },
+        Err(e) => {
+            log::warn!("{}", e);
+            GeneralError::new(StatusCode::CONFLICT, e.to_string()).into_response()
+        }
+    }
+}
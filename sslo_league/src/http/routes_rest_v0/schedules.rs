@@ -0,0 +1,356 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::app_state::AppState;
+use crate::db2::content::Simulator;
+use crate::db2::results::SessionType;
+use crate::db2::results::penalties::PenaltyType;
+use crate::db2::series::events::{EventItem, RegistrationStatus, SessionSchedule};
+use crate::http::http_user::{levels, RequireLogin, RequirePromotion};
+use super::{internal_error, GeneralError};
+use super::user::EmptyResponse;
+
+#[derive(Serialize, Deserialize)]
+pub struct SessionScheduleJson {
+
+    /// null if the session is not held
+    start: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    duration_minutes: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RegistrationWindowJson {
+    opens: Option<DateTime<Utc>>,
+    closes: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct RegistrationJson {
+    user: i64,
+    car: Option<i64>,
+    registered: DateTime<Utc>,
+    status: String,
+    waitlist_position: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct EventJson {
+    id: i64,
+    name: String,
+    description: String,
+    simulator: Simulator,
+    track_layout: Option<i64>,
+    server: String,
+    practice: SessionScheduleJson,
+    qualifying: SessionScheduleJson,
+    race: SessionScheduleJson,
+    registration: RegistrationWindowJson,
+    registration_open: bool,
+    max_grid: i64,
+    car_classes: Vec<i64>,
+    registrations: Vec<RegistrationJson>,
+}
+
+#[derive(Deserialize)]
+pub struct EventRequest {
+    name: Option<String>,
+    description: Option<String>,
+    simulator: Option<Simulator>,
+
+    /// zero removes the track layout
+    track_layout: Option<i64>,
+
+    server: Option<String>,
+    practice: Option<SessionScheduleJson>,
+    qualifying: Option<SessionScheduleJson>,
+    race: Option<SessionScheduleJson>,
+    registration: Option<RegistrationWindowJson>,
+    max_grid: Option<i64>,
+    car_classes: Option<Vec<i64>>,
+}
+
+#[derive(Deserialize)]
+pub struct RegistrationRequest {
+    car: Option<i64>,
+}
+
+fn registration_json(user: i64, car: Option<i64>, registered: DateTime<Utc>, status: RegistrationStatus) -> RegistrationJson {
+    let (status, waitlist_position) = match status {
+        RegistrationStatus::Grid => ("grid".to_string(), None),
+        RegistrationStatus::Waitlist(position) => ("waitlist".to_string(), Some(position)),
+    };
+    RegistrationJson { user, car, registered, status, waitlist_position }
+}
+
+async fn event_json(event: &EventItem) -> EventJson {
+    let mut schedules = Vec::new();
+    for session_type in [SessionType::Practice, SessionType::Qualifying, SessionType::Race] {
+        let schedule = event.session_schedule(session_type).await;
+        schedules.push(SessionScheduleJson {
+            start: schedule.map(|s| s.start),
+            duration_minutes: schedule.map(|s| s.duration_minutes).unwrap_or(0),
+        });
+    }
+    let race = schedules.pop().unwrap();
+    let qualifying = schedules.pop().unwrap();
+    let practice = schedules.pop().unwrap();
+
+    let (grid, waitlist) = event.grid_and_waitlist().await;
+    let mut registrations: Vec<RegistrationJson> = grid.into_iter()
+        .map(|r| registration_json(r.user, r.car, r.registered, RegistrationStatus::Grid))
+        .collect();
+    for (index, r) in waitlist.into_iter().enumerate() {
+        registrations.push(registration_json(r.user, r.car, r.registered, RegistrationStatus::Waitlist(index + 1)));
+    }
+
+    let (opens, closes) = event.registration_window().await;
+    EventJson {
+        id: event.id().await,
+        name: event.name().await,
+        description: event.description().await,
+        simulator: event.simulator().await,
+        track_layout: event.track_layout().await,
+        server: event.server().await,
+        practice,
+        qualifying,
+        race,
+        registration: RegistrationWindowJson { opens, closes },
+        registration_open: event.is_registration_open(Utc::now()).await,
+        max_grid: event.max_grid().await,
+        car_classes: event.car_classes().await,
+        registrations,
+    }
+}
+
+fn event_not_found() -> Response {
+    GeneralError::new(StatusCode::NOT_FOUND, "Event not found".to_string()).into_response()
+}
+
+async fn apply_event_request(app_state: &AppState, event: &EventItem, input: EventRequest) -> Result<(), Response> {
+    if let Some(name) = input.name {
+        event.set_name(name).await.map_err(internal_error)?;
+    }
+    if let Some(description) = input.description {
+        event.set_description(description).await.map_err(internal_error)?;
+    }
+    if let Some(simulator) = input.simulator {
+        event.set_simulator(simulator).await.map_err(internal_error)?;
+    }
+    if let Some(track_layout) = input.track_layout {
+        let track_layout = match track_layout {
+            0 => None,
+            id => {
+                let tbl_lay = app_state.database.db_content().await.tbl_track_layouts().await;
+                if tbl_lay.item_by_id(id).await.is_none() {
+                    return Err(GeneralError::new(StatusCode::BAD_REQUEST, format!("Track layout {} not found", id)).into_response());
+                }
+                Some(id)
+            }
+        };
+        event.set_track_layout(track_layout).await.map_err(internal_error)?;
+    }
+    if let Some(server) = input.server {
+        event.set_server(server).await.map_err(internal_error)?;
+    }
+    for (session_type, schedule) in [(SessionType::Practice, input.practice),
+                                     (SessionType::Qualifying, input.qualifying),
+                                     (SessionType::Race, input.race)] {
+        if let Some(schedule) = schedule {
+            let schedule = schedule.start.map(|start| SessionSchedule { start, duration_minutes: schedule.duration_minutes });
+            event.set_session_schedule(session_type, schedule).await.map_err(internal_error)?;
+        }
+    }
+    if let Some(registration) = input.registration {
+        if let (Some(opens), Some(closes)) = (registration.opens, registration.closes) {
+            if closes < opens {
+                return Err(GeneralError::new(StatusCode::BAD_REQUEST, "Registration closes before it opens".to_string()).into_response());
+            }
+        }
+        event.set_registration_window(registration.opens, registration.closes).await.map_err(internal_error)?;
+    }
+    if let Some(max_grid) = input.max_grid {
+        event.set_max_grid(max_grid).await.map_err(internal_error)?;
+    }
+    if let Some(car_classes) = input.car_classes {
+        let tbl_cls = app_state.database.db_content().await.tbl_car_classes().await;
+        for class_id in car_classes.iter() {
+            if tbl_cls.item_by_id(*class_id).await.is_none() {
+                return Err(GeneralError::new(StatusCode::BAD_REQUEST, format!("Car class {} not found", class_id)).into_response());
+            }
+        }
+        event.set_car_classes(&car_classes).await.map_err(internal_error)?;
+    }
+    Ok(())
+}
+
+/// List all scheduled events
+pub async fn handler_list(State(app_state): State<AppState>) -> Response {
+    let tbl_evt = app_state.database.db_series().await.tbl_events().await;
+    let mut events = Vec::new();
+    for event in tbl_evt.items_all().await.iter() {
+        events.push(event_json(event).await);
+    }
+    Json(events).into_response()
+}
+
+/// Get a single event
+pub async fn handler_get(State(app_state): State<AppState>,
+                         Path(event_id): Path<i64>) -> Response {
+    let tbl_evt = app_state.database.db_series().await.tbl_events().await;
+    match tbl_evt.item_by_id(event_id).await {
+        Some(event) => Json(event_json(&event).await).into_response(),
+        None => event_not_found(),
+    }
+}
+
+/// Schedule a new event
+pub async fn handler_create(State(app_state): State<AppState>,
+                            RequirePromotion(http_user, _): RequirePromotion<levels::Officer>,
+                            Json(input): Json<EventRequest>) -> Response {
+    let name = match input.name.as_ref() {
+        Some(name) if !name.trim().is_empty() => name.clone(),
+        _ => return GeneralError::new(StatusCode::BAD_REQUEST, "Event name required".to_string()).into_response(),
+    };
+    let simulator = match input.simulator {
+        Some(simulator) => simulator,
+        None => return GeneralError::new(StatusCode::BAD_REQUEST, "Simulator required".to_string()).into_response(),
+    };
+    let tbl_evt = app_state.database.db_series().await.tbl_events().await;
+    let event = match tbl_evt.create_event(name, simulator).await {
+        Some(event) => event,
+        None => return internal_error("Could not create event"),
+    };
+    if let Err(response) = apply_event_request(&app_state, &event, input).await { return response; }
+    log::info!("{} created {}", http_user.user.display().await, event.display().await);
+    (StatusCode::CREATED, Json(event_json(&event).await)).into_response()
+}
+
+/// Modify an event
+pub async fn handler_update(State(app_state): State<AppState>,
+                            RequirePromotion(_, _): RequirePromotion<levels::Officer>,
+                            Path(event_id): Path<i64>,
+                            Json(input): Json<EventRequest>) -> Response {
+    let tbl_evt = app_state.database.db_series().await.tbl_events().await;
+    let event = match tbl_evt.item_by_id(event_id).await {
+        Some(event) => event,
+        None => return event_not_found(),
+    };
+    if let Err(response) = apply_event_request(&app_state, &event, input).await { return response; }
+    Json(event_json(&event).await).into_response()
+}
+
+/// Delete an event (including all registrations)
+pub async fn handler_delete(State(app_state): State<AppState>,
+                            RequirePromotion(http_user, _): RequirePromotion<levels::Officer>,
+                            Path(event_id): Path<i64>) -> Response {
+    let tbl_evt = app_state.database.db_series().await.tbl_events().await;
+    let event = match tbl_evt.item_by_id(event_id).await {
+        Some(event) => event,
+        None => return event_not_found(),
+    };
+    log::info!("{} deletes {}", http_user.user.display().await, event.display().await);
+    match tbl_evt.delete_event(event).await {
+        Ok(_) => Json(EmptyResponse{}).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Sign up the current user for an event (or change the car of an existing registration)
+pub async fn handler_register(State(app_state): State<AppState>,
+                              RequireLogin(http_user): RequireLogin,
+                              Path(event_id): Path<i64>,
+                              Json(input): Json<RegistrationRequest>) -> Response {
+    let tbl_evt = app_state.database.db_series().await.tbl_events().await;
+    let event = match tbl_evt.item_by_id(event_id).await {
+        Some(event) => event,
+        None => return event_not_found(),
+    };
+    if !event.is_registration_open(Utc::now()).await {
+        return GeneralError::new(StatusCode::CONFLICT, "Registration is not open".to_string()).into_response();
+    }
+
+    let user_id = http_user.user.id().await;
+    if is_race_banned(&app_state, user_id, &event).await {
+        return GeneralError::new(StatusCode::CONFLICT, "Registration is blocked by a race ban".to_string()).into_response();
+    }
+
+    // check car
+    if let Some(car_id) = input.car {
+        let car = match app_state.database.db_content().await.tbl_cars().await.item_by_id(car_id).await {
+            Some(car) => car,
+            None => return GeneralError::new(StatusCode::BAD_REQUEST, format!("Car {} not found", car_id)).into_response(),
+        };
+        let allowed_classes = event.car_classes().await;
+        if !allowed_classes.is_empty() {
+            let car_class = match car.car_class().await {
+                Some(car_class) => Some(car_class.id().await),
+                None => None,
+            };
+            if !car_class.is_some_and(|c| allowed_classes.contains(&c)) {
+                return GeneralError::new(StatusCode::BAD_REQUEST, format!("Car {} is not allowed at this event", car_id)).into_response();
+            }
+        }
+    }
+
+    let status = match event.register(user_id, input.car).await {
+        Ok(status) => status,
+        Err(e) => return internal_error(e),
+    };
+    let registration = match event.registrations().await.into_iter().find(|r| r.user == user_id) {
+        Some(registration) => registration,
+        None => return internal_error("registration not found"),
+    };
+    log::info!("{} registered for {} ({})", http_user.user.display().await, event.display().await, status.label());
+    Json(registration_json(registration.user, registration.car, registration.registered, status)).into_response()
+}
+
+/// Check if a race ban of the user covers an event
+///
+/// A race ban of N races applies to the next N scheduled events after the penalty was issued.
+async fn is_race_banned(app_state: &AppState, user_id: i64, event: &EventItem) -> bool {
+    let event_id = event.id().await;
+    let events = app_state.database.db_series().await.tbl_events().await.items_all().await;
+    let tbl_pen = app_state.database.db_results().await.tbl_penalties().await;
+    for penalty in tbl_pen.items_by_user(user_id).await.iter() {
+        if penalty.revoked().await || penalty.penalty_type().await != PenaltyType::RaceBan {
+            continue;
+        }
+        let issued = penalty.issued().await;
+        let mut banned_races = penalty.value().await;
+        for evt in events.iter() {
+            if banned_races <= 0 {
+                break;
+            }
+            if evt.start().await.is_none_or(|start| start <= issued) {
+                continue;
+            }
+            if evt.id().await == event_id {
+                return true;
+            }
+            banned_races -= 1;
+        }
+    }
+    false
+}
+
+/// Withdraw the registration of the current user
+pub async fn handler_unregister(State(app_state): State<AppState>,
+                                RequireLogin(http_user): RequireLogin,
+                                Path(event_id): Path<i64>) -> Response {
+    let tbl_evt = app_state.database.db_series().await.tbl_events().await;
+    let event = match tbl_evt.item_by_id(event_id).await {
+        Some(event) => event,
+        None => return event_not_found(),
+    };
+    let user_id = http_user.user.id().await;
+    match event.unregister(user_id).await {
+        Ok(_) => Json(EmptyResponse{}).into_response(),
+        Err(e) if e.is_db_not_found_type() => GeneralError::new(StatusCode::NOT_FOUND, "Not registered".to_string()).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
@@ -0,0 +1,246 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use crate::app_state::AppState;
+use crate::db2::content::Simulator;
+use crate::db2::content::track_layouts::TrackLayoutItem;
+use crate::db2::content::tracks::TrackItem;
+use crate::http::http_user::{levels, RequirePromotion};
+use super::{internal_error, GeneralError};
+use super::user::EmptyResponse;
+
+#[derive(Serialize, Deserialize)]
+pub struct SimIdJson {
+    pub simulator: Simulator,
+    pub identifier: String,
+}
+
+#[derive(Serialize)]
+pub struct LayoutJson {
+    id: i64,
+    name: String,
+    length: Option<f64>,
+    pit_lane_length: Option<f64>,
+    pit_speed_limit: Option<f64>,
+    pit_boxes: Option<i64>,
+    sim_ids: Vec<SimIdJson>,
+}
+
+#[derive(Serialize)]
+pub struct TrackJson {
+    id: i64,
+    name: String,
+    country: String,
+    layouts: Vec<LayoutJson>,
+}
+
+#[derive(Deserialize)]
+pub struct TrackRequest {
+    name: Option<String>,
+    country: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LayoutRequest {
+    name: Option<String>,
+    length: Option<f64>,
+    pit_lane_length: Option<f64>,
+    pit_speed_limit: Option<f64>,
+    pit_boxes: Option<i64>,
+    sim_ids: Option<Vec<SimIdJson>>,
+}
+
+async fn layout_json(layout: &TrackLayoutItem) -> LayoutJson {
+    LayoutJson {
+        id: layout.id().await,
+        name: layout.name().await,
+        length: layout.length().await,
+        pit_lane_length: layout.pit_lane_length().await,
+        pit_speed_limit: layout.pit_speed_limit().await,
+        pit_boxes: layout.pit_boxes().await,
+        sim_ids: layout.sim_ids().await.into_iter()
+            .map(|row| SimIdJson{simulator: row.simulator, identifier: row.identifier})
+            .collect(),
+    }
+}
+
+async fn track_json(track: &TrackItem) -> TrackJson {
+    let mut layouts = Vec::new();
+    for layout in track.layouts().await.iter() {
+        layouts.push(layout_json(layout).await);
+    }
+    TrackJson {
+        id: track.id().await,
+        name: track.name().await,
+        country: track.country().await,
+        layouts,
+    }
+}
+
+fn track_not_found() -> Response {
+    GeneralError::new(StatusCode::NOT_FOUND, "Track not found".to_string()).into_response()
+}
+
+fn layout_not_found() -> Response {
+    GeneralError::new(StatusCode::NOT_FOUND, "Layout not found".to_string()).into_response()
+}
+
+async fn apply_track_request(track: &TrackItem, input: TrackRequest) -> Result<(), Response> {
+    if let Some(name) = input.name {
+        track.set_name(name).await.map_err(internal_error)?;
+    }
+    if let Some(country) = input.country {
+        track.set_country(country).await.map_err(internal_error)?;
+    }
+    Ok(())
+}
+
+async fn apply_layout_request(layout: &TrackLayoutItem, input: LayoutRequest) -> Result<(), Response> {
+    if let Some(name) = input.name {
+        layout.set_name(name).await.map_err(internal_error)?;
+    }
+    if input.length.is_some() {
+        layout.set_length(input.length).await.map_err(internal_error)?;
+    }
+    if input.pit_lane_length.is_some() {
+        layout.set_pit_lane_length(input.pit_lane_length).await.map_err(internal_error)?;
+    }
+    if input.pit_speed_limit.is_some() {
+        layout.set_pit_speed_limit(input.pit_speed_limit).await.map_err(internal_error)?;
+    }
+    if input.pit_boxes.is_some() {
+        layout.set_pit_boxes(input.pit_boxes).await.map_err(internal_error)?;
+    }
+    if let Some(sim_ids) = input.sim_ids {
+        for sim_id in sim_ids.into_iter() {
+            if sim_id.identifier.trim().is_empty() {
+                layout.remove_sim_id(sim_id.simulator).await.map_err(internal_error)?;
+            } else {
+                layout.set_sim_id(sim_id.simulator, sim_id.identifier).await.map_err(internal_error)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// List all tracks
+pub async fn handler_list(State(app_state): State<AppState>) -> Response {
+    let tbl_trk = app_state.database.db_content().await.tbl_tracks().await;
+    let mut tracks = Vec::new();
+    for track in tbl_trk.items_all().await.iter() {
+        tracks.push(track_json(track).await);
+    }
+    Json(tracks).into_response()
+}
+
+/// Get a single track
+pub async fn handler_get(State(app_state): State<AppState>,
+                         Path(track_id): Path<i64>) -> Response {
+    let tbl_trk = app_state.database.db_content().await.tbl_tracks().await;
+    match tbl_trk.item_by_id(track_id).await {
+        Some(track) => Json(track_json(&track).await).into_response(),
+        None => track_not_found(),
+    }
+}
+
+/// Create a new track
+pub async fn handler_create(State(app_state): State<AppState>,
+                            RequirePromotion(http_user, _): RequirePromotion<levels::Director>,
+                            Json(input): Json<TrackRequest>) -> Response {
+    let name = match input.name.as_ref() {
+        Some(name) if !name.trim().is_empty() => name.clone(),
+        _ => return GeneralError::new(StatusCode::BAD_REQUEST, "Track name required".to_string()).into_response(),
+    };
+    let tbl_trk = app_state.database.db_content().await.tbl_tracks().await;
+    let track = match tbl_trk.create_track(name).await {
+        Some(track) => track,
+        None => return internal_error("Could not create track"),
+    };
+    if let Err(response) = apply_track_request(&track, input).await { return response; }
+    log::info!("{} created {}", http_user.user.display().await, track.display().await);
+    (StatusCode::CREATED, Json(track_json(&track).await)).into_response()
+}
+
+/// Modify a track
+pub async fn handler_update(State(app_state): State<AppState>,
+                            RequirePromotion(_, _): RequirePromotion<levels::Director>,
+                            Path(track_id): Path<i64>,
+                            Json(input): Json<TrackRequest>) -> Response {
+    let tbl_trk = app_state.database.db_content().await.tbl_tracks().await;
+    let track = match tbl_trk.item_by_id(track_id).await {
+        Some(track) => track,
+        None => return track_not_found(),
+    };
+    if let Err(response) = apply_track_request(&track, input).await { return response; }
+    Json(track_json(&track).await).into_response()
+}
+
+/// Delete a track (including all layouts)
+pub async fn handler_delete(State(app_state): State<AppState>,
+                            RequirePromotion(http_user, _): RequirePromotion<levels::Director>,
+                            Path(track_id): Path<i64>) -> Response {
+    let tbl_trk = app_state.database.db_content().await.tbl_tracks().await;
+    let track = match tbl_trk.item_by_id(track_id).await {
+        Some(track) => track,
+        None => return track_not_found(),
+    };
+    log::info!("{} deletes {}", http_user.user.display().await, track.display().await);
+    match tbl_trk.delete_track(track).await {
+        Ok(_) => Json(EmptyResponse{}).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Add a new layout to a track
+pub async fn handler_layout_create(State(app_state): State<AppState>,
+                                   RequirePromotion(_, _): RequirePromotion<levels::Director>,
+                                   Path(track_id): Path<i64>,
+                                   Json(input): Json<LayoutRequest>) -> Response {
+    let name = match input.name.as_ref() {
+        Some(name) if !name.trim().is_empty() => name.clone(),
+        _ => return GeneralError::new(StatusCode::BAD_REQUEST, "Layout name required".to_string()).into_response(),
+    };
+    let tbl_trk = app_state.database.db_content().await.tbl_tracks().await;
+    let track = match tbl_trk.item_by_id(track_id).await {
+        Some(track) => track,
+        None => return track_not_found(),
+    };
+    let layout = match track.create_layout(name).await {
+        Some(layout) => layout,
+        None => return internal_error("Could not create layout"),
+    };
+    if let Err(response) = apply_layout_request(&layout, input).await { return response; }
+    (StatusCode::CREATED, Json(layout_json(&layout).await)).into_response()
+}
+
+/// Modify a layout
+pub async fn handler_layout_update(State(app_state): State<AppState>,
+                                   RequirePromotion(_, _): RequirePromotion<levels::Director>,
+                                   Path(layout_id): Path<i64>,
+                                   Json(input): Json<LayoutRequest>) -> Response {
+    let tbl_lay = app_state.database.db_content().await.tbl_track_layouts().await;
+    let layout = match tbl_lay.item_by_id(layout_id).await {
+        Some(layout) => layout,
+        None => return layout_not_found(),
+    };
+    if let Err(response) = apply_layout_request(&layout, input).await { return response; }
+    Json(layout_json(&layout).await).into_response()
+}
+
+/// Delete a layout
+pub async fn handler_layout_delete(State(app_state): State<AppState>,
+                                   RequirePromotion(http_user, _): RequirePromotion<levels::Director>,
+                                   Path(layout_id): Path<i64>) -> Response {
+    let tbl_lay = app_state.database.db_content().await.tbl_track_layouts().await;
+    let layout = match tbl_lay.item_by_id(layout_id).await {
+        Some(layout) => layout,
+        None => return layout_not_found(),
+    };
+    log::info!("{} deletes {}", http_user.user.display().await, layout.display().await);
+    match tbl_lay.delete_layout(layout).await {
+        Ok(_) => Json(EmptyResponse{}).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
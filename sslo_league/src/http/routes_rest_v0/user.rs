@@ -1,4 +1,11 @@
 pub mod account;
+pub mod calendar;
+pub mod data;
+pub mod merge;
+pub mod promotion;
+pub mod session;
+pub mod totp;
+pub mod webauthn;
 
 use axum::extract::State;
 use axum::http::StatusCode;
@@ -6,7 +13,7 @@ use axum::Json;
 use axum::response::{IntoResponse, Response};
 use serde::{Deserialize, Serialize};
 use crate::app_state::AppState;
-use crate::http::http_user::HttpUserExtractor;
+use crate::http::http_user::RequireLogin;
 use super::GeneralError;
 
 #[derive(Serialize)]
@@ -20,13 +27,8 @@ pub struct SetNameRequest {
 }
 
 pub async fn handler_set_name(State(_app_state): State<AppState>,
-                              HttpUserExtractor(mut http_user): HttpUserExtractor,
+                              RequireLogin(mut http_user): RequireLogin,
                               Json(input): Json<SetNameRequest>) -> Response {
-
-    if !http_user.is_logged_in() {
-        return StatusCode::UNAUTHORIZED.into_response();
-    }
-
     match http_user.user.set_name(input.name).await {
         Ok(_) => {},
         Err(e) => {
@@ -47,15 +49,8 @@ pub struct SetPasswordRequest {
 }
 
 pub async fn handler_set_password(State(_app_state): State<AppState>,
-                                     HttpUserExtractor(mut http_user): HttpUserExtractor,
+                                     RequireLogin(mut http_user): RequireLogin,
                                      Json(input): Json<SetPasswordRequest>) -> Response {
-
-    if !http_user.is_logged_in() {
-        return GeneralError::new(StatusCode::UNAUTHORIZED,
-                                 "No user logged in".to_string(),
-        ).into_response();
-    }
-
     if let Some(new_password) = input.new_password {
         if !http_user.user.update_password(input.old_password, Some(new_password)).await {
             log::error!("Failed to update password for {}", http_user.user.display().await);
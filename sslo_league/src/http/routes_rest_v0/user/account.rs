@@ -6,10 +6,10 @@ use axum::response::IntoResponse;
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use crate::app_state::AppState;
-use crate::db2::members::email_accounts::EmailAccountItem;
-use crate::db2::members::users::UserItem;
-use crate::http::http_user::HttpUserExtractor;
-use crate::http::routes_rest_v0::GeneralError;
+use sslo_lib::db::members::email_accounts::EmailAccountItem;
+use sslo_lib::db::members::users::UserItem;
+use crate::http::http_user::RequireLogin;
+use crate::http::routes_rest_v0::{internal_error, GeneralError};
 
 #[derive(Deserialize)]
 pub struct RequestData {
@@ -17,17 +17,10 @@ pub struct RequestData {
 }
 
 pub async fn email_put(State(app_state): State<AppState>,
-                       HttpUserExtractor(http_user): HttpUserExtractor,
+                       RequireLogin(http_user): RequireLogin,
                        OriginalUri(uri): OriginalUri,
                        Json(input): Json<RequestData>,
 ) -> Response {
-
-    // verify user
-    if http_user.user.id().await <= 0 {
-        log::warn!("Deny adding email '{}' to invalid {}", input.email, http_user.user.display().await);
-        return GeneralError::new(StatusCode::FORBIDDEN, "No valid user logged in to add an email account!".to_string()).into_response();
-    }
-
     // artificial slowdown
     let wait_ms: u64 = 1000u64 + u64::from(rand::thread_rng().next_u32()) / 0x200_000u64; // results in 1000..3048ms
     tokio::time::sleep(std::time::Duration::from_millis(wait_ms)).await;
@@ -75,16 +68,9 @@ pub async fn email_put(State(app_state): State<AppState>,
 }
 
 pub async fn email_delete(State(app_state): State<AppState>,
-                       HttpUserExtractor(http_user): HttpUserExtractor,
+                       RequireLogin(http_user): RequireLogin,
                        Json(input): Json<RequestData>,
 ) -> Response {
-
-    // verify user
-    if http_user.user.id().await <= 0 {
-        log::warn!("Deny adding email '{}' to invalid {}", input.email, http_user.user.display().await);
-        return GeneralError::new(StatusCode::FORBIDDEN, "No valid user logged in to add an email account!".to_string()).into_response();
-    }
-
     // artificial slowdown
     let wait_ms: u64 = 1000u64 + u64::from(rand::thread_rng().next_u32()) / 0x200_000u64; // results in 1000..3048ms
     tokio::time::sleep(std::time::Duration::from_millis(wait_ms)).await;
@@ -110,3 +96,46 @@ pub async fn email_delete(State(app_state): State<AppState>,
     email_item.create_token(None).await;
     StatusCode::NO_CONTENT.into_response()
 }
+
+#[derive(Deserialize)]
+pub struct SteamRequestData {
+    id: i64,
+}
+
+pub async fn steam_delete(State(app_state): State<AppState>,
+                          RequireLogin(http_user): RequireLogin,
+                          Json(input): Json<SteamRequestData>,
+) -> Response {
+    let db_members = app_state.database.db_members().await;
+    let tbl_steam = db_members.tbl_steam_accounts().await;
+
+    // get steam account of the current user
+    let steam_item = match tbl_steam.item_by_id(input.id).await {
+        Some(steam) => steam,
+        None => {
+            log::warn!("Deny deleting steam account id={} from {}, because it does not exist!", input.id, http_user.user.display().await);
+            return GeneralError::new(StatusCode::NOT_FOUND, "Steam account not found!".to_string()).into_response()
+        },
+    };
+    let is_owner = match steam_item.has_user().await {
+        true => match steam_item.user().await {
+            Some(steam_user) => steam_user.id().await == http_user.user.id().await,
+            None => false,
+        },
+        false => false,
+    };
+    if !is_owner {
+        log::warn!("Deny deleting {} for {}, because it is not assigned to this user", steam_item.display().await, http_user.user.display().await);
+        return GeneralError::new(StatusCode::NOT_FOUND, "Steam account not found!".to_string()).into_response()
+    }
+
+    // unset user (the last login method is not removed)
+    match steam_item.unset_user().await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => {
+            log::warn!("Deny deleting {} for {}, because it is the last login method", steam_item.display().await, http_user.user.display().await);
+            GeneralError::new(StatusCode::CONFLICT, "Cannot remove the last login method!".to_string()).into_response()
+        },
+        Err(e) => internal_error(e),
+    }
+}
@@ -0,0 +1,36 @@
+use axum::extract::{Host, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use crate::app_state::AppState;
+use crate::http::http_user::RequireLogin;
+use super::super::{internal_error, GeneralError};
+use super::EmptyResponse;
+
+#[derive(Serialize)]
+pub struct CalendarTokenResponse {
+    url: String,
+}
+
+/// Create a new personal calendar feed (an existing feed URL becomes invalid)
+pub async fn handler_create(State(app_state): State<AppState>,
+                            RequireLogin(http_user): RequireLogin,
+                            Host(host): Host) -> Response {
+    let tbl_ict = app_state.database.db_series().await.tbl_ical_tokens().await;
+    match tbl_ict.create(http_user.user.id().await).await {
+        Ok(token) => Json(CalendarTokenResponse { url: format!("https://{}/ical/user/{}.ics", host, token) }).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Revoke the personal calendar feed
+pub async fn handler_revoke(State(app_state): State<AppState>,
+                            RequireLogin(http_user): RequireLogin) -> Response {
+    let tbl_ict = app_state.database.db_series().await.tbl_ical_tokens().await;
+    match tbl_ict.revoke(http_user.user.id().await).await {
+        Ok(true) => Json(EmptyResponse{}).into_response(),
+        Ok(false) => GeneralError::new(StatusCode::NOT_FOUND, "No calendar feed".to_string()).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
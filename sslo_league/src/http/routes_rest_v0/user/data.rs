@@ -0,0 +1,497 @@
+use axum::extract::State;
+use axum::http::header::{CONTENT_DISPOSITION, SET_COOKIE};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sslo_lib::db::members::cookie_logins::UNSET_COOKIE;
+use sslo_lib::db::members::users::{PromotionAuthority, PromotionLevel};
+use crate::app_state::AppState;
+use crate::db2::results::penalties::{AppealState, PenaltyType};
+use crate::db2::results::protests::ProtestState;
+use crate::http::http_user::{HttpUser, RequireLogin};
+use super::super::{internal_error, GeneralError};
+use super::EmptyResponse;
+
+#[derive(Serialize)]
+pub struct UserData {
+    exported: DateTime<Utc>,
+    user: UserRow,
+    promotion_history: Vec<PromotionChange>,
+    email_accounts: Vec<EmailAccount>,
+    steam_accounts: Vec<SteamAccount>,
+    discord_accounts: Vec<DiscordAccount>,
+    passkeys: Vec<Passkey>,
+    cookie_logins: Vec<CookieLogin>,
+    results: Vec<SessionResult>,
+    laps: Vec<SessionLap>,
+    incidents: Vec<SessionIncident>,
+    session_penalties: Vec<SessionPenalty>,
+    steward_penalties: Vec<StewardPenalty>,
+    protests: Vec<Protest>,
+    protest_involvements: Vec<ProtestInvolvement>,
+    protest_comments: Vec<ProtestComment>,
+    event_registrations: Vec<EventRegistration>,
+    calendar_feed_creation: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct UserRow {
+    id: i64,
+    name: String,
+    promotion_level: PromotionLevel,
+    promotion_authority: PromotionAuthority,
+    last_lap: Option<DateTime<Utc>>,
+    last_login: Option<DateTime<Utc>>,
+    totp_enabled: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct PromotionChange {
+    granted_by: Option<i64>,
+    promotion_level: PromotionLevel,
+    promotion_authority: PromotionAuthority,
+    date: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct EmailAccount {
+    email: String,
+    verified_since: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct SteamAccount {
+    steam_id: String,
+    creation: DateTime<Utc>,
+    last_login: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct DiscordAccount {
+    discord_id: String,
+    username: String,
+    creation: DateTime<Utc>,
+    last_login: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct Passkey {
+    name: String,
+    creation: DateTime<Utc>,
+    last_usage: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct CookieLogin {
+    id: i64,
+    creation: DateTime<Utc>,
+    last_usage: Option<DateTime<Utc>>,
+    last_useragent: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SessionResult {
+    session: i64,
+    position: i64,
+    driver_name: String,
+    driver_guid: String,
+    car_identifier: String,
+    car_number: i64,
+    team: String,
+    lap_count: i64,
+    best_lap: Option<i64>,
+    total_time: Option<i64>,
+    incidents: i64,
+}
+
+#[derive(Serialize)]
+pub struct SessionLap {
+    session: i64,
+    driver_guid: String,
+    car_number: i64,
+    lap_number: i64,
+    lap_time: i64,
+    sectors: String,
+    valid: bool,
+}
+
+#[derive(Serialize)]
+pub struct SessionIncident {
+    session: i64,
+    lap: Option<i64>,
+    incident_type: String,
+    impact_speed: Option<f64>,
+
+    /// the simulator guid of the user in this incident
+    driver_guid: String,
+
+    /// the user was the other driver of the incident
+    other_driver: bool,
+}
+
+#[derive(Serialize)]
+pub struct SessionPenalty {
+    session: i64,
+    driver_guid: String,
+    reason: String,
+    penalty: String,
+    penalty_value: i64,
+    violation_lap: i64,
+    cleared_lap: Option<i64>,
+    post_race: bool,
+}
+
+#[derive(Serialize)]
+pub struct StewardPenalty {
+    id: i64,
+    session: i64,
+    penalty_type: PenaltyType,
+    value: i64,
+    reason: String,
+    issued: DateTime<Utc>,
+    appeal_state: AppealState,
+    appeal_text: String,
+    revoked: bool,
+}
+
+#[derive(Serialize)]
+pub struct Protest {
+    id: i64,
+    session: i64,
+    lap: Option<i64>,
+    description: String,
+    evidence: Vec<String>,
+    filed: DateTime<Utc>,
+    state: ProtestState,
+    resolution: String,
+    resolved: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct ProtestInvolvement {
+    protest: i64,
+    driver_guid: String,
+}
+
+#[derive(Serialize)]
+pub struct ProtestComment {
+    protest: i64,
+    date: DateTime<Utc>,
+    text: String,
+}
+
+#[derive(Serialize)]
+pub struct EventRegistration {
+    event: i64,
+    car: Option<i64>,
+    registered: DateTime<Utc>,
+}
+
+/// Export all personal data of the current user (as JSON file download)
+pub async fn handler_export(State(app_state): State<AppState>,
+                            RequireLogin(http_user): RequireLogin) -> Response {
+    let user = &http_user.user;
+    let user_id = user.id().await;
+    let db_members = app_state.database.db_members().await;
+    let db_results = app_state.database.db_results().await;
+
+    let promotion = user.promotion().await;
+    let user_row = UserRow {
+        id: user_id,
+        name: user.name().await,
+        promotion_level: promotion.level,
+        promotion_authority: promotion.authority,
+        last_lap: *user.last_lap().await.raw(),
+        last_login: *user.last_login().await.raw(),
+        totp_enabled: *user.totp_enabled().await.raw(),
+    };
+
+    let promotion_history = user.promotion_history().await.into_iter().map(|row| PromotionChange {
+        granted_by: row.granted_by,
+        promotion_level: row.promotion_level,
+        promotion_authority: row.promotion_authority,
+        date: row.date,
+    }).collect();
+
+    let mut email_accounts = Vec::new();
+    for item in db_members.tbl_email_accounts().await.items_by_user(user).await {
+        email_accounts.push(EmailAccount {
+            email: item.email().await,
+            verified_since: *item.verified_since().await.raw(),
+        });
+    }
+
+    let mut steam_accounts = Vec::new();
+    for item in db_members.tbl_steam_accounts().await.items_by_user(user).await {
+        steam_accounts.push(SteamAccount {
+            steam_id: item.steam_id().await,
+            creation: item.creation().await,
+            last_login: *item.last_login().await.raw(),
+        });
+    }
+
+    let mut discord_accounts = Vec::new();
+    for item in db_members.tbl_discord_accounts().await.items_by_user(user).await {
+        discord_accounts.push(DiscordAccount {
+            discord_id: item.discord_id().await,
+            username: item.username().await,
+            creation: item.creation().await,
+            last_login: *item.last_login().await.raw(),
+        });
+    }
+
+    let mut passkeys = Vec::new();
+    for item in db_members.tbl_webauthn_credentials().await.items_by_user(user).await {
+        passkeys.push(Passkey {
+            name: item.name().await,
+            creation: item.creation().await,
+            last_usage: *item.last_usage().await.raw(),
+        });
+    }
+
+    let mut cookie_logins = Vec::new();
+    for item in db_members.tbl_cookie_logins().await.items_by_user(user).await {
+        cookie_logins.push(CookieLogin {
+            id: item.id().await,
+            creation: item.creation().await,
+            last_usage: item.last_usage().await,
+            last_useragent: item.last_useragent().await,
+        });
+    }
+
+    let tbl_ses = db_results.tbl_sessions().await;
+    let results = tbl_ses.results_by_user(user_id).await.into_iter().map(|row| SessionResult {
+        session: row.session,
+        position: row.position,
+        driver_name: row.driver_name,
+        driver_guid: row.driver_guid,
+        car_identifier: row.car_identifier,
+        car_number: row.car_number,
+        team: row.team,
+        lap_count: row.lap_count,
+        best_lap: row.best_lap,
+        total_time: row.total_time,
+        incidents: row.incidents,
+    }).collect();
+    let laps = tbl_ses.laps_by_user(user_id).await.into_iter().map(|row| SessionLap {
+        session: row.session,
+        driver_guid: row.driver_guid,
+        car_number: row.car_number,
+        lap_number: row.lap_number,
+        lap_time: row.lap_time,
+        sectors: row.sectors,
+        valid: row.valid,
+    }).collect();
+    let incidents = tbl_ses.incidents_by_user(user_id).await.into_iter().map(|row| {
+        let other_driver = row.user != Some(user_id);
+        SessionIncident {
+            session: row.session,
+            lap: row.lap,
+            incident_type: row.incident_type,
+            impact_speed: row.impact_speed,
+            driver_guid: match other_driver {
+                true => row.other_driver_guid.unwrap_or_default(),
+                false => row.driver_guid,
+            },
+            other_driver,
+        }
+    }).collect();
+    let session_penalties = tbl_ses.penalties_by_user(user_id).await.into_iter().map(|row| SessionPenalty {
+        session: row.session,
+        driver_guid: row.driver_guid,
+        reason: row.reason,
+        penalty: row.penalty,
+        penalty_value: row.penalty_value,
+        violation_lap: row.violation_lap,
+        cleared_lap: row.cleared_lap,
+        post_race: row.post_race,
+    }).collect();
+
+    let mut steward_penalties = Vec::new();
+    for item in db_results.tbl_penalties().await.items_by_user(user_id).await {
+        steward_penalties.push(StewardPenalty {
+            id: item.id().await,
+            session: item.session().await,
+            penalty_type: item.penalty_type().await,
+            value: item.value().await,
+            reason: item.reason().await,
+            issued: item.issued().await,
+            appeal_state: item.appeal_state().await,
+            appeal_text: item.appeal_text().await,
+            revoked: item.revoked().await,
+        });
+    }
+
+    let tbl_pro = db_results.tbl_protests().await;
+    let mut protests = Vec::new();
+    for item in tbl_pro.items_by_reporter(user_id).await {
+        protests.push(Protest {
+            id: item.id().await,
+            session: item.session().await,
+            lap: item.lap().await,
+            description: item.description().await,
+            evidence: item.evidence().await,
+            filed: item.filed().await,
+            state: item.state().await,
+            resolution: item.resolution().await,
+            resolved: item.resolved().await,
+        });
+    }
+    let protest_involvements = tbl_pro.drivers_by_user(user_id).await.into_iter().map(|row| ProtestInvolvement {
+        protest: row.protest,
+        driver_guid: row.driver_guid,
+    }).collect();
+    let protest_comments = tbl_pro.comments_by_user(user_id).await.into_iter().map(|row| ProtestComment {
+        protest: row.protest,
+        date: row.date,
+        text: row.text,
+    }).collect();
+
+    let db_series = app_state.database.db_series().await;
+    let tbl_evt = db_series.tbl_events().await;
+    let event_registrations = tbl_evt.registrations_by_user(user_id).await.into_iter().map(|row| EventRegistration {
+        event: row.event,
+        car: row.car,
+        registered: row.registered,
+    }).collect();
+    let calendar_feed_creation = db_series.tbl_ical_tokens().await.creation(user_id).await;
+
+    let user_data = UserData {
+        exported: Utc::now(),
+        user: user_row,
+        promotion_history,
+        email_accounts,
+        steam_accounts,
+        discord_accounts,
+        passkeys,
+        cookie_logins,
+        results,
+        laps,
+        incidents,
+        session_penalties,
+        steward_penalties,
+        protests,
+        protest_involvements,
+        protest_comments,
+        event_registrations,
+        calendar_feed_creation,
+    };
+    log::info!("exporting personal data of {}", user.display().await);
+    let mut response = Json(user_data).into_response();
+    let disposition = format!("attachment; filename=\"sslo_user_{}.json\"", user_id);
+    response.headers_mut().insert(CONTENT_DISPOSITION, disposition.parse().unwrap());
+    response
+}
+
+#[derive(Deserialize)]
+pub struct DeleteRequest {
+    password: Option<String>,
+    totp: Option<String>,
+
+    /// the name of the user, as confirmation of users without password and TOTP
+    name: Option<String>,
+}
+
+/// Verify the user again before the irreversible deletion
+/// The password and the TOTP code are required if set, other users must confirm with their name.
+async fn verify_deletion(app_state: &AppState, http_user: &mut HttpUser, input: DeleteRequest) -> bool {
+    let has_password = http_user.user.has_password().await;
+    let totp_enabled = http_user.user.totp_enabled().await.raw().is_some();
+    if has_password {
+        let Some(password) = input.password else { return false };
+        if !http_user.user.verify_password(password, http_user.user_agent.clone()).await {
+            return false;
+        }
+    }
+    if totp_enabled {
+        let key = match app_state.config.totp.as_ref() {
+            Some(totp) => &totp.key,
+            None => return false,
+        };
+        let Some(code) = input.totp else { return false };
+        if !http_user.user.totp_verify(key, &code).await {
+            return false;
+        }
+    }
+    if !has_password && !totp_enabled {
+        let user_name = http_user.user.name().await;
+        return input.name.is_some_and(|name| name == user_name);
+    }
+    true
+}
+
+/// Delete the current user
+/// All personal data is removed, results are kept with an anonymous driver name.
+pub async fn handler_delete(State(app_state): State<AppState>,
+                            RequireLogin(mut http_user): RequireLogin,
+                            Json(input): Json<DeleteRequest>) -> Response {
+    if app_state.config.general.root_user_id == Some(http_user.user.id().await) {
+        return GeneralError::new(StatusCode::FORBIDDEN, "The root user cannot be deleted".to_string()).into_response();
+    }
+    if !verify_deletion(&app_state, &mut http_user, input).await {
+        log::warn!("deny deletion of {}, because it was not confirmed", http_user.user.display().await);
+        return GeneralError::new(StatusCode::FORBIDDEN, "Deletion not confirmed (password, TOTP code or name required)".to_string()).into_response();
+    }
+    log::info!("{} requested deletion of personal data", http_user.user.display().await);
+
+    // anonymize first, so that the user stays logged in if this fails
+    if let Err(e) = app_state.database.anonymize_user(&http_user.user).await {
+        return internal_error(e);
+    }
+
+    // logout (the login cookies were already deleted by the anonymization)
+    let mut response = Json(EmptyResponse{}).into_response();
+    response.headers_mut().insert(SET_COOKIE, UNSET_COOKIE.parse().unwrap());
+    response
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use sslo_lib::db::members::users::{anonymous_name, UserItem};
+    use crate::config::Config;
+
+    async fn delete(app_state: &AppState, user: &UserItem, input: serde_json::Value) -> StatusCode {
+        let cookie_login = app_state.database.db_members().await.tbl_cookie_logins().await.create_new_cookie(user).await;
+        let http_user = HttpUser {
+            user: user.clone(),
+            cookie_login,
+            user_agent: "unit test".to_string(),
+            is_root: false,
+            promotion_limit: None,
+        };
+        let input: DeleteRequest = serde_json::from_value(input).unwrap();
+        handler_delete(State(app_state.clone()), RequireLogin(http_user), Json(input)).await.status()
+    }
+
+    #[test(tokio::test)]
+    async fn delete_requires_confirmation() {
+        let app_state = AppState::new_in_memory(Config::new_for_test()).await;
+        let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+
+        // a user with password must enter the password
+        let mut user = tbl_usr.create_new_user().await.unwrap();
+        let user_name = user.name().await;
+        assert!(user.set_password(Some("SecretPassword".to_string())).await);
+        assert_eq!(delete(&app_state, &user, serde_json::json!({})).await, StatusCode::FORBIDDEN);
+        assert_eq!(delete(&app_state, &user, serde_json::json!({"name": user_name})).await, StatusCode::FORBIDDEN);
+        assert_eq!(delete(&app_state, &user, serde_json::json!({"password": "WrongPassword"})).await, StatusCode::FORBIDDEN);
+        assert!(user.has_password().await);
+        assert_eq!(delete(&app_state, &user, serde_json::json!({"password": "SecretPassword"})).await, StatusCode::OK);
+        assert!(!user.has_password().await);
+        assert_eq!(user.name().await, anonymous_name(user.id().await));
+
+        // a user without password and TOTP must confirm with the own name
+        let mut user = tbl_usr.create_new_user().await.unwrap();
+        user.set_name("Some Driver".to_string()).await.unwrap();
+        assert_eq!(delete(&app_state, &user, serde_json::json!({})).await, StatusCode::FORBIDDEN);
+        assert_eq!(delete(&app_state, &user, serde_json::json!({"name": "Other Driver"})).await, StatusCode::FORBIDDEN);
+        assert_eq!(user.name().await, "Some Driver");
+        assert_eq!(delete(&app_state, &user, serde_json::json!({"name": "Some Driver"})).await, StatusCode::OK);
+        assert_eq!(user.name().await, anonymous_name(user.id().await));
+    }
+}
@@ -0,0 +1,154 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use sslo_lib::db::members::users::{UserItem, MERGE_TOKEN_VALIDITY_MINUTES};
+use crate::app_state::AppState;
+use crate::http::http_user::{levels, RequireLogin, RequirePromotion};
+use super::super::{internal_error, GeneralError};
+use super::EmptyResponse;
+
+#[derive(Serialize)]
+pub struct MergeCodeResponse {
+    user_id: i64,
+    code: String,
+    valid_minutes: i64,
+}
+
+#[derive(Deserialize)]
+pub struct MergeRequest {
+    user_id: i64,
+    code: String,
+}
+
+#[derive(Deserialize)]
+pub struct AdminMergeRequest {
+    into: i64,
+}
+
+fn user_not_found() -> Response {
+    GeneralError::new(StatusCode::NOT_FOUND, "User not found".to_string()).into_response()
+}
+
+/// Merge a user into another user and recompute the ratings
+async fn merge(app_state: &AppState, from: &UserItem, into: &UserItem) -> Response {
+    if from.id().await == into.id().await {
+        return GeneralError::new(StatusCode::BAD_REQUEST, "Cannot merge a user into itself".to_string()).into_response();
+    }
+    if app_state.config.general.root_user_id == Some(from.id().await) {
+        return GeneralError::new(StatusCode::FORBIDDEN, "Cannot merge the root user into another user".to_string()).into_response();
+    }
+    if let Err(e) = app_state.database.merge_users(from, into).await {
+        return internal_error(e);
+    }
+    if let Err(e) = crate::ranking::recompute(&app_state.database).await {
+        log::error!("Could not recompute ratings after merging into {}: {}", into.display().await, e);
+    }
+    Json(EmptyResponse{}).into_response()
+}
+
+/// Create a code that proves the ownership of the current user
+/// The code is entered while logged in with the other user, to merge the current user into the other one.
+pub async fn handler_code(RequireLogin(http_user): RequireLogin) -> Response {
+    match http_user.user.create_merge_token().await {
+        Some(code) => Json(MergeCodeResponse {
+            user_id: http_user.user.id().await,
+            code,
+            valid_minutes: MERGE_TOKEN_VALIDITY_MINUTES,
+        }).into_response(),
+        None => GeneralError::new(StatusCode::INTERNAL_SERVER_ERROR, "Could not create merge code".to_string()).into_response(),
+    }
+}
+
+/// Merge another user (proven by a merge code) into the current user
+pub async fn handler_merge(State(app_state): State<AppState>,
+                           RequireLogin(http_user): RequireLogin,
+                           Json(input): Json<MergeRequest>) -> Response {
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+    let from = match tbl_usr.user_by_id(input.user_id).await {
+        Some(user) => user,
+        None => return user_not_found(),
+    };
+    if !from.consume_merge_token(&input.code).await {
+        log::warn!("deny {} to merge {}, because of invalid merge code", http_user.user.display().await, from.display().await);
+        return GeneralError::new(StatusCode::FORBIDDEN, "Invalid merge code".to_string()).into_response();
+    }
+    merge(&app_state, &from, &http_user.user).await
+}
+
+/// Merge a user into another user (administrators only, for users below the own promotion level)
+pub async fn handler_admin_merge(State(app_state): State<AppState>,
+                                 RequirePromotion(http_user, _): RequirePromotion<levels::Admin>,
+                                 Path(user_id): Path<i64>,
+                                 Json(input): Json<AdminMergeRequest>) -> Response {
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+    let (from, into) = match (tbl_usr.user_by_id(user_id).await, tbl_usr.user_by_id(input.into).await) {
+        (Some(from), Some(into)) => (from, into),
+        _ => return user_not_found(),
+    };
+
+    // administrators cannot take over the login methods of users at or above their own promotion level
+    let own_level = http_user.user.promotion().await.level;
+    if !http_user.has_root_permissions() && (from.promotion().await.level >= own_level || into.promotion().await.level >= own_level) {
+        log::warn!("deny {} to merge {} into {}", http_user.user.display().await, from.display().await, into.display().await);
+        return GeneralError::new(StatusCode::FORBIDDEN, "Insufficient promotion authority".to_string()).into_response();
+    }
+
+    log::info!("{} merges {} into {}", http_user.user.display().await, from.display().await, into.display().await);
+    merge(&app_state, &from, &into).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::marker::PhantomData;
+    use test_log::test;
+    use sslo_lib::db::members::users::{Promotion, PromotionAuthority, PromotionLevel};
+    use crate::config::Config;
+    use crate::http::http_user::HttpUser;
+
+    async fn admin_merge(app_state: &AppState, admin: &UserItem, from: &UserItem, into: &UserItem) -> StatusCode {
+        let cookie_login = app_state.database.db_members().await.tbl_cookie_logins().await.create_new_cookie(admin).await;
+        let http_user = HttpUser {
+            user: admin.clone(),
+            cookie_login,
+            user_agent: "unit test".to_string(),
+            is_root: app_state.config.general.root_user_id == Some(admin.id().await),
+            promotion_limit: None,
+        };
+        let input = AdminMergeRequest { into: into.id().await };
+        handler_admin_merge(State(app_state.clone()), RequirePromotion(http_user, PhantomData), Path(from.id().await), Json(input))
+            .await.status()
+    }
+
+    #[test(tokio::test)]
+    async fn admin_merge_escalation() {
+        let mut config = Config::new_for_test();
+        config.general.root_user_id = Some(1);
+        let app_state = AppState::new_in_memory(config).await;
+        let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+        let root = tbl_usr.create_new_user().await.unwrap();
+        let mut chief = tbl_usr.create_new_user().await.unwrap();
+        let mut admin = tbl_usr.create_new_user().await.unwrap();
+        let driver = tbl_usr.create_new_user().await.unwrap();
+        chief.grant_promotion(Promotion::new(PromotionLevel::Admin, PromotionAuthority::Chief), None).await.unwrap();
+        admin.grant_promotion(Promotion::new(PromotionLevel::Admin, PromotionAuthority::Executing), None).await.unwrap();
+
+        // an administrator cannot merge users at the own promotion level in either direction
+        assert_eq!(admin_merge(&app_state, &admin, &chief, &admin).await, StatusCode::FORBIDDEN);
+        assert_eq!(admin_merge(&app_state, &admin, &driver, &chief).await, StatusCode::FORBIDDEN);
+        assert!(tbl_usr.user_by_id(chief.id().await).await.is_some());
+        assert_eq!(admin.promotion().await, Promotion::new(PromotionLevel::Admin, PromotionAuthority::Executing));
+
+        // the root user can, but the promotion of the merged user is not carried over
+        assert_eq!(admin_merge(&app_state, &root, &chief, &admin).await, StatusCode::OK);
+        assert!(tbl_usr.user_by_id(chief.id().await).await.is_none());
+        assert_eq!(admin.promotion().await, Promotion::new(PromotionLevel::Admin, PromotionAuthority::Executing));
+
+        // users below the own promotion level can be merged
+        let other_driver = tbl_usr.create_new_user().await.unwrap();
+        assert_eq!(admin_merge(&app_state, &admin, &other_driver, &driver).await, StatusCode::OK);
+    }
+}
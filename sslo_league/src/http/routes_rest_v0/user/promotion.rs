@@ -0,0 +1,101 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sslo_lib::db::members::users::{Promotion, PromotionAuthority, PromotionLevel, UserItem};
+use crate::app_state::AppState;
+use crate::http::http_user::RequireLogin;
+use super::super::{internal_error, GeneralError};
+
+#[derive(Serialize)]
+pub struct PromotionHistoryJson {
+    granted_by: Option<i64>,
+    level: PromotionLevel,
+    authority: PromotionAuthority,
+    date: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct PromotionJson {
+    user: i64,
+    level: PromotionLevel,
+    authority: PromotionAuthority,
+    label: String,
+    symbol: String,
+    history: Vec<PromotionHistoryJson>,
+}
+
+#[derive(Deserialize)]
+pub struct PromotionRequest {
+    level: PromotionLevel,
+    authority: PromotionAuthority,
+}
+
+async fn promotion_json(user: &UserItem) -> PromotionJson {
+    let promotion = user.promotion().await;
+    PromotionJson {
+        user: user.id().await,
+        label: promotion.label().to_string(),
+        symbol: promotion.symbol().to_string(),
+        level: promotion.level,
+        authority: promotion.authority,
+        history: user.promotion_history().await.into_iter()
+            .map(|row| PromotionHistoryJson {
+                granted_by: row.granted_by,
+                level: row.promotion_level,
+                authority: row.promotion_authority,
+                date: row.date,
+            })
+            .collect(),
+    }
+}
+
+fn user_not_found() -> Response {
+    GeneralError::new(StatusCode::NOT_FOUND, "User not found".to_string()).into_response()
+}
+
+/// Current promotion of a user with the history of changes
+pub async fn handler_get(State(app_state): State<AppState>,
+                         Path(user_id): Path<i64>) -> Response {
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+    match tbl_usr.user_by_id(user_id).await {
+        Some(user) => Json(promotion_json(&user).await).into_response(),
+        None => user_not_found(),
+    }
+}
+
+/// Change the promotion of a user (only Chiefs, up to one level below the own level, or the root user)
+pub async fn handler_set(State(app_state): State<AppState>,
+                         RequireLogin(http_user): RequireLogin,
+                         Path(user_id): Path<i64>,
+                         Json(input): Json<PromotionRequest>) -> Response {
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+    let mut user = match tbl_usr.user_by_id(user_id).await {
+        Some(user) => user,
+        None => return user_not_found(),
+    };
+    let grantor_id = http_user.user.id().await;
+    let is_root = http_user.has_root_permissions();
+    if grantor_id == user_id && !is_root {
+        return GeneralError::new(StatusCode::FORBIDDEN, "Cannot change own promotion".to_string()).into_response();
+    }
+    let own_promotion = http_user.user.promotion().await;
+    if http_user.promotion_limit.as_ref().is_some_and(|limit| http_user.is_root || own_promotion.level >= *limit) {
+        return GeneralError::new(StatusCode::FORBIDDEN, "Two-factor authentication (TOTP) required for this promotion".to_string()).into_response();
+    }
+    let new_promotion = Promotion::new(input.level, input.authority);
+    if !is_root && !own_promotion.may_grant(&user.promotion().await, &new_promotion) {
+        log::warn!("deny {} to promote {} to {}", http_user.user.display().await, user.display().await, new_promotion.label());
+        return GeneralError::new(StatusCode::FORBIDDEN, "Insufficient promotion authority".to_string()).into_response();
+    }
+
+    match user.grant_promotion(new_promotion, Some(grantor_id)).await {
+        Ok(_) => {
+            log::info!("{} promoted {} to {}", http_user.user.display().await, user.display().await, user.promotion().await.label());
+            Json(promotion_json(&user).await).into_response()
+        },
+        Err(e) => internal_error(e),
+    }
+}
@@ -0,0 +1,59 @@
+use axum::extract::{Path, State};
+use axum::http::header::SET_COOKIE;
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use crate::app_state::AppState;
+use crate::http::http_user::RequireLogin;
+use super::super::GeneralError;
+use super::EmptyResponse;
+
+#[derive(Serialize)]
+pub struct DeletedSessionsResponse {
+    deleted: usize,
+}
+
+/// Revoke a single login session of the current user
+pub async fn handler_delete(State(app_state): State<AppState>,
+                            RequireLogin(http_user): RequireLogin,
+                            Path(session_id): Path<i64>) -> Response {
+    let response_not_found = GeneralError::new(StatusCode::NOT_FOUND, "Session not found".to_string()).into_response();
+    let tbl_cookie = app_state.database.db_members().await.tbl_cookie_logins().await;
+    let item = match tbl_cookie.item_by_id(session_id).await {
+        Some(item) => item,
+        None => return response_not_found,
+    };
+    let owner_id = match item.user().await {
+        Some(owner) => owner.id().await,
+        None => return response_not_found,
+    };
+    if owner_id != http_user.user.id().await {
+        log::warn!("deny {} to delete {}", http_user.user.display().await, item.display().await);
+        return response_not_found;
+    }
+
+    // revoking the current session is a logout
+    let is_current = match http_user.cookie_login.as_ref() {
+        Some(current) => current.id().await == session_id,
+        None => false,
+    };
+    let cookie = tbl_cookie.delete_cookie(item).await;
+    let mut response = Json(EmptyResponse{}).into_response();
+    if is_current {
+        response.headers_mut().insert(SET_COOKIE, cookie.parse().unwrap());
+    }
+    response
+}
+
+/// Revoke all login sessions of the current user, except the current one
+pub async fn handler_delete_others(State(app_state): State<AppState>,
+                                   RequireLogin(http_user): RequireLogin) -> Response {
+    let current = match http_user.cookie_login.as_ref() {
+        Some(current) => current,
+        None => return GeneralError::new(StatusCode::UNAUTHORIZED, "No login session".to_string()).into_response(),
+    };
+    let tbl_cookie = app_state.database.db_members().await.tbl_cookie_logins().await;
+    let deleted = tbl_cookie.delete_other_user_cookies(&http_user.user, current).await;
+    Json(DeletedSessionsResponse { deleted }).into_response()
+}
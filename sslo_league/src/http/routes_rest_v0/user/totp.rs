@@ -0,0 +1,135 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use crate::app_state::AppState;
+use crate::http::http_user::{HttpUser, RequireLogin};
+use super::super::{internal_error, GeneralError};
+use super::EmptyResponse;
+
+#[derive(Serialize)]
+pub struct EnrollResponse {
+    secret: String,
+    uri: String,
+}
+
+#[derive(Deserialize)]
+pub struct CodeRequest {
+    code: String,
+}
+
+#[derive(Serialize)]
+pub struct RecoveryCodesResponse {
+    recovery_codes: Vec<String>,
+}
+
+fn not_configured() -> Response {
+    GeneralError::new(StatusCode::NOT_IMPLEMENTED, "TOTP is not configured".to_string()).into_response()
+}
+
+fn invalid_code() -> Response {
+    GeneralError::new(StatusCode::FORBIDDEN, "Invalid code".to_string()).into_response()
+}
+
+async fn set_totp_verified(http_user: &HttpUser) {
+    if let Some(cookie_login) = http_user.cookie_login.as_ref() {
+        if let Err(e) = cookie_login.set_totp_verified().await {
+            log::error!("Could not mark {} as TOTP verified: {}", cookie_login.display().await, e);
+        }
+    }
+}
+
+/// Start enrollment of an authenticator app (returns the secret and the provisioning URI for a QR code)
+pub async fn handler_enroll(State(app_state): State<AppState>,
+                            RequireLogin(mut http_user): RequireLogin) -> Response {
+    let key = match app_state.config.totp.as_ref() {
+        Some(totp) => totp.key.clone(),
+        None => return not_configured(),
+    };
+    if http_user.user.totp_enabled().await.raw().is_some() {
+        return GeneralError::new(StatusCode::CONFLICT, "TOTP is already enabled".to_string()).into_response();
+    }
+    match http_user.user.totp_enroll(&key).await {
+        Ok(secret) => Json(EnrollResponse {
+            secret: sslo_lib::totp::base32_encode(&secret),
+            uri: sslo_lib::totp::provisioning_uri(&secret, "SSLO League", &http_user.user.name().await),
+        }).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Confirm the enrollment with a code from the authenticator app (returns the recovery codes)
+pub async fn handler_confirm(State(app_state): State<AppState>,
+                             RequireLogin(mut http_user): RequireLogin,
+                             Json(input): Json<CodeRequest>) -> Response {
+    let key = match app_state.config.totp.as_ref() {
+        Some(totp) => totp.key.clone(),
+        None => return not_configured(),
+    };
+    match http_user.user.totp_confirm(&key, &input.code).await {
+        Ok(Some(recovery_codes)) => {
+            // the current login just proved the second factor
+            set_totp_verified(&http_user).await;
+            Json(RecoveryCodesResponse { recovery_codes }).into_response()
+        },
+        Ok(None) => invalid_code(),
+        Err(e) => {
+            log::warn!("TOTP confirmation of {} failed: {}", http_user.user.display().await, e);
+            GeneralError::new(StatusCode::CONFLICT, "No TOTP enrollment pending".to_string()).into_response()
+        },
+    }
+}
+
+/// Verify the current login with a second factor (for logins without TOTP, like Steam, Discord, email or passkeys)
+pub async fn handler_verify(State(app_state): State<AppState>,
+                            RequireLogin(mut http_user): RequireLogin,
+                            Json(input): Json<CodeRequest>) -> Response {
+    let key = match app_state.config.totp.as_ref() {
+        Some(totp) => totp.key.clone(),
+        None => return not_configured(),
+    };
+    if !http_user.user.totp_verify(&key, &input.code).await {
+        return invalid_code();
+    }
+    set_totp_verified(&http_user).await;
+    Json(EmptyResponse{}).into_response()
+}
+
+/// Disable TOTP (requires a current code or a recovery code)
+pub async fn handler_disable(State(app_state): State<AppState>,
+                             RequireLogin(mut http_user): RequireLogin,
+                             Json(input): Json<CodeRequest>) -> Response {
+    let key = match app_state.config.totp.as_ref() {
+        Some(totp) => totp.key.clone(),
+        None => return not_configured(),
+    };
+    if !http_user.user.totp_verify(&key, &input.code).await {
+        return invalid_code();
+    }
+    if let Err(e) = http_user.user.totp_disable().await {
+        return internal_error(e);
+    }
+    let tbl_cookie = app_state.database.db_members().await.tbl_cookie_logins().await;
+    match tbl_cookie.reset_totp_verified(&http_user.user).await {
+        Ok(_) => Json(EmptyResponse{}).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Replace all recovery codes by new ones (requires a current code or a recovery code)
+pub async fn handler_recovery_codes(State(app_state): State<AppState>,
+                                    RequireLogin(mut http_user): RequireLogin,
+                                    Json(input): Json<CodeRequest>) -> Response {
+    let key = match app_state.config.totp.as_ref() {
+        Some(totp) => totp.key.clone(),
+        None => return not_configured(),
+    };
+    if !http_user.user.totp_verify(&key, &input.code).await {
+        return invalid_code();
+    }
+    match http_user.user.totp_renew_recovery_codes().await {
+        Ok(recovery_codes) => Json(RecoveryCodesResponse { recovery_codes }).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
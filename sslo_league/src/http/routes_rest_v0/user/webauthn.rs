@@ -0,0 +1,121 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use sslo_lib::webauthn::{base64url_decode, base64url_encode};
+use crate::app_state::AppState;
+use crate::http::http_user::RequireLogin;
+use super::super::{internal_error, GeneralError};
+use super::super::login_webauthn::relying_party;
+use super::EmptyResponse;
+
+#[derive(Serialize)]
+pub struct RegistrationChallengeResponse {
+    challenge: String,
+    rp_id: String,
+    rp_name: String,
+
+    /// base64url encoded user handle
+    user_id: String,
+    user_name: String,
+
+    /// already registered credentials (to prevent registering an authenticator twice)
+    exclude_credentials: Vec<String>,
+}
+
+/// The response of navigator.credentials.create() (base64url encoded)
+#[derive(Deserialize)]
+pub struct RegistrationRequest {
+    name: String,
+    client_data_json: String,
+    attestation_object: String,
+}
+
+#[derive(Serialize)]
+pub struct CredentialJson {
+    id: i64,
+    name: String,
+}
+
+/// Create a challenge for registering a new passkey
+pub async fn handler_challenge(State(app_state): State<AppState>,
+                               RequireLogin(http_user): RequireLogin) -> Response {
+    let rp = match relying_party(&app_state) {
+        Ok(rp) => rp,
+        Err(response) => return response,
+    };
+    let tbl_wac = app_state.database.db_members().await.tbl_webauthn_credentials().await;
+    let challenge = match tbl_wac.create_challenge(Some(&http_user.user)).await {
+        Ok(challenge) => challenge,
+        Err(e) => return internal_error(e),
+    };
+    let mut exclude_credentials = Vec::new();
+    for item in tbl_wac.items_by_user(&http_user.user).await {
+        exclude_credentials.push(item.credential_id().await);
+    }
+    Json(RegistrationChallengeResponse {
+        challenge,
+        rp_id: rp.id,
+        rp_name: "SSLO League".to_string(),
+        user_id: base64url_encode(&http_user.user.id().await.to_be_bytes()),
+        user_name: http_user.user.name().await,
+        exclude_credentials,
+    }).into_response()
+}
+
+/// Register a new passkey
+pub async fn handler_register(State(app_state): State<AppState>,
+                              RequireLogin(http_user): RequireLogin,
+                              Json(input): Json<RegistrationRequest>) -> Response {
+    let rp = match relying_party(&app_state) {
+        Ok(rp) => rp,
+        Err(response) => return response,
+    };
+    let (client_data_json, attestation_object) = match (base64url_decode(&input.client_data_json),
+                                                        base64url_decode(&input.attestation_object)) {
+        (Ok(c), Ok(a)) => (c, a),
+        _ => return GeneralError::new(StatusCode::BAD_REQUEST, "Invalid encoding".to_string()).into_response(),
+    };
+    let name = match input.name.trim() {
+        "" => "Passkey".to_string(),
+        name => name.to_string(),
+    };
+
+    let tbl_wac = app_state.database.db_members().await.tbl_webauthn_credentials().await;
+    match tbl_wac.register_credential(&rp, &http_user.user, name,
+                                      &client_data_json, &attestation_object).await {
+        Ok(item) => (StatusCode::CREATED, Json(CredentialJson {
+            id: item.id().await,
+            name: item.name().await,
+        })).into_response(),
+        Err(e) => {
+            log::warn!("deny passkey registration of {}: {}", http_user.user.display().await, e);
+            GeneralError::new(StatusCode::FORBIDDEN, "Passkey registration failed".to_string()).into_response()
+        }
+    }
+}
+
+/// Remove a passkey of the current user (unless it is the last login method)
+pub async fn handler_delete(State(app_state): State<AppState>,
+                            RequireLogin(http_user): RequireLogin,
+                            Path(credential_id): Path<i64>) -> Response {
+    let tbl_wac = app_state.database.db_members().await.tbl_webauthn_credentials().await;
+    let item = match tbl_wac.item_by_id(credential_id).await {
+        Some(item) => item,
+        None => return GeneralError::new(StatusCode::NOT_FOUND, "Passkey not found".to_string()).into_response(),
+    };
+    let owner_id = match item.user().await {
+        Some(owner) => owner.id().await,
+        None => return GeneralError::new(StatusCode::NOT_FOUND, "Passkey not found".to_string()).into_response(),
+    };
+    if owner_id != http_user.user.id().await {
+        log::warn!("deny {} to delete {}", http_user.user.display().await, item.display().await);
+        return GeneralError::new(StatusCode::NOT_FOUND, "Passkey not found".to_string()).into_response();
+    }
+    match tbl_wac.delete_credential(item).await {
+        Ok(true) => Json(EmptyResponse{}).into_response(),
+        Ok(false) => GeneralError::new(StatusCode::CONFLICT, "Cannot remove the last login method".to_string()).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
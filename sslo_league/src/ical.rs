@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use crate::db2::DatabaseManager;
+use crate::db2::results::SessionType;
+use crate::db2::series::events::EventItem;
+
+/// Escape a TEXT value (RFC 5545, section 3.3.11)
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {},
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Fold a content line into chunks of at most 75 octets, terminated by CRLF (RFC 5545, section 3.1)
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+fn format_datetime(datetime: DateTime<Utc>) -> String {
+    datetime.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// The track and layout name of an event
+async fn track_name(database: &DatabaseManager, event: &EventItem) -> Option<String> {
+    let layout = database.db_content().await.tbl_track_layouts().await.item_by_id(event.track_layout().await?).await?;
+    match layout.track().await {
+        Some(track) => Some(format!("{} {}", track.name().await, layout.name().await).trim().to_string()),
+        None => Some(layout.name().await),
+    }
+}
+
+/// Generate an iCalendar object with one VEVENT per scheduled event (events without session timings are skipped)
+/// The host is used for unique IDs and links to the event pages.
+pub async fn calendar(database: &DatabaseManager, events: &[EventItem], host: &str, calendar_name: &str) -> String {
+    let now = format_datetime(Utc::now());
+    let mut ics = String::new();
+    ics += &fold_line("BEGIN:VCALENDAR");
+    ics += &fold_line("VERSION:2.0");
+    ics += &fold_line("PRODID:-//SSLO//Simracing Sports League Organization//EN");
+    ics += &fold_line("CALSCALE:GREGORIAN");
+    ics += &fold_line("METHOD:PUBLISH");
+    ics += &fold_line(&format!("X-WR-CALNAME:{}", escape_text(calendar_name)));
+
+    for event in events.iter() {
+        let (start, end) = match (event.start().await, event.end().await) {
+            (Some(start), Some(end)) => (start, end),
+            _ => continue,
+        };
+        let event_id = event.id().await;
+
+        // description with the session timings
+        let mut description = format!("Simulator: {}", event.simulator().await.label());
+        let server = event.server().await;
+        if !server.is_empty() {
+            description += &format!("\nServer: {}", server);
+        }
+        for session_type in [SessionType::Practice, SessionType::Qualifying, SessionType::Race] {
+            if let Some(schedule) = event.session_schedule(session_type).await {
+                description += &format!("\n{}: {} ({} min)",
+                                        session_type.label(),
+                                        schedule.start.format("%Y-%m-%d %H:%M UTC"),
+                                        schedule.duration_minutes);
+            }
+        }
+        let event_description = event.description().await;
+        if !event_description.is_empty() {
+            description += "\n\n";
+            description += &event_description;
+        }
+
+        ics += &fold_line("BEGIN:VEVENT");
+        ics += &fold_line(&format!("UID:sslo-event-{}@{}", event_id, host));
+        ics += &fold_line(&format!("DTSTAMP:{}", now));
+        ics += &fold_line(&format!("DTSTART:{}", format_datetime(start)));
+        ics += &fold_line(&format!("DTEND:{}", format_datetime(end)));
+        ics += &fold_line(&format!("SUMMARY:{}", escape_text(&event.name().await)));
+        if let Some(track) = track_name(database, event).await {
+            ics += &fold_line(&format!("LOCATION:{}", escape_text(&track)));
+        }
+        ics += &fold_line(&format!("DESCRIPTION:{}", escape_text(&description)));
+        ics += &fold_line(&format!("URL:https://{}/html/schedules/{}", host, event_id));
+        ics += &fold_line("END:VEVENT");
+    }
+
+    ics += &fold_line("END:VCALENDAR");
+    ics
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use chrono::{Duration, TimeZone};
+    use crate::db2::content::Simulator;
+    use crate::db2::series::events::SessionSchedule;
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape_text("a,b;c\\d\r\ne"), "a\\,b\\;c\\\\d\\ne");
+    }
+
+    #[test]
+    fn folding() {
+        assert_eq!(fold_line("SUMMARY:short"), "SUMMARY:short\r\n");
+        let folded = fold_line(&format!("SUMMARY:{}", "ä".repeat(50)));
+        for line in folded.split("\r\n") {
+            assert!(line.len() <= 75);
+        }
+        assert_eq!(folded.replace("\r\n ", ""), format!("SUMMARY:{}\r\n", "ä".repeat(50)));
+    }
+
+    #[test(tokio::test)]
+    async fn calendar_events() {
+        let database = DatabaseManager::new_in_memory().await;
+        let tbl_evt = database.db_series().await.tbl_events().await;
+        let start = Utc.with_ymd_and_hms(2025, 5, 1, 19, 0, 0).unwrap();
+
+        let event = tbl_evt.create_event("Spa, 6 Hours".to_string(), Simulator::AssettoCorsaCompetizione).await.unwrap();
+        event.set_session_schedule(SessionType::Qualifying, Some(SessionSchedule { start, duration_minutes: 20 })).await.unwrap();
+        event.set_session_schedule(SessionType::Race, Some(SessionSchedule { start: start + Duration::minutes(30), duration_minutes: 360 })).await.unwrap();
+        let unscheduled = tbl_evt.create_event("Unscheduled".to_string(), Simulator::AssettoCorsa).await.unwrap();
+
+        let ics = calendar(&database, &[event, unscheduled], "league.example.com", "Test League").await;
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+        assert!(ics.contains("\r\nUID:sslo-event-1@league.example.com\r\n"));
+        assert!(ics.contains("\r\nDTSTART:20250501T190000Z\r\n"));
+        assert!(ics.contains("\r\nDTEND:20250502T013000Z\r\n"));
+        assert!(ics.contains("\r\nSUMMARY:Spa\\, 6 Hours\r\n"));
+        assert!(!ics.contains("Unscheduled"));
+    }
+}
@@ -0,0 +1,336 @@
+pub mod ac;
+pub mod acc;
+pub mod ams2;
+pub mod rf2;
+
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use sslo_lib::db::members::users::UserItem;
+use sslo_lib::db::DbRow;
+use sslo_lib::error::SsloError;
+use crate::db2::content::Simulator;
+use crate::db2::DatabaseManager;
+use crate::db2::results::SessionType;
+use crate::db2::results::sessions::{IncidentRow, LapRow, PenaltyRow, ResultRow, SessionItem};
+
+/// A driver, as identified by the simulator
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedDriver {
+
+    /// the steam-id of the driver
+    pub guid: String,
+    pub name: String,
+}
+
+#[derive(Debug)]
+pub struct ImportedResult {
+    pub position: i64,
+    pub driver: ImportedDriver,
+    pub car_identifier: String,
+    pub car_number: i64,
+    pub team: String,
+    pub lap_count: i64,
+
+    /// milliseconds
+    pub best_lap: Option<i64>,
+
+    /// milliseconds
+    pub total_time: Option<i64>,
+    pub incidents: i64,
+}
+
+#[derive(Debug)]
+pub struct ImportedLap {
+    pub driver_guid: String,
+    pub car_number: i64,
+    pub lap_number: i64,
+
+    /// milliseconds
+    pub lap_time: i64,
+
+    /// milliseconds
+    pub sectors: Vec<i64>,
+    pub valid: bool,
+}
+
+#[derive(Debug)]
+pub struct ImportedPenalty {
+    pub driver_guid: String,
+    pub car_number: i64,
+    pub reason: String,
+    pub penalty: String,
+    pub penalty_value: i64,
+    pub violation_lap: i64,
+    pub cleared_lap: Option<i64>,
+    pub post_race: bool,
+}
+
+#[derive(Debug)]
+pub struct ImportedIncident {
+    pub driver_guid: String,
+    pub car_number: i64,
+
+    /// None for incidents without other drivers (e.g. collision with environment)
+    pub other_driver_guid: Option<String>,
+    pub incident_type: String,
+
+    /// km/h
+    pub impact_speed: Option<f64>,
+    pub lap: Option<i64>,
+}
+
+/// A session, as it is parsed from a simulator result file
+#[derive(Debug)]
+pub struct ImportedSession {
+
+    /// identifies the source of the session (normally the file name), must be unique per server
+    pub file_name: String,
+    pub simulator: Simulator,
+    pub session_type: SessionType,
+    pub server_name: String,
+    pub track_identifier: String,
+    pub started: Option<DateTime<Utc>>,
+    pub wet: bool,
+    pub results: Vec<ImportedResult>,
+    pub laps: Vec<ImportedLap>,
+    pub penalties: Vec<ImportedPenalty>,
+    pub incidents: Vec<ImportedIncident>,
+}
+
+/// A parser for the result files of a certain simulator
+pub trait ResultImporter: Send + Sync {
+
+    /// The simulator which result files are parsed
+    fn simulator(&self) -> Simulator;
+
+    /// Parse the content of a result file
+    /// A file can contain multiple sessions (e.g. practice, qualifying and race)
+    fn parse(&self, file_name: &str, data: &[u8]) -> Result<Vec<ImportedSession>, SsloError>;
+}
+
+/// Get the importer for a simulator by its short name (ac, acc, rf2, lmu, ams2)
+pub fn importer_by_name(name: &str) -> Option<Box<dyn ResultImporter>> {
+    match name {
+        "ac" => Some(Box::new(ac::AcImporter)),
+        "acc" => Some(Box::new(acc::AccImporter)),
+        "rf2" => Some(Box::new(rf2::Rf2Importer::new(Simulator::RFactor2))),
+        "lmu" => Some(Box::new(rf2::Rf2Importer::new(Simulator::LeMansUltimate))),
+        "ams2" => Some(Box::new(ams2::Ams2Importer)),
+        _ => None,
+    }
+}
+
+/// Decode the text of a result file
+/// Some simulators (e.g. ACC) write their result files in UTF-16LE
+fn decode_text(file_name: &str, data: &[u8]) -> Result<String, SsloError> {
+    let is_utf16 = data.starts_with(&[0xFF, 0xFE]) || (data.len() > 1 && data[1] == 0);
+    let text = if is_utf16 {
+        let words: Vec<u16> = data.chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16(&words)
+            .map_err(|e| SsloError::ResultImportFailed(file_name.to_string(), e.to_string()))?
+    } else {
+        String::from_utf8(data.to_vec())
+            .map_err(|e| SsloError::ResultImportFailed(file_name.to_string(), e.to_string()))?
+    };
+    Ok(text.trim_start_matches('\u{FEFF}').to_string())
+}
+
+/// Find (or create) the user that belongs to a driver
+async fn resolve_user(database: &DatabaseManager, driver: &ImportedDriver) -> Option<UserItem> {
+    if driver.guid.is_empty() || !driver.guid.chars().all(|c| c.is_ascii_digit()) {
+        log::warn!("cannot identify driver '{}' with guid '{}'", driver.name, driver.guid);
+        return None;
+    }
+
+    let tbl_stm = database.db_members().await.tbl_steam_accounts().await;
+    let steam_account = tbl_stm.item_by_steam_id(&driver.guid, true).await?;
+    let is_new_user = !steam_account.has_user().await;
+    let mut user = steam_account.user().await?;
+    if is_new_user && !driver.name.trim().is_empty() {
+        if let Err(e) = user.set_name(driver.name.clone()).await {
+            log::error!("failed to set name for {}: {}", user.display().await, e);
+        }
+    }
+    Some(user)
+}
+
+/// Store all sessions that have not been imported before
+/// Fails, if there is no new session. If one session fails, the sessions stored before are removed again.
+pub async fn store_new(database: &DatabaseManager,
+                       file_name: &str,
+                       imported: Vec<ImportedSession>,
+) -> Result<Vec<SessionItem>, SsloError> {
+    let tbl_ses = database.db_results().await.tbl_sessions().await;
+    let mut sessions = Vec::new();
+    for imported in imported.into_iter() {
+        if tbl_ses.item_by_file(imported.simulator, &imported.server_name, &imported.file_name).await.is_some() {
+            log::debug!("skip already imported session '{}'", imported.file_name);
+            continue;
+        }
+        match store(database, imported).await {
+            Ok(session) => sessions.push(session),
+            Err(e) => {
+                rollback(database, sessions).await;
+                return Err(e);
+            }
+        }
+    }
+    if sessions.is_empty() {
+        return Err(SsloError::ResultImportFailed(file_name.to_string(), "no new sessions".to_string()));
+    }
+    Ok(sessions)
+}
+
+/// Store an imported session into the results database
+/// Drivers are assigned to users (by steam-id) and the last lap of the users is updated.
+/// If storing fails, the session is removed again (created users and steam accounts are kept).
+pub async fn store(database: &DatabaseManager, imported: ImportedSession) -> Result<SessionItem, SsloError> {
+    let file_name = imported.file_name.clone();
+    let import_error = |description: &str| SsloError::ResultImportFailed(file_name.clone(), description.to_string());
+
+    // create session
+    let tbl_ses = database.db_results().await.tbl_sessions().await;
+    if tbl_ses.item_by_file(imported.simulator, &imported.server_name, &imported.file_name).await.is_some() {
+        return Err(import_error("already imported"));
+    }
+    let session = tbl_ses.create_session(imported.simulator,
+                                         imported.session_type,
+                                         imported.server_name.clone(),
+                                         imported.file_name.clone(),
+                                         imported.track_identifier.clone(),
+                                         imported.started,
+    ).await.ok_or_else(|| import_error("cannot create session"))?;
+
+    // a partly imported session is removed again
+    if let Err(e) = store_data(database, &session, imported).await {
+        rollback(database, vec![session]).await;
+        return Err(e);
+    }
+
+    log::info!("imported {} from '{}'", session.display().await, file_name);
+    Ok(session)
+}
+
+/// Remove sessions of a failed import
+async fn rollback(database: &DatabaseManager, sessions: Vec<SessionItem>) {
+    let tbl_ses = database.db_results().await.tbl_sessions().await;
+    for session in sessions.into_iter() {
+        let session_display = session.display().await;
+        if let Err(e) = tbl_ses.delete_session(session).await {
+            log::error!("failed to roll back import of {}: {}", session_display, e);
+        }
+    }
+}
+
+/// Store the data of an imported session
+async fn store_data(database: &DatabaseManager, session: &SessionItem, imported: ImportedSession) -> Result<(), SsloError> {
+    session.set_wet(imported.wet).await?;
+
+    // assign track
+    let db_content = database.db_content().await;
+    let tbl_lay = db_content.tbl_track_layouts().await;
+    match tbl_lay.item_by_sim_id(imported.simulator, &imported.track_identifier).await {
+        Some(layout) => session.set_track_layout(Some(layout.id().await)).await?,
+        None => log::warn!("unknown track '{}' for {}", imported.track_identifier, imported.simulator.label()),
+    }
+
+    // resolve users
+    let mut users: HashMap<String, Option<UserItem>> = HashMap::new();
+    for result in imported.results.iter() {
+        if !users.contains_key(&result.driver.guid) {
+            users.insert(result.driver.guid.clone(), resolve_user(database, &result.driver).await);
+        }
+    }
+    let mut user_ids: HashMap<String, i64> = HashMap::new();
+    for (guid, user) in users.iter() {
+        if let Some(user) = user {
+            user_ids.insert(guid.clone(), user.id().await);
+        }
+    }
+
+    // results
+    let tbl_car = db_content.tbl_cars().await;
+    for result in imported.results.into_iter() {
+        let mut row = ResultRow::new(0);
+        row.position = result.position;
+        row.user = user_ids.get(&result.driver.guid).copied();
+        row.car = match tbl_car.item_by_sim_id(imported.simulator, &result.car_identifier).await {
+            Some(car) => Some(car.id().await),
+            None => None,
+        };
+        row.driver_name = result.driver.name;
+        row.driver_guid = result.driver.guid;
+        row.car_identifier = result.car_identifier;
+        row.car_number = result.car_number;
+        row.team = result.team;
+        row.lap_count = result.lap_count;
+        row.best_lap = result.best_lap;
+        row.total_time = result.total_time;
+        row.incidents = result.incidents;
+        session.add_result(row).await?;
+    }
+
+    // laps
+    let mut drivers_with_laps: Vec<String> = Vec::new();
+    for lap in imported.laps.into_iter() {
+        let mut row = LapRow::new(0);
+        row.user = user_ids.get(&lap.driver_guid).copied();
+        row.car_number = lap.car_number;
+        row.lap_number = lap.lap_number;
+        row.lap_time = lap.lap_time;
+        row.sectors = lap.sectors.iter().map(|s| s.to_string()).collect::<Vec<String>>().join(",");
+        row.valid = lap.valid;
+        if !drivers_with_laps.contains(&lap.driver_guid) {
+            drivers_with_laps.push(lap.driver_guid.clone());
+        }
+        row.driver_guid = lap.driver_guid;
+        session.add_lap(row).await?;
+    }
+
+    // penalties
+    for penalty in imported.penalties.into_iter() {
+        let mut row = PenaltyRow::new(0);
+        row.user = user_ids.get(&penalty.driver_guid).copied();
+        row.driver_guid = penalty.driver_guid;
+        row.car_number = penalty.car_number;
+        row.reason = penalty.reason;
+        row.penalty = penalty.penalty;
+        row.penalty_value = penalty.penalty_value;
+        row.violation_lap = penalty.violation_lap;
+        row.cleared_lap = penalty.cleared_lap;
+        row.post_race = penalty.post_race;
+        session.add_penalty(row).await?;
+    }
+
+    // incidents
+    for incident in imported.incidents.into_iter() {
+        let mut row = IncidentRow::new(0);
+        row.user = user_ids.get(&incident.driver_guid).copied();
+        row.driver_guid = incident.driver_guid;
+        row.car_number = incident.car_number;
+        row.other_user = incident.other_driver_guid.as_ref().and_then(|guid| user_ids.get(guid).copied());
+        row.other_driver_guid = incident.other_driver_guid;
+        row.incident_type = incident.incident_type;
+        row.impact_speed = incident.impact_speed;
+        row.lap = incident.lap;
+        session.add_incident(row).await?;
+    }
+
+    // update last lap of the drivers
+    let lap_time = imported.started.unwrap_or_else(Utc::now);
+    for guid in drivers_with_laps.iter() {
+        if let Some(Some(user)) = users.get_mut(guid) {
+            let is_newer = match user.last_lap().await.raw() {
+                Some(last_lap) => *last_lap < lap_time,
+                None => true,
+            };
+            if is_newer {
+                user.set_last_lap(lap_time).await;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use sslo_lib::error::SsloError;
+use crate::db2::content::Simulator;
+use crate::db2::results::SessionType;
+use super::{ResultImporter, ImportedDriver, ImportedIncident, ImportedLap, ImportedResult, ImportedSession};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AcResult {
+    track_name: String,
+    #[serde(default)]
+    track_config: String,
+    #[serde(rename = "Type")]
+    session_type: String,
+    #[serde(default)]
+    cars: Vec<AcCar>,
+    #[serde(default)]
+    result: Vec<AcResultLine>,
+    #[serde(default)]
+    laps: Vec<AcLap>,
+    #[serde(default)]
+    events: Option<Vec<AcEvent>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AcCar {
+    car_id: i64,
+    driver: AcDriver,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AcDriver {
+    #[serde(default)]
+    team: String,
+    #[serde(default)]
+    guid: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AcResultLine {
+    #[serde(default)]
+    driver_name: String,
+    #[serde(default)]
+    driver_guid: String,
+    car_id: i64,
+    #[serde(default)]
+    car_model: String,
+    #[serde(default)]
+    best_lap: i64,
+    #[serde(default)]
+    total_time: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AcLap {
+    #[serde(default)]
+    driver_guid: String,
+    car_id: i64,
+    lap_time: i64,
+    #[serde(default)]
+    sectors: Vec<i64>,
+    #[serde(default)]
+    cuts: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AcEvent {
+    #[serde(rename = "Type")]
+    event_type: String,
+    car_id: i64,
+    driver: AcDriver,
+    #[serde(default)]
+    other_driver: Option<AcDriver>,
+    #[serde(default)]
+    impact_speed: Option<f64>,
+}
+
+/// AC uses this value for lap times that are not set
+const NO_TIME: i64 = 999_999_999;
+
+fn optional_time(time: i64) -> Option<i64> {
+    if time <= 0 || time >= NO_TIME { None } else { Some(time) }
+}
+
+/// AC names result files by the session start (e.g. '2025_4_14_20_30_RACE.json')
+fn started_from_file_name(file_name: &str) -> Option<DateTime<Utc>> {
+    let base_name = file_name.rsplit(['/', '\\']).next()?;
+    let parts: Vec<u32> = base_name.split('_').take(5)
+        .map(|p| p.parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    if parts.len() < 5 { return None; }
+    NaiveDate::from_ymd_opt(parts[0] as i32, parts[1], parts[2])?
+        .and_hms_opt(parts[3], parts[4], 0)
+        .map(|dt| dt.and_utc())
+}
+
+/// Parse the content of an Assetto Corsa dedicated server result file (results/*.json)
+pub fn parse(file_name: &str, data: &[u8]) -> Result<ImportedSession, SsloError> {
+    let import_error = |description: String| SsloError::ResultImportFailed(file_name.to_string(), description);
+
+    let text = super::decode_text(file_name, data)?;
+    let ac: AcResult = serde_json::from_str(&text).map_err(|e| import_error(e.to_string()))?;
+
+    let session_type = match ac.session_type.as_str() {
+        "PRACTICE" => SessionType::Practice,
+        "QUALIFY" => SessionType::Qualifying,
+        "RACE" => SessionType::Race,
+        other => return Err(import_error(format!("unknown session type '{}'", other))),
+    };
+
+    // the track layout is identified by track and config (e.g. 'ks_nordschleife/touristenfahrten')
+    let track_identifier = if ac.track_config.is_empty() {
+        ac.track_name.clone()
+    } else {
+        format!("{}/{}", ac.track_name, ac.track_config)
+    };
+
+    // teams
+    let teams: HashMap<i64, String> = ac.cars.iter()
+        .map(|car| (car.car_id, car.driver.team.clone()))
+        .collect();
+
+    // laps
+    let mut laps = Vec::new();
+    let mut lap_numbers: HashMap<i64, i64> = HashMap::new();
+    for lap in ac.laps.iter() {
+        let lap_number = lap_numbers.entry(lap.car_id).or_insert(0);
+        *lap_number += 1;
+        laps.push(ImportedLap {
+            driver_guid: lap.driver_guid.clone(),
+            car_number: lap.car_id,
+            lap_number: *lap_number,
+            lap_time: lap.lap_time,
+            sectors: lap.sectors.clone(),
+            valid: lap.cuts == 0,
+        });
+    }
+
+    // incidents
+    let mut incidents = Vec::new();
+    for event in ac.events.iter().flatten() {
+        let other_driver_guid = event.other_driver.as_ref()
+            .map(|d| d.guid.clone())
+            .filter(|guid| !guid.is_empty());
+        incidents.push(ImportedIncident {
+            driver_guid: event.driver.guid.clone(),
+            car_number: event.car_id,
+            other_driver_guid,
+            incident_type: event.event_type.clone(),
+            impact_speed: event.impact_speed,
+            lap: None,
+        });
+    }
+
+    // results (empty slots of the server are skipped)
+    let mut results = Vec::new();
+    for line in ac.result.iter().filter(|l| !l.driver_guid.is_empty()) {
+        let driver_laps: Vec<&AcLap> = ac.laps.iter()
+            .filter(|l| l.driver_guid == line.driver_guid)
+            .collect();
+        results.push(ImportedResult {
+            position: results.len() as i64 + 1,
+            driver: ImportedDriver {
+                guid: line.driver_guid.clone(),
+                name: line.driver_name.trim().to_string(),
+            },
+            car_identifier: line.car_model.clone(),
+            car_number: line.car_id,
+            team: teams.get(&line.car_id).cloned().unwrap_or_default(),
+            lap_count: driver_laps.len() as i64,
+            best_lap: optional_time(line.best_lap),
+            total_time: optional_time(line.total_time),
+            incidents: incidents.iter().filter(|i| i.driver_guid == line.driver_guid).count() as i64,
+        });
+    }
+
+    Ok(ImportedSession {
+        file_name: file_name.to_string(),
+        simulator: Simulator::AssettoCorsa,
+        session_type,
+        server_name: String::new(),
+        track_identifier,
+        started: started_from_file_name(file_name),
+        wet: false,
+        results,
+        laps,
+        penalties: Vec::new(),
+        incidents,
+    })
+}
+
+/// Importer for Assetto Corsa server result files
+pub struct AcImporter;
+
+impl ResultImporter for AcImporter {
+    fn simulator(&self) -> Simulator { Simulator::AssettoCorsa }
+
+    fn parse(&self, file_name: &str, data: &[u8]) -> Result<Vec<ImportedSession>, SsloError> {
+        parse(file_name, data).map(|session| vec![session])
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use crate::db2::DatabaseManager;
+
+    const RACE_FILE_NAME: &str = "2025_4_14_20_30_RACE.json";
+    const RACE_SAMPLE: &[u8] = include_bytes!("../../test_data/ac/2025_4_14_20_30_RACE.json");
+    const QUALIFY_FILE_NAME: &str = "2025_4_14_20_0_QUALIFY.json";
+    const QUALIFY_SAMPLE: &[u8] = include_bytes!("../../test_data/ac/2025_4_14_20_0_QUALIFY.json");
+
+    #[test]
+    fn parse_race() {
+        let session = parse(RACE_FILE_NAME, RACE_SAMPLE).unwrap();
+        assert_eq!(session.session_type, SessionType::Race);
+        assert_eq!(session.track_identifier, "ks_nordschleife/touristenfahrten");
+        assert_eq!(session.started.unwrap().to_rfc3339(), "2025-04-14T20:30:00+00:00");
+
+        // the empty slot is skipped
+        assert_eq!(session.results.len(), 3);
+        assert_eq!(session.results[0].driver.name, "Anna Alpha");
+        assert_eq!(session.results[0].team, "Alpha Racing");
+        assert_eq!(session.results[0].best_lap, Some(418512));
+        assert_eq!(session.results[0].lap_count, 2);
+        assert_eq!(session.results[2].total_time, None);
+
+        // laps with cuts are invalid
+        assert_eq!(session.laps.len(), 5);
+        assert!(!session.laps[3].valid);
+        assert_eq!(session.laps[4].lap_number, 2);
+
+        // collisions
+        assert_eq!(session.incidents.len(), 3);
+        assert_eq!(session.incidents[0].other_driver_guid, Some("76561198000000002".to_string()));
+        assert_eq!(session.incidents[2].other_driver_guid, None);
+        assert_eq!(session.results[1].incidents, 2);
+        assert_eq!(session.results[0].incidents, 1);
+    }
+
+    #[test]
+    fn parse_qualify() {
+        let session = parse(QUALIFY_FILE_NAME, QUALIFY_SAMPLE).unwrap();
+        assert_eq!(session.session_type, SessionType::Qualifying);
+        assert_eq!(session.track_identifier, "ks_nordschleife/touristenfahrten");
+        assert_eq!(session.results.len(), 2);
+        assert_eq!(session.results[0].driver.guid, "76561198000000002");
+        assert_eq!(session.incidents.len(), 0);
+    }
+
+    #[test]
+    fn file_name() {
+        assert_eq!(started_from_file_name("results/2025_12_1_9_5_RACE.json").unwrap().to_rfc3339(), "2025-12-01T09:05:00+00:00");
+        assert!(started_from_file_name("RACE.json").is_none());
+    }
+
+    #[test(tokio::test)]
+    async fn import_race() {
+        let database = DatabaseManager::new_in_memory().await;
+        let imported = parse(RACE_FILE_NAME, RACE_SAMPLE).unwrap();
+        let session = super::super::store(&database, imported).await.unwrap();
+
+        // incidents refer to users
+        let tbl_stm = database.db_members().await.tbl_steam_accounts().await;
+        let user1 = tbl_stm.item_by_steam_id("76561198000000001", false).await.unwrap().user().await.unwrap();
+        let user2 = tbl_stm.item_by_steam_id("76561198000000002", false).await.unwrap().user().await.unwrap();
+        let incidents = session.incidents().await;
+        assert_eq!(incidents.len(), 3);
+        assert_eq!(incidents[0].user, Some(user1.id().await));
+        assert_eq!(incidents[0].other_user, Some(user2.id().await));
+        assert_eq!(incidents[0].impact_speed, Some(32.5));
+        assert_eq!(incidents[2].other_user, None);
+        assert!(user1.last_lap().await.raw().is_some());
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Deserialize;
+use sslo_lib::error::SsloError;
+use crate::db2::content::Simulator;
+use crate::db2::results::SessionType;
+use super::{ResultImporter, ImportedDriver, ImportedLap, ImportedPenalty, ImportedResult, ImportedSession};
+
+/// ACC uses this value for times that are not set
+const NO_TIME: i64 = i32::MAX as i64;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccResult {
+    session_type: String,
+    track_name: String,
+    #[serde(default)]
+    server_name: String,
+    session_result: AccSessionResult,
+    #[serde(default)]
+    laps: Vec<AccLap>,
+    #[serde(default)]
+    penalties: Vec<AccPenalty>,
+    #[serde(default, rename = "post_race_penalties")]
+    post_race_penalties: Option<Vec<AccPenalty>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccSessionResult {
+    #[serde(default)]
+    is_wet_session: i64,
+    #[serde(default)]
+    leader_board_lines: Vec<AccLeaderBoardLine>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccLeaderBoardLine {
+    car: AccCar,
+    timing: AccTiming,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccCar {
+    car_id: i64,
+    race_number: i64,
+    car_model: i64,
+    #[serde(default)]
+    team_name: String,
+    #[serde(default)]
+    drivers: Vec<AccDriver>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccDriver {
+    #[serde(default)]
+    first_name: String,
+    #[serde(default)]
+    last_name: String,
+    player_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccTiming {
+    #[serde(default)]
+    total_time: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccLap {
+    car_id: i64,
+    driver_index: usize,
+    laptime: i64,
+    #[serde(default)]
+    is_valid_for_best: bool,
+    #[serde(default)]
+    splits: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccPenalty {
+    car_id: i64,
+    driver_index: usize,
+    #[serde(default)]
+    reason: String,
+    #[serde(default)]
+    penalty: String,
+    #[serde(default)]
+    penalty_value: i64,
+    #[serde(default)]
+    violation_in_lap: i64,
+    #[serde(default)]
+    cleared_in_lap: i64,
+}
+
+impl AccDriver {
+    fn imported(&self) -> ImportedDriver {
+        ImportedDriver {
+            guid: self.player_id.trim_start_matches('S').to_string(),
+            name: format!("{} {}", self.first_name.trim(), self.last_name.trim()).trim().to_string(),
+        }
+    }
+}
+
+fn optional_time(time: i64) -> Option<i64> {
+    if time <= 0 || time >= NO_TIME { None } else { Some(time) }
+}
+
+/// ACC names result files by the session start (e.g. '250414_203000_R.json')
+fn started_from_file_name(file_name: &str) -> Option<DateTime<Utc>> {
+    let base_name = file_name.rsplit(['/', '\\']).next()?;
+    let timestamp = base_name.get(0..13)?;
+    NaiveDateTime::parse_from_str(timestamp, "%y%m%d_%H%M%S").ok().map(|dt| dt.and_utc())
+}
+
+/// Parse the content of an ACC server result file (results/*.json)
+pub fn parse(file_name: &str, data: &[u8]) -> Result<ImportedSession, SsloError> {
+    let import_error = |description: String| SsloError::ResultImportFailed(file_name.to_string(), description);
+
+    let text = super::decode_text(file_name, data)?;
+    let acc: AccResult = serde_json::from_str(&text).map_err(|e| import_error(e.to_string()))?;
+
+    let session_type = match acc.session_type.as_str() {
+        "FP" | "P" => SessionType::Practice,
+        "Q" => SessionType::Qualifying,
+        "R" => SessionType::Race,
+        other => return Err(import_error(format!("unknown session type '{}'", other))),
+    };
+
+    // cars
+    let cars: HashMap<i64, &AccCar> = acc.session_result.leader_board_lines.iter()
+        .map(|line| (line.car.car_id, &line.car))
+        .collect();
+    let driver_guid = |car_id: i64, driver_index: usize| -> String {
+        cars.get(&car_id)
+            .and_then(|car| car.drivers.get(driver_index))
+            .map(|driver| driver.imported().guid)
+            .unwrap_or_default()
+    };
+    let car_number = |car_id: i64| -> i64 {
+        cars.get(&car_id).map(|car| car.race_number).unwrap_or_default()
+    };
+
+    // laps
+    let mut laps = Vec::new();
+    let mut lap_numbers: HashMap<i64, i64> = HashMap::new();
+    for lap in acc.laps.iter() {
+        let lap_number = lap_numbers.entry(lap.car_id).or_insert(0);
+        *lap_number += 1;
+        laps.push(ImportedLap {
+            driver_guid: driver_guid(lap.car_id, lap.driver_index),
+            car_number: car_number(lap.car_id),
+            lap_number: *lap_number,
+            lap_time: lap.laptime,
+            sectors: lap.splits.clone(),
+            valid: lap.is_valid_for_best,
+        });
+    }
+
+    // results (one entry per driver of a car)
+    let mut results = Vec::new();
+    for (index, line) in acc.session_result.leader_board_lines.iter().enumerate() {
+        for (driver_index, driver) in line.car.drivers.iter().enumerate() {
+            let driver_laps: Vec<&AccLap> = acc.laps.iter()
+                .filter(|l| l.car_id == line.car.car_id && l.driver_index == driver_index)
+                .collect();
+            if driver_laps.is_empty() && driver_index > 0 {
+                continue;
+            }
+            results.push(ImportedResult {
+                position: index as i64 + 1,
+                driver: driver.imported(),
+                car_identifier: line.car.car_model.to_string(),
+                car_number: line.car.race_number,
+                team: line.car.team_name.clone(),
+                lap_count: driver_laps.len() as i64,
+                best_lap: driver_laps.iter()
+                    .filter(|l| l.is_valid_for_best)
+                    .filter_map(|l| optional_time(l.laptime))
+                    .min(),
+                total_time: optional_time(line.timing.total_time),
+                incidents: 0,
+            });
+        }
+    }
+
+    // penalties
+    let mut penalties = Vec::new();
+    let post_race_penalties = acc.post_race_penalties.unwrap_or_default();
+    for (penalty, post_race) in acc.penalties.iter().map(|p| (p, false))
+        .chain(post_race_penalties.iter().map(|p| (p, true))) {
+        penalties.push(ImportedPenalty {
+            driver_guid: driver_guid(penalty.car_id, penalty.driver_index),
+            car_number: car_number(penalty.car_id),
+            reason: penalty.reason.clone(),
+            penalty: penalty.penalty.clone(),
+            penalty_value: penalty.penalty_value,
+            violation_lap: penalty.violation_in_lap,
+            cleared_lap: if penalty.cleared_in_lap > 0 { Some(penalty.cleared_in_lap) } else { None },
+            post_race,
+        });
+    }
+
+    Ok(ImportedSession {
+        file_name: file_name.to_string(),
+        simulator: Simulator::AssettoCorsaCompetizione,
+        session_type,
+        server_name: acc.server_name,
+        track_identifier: acc.track_name,
+        started: started_from_file_name(file_name),
+        wet: acc.session_result.is_wet_session != 0,
+        results,
+        laps,
+        penalties,
+        incidents: Vec::new(),
+    })
+}
+
+/// Importer for ACC server result files
+pub struct AccImporter;
+
+impl ResultImporter for AccImporter {
+    fn simulator(&self) -> Simulator { Simulator::AssettoCorsaCompetizione }
+
+    fn parse(&self, file_name: &str, data: &[u8]) -> Result<Vec<ImportedSession>, SsloError> {
+        parse(file_name, data).map(|session| vec![session])
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use crate::db2::DatabaseManager;
+
+    const FILE_NAME: &str = "250414_203000_R.json";
+    const SAMPLE: &[u8] = include_bytes!("../../test_data/acc/250414_203000_R.json");
+
+    #[test]
+    fn parse_sample() {
+        let session = parse(FILE_NAME, SAMPLE).unwrap();
+        assert_eq!(session.session_type, SessionType::Race);
+        assert_eq!(session.track_identifier, "monza");
+        assert_eq!(session.started.unwrap().to_rfc3339(), "2025-04-14T20:30:00+00:00");
+        assert!(!session.wet);
+
+        // two drivers share car #7
+        assert_eq!(session.results.len(), 4);
+        assert_eq!(session.results[0].driver, ImportedDriver{guid: "76561198000000001".to_string(), name: "Anna Alpha".to_string()});
+        assert_eq!(session.results[0].car_identifier, "25");
+        assert_eq!(session.results[1].position, 1);
+        assert_eq!(session.results[1].lap_count, 1);
+        assert_eq!(session.results[0].best_lap, Some(108512));
+        assert_eq!(session.results[3].position, 3);
+        assert_eq!(session.results[3].best_lap, None);
+        assert_eq!(session.results[3].total_time, None);
+
+        // laps
+        assert_eq!(session.laps.len(), 6);
+        assert_eq!(session.laps[5].lap_number, 3);
+        assert_eq!(session.laps[5].sectors, vec![36000, 38000, 35234]);
+
+        // penalties
+        assert_eq!(session.penalties.len(), 2);
+        assert_eq!(session.penalties[0].penalty, "DriveThrough");
+        assert_eq!(session.penalties[0].cleared_lap, Some(3));
+        assert!(session.penalties[1].post_race);
+    }
+
+    #[test]
+    fn parse_utf16() {
+        let text = std::str::from_utf8(SAMPLE).unwrap();
+        let mut data: Vec<u8> = vec![0xFF, 0xFE];
+        for word in text.encode_utf16() {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        let session = parse(FILE_NAME, &data).unwrap();
+        assert_eq!(session.results.len(), 4);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(parse(FILE_NAME, b"{}").is_err());
+        assert!(parse(FILE_NAME, b"{\"sessionType\": \"X\", \"trackName\": \"monza\", \"sessionResult\": {}}").is_err());
+    }
+
+    #[test(tokio::test)]
+    async fn import_sample() {
+        let database = DatabaseManager::new_in_memory().await;
+        let db_content = database.db_content().await;
+        let track = db_content.tbl_tracks().await.create_track("Monza".to_string()).await.unwrap();
+        let layout = track.create_layout("GP".to_string()).await.unwrap();
+        layout.set_sim_id(Simulator::AssettoCorsaCompetizione, "monza".to_string()).await.unwrap();
+
+        // import
+        let imported = parse(FILE_NAME, SAMPLE).unwrap();
+        let session = super::super::store(&database, imported).await.unwrap();
+        assert_eq!(session.track_layout().await, Some(layout.id().await));
+        assert_eq!(session.results().await.len(), 4);
+        assert_eq!(session.laps().await.len(), 6);
+        assert_eq!(session.penalties().await.len(), 2);
+
+        // users are created and last lap is set
+        let tbl_stm = database.db_members().await.tbl_steam_accounts().await;
+        let steam_account = tbl_stm.item_by_steam_id("76561198000000001", false).await.unwrap();
+        let user = steam_account.user().await.unwrap();
+        assert_eq!(user.name().await, "Anna Alpha");
+        assert_eq!(user.last_lap().await.raw(), &started_from_file_name(FILE_NAME));
+        assert_eq!(session.results().await[0].user, Some(user.id().await));
+
+        // driver without laps has no last lap
+        let steam_account = tbl_stm.item_by_steam_id("76561198000000004", false).await.unwrap();
+        let user = steam_account.user().await.unwrap();
+        assert_eq!(user.last_lap().await.raw(), &None);
+
+        // second import is denied
+        let imported = parse(FILE_NAME, SAMPLE).unwrap();
+        assert!(super::super::store(&database, imported).await.is_err());
+    }
+
+    #[test(tokio::test)]
+    async fn import_rollback() {
+        let database = DatabaseManager::new_in_memory().await;
+        let pool = database.db_results().await.pool().await;
+
+        // let the import fail after results and laps are stored
+        sqlx::query("DROP TABLE session_penalties;").execute(&pool).await.unwrap();
+        let imported = parse(FILE_NAME, SAMPLE).unwrap();
+        assert!(super::super::store_new(&database, FILE_NAME, vec![imported]).await.is_err());
+
+        // the session is removed again
+        let tbl_ses = database.db_results().await.tbl_sessions().await;
+        assert!(tbl_ses.item_by_file(Simulator::AssettoCorsaCompetizione, "SSLO Test Server", FILE_NAME).await.is_none());
+        for table in ["sessions", "session_results", "session_laps"] {
+            let (count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {};", table)).fetch_one(&pool).await.unwrap();
+            assert_eq!(count, 0);
+        }
+
+        // the last lap of the drivers is not set
+        let tbl_stm = database.db_members().await.tbl_steam_accounts().await;
+        let user = tbl_stm.item_by_steam_id("76561198000000001", false).await.unwrap().user().await.unwrap();
+        assert_eq!(user.last_lap().await.raw(), &None);
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::{BTreeMap, HashMap};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sslo_lib::error::SsloError;
+use crate::db2::content::Simulator;
+use crate::db2::results::SessionType;
+use super::{ImportedDriver, ImportedIncident, ImportedLap, ImportedResult, ImportedSession, ResultImporter};
+
+/// Importer for the stats of Automobilista 2 dedicated servers (sms_stats_data.json)
+pub struct Ams2Importer;
+
+impl ResultImporter for Ams2Importer {
+    fn simulator(&self) -> Simulator { Simulator::Automobilista2 }
+
+    fn parse(&self, file_name: &str, data: &[u8]) -> Result<Vec<ImportedSession>, SsloError> {
+        parse(file_name, data)
+    }
+}
+
+#[derive(Deserialize)]
+struct Ams2Data {
+    stats: Ams2Stats,
+}
+
+#[derive(Deserialize)]
+struct Ams2Stats {
+    #[serde(default)]
+    server: Option<Ams2Server>,
+    #[serde(default)]
+    history: Vec<Ams2History>,
+}
+
+#[derive(Deserialize)]
+struct Ams2Server {
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Ams2History {
+    index: i64,
+    #[serde(default)]
+    setup: Ams2Setup,
+    #[serde(default)]
+    members: HashMap<String, Ams2Member>,
+    #[serde(default)]
+    participants: HashMap<String, Ams2Participant>,
+    #[serde(default)]
+    stages: BTreeMap<String, Ams2Stage>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+struct Ams2Setup {
+    #[serde(default)]
+    track_id: i64,
+}
+
+#[derive(Deserialize)]
+struct Ams2Member {
+    refid: i64,
+    steamid: String,
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Ams2Participant {
+    ref_id: i64,
+    #[serde(default)]
+    vehicle_id: i64,
+}
+
+#[derive(Deserialize)]
+struct Ams2Stage {
+    start_time: i64,
+    #[serde(default)]
+    events: Vec<Ams2Event>,
+    #[serde(default)]
+    results: Vec<Ams2StageResult>,
+}
+
+#[derive(Deserialize)]
+struct Ams2Event {
+    event_name: String,
+    participantid: i64,
+    #[serde(default)]
+    attributes: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct Ams2StageResult {
+    participantid: i64,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    attributes: HashMap<String, serde_json::Value>,
+}
+
+fn attribute(attributes: &HashMap<String, serde_json::Value>, key: &str) -> i64 {
+    attributes.get(key).and_then(|v| v.as_i64()).unwrap_or_default()
+}
+
+fn optional_time(time: i64) -> Option<i64> {
+    if time > 0 { Some(time) } else { None }
+}
+
+fn session_type(stage_name: &str) -> Option<SessionType> {
+    if stage_name.starts_with("practice") || stage_name.starts_with("warmup") {
+        Some(SessionType::Practice)
+    } else if stage_name.starts_with("qualifying") {
+        Some(SessionType::Qualifying)
+    } else if stage_name.starts_with("race") {
+        Some(SessionType::Race)
+    } else {
+        None
+    }
+}
+
+/// Parse the stats file of an Automobilista 2 dedicated server
+/// Every stage (practice, qualifying, race) of every history entry results in a separate session.
+pub fn parse(file_name: &str, data: &[u8]) -> Result<Vec<ImportedSession>, SsloError> {
+    let import_error = |description: String| SsloError::ResultImportFailed(file_name.to_string(), description);
+
+    // the server prepends comment lines
+    let text = super::decode_text(file_name, data)?;
+    let text: String = text.lines()
+        .filter(|l| !l.trim_start().starts_with("//"))
+        .collect::<Vec<&str>>()
+        .join("\n");
+    let ams2: Ams2Data = serde_json::from_str(&text).map_err(|e| import_error(e.to_string()))?;
+    let server_name = ams2.stats.server.map(|s| s.name).unwrap_or_default();
+
+    let mut sessions = Vec::new();
+    for history in ams2.stats.history.iter() {
+
+        // participants are identified by their steam-id
+        let guids_by_refid: HashMap<i64, String> = history.members.values()
+            .map(|m| (m.refid, m.steamid.clone()))
+            .collect();
+        let names_by_refid: HashMap<i64, String> = history.members.values()
+            .map(|m| (m.refid, m.name.clone()))
+            .collect();
+        let participant = |participant_id: i64| history.participants.get(&participant_id.to_string());
+        let guid = |participant_id: i64| -> String {
+            participant(participant_id)
+                .and_then(|p| guids_by_refid.get(&p.ref_id))
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        for (stage_name, stage) in history.stages.iter() {
+            let session_type = match session_type(stage_name) {
+                Some(session_type) => session_type,
+                None => continue,
+            };
+            if stage.results.is_empty() {
+                continue;
+            }
+
+            // laps
+            let mut laps = Vec::new();
+            for event in stage.events.iter().filter(|e| e.event_name == "Lap") {
+                laps.push(ImportedLap {
+                    driver_guid: guid(event.participantid),
+                    car_number: event.participantid,
+                    lap_number: attribute(&event.attributes, "Lap") + 1,
+                    lap_time: attribute(&event.attributes, "LapTime"),
+                    sectors: ["Sector1Time", "Sector2Time", "Sector3Time"].iter()
+                        .map(|s| attribute(&event.attributes, s))
+                        .collect(),
+                    valid: attribute(&event.attributes, "CountThisLapTimes") != 0,
+                });
+            }
+
+            // incidents
+            let mut incidents = Vec::new();
+            for event in stage.events.iter().filter(|e| e.event_name == "Impact") {
+                let other_participant_id = attribute(&event.attributes, "OtherParticipantId");
+                let other_driver_guid = if other_participant_id >= 0 {
+                    Some(guid(other_participant_id)).filter(|g| !g.is_empty())
+                } else {
+                    None
+                };
+                incidents.push(ImportedIncident {
+                    driver_guid: guid(event.participantid),
+                    car_number: event.participantid,
+                    incident_type: if other_participant_id >= 0 { "COLLISION_WITH_CAR" } else { "COLLISION_WITH_ENV" }.to_string(),
+                    other_driver_guid,
+                    impact_speed: None,
+                    lap: None,
+                });
+            }
+
+            // results
+            let mut results = Vec::new();
+            for result in stage.results.iter() {
+                let driver_guid = guid(result.participantid);
+                let name = participant(result.participantid)
+                    .and_then(|p| names_by_refid.get(&p.ref_id))
+                    .cloned()
+                    .unwrap_or_else(|| result.name.clone());
+                results.push(ImportedResult {
+                    position: attribute(&result.attributes, "RacePosition"),
+                    car_identifier: participant(result.participantid)
+                        .map(|p| p.vehicle_id.to_string())
+                        .unwrap_or_default(),
+                    car_number: result.participantid,
+                    team: String::new(),
+                    lap_count: attribute(&result.attributes, "Lap"),
+                    best_lap: optional_time(attribute(&result.attributes, "FastestLapTime")),
+                    total_time: match session_type {
+                        SessionType::Race => optional_time(attribute(&result.attributes, "TotalTime")),
+                        _ => None,
+                    },
+                    incidents: incidents.iter().filter(|i| i.driver_guid == driver_guid).count() as i64,
+                    driver: ImportedDriver { guid: driver_guid, name },
+                });
+            }
+            results.sort_by_key(|r| r.position);
+
+            sessions.push(ImportedSession {
+                file_name: format!("{}#{}/{}", file_name, history.index, stage_name),
+                simulator: Simulator::Automobilista2,
+                session_type,
+                server_name: server_name.clone(),
+                track_identifier: history.setup.track_id.to_string(),
+                started: DateTime::<Utc>::from_timestamp(stage.start_time, 0),
+                wet: false,
+                results,
+                laps,
+                penalties: Vec::new(),
+                incidents,
+            });
+        }
+    }
+
+    if sessions.is_empty() {
+        return Err(import_error("no session found".to_string()));
+    }
+    Ok(sessions)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use crate::db2::DatabaseManager;
+
+    const FILE_NAME: &str = "sms_stats_data.json";
+    const SAMPLE: &[u8] = include_bytes!("../../test_data/ams2/sms_stats_data.json");
+
+    #[test]
+    fn parse_sample() {
+        let sessions = parse(FILE_NAME, SAMPLE).unwrap();
+
+        // stages without results are skipped
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].file_name, "sms_stats_data.json#1/qualifying1");
+        assert_eq!(sessions[0].session_type, SessionType::Qualifying);
+        assert_eq!(sessions[0].server_name, "SSLO AMS2");
+        assert_eq!(sessions[0].track_identifier, "-559709709");
+        assert_eq!(sessions[0].results[0].total_time, None);
+
+        let race = &sessions[1];
+        assert_eq!(race.session_type, SessionType::Race);
+        assert_eq!(race.started.unwrap().to_rfc3339(), "2025-04-14T20:30:00+00:00");
+        assert_eq!(race.results.len(), 2);
+        assert_eq!(race.results[0].driver, ImportedDriver{guid: "76561198000000001".to_string(), name: "Anna Alpha".to_string()});
+        assert_eq!(race.results[0].car_identifier, "1764851930");
+        assert_eq!(race.results[0].best_lap, Some(98512));
+        assert_eq!(race.results[0].total_time, Some(198987));
+
+        // laps
+        assert_eq!(race.laps.len(), 4);
+        assert_eq!(race.laps[0].lap_number, 1);
+        assert_eq!(race.laps[0].sectors, vec![30100, 35000, 35250]);
+        assert!(!race.laps[3].valid);
+
+        // incidents
+        assert_eq!(race.incidents.len(), 2);
+        assert_eq!(race.incidents[0].other_driver_guid, Some("76561198000000002".to_string()));
+        assert_eq!(race.incidents[1].incident_type, "COLLISION_WITH_ENV");
+        assert_eq!(race.results[1].incidents, 1);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(parse(FILE_NAME, b"{}").is_err());
+        assert!(parse(FILE_NAME, b"{\"stats\": {\"history\": []}}").is_err());
+    }
+
+    #[test(tokio::test)]
+    async fn import_sample() {
+        let database = DatabaseManager::new_in_memory().await;
+        let imported = Ams2Importer.parse(FILE_NAME, SAMPLE).unwrap();
+        let sessions = super::super::store_new(&database, FILE_NAME, imported).await.unwrap();
+        assert_eq!(sessions.len(), 2);
+
+        // drivers are linked via their steam-id
+        let tbl_stm = database.db_members().await.tbl_steam_accounts().await;
+        let user = tbl_stm.item_by_steam_id("76561198000000002", false).await.unwrap().user().await.unwrap();
+        assert_eq!(user.name().await, "Bert Beta");
+        assert_eq!(sessions[1].results().await[1].user, Some(user.id().await));
+
+        // already imported stages are skipped
+        let imported = Ams2Importer.parse(FILE_NAME, SAMPLE).unwrap();
+        assert!(super::super::store_new(&database, FILE_NAME, imported).await.is_err());
+    }
+}
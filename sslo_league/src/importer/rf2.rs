@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use roxmltree::Node;
+use sslo_lib::error::SsloError;
+use crate::db2::content::Simulator;
+use crate::db2::results::SessionType;
+use super::{ImportedDriver, ImportedIncident, ImportedLap, ImportedResult, ImportedSession, ResultImporter};
+
+/// Importer for rFactor 2 server result files (also used by Le Mans Ultimate)
+pub struct Rf2Importer {
+    simulator: Simulator,
+}
+
+impl Rf2Importer {
+    pub fn new(simulator: Simulator) -> Self {
+        Self { simulator }
+    }
+}
+
+impl ResultImporter for Rf2Importer {
+    fn simulator(&self) -> Simulator { self.simulator }
+
+    fn parse(&self, file_name: &str, data: &[u8]) -> Result<Vec<ImportedSession>, SsloError> {
+        parse(self.simulator, file_name, data)
+    }
+}
+
+/// Text of a direct child element
+fn child_text<'a>(node: &Node<'a, '_>, tag: &str) -> Option<&'a str> {
+    node.children()
+        .find(|n| n.has_tag_name(tag))
+        .and_then(|n| n.text())
+        .map(|t| t.trim())
+}
+
+/// rF2 reports times in seconds, invalid times are written as '--.----'
+fn milliseconds(seconds: &str) -> Option<i64> {
+    match seconds.trim().parse::<f64>() {
+        Ok(s) if s > 0.0 => Some((s * 1000.0).round() as i64),
+        _ => None,
+    }
+}
+
+fn session_type(tag: &str) -> Option<SessionType> {
+    if tag.starts_with("Practice") || tag.starts_with("Warmup") {
+        Some(SessionType::Practice)
+    } else if tag.starts_with("Qualify") {
+        Some(SessionType::Qualifying)
+    } else if tag.starts_with("Race") {
+        Some(SessionType::Race)
+    } else {
+        None
+    }
+}
+
+fn timestamp(node: &Node) -> Option<DateTime<Utc>> {
+    child_text(node, "DateTime")
+        .and_then(|t| t.parse::<i64>().ok())
+        .and_then(|t| DateTime::from_timestamp(t, 0))
+}
+
+/// Parse the content of an rFactor 2 / Le Mans Ultimate result file (UserData/Log/Results/*.xml)
+pub fn parse(simulator: Simulator, file_name: &str, data: &[u8]) -> Result<Vec<ImportedSession>, SsloError> {
+    let import_error = |description: String| SsloError::ResultImportFailed(file_name.to_string(), description);
+
+    let text = super::decode_text(file_name, data)?;
+    let document = roxmltree::Document::parse(&text).map_err(|e| import_error(e.to_string()))?;
+    let race_results = document.descendants()
+        .find(|n| n.has_tag_name("RaceResults"))
+        .ok_or_else(|| import_error("missing <RaceResults>".to_string()))?;
+
+    let server_name = child_text(&race_results, "ServerName").unwrap_or_default().to_string();
+    let track_identifier = child_text(&race_results, "TrackCourse")
+        .or_else(|| child_text(&race_results, "TrackVenue"))
+        .unwrap_or_default()
+        .to_string();
+    let session_nodes: Vec<(Node, SessionType)> = race_results.children()
+        .filter(|n| n.is_element())
+        .filter_map(|n| session_type(n.tag_name().name()).map(|t| (n, t)))
+        .filter(|(n, _)| n.children().any(|c| c.has_tag_name("Driver")))
+        .collect();
+    if session_nodes.is_empty() {
+        return Err(import_error("no session found".to_string()));
+    }
+
+    // 'Anna Alpha(0) reported contact (0.35) with another vehicle Bert Beta(1)'
+    // 'Bert Beta(1) reported contact (0.71) with Wall'
+    let re_incident = Regex::new(r"^(.+)\((\d+)\) reported contact \(([\d.]+)\) with (?:another vehicle (.+)\((\d+)\)|(.+))$").unwrap();
+
+    let mut sessions = Vec::new();
+    for (session_node, session_type) in session_nodes.iter() {
+        let drivers: Vec<Node> = session_node.children().filter(|n| n.has_tag_name("Driver")).collect();
+
+        // drivers are identified by their steam-id, incidents only refer to the driver name
+        let mut guids: HashMap<String, String> = HashMap::new();
+        let mut car_numbers: HashMap<String, i64> = HashMap::new();
+        for driver in drivers.iter() {
+            let name = child_text(driver, "Name").unwrap_or_default().to_string();
+            guids.insert(name.clone(), child_text(driver, "SteamID").unwrap_or_default().to_string());
+            car_numbers.insert(name, child_text(driver, "CarNumber").and_then(|n| n.parse().ok()).unwrap_or_default());
+        }
+
+        // results and laps
+        let mut results = Vec::new();
+        let mut laps = Vec::new();
+        for driver in drivers.iter() {
+            let name = child_text(driver, "Name").unwrap_or_default().to_string();
+            let guid = guids.get(&name).cloned().unwrap_or_default();
+            let car_number = car_numbers.get(&name).copied().unwrap_or_default();
+
+            for lap in driver.children().filter(|n| n.has_tag_name("Lap")) {
+                let lap_time = match lap.text().and_then(milliseconds) {
+                    Some(lap_time) => lap_time,
+                    None => continue,
+                };
+                laps.push(ImportedLap {
+                    driver_guid: guid.clone(),
+                    car_number,
+                    lap_number: lap.attribute("num").and_then(|n| n.parse().ok()).unwrap_or_default(),
+                    lap_time,
+                    sectors: ["s1", "s2", "s3"].iter()
+                        .filter_map(|s| lap.attribute(*s).and_then(milliseconds))
+                        .collect(),
+                    valid: true,
+                });
+            }
+
+            results.push(ImportedResult {
+                position: child_text(driver, "Position").and_then(|p| p.parse().ok()).unwrap_or_default(),
+                driver: ImportedDriver { guid, name },
+                car_identifier: child_text(driver, "CarType")
+                    .or_else(|| child_text(driver, "VehName"))
+                    .unwrap_or_default()
+                    .to_string(),
+                car_number,
+                team: child_text(driver, "TeamName").unwrap_or_default().to_string(),
+                lap_count: child_text(driver, "Laps").and_then(|l| l.parse().ok()).unwrap_or_default(),
+                best_lap: child_text(driver, "BestLapTime").and_then(milliseconds),
+                total_time: child_text(driver, "FinishTime").and_then(milliseconds),
+                incidents: 0,
+            });
+        }
+        results.sort_by_key(|r| r.position);
+
+        // incidents
+        let mut incidents = Vec::new();
+        let stream_incidents = session_node.children()
+            .filter(|n| n.has_tag_name("Stream"))
+            .flat_map(|n| n.children())
+            .filter(|n| n.has_tag_name("Incident"));
+        for incident in stream_incidents {
+            let captures = match incident.text().and_then(|t| re_incident.captures(t.trim())) {
+                Some(captures) => captures,
+                None => continue,
+            };
+            let driver_name = captures[1].to_string();
+            let other_driver_name = captures.get(4).map(|m| m.as_str().to_string());
+            incidents.push(ImportedIncident {
+                driver_guid: guids.get(&driver_name).cloned().unwrap_or_default(),
+                car_number: car_numbers.get(&driver_name).copied().unwrap_or_default(),
+                other_driver_guid: other_driver_name.as_ref()
+                    .and_then(|name| guids.get(name).cloned())
+                    .filter(|guid| !guid.is_empty()),
+                incident_type: match other_driver_name {
+                    Some(_) => "COLLISION_WITH_CAR".to_string(),
+                    None => "COLLISION_WITH_ENV".to_string(),
+                },
+                impact_speed: None,
+                lap: None,
+            });
+        }
+        for result in results.iter_mut() {
+            result.incidents = incidents.iter().filter(|i| i.driver_guid == result.driver.guid).count() as i64;
+        }
+
+        sessions.push(ImportedSession {
+            file_name: if session_nodes.len() == 1 {
+                file_name.to_string()
+            } else {
+                format!("{}#{}", file_name, session_node.tag_name().name())
+            },
+            simulator,
+            session_type: *session_type,
+            server_name: server_name.clone(),
+            track_identifier: track_identifier.clone(),
+            started: timestamp(session_node).or_else(|| timestamp(&race_results)),
+            wet: false,
+            results,
+            laps,
+            penalties: Vec::new(),
+            incidents,
+        });
+    }
+
+    Ok(sessions)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use crate::db2::DatabaseManager;
+
+    const FILE_NAME: &str = "2025_04_14_20_30_00-R1.xml";
+    const SAMPLE: &[u8] = include_bytes!("../../test_data/rf2/2025_04_14_20_30_00-R1.xml");
+
+    #[test]
+    fn parse_sample() {
+        let sessions = parse(Simulator::RFactor2, FILE_NAME, SAMPLE).unwrap();
+        assert_eq!(sessions.len(), 1);
+        let session = &sessions[0];
+        assert_eq!(session.file_name, FILE_NAME);
+        assert_eq!(session.session_type, SessionType::Race);
+        assert_eq!(session.server_name, "SSLO Test Server");
+        assert_eq!(session.track_identifier, "Sebring 12h Course");
+        assert_eq!(session.started.unwrap().to_rfc3339(), "2025-04-14T20:30:00+00:00");
+
+        // results are sorted by position
+        assert_eq!(session.results.len(), 3);
+        assert_eq!(session.results[0].driver, ImportedDriver{guid: "76561198000000002".to_string(), name: "Bert Beta".to_string()});
+        assert_eq!(session.results[0].car_identifier, "Porsche 911 GT3 R");
+        assert_eq!(session.results[0].car_number, 92);
+        assert_eq!(session.results[0].best_lap, Some(125312));
+        assert_eq!(session.results[0].total_time, Some(253125));
+        assert_eq!(session.results[2].best_lap, None);
+
+        // laps without time are skipped
+        assert_eq!(session.laps.len(), 4);
+        assert_eq!(session.laps[0].sectors, vec![40100, 45000, 45100]);
+
+        // incidents
+        assert_eq!(session.incidents.len(), 2);
+        assert_eq!(session.incidents[0].incident_type, "COLLISION_WITH_CAR");
+        assert_eq!(session.incidents[0].other_driver_guid, Some("76561198000000002".to_string()));
+        assert_eq!(session.incidents[1].incident_type, "COLLISION_WITH_ENV");
+        assert_eq!(session.incidents[1].other_driver_guid, None);
+        assert_eq!(session.results[1].incidents, 2);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(parse(Simulator::RFactor2, FILE_NAME, b"<rFactorXML></rFactorXML>").is_err());
+        assert!(parse(Simulator::RFactor2, FILE_NAME, b"<rFactorXML><RaceResults></RaceResults></rFactorXML>").is_err());
+        assert!(parse(Simulator::RFactor2, FILE_NAME, b"no xml").is_err());
+    }
+
+    #[test(tokio::test)]
+    async fn import_le_mans_ultimate() {
+        let database = DatabaseManager::new_in_memory().await;
+        let importer = Rf2Importer::new(Simulator::LeMansUltimate);
+        let imported = importer.parse(FILE_NAME, SAMPLE).unwrap();
+        assert_eq!(imported[0].simulator, Simulator::LeMansUltimate);
+        let sessions = super::super::store_new(&database, FILE_NAME, imported).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(database.db_results().await.tbl_sessions().await
+            .item_by_file(Simulator::LeMansUltimate, &sessions[0].server_name().await, FILE_NAME).await.is_some());
+        assert_eq!(sessions[0].results().await.len(), 3);
+
+        // the same file is not imported twice
+        let imported = importer.parse(FILE_NAME, SAMPLE).unwrap();
+        assert!(super::super::store_new(&database, FILE_NAME, imported).await.is_err());
+    }
+}
@@ -1,19 +1,81 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::net::{Ipv4Addr, SocketAddr};
 use env_logger::fmt::Formatter;
 use std::io::Write;
 use log::{Level, Record};
 use app_state::AppState;
+use sslo_lib::db::members::users::{Promotion, PromotionAuthority, PromotionLevel};
 
 mod http;
 mod config;
 mod app_state;
 mod helpers;
 mod db2;
+mod discord;
+mod ical;
+mod importer;
+mod ranking;
+mod standings;
 
 #[derive(Parser)]
 struct CliArgs {
     config_file: std::path::PathBuf,
+
+    /// run an administrative command instead of the server
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+
+    /// Create a new user with Chief Administrator promotion (the server must not be running)
+    /// The login password is read from stdin.
+    CreateAdmin {
+
+        /// name of the new user
+        #[arg(long)]
+        name: String,
+    },
+}
+
+/// Read a password as the first line of an input (without line break)
+fn read_password(mut input: impl std::io::BufRead) -> std::io::Result<String> {
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Create a new administrator user offline, returns the user id
+async fn create_admin(app_state: &AppState, name: String, password: String) -> Result<i64, String> {
+    if password.is_empty() {
+        return Err("empty password".to_string());
+    }
+    let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+    let mut user = tbl_usr.create_new_user().await.ok_or("could not create user")?;
+    user.set_name(name).await.map_err(|e| e.to_string())?;
+    if !user.update_password(None, Some(password)).await {
+        return Err("could not set password".to_string());
+    }
+    user.grant_promotion(Promotion::new(PromotionLevel::Admin, PromotionAuthority::Chief), None).await.map_err(|e| e.to_string())?;
+    Ok(user.id().await)
+}
+
+/// Regularly delete login cookies that were not used for the configured amount of days
+async fn login_expiry_background_service(app_state: AppState) {
+    let days = app_state.config.general.days_login_expiry;
+    if days == 0 {
+        return;
+    }
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(3600));
+    loop {
+        interval.tick().await;
+        let tbl_cookie = app_state.database.db_members().await.tbl_cookie_logins().await;
+        let unused_since = chrono::Utc::now() - chrono::Duration::days(i64::from(days));
+        if let Err(e) = tbl_cookie.delete_unused_cookies(unused_since).await {
+            log::error!("Failed to delete expired login cookies: {}", e);
+        }
+    }
 }
 
 fn env_logger_format(buf: &mut Formatter, record: &Record<'_>) -> std::io::Result<()> {
@@ -65,12 +127,36 @@ async fn main() {
     //     }
     // };
 
+    // administrative commands
+    if let Some(command) = cli_args.command {
+        match command {
+            CliCommand::CreateAdmin { name } => {
+                eprint!("Password: ");
+                let password = match read_password(std::io::stdin().lock()) {
+                    Ok(password) => password,
+                    Err(e) => {
+                        log::error!("Failed to read password from stdin: {}", e);
+                        return;
+                    }
+                };
+                match create_admin(&app_state, name, password).await {
+                    Ok(user_id) => println!("Created administrator with user id {} (login with this id and the password)", user_id),
+                    Err(e) => log::error!("Failed to create administrator: {}", e),
+                }
+            },
+        }
+        return;
+    }
+
     // user info
     log::info!("initialization complete");
 
     // HTTP to HTTPS forwarder (background service)
     tokio::spawn(http::http2https_background_service(app_state.config.http.port_http, app_state.config.http.port_https));
 
+    // expiry of unused logins (background service)
+    tokio::spawn(login_expiry_background_service(app_state.clone()));
+
     // run https server
     let app = http::create_router(app_state.clone());
     let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, app_state.config.http.port_https));
@@ -83,3 +169,37 @@ async fn main() {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use crate::config::{Config, Totp};
+
+    #[test(tokio::test)]
+    async fn create_admin() {
+        let mut config = Config::new_for_test();
+        config.totp = Some(Totp { key: "some_totp_test_key".to_string(), required_level: Some(PromotionLevel::Director) });
+        let app_state = AppState::new_in_memory(config).await;
+        assert!(super::create_admin(&app_state, "Admin".to_string(), "".to_string()).await.is_err());
+
+        // the administrator can login with the password
+        let user_id = super::create_admin(&app_state, "Admin".to_string(), "secret".to_string()).await.unwrap();
+        let tbl_usr = app_state.database.db_members().await.tbl_users().await;
+        let user = tbl_usr.user_by_id(user_id).await.unwrap();
+        assert_eq!(user.name().await, "Admin");
+        assert_eq!(user.promotion().await, Promotion::new(PromotionLevel::Admin, PromotionAuthority::Chief));
+        assert_eq!(user.promotion_history().await.len(), 1);
+        assert!(user.verify_password("secret".to_string(), "unit test".to_string()).await);
+
+        // TOTP must be enabled by the administrator (if required)
+        assert!(user.totp_enabled().await.raw().is_none());
+    }
+
+    #[test]
+    fn read_password() {
+        assert_eq!(super::read_password("secret\n".as_bytes()).unwrap(), "secret");
+        assert_eq!(super::read_password("se cret \r\nnext line\n".as_bytes()).unwrap(), "se cret ");
+        assert_eq!(super::read_password("".as_bytes()).unwrap(), "");
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use sslo_lib::db::DbRow;
+use sslo_lib::db::members::users::{Activity, UserItem};
+use sslo_lib::error::SsloError;
+use crate::db2::DatabaseManager;
+use crate::db2::results::SessionType;
+use crate::db2::results::ratings::{RatingHistoryRow, RatingRow};
+
+/// Conversion factor between the Glicko and the Glicko-2 scale
+const SCALE: f64 = 173.7178;
+
+/// System constant, which constrains the change of volatility over time
+const TAU: f64 = 0.5;
+
+/// Convergence tolerance for the volatility iteration
+const EPSILON: f64 = 0.000001;
+
+/// The rating of a driver according to the Glicko-2 system
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glicko2 {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Glicko2 {
+    fn default() -> Self {
+        Self { rating: 1500.0, deviation: 350.0, volatility: 0.06 }
+    }
+}
+
+impl Glicko2 {
+
+    fn from_row(row: &RatingRow) -> Self {
+        Self { rating: row.rating, deviation: row.deviation, volatility: row.volatility }
+    }
+
+    /// Calculate the new rating after a rating period
+    /// Each game is the rating of the opponent and the score (1.0 win, 0.5 draw, 0.0 loss).
+    pub fn update(&self, games: &[(Glicko2, f64)]) -> Glicko2 {
+        let mu = (self.rating - 1500.0) / SCALE;
+        let phi = self.deviation / SCALE;
+        let sigma = self.volatility;
+
+        // without games only the deviation increases
+        if games.is_empty() {
+            return Glicko2 {
+                rating: self.rating,
+                deviation: (phi * phi + sigma * sigma).sqrt() * SCALE,
+                volatility: sigma,
+            };
+        }
+
+        let g = |phi: f64| 1.0 / (1.0 + 3.0 * phi * phi / (std::f64::consts::PI * std::f64::consts::PI)).sqrt();
+        let mut v_inv = 0.0;
+        let mut score_sum = 0.0;
+        for (opponent, score) in games.iter() {
+            let mu_j = (opponent.rating - 1500.0) / SCALE;
+            let g_j = g(opponent.deviation / SCALE);
+            let e = 1.0 / (1.0 + (-g_j * (mu - mu_j)).exp());
+            v_inv += g_j * g_j * e * (1.0 - e);
+            score_sum += g_j * (score - e);
+        }
+        let v = 1.0 / v_inv;
+        let delta = v * score_sum;
+
+        // new volatility (Illinois algorithm)
+        let a = (sigma * sigma).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            ex * (delta * delta - phi * phi - v - ex) / (2.0 * (phi * phi + v + ex).powi(2)) - (x - a) / (TAU * TAU)
+        };
+        let mut big_a = a;
+        let mut big_b = if delta * delta > phi * phi + v {
+            (delta * delta - phi * phi - v).ln()
+        } else {
+            let mut k = 1.0;
+            while f(a - k * TAU) < 0.0 {
+                k += 1.0;
+            }
+            a - k * TAU
+        };
+        let mut f_a = f(big_a);
+        let mut f_b = f(big_b);
+        while (big_b - big_a).abs() > EPSILON {
+            let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+            let f_c = f(big_c);
+            if f_c * f_b <= 0.0 {
+                big_a = big_b;
+                f_a = f_b;
+            } else {
+                f_a /= 2.0;
+            }
+            big_b = big_c;
+            f_b = f_c;
+        }
+        let sigma_new = (big_a / 2.0).exp();
+
+        // new rating and deviation
+        let phi_star = (phi * phi + sigma_new * sigma_new).sqrt();
+        let phi_new = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+        let mu_new = mu + phi_new * phi_new * score_sum;
+        Glicko2 {
+            rating: mu_new * SCALE + 1500.0,
+            deviation: phi_new * SCALE,
+            volatility: sigma_new,
+        }
+    }
+}
+
+/// Rate the classification of a race
+/// Every driver plays against every other driver (a better position wins).
+fn rate_race(ratings: &mut HashMap<(i64, i64), RatingRow>,
+             history: &mut Vec<RatingHistoryRow>,
+             session_id: i64,
+             date: DateTime<Utc>,
+             car_class: i64,
+             drivers: &[(i64, i64)],
+) {
+    if drivers.len() < 2 {
+        return;
+    }
+
+    let ratings_before: Vec<Glicko2> = drivers.iter()
+        .map(|(user, _)| ratings.get(&(*user, car_class)).map(Glicko2::from_row).unwrap_or_default())
+        .collect();
+    for (index, (user, position)) in drivers.iter().enumerate() {
+        let games: Vec<(Glicko2, f64)> = drivers.iter().enumerate()
+            .filter(|(other_index, _)| *other_index != index)
+            .map(|(other_index, (_, other_position))| {
+                let score = if position < other_position { 1.0 } else if position == other_position { 0.5 } else { 0.0 };
+                (ratings_before[other_index], score)
+            })
+            .collect();
+        let rating = ratings_before[index].update(&games);
+
+        let row = ratings.entry((*user, car_class)).or_insert_with(|| {
+            let mut row = RatingRow::new(0);
+            row.user = *user;
+            row.car_class = car_class;
+            row
+        });
+        row.rating = rating.rating;
+        row.deviation = rating.deviation;
+        row.volatility = rating.volatility;
+        row.races += 1;
+        row.updated = date;
+
+        let mut history_row = RatingHistoryRow::new(0);
+        history_row.user = *user;
+        history_row.car_class = car_class;
+        history_row.session = session_id;
+        history_row.rating = rating.rating;
+        history_row.deviation = rating.deviation;
+        history_row.volatility = rating.volatility;
+        history_row.date = date;
+        history.push(history_row);
+    }
+}
+
+/// Compute all ratings from the stored race results (in chronological order)
+/// Drivers are rated overall and within the car class of their car.
+pub async fn recompute(database: &DatabaseManager) -> Result<(), SsloError> {
+    let db_results = database.db_results().await;
+    let tbl_car = database.db_content().await.tbl_cars().await;
+
+    let mut class_by_car: HashMap<i64, i64> = HashMap::new();
+    let mut ratings: HashMap<(i64, i64), RatingRow> = HashMap::new();
+    let mut history: Vec<RatingHistoryRow> = Vec::new();
+    for session in db_results.tbl_sessions().await.items_by_type(SessionType::Race).await {
+        let session_id = session.id().await;
+        let date = session.started().await.unwrap_or(session.imported().await);
+
+        // one entry per user (user, position, car class)
+        let mut drivers: Vec<(i64, i64, i64)> = Vec::new();
+        for result in session.results().await.iter() {
+            let user = match result.user {
+                Some(user) => user,
+                None => continue,
+            };
+            if drivers.iter().any(|(u, _, _)| *u == user) {
+                continue;
+            }
+            let car_class = match result.car {
+                Some(car_id) => match class_by_car.get(&car_id) {
+                    Some(car_class) => *car_class,
+                    None => {
+                        let car_class = match tbl_car.item_by_id(car_id).await {
+                            Some(car) => match car.car_class().await {
+                                Some(car_class) => car_class.id().await,
+                                None => 0,
+                            },
+                            None => 0,
+                        };
+                        class_by_car.insert(car_id, car_class);
+                        car_class
+                    }
+                },
+                None => 0,
+            };
+            drivers.push((user, result.position, car_class));
+        }
+
+        // overall
+        let overall: Vec<(i64, i64)> = drivers.iter().map(|(u, p, _)| (*u, *p)).collect();
+        rate_race(&mut ratings, &mut history, session_id, date, 0, &overall);
+
+        // per car class
+        let mut car_classes: Vec<i64> = drivers.iter().map(|(_, _, c)| *c).filter(|c| *c != 0).collect();
+        car_classes.sort();
+        car_classes.dedup();
+        for car_class in car_classes.into_iter() {
+            let class_drivers: Vec<(i64, i64)> = drivers.iter()
+                .filter(|(_, _, c)| *c == car_class)
+                .map(|(u, p, _)| (*u, *p))
+                .collect();
+            rate_race(&mut ratings, &mut history, session_id, date, car_class, &class_drivers);
+        }
+    }
+
+    log::info!("recomputed {} ratings from {} rated results", ratings.len(), history.len());
+    db_results.tbl_ratings().await.replace_all(ratings.into_values().collect(), history).await
+}
+
+/// The ranking of a car class (zero for overall)
+/// Only users with recent driving activity are considered.
+pub async fn ranking(database: &DatabaseManager, car_class: i64) -> Vec<(UserItem, RatingRow)> {
+    let tbl_usr = database.db_members().await.tbl_users().await;
+    let mut ranking = Vec::new();
+    for row in database.db_results().await.tbl_ratings().await.ratings(car_class).await.into_iter() {
+        if let Some(user) = tbl_usr.user_by_id(row.user).await {
+            if *user.activity().await.driving() == Activity::Recent {
+                ranking.push((user, row));
+            }
+        }
+    }
+    ranking
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use crate::db2::content::Simulator;
+    use crate::db2::results::sessions::ResultRow;
+
+    #[test]
+    fn glicko2_example() {
+        // example from the Glicko-2 paper of Mark E. Glickman
+        let player = Glicko2 { rating: 1500.0, deviation: 200.0, volatility: 0.06 };
+        let games = vec![
+            (Glicko2 { rating: 1400.0, deviation: 30.0, volatility: 0.06 }, 1.0),
+            (Glicko2 { rating: 1550.0, deviation: 100.0, volatility: 0.06 }, 0.0),
+            (Glicko2 { rating: 1700.0, deviation: 300.0, volatility: 0.06 }, 0.0),
+        ];
+        let rating = player.update(&games);
+        assert!((rating.rating - 1464.06).abs() < 0.01);
+        assert!((rating.deviation - 151.52).abs() < 0.01);
+        assert!((rating.volatility - 0.05999).abs() < 0.00001);
+
+        // inactivity increases the deviation
+        assert!(player.update(&[]).deviation > player.deviation);
+    }
+
+    #[test(tokio::test)]
+    async fn recompute_ratings() {
+        let database = DatabaseManager::new_in_memory().await;
+        let tbl_usr = database.db_members().await.tbl_users().await;
+        let mut user1 = tbl_usr.create_new_user().await.unwrap();
+        let mut user2 = tbl_usr.create_new_user().await.unwrap();
+        let user3 = tbl_usr.create_new_user().await.unwrap();
+        user1.set_last_lap(Utc::now()).await;
+        user2.set_last_lap(Utc::now()).await;
+
+        // car classes
+        let db_content = database.db_content().await;
+        let gt3 = db_content.tbl_car_classes().await.create_class("GT3".to_string()).await.unwrap();
+        let car = db_content.tbl_cars().await.create_car("Porsche 911 GT3 R".to_string()).await.unwrap();
+        car.set_car_class(Some(&gt3)).await.unwrap();
+
+        // two races, user1 wins both
+        let tbl_ses = database.db_results().await.tbl_sessions().await;
+        for file_name in ["race1.json", "race2.json"] {
+            let session = tbl_ses.create_session(Simulator::AssettoCorsa, SessionType::Race, "".to_string(),
+                                                 file_name.to_string(), "".to_string(), None).await.unwrap();
+            for (position, user) in [&user1, &user2, &user3].iter().enumerate() {
+                let mut row = ResultRow::new(0);
+                row.position = position as i64 + 1;
+                row.user = Some(user.id().await);
+                if position < 2 {
+                    row.car = Some(car.id().await);
+                }
+                session.add_result(row).await.unwrap();
+            }
+        }
+        recompute(&database).await.unwrap();
+
+        // overall ranking only contains recent drivers
+        let overall = ranking(&database, 0).await;
+        assert_eq!(overall.len(), 2);
+        assert_eq!(overall[0].0.id().await, user1.id().await);
+        assert_eq!(overall[0].1.races, 2);
+        assert!(overall[0].1.rating > 1500.0);
+        assert!(overall[1].1.rating < overall[0].1.rating);
+
+        // class ranking
+        let class_ranking = ranking(&database, gt3.id().await).await;
+        assert_eq!(class_ranking.len(), 2);
+
+        // history
+        let tbl_rat = database.db_results().await.tbl_ratings().await;
+        assert_eq!(tbl_rat.history(user3.id().await, 0).await.len(), 2);
+        assert_eq!(tbl_rat.history(user1.id().await, gt3.id().await).await.len(), 2);
+
+        // recomputing yields the same result
+        recompute(&database).await.unwrap();
+        assert_eq!(tbl_rat.history(user3.id().await, 0).await.len(), 2);
+        assert_eq!(ranking(&database, 0).await[0].1.rating, overall[0].1.rating);
+    }
+}
@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+use crate::db2::DatabaseManager;
+use crate::db2::results::SessionType;
+use crate::db2::results::penalties::PenaltyType;
+use crate::db2::results::sessions::{ResultRow, SessionItem};
+use crate::db2::series::championships::ChampionshipItem;
+
+/// The standing of a driver in a championship
+pub struct DriverStanding {
+    pub user: Option<i64>,
+    pub driver_guid: String,
+    pub name: String,
+
+    /// the team of the latest round
+    pub team: String,
+
+    /// points per round (None if not participated)
+    pub round_points: Vec<Option<i64>>,
+
+    /// rounds that are not counted (drop-worst rule)
+    pub dropped: Vec<bool>,
+    pub points: i64,
+}
+
+/// The standing of a team in a championship
+pub struct TeamStanding {
+    pub team: String,
+    pub round_points: Vec<Option<i64>>,
+    pub dropped: Vec<bool>,
+    pub points: i64,
+}
+
+pub struct Standings {
+    pub drivers: Vec<DriverStanding>,
+    pub teams: Vec<TeamStanding>,
+}
+
+/// Identifies a driver over multiple sessions (by user, or by simulator guid if not known)
+fn driver_key(result: &ResultRow) -> String {
+    match result.user {
+        Some(user) => format!("user:{}", user),
+        None => format!("guid:{}", result.driver_guid),
+    }
+}
+
+/// Mark the worst rounds as dropped and return the sum of the remaining points
+/// Only rounds with results are considered, not participated rounds count as zero points.
+fn apply_drop_worst(round_points: &[Option<i64>], rounds_with_results: &[bool], drop_worst: usize) -> (Vec<bool>, i64) {
+    let mut candidates: Vec<(usize, i64)> = round_points.iter().enumerate()
+        .filter(|(index, _)| rounds_with_results[*index])
+        .map(|(index, points)| (index, points.unwrap_or(0)))
+        .collect();
+    candidates.sort_by_key(|(_, points)| *points);
+    let mut dropped = vec![false; round_points.len()];
+    for (index, _) in candidates.iter().take(drop_worst) {
+        dropped[*index] = true;
+    }
+    let points = round_points.iter().enumerate()
+        .filter(|(index, _)| !dropped[*index])
+        .map(|(_, points)| points.unwrap_or(0))
+        .sum();
+    (dropped, points)
+}
+
+/// Apply steward penalties to the classification of a session
+/// Each result comes with its active penalties, drivers of the same car are penalized together.
+/// Time penalties are only considered if apply_time is set (race sessions).
+fn apply_penalties(results: Vec<(ResultRow, Vec<(PenaltyType, i64)>)>, apply_time: bool) -> Vec<ResultRow> {
+
+    // group drivers by car (same position)
+    struct Car { rows: Vec<ResultRow>, laps: i64, time: Option<i64>, drop: i64 }
+    let mut cars: Vec<Car> = Vec::new();
+    let mut any_time_penalty = false;
+    for (row, penalties) in results.into_iter() {
+        if cars.last().is_none_or(|car| car.rows[0].position != row.position) {
+            cars.push(Car { laps: row.lap_count, time: row.total_time, drop: 0, rows: Vec::new() });
+        }
+        let car = cars.last_mut().unwrap();
+        for (penalty_type, value) in penalties.into_iter() {
+            match penalty_type {
+                PenaltyType::Time if apply_time => {
+                    car.time = car.time.map(|t| t + value * 1000);
+                    any_time_penalty = true;
+                },
+                PenaltyType::PositionDrop => car.drop += value,
+                _ => {},
+            }
+        }
+        car.rows.push(row);
+    }
+
+    // time penalties re-classify by laps and total time
+    if any_time_penalty {
+        cars.sort_by_key(|car| (-car.laps, car.time.unwrap_or(i64::MAX)));
+    }
+
+    // position drops
+    let dropped: Vec<usize> = cars.iter().enumerate().filter(|(_, car)| car.drop > 0).map(|(index, _)| index).collect();
+    for index in dropped.into_iter().rev() {
+        let new_index = (index + cars[index].drop as usize).min(cars.len() - 1);
+        let car = cars.remove(index);
+        cars.insert(new_index, car);
+    }
+
+    // renumber
+    let mut classification = Vec::new();
+    for (index, car) in cars.into_iter().enumerate() {
+        for mut row in car.rows.into_iter() {
+            row.position = index as i64 + 1;
+            classification.push(row);
+        }
+    }
+    classification
+}
+
+/// The classification of a session after applying the steward penalties
+/// Also returns the points deductions per driver key.
+async fn penalized_results(database: &DatabaseManager, session: &SessionItem) -> (Vec<ResultRow>, HashMap<String, i64>) {
+    let tbl_pen = database.db_results().await.tbl_penalties().await;
+    let mut penalties = Vec::new();
+    for penalty in tbl_pen.items_by_session(session.id().await).await.into_iter() {
+        if !penalty.revoked().await {
+            penalties.push(penalty);
+        }
+    }
+
+    let mut deductions: HashMap<String, i64> = HashMap::new();
+    let mut results = Vec::new();
+    for result in session.results().await.into_iter() {
+        let mut result_penalties = Vec::new();
+        for penalty in penalties.iter() {
+            if penalty.concerns(result.user, &result.driver_guid).await {
+                let (penalty_type, value) = (penalty.penalty_type().await, penalty.value().await);
+                if penalty_type == PenaltyType::PointsDeduction {
+                    *deductions.entry(driver_key(&result)).or_insert(0) += value;
+                }
+                result_penalties.push((penalty_type, value));
+            }
+        }
+        results.push((result, result_penalties));
+    }
+    let apply_time = session.session_type().await == SessionType::Race;
+    (apply_penalties(results, apply_time), deductions)
+}
+
+/// Filter results, which cars belong to the allowed classes (all results, if no classes are given)
+async fn eligible_results(database: &DatabaseManager,
+                          allowed_classes: &[i64],
+                          class_by_car: &mut HashMap<i64, Option<i64>>,
+                          results: Vec<ResultRow>,
+) -> Vec<ResultRow> {
+    if allowed_classes.is_empty() {
+        return results;
+    }
+    let tbl_car = database.db_content().await.tbl_cars().await;
+    let mut eligible = Vec::new();
+    for result in results.into_iter() {
+        let car_id = match result.car {
+            Some(car_id) => car_id,
+            None => continue,
+        };
+        let car_class = match class_by_car.get(&car_id) {
+            Some(car_class) => *car_class,
+            None => {
+                let car_class = match tbl_car.item_by_id(car_id).await {
+                    Some(car) => match car.car_class().await {
+                        Some(car_class) => Some(car_class.id().await),
+                        None => None,
+                    },
+                    None => None,
+                };
+                class_by_car.insert(car_id, car_class);
+                car_class
+            }
+        };
+        if let Some(car_class) = car_class {
+            if allowed_classes.contains(&car_class) {
+                eligible.push(result);
+            }
+        }
+    }
+    eligible
+}
+
+/// Compute the driver and team standings of a championship from the imported results
+pub async fn standings(database: &DatabaseManager, championship: &ChampionshipItem) -> Standings {
+    let tbl_ses = database.db_results().await.tbl_sessions().await;
+    let tbl_usr = database.db_members().await.tbl_users().await;
+
+    let rounds = championship.rounds().await;
+    let allowed_classes = championship.car_classes().await;
+    let points_table = championship.points_table().await;
+    let points_pole = championship.points_pole().await;
+    let points_fastest_lap = championship.points_fastest_lap().await;
+
+    let mut class_by_car: HashMap<i64, Option<i64>> = HashMap::new();
+
+    let mut drivers: HashMap<String, DriverStanding> = HashMap::new();
+    let mut team_points: HashMap<String, Vec<Option<i64>>> = HashMap::new();
+    let mut rounds_with_results = vec![false; rounds.len()];
+    for (round_index, round) in rounds.iter().enumerate() {
+        let mut points_by_driver: HashMap<String, (ResultRow, i64)> = HashMap::new();
+        let mut deductions: HashMap<String, i64> = HashMap::new();
+
+        // race
+        if let Some(session) = match round.race_session {
+            Some(session_id) => tbl_ses.item_by_id(session_id).await,
+            None => None,
+        } {
+            let (results, race_deductions) = penalized_results(database, &session).await;
+            deductions.extend(race_deductions);
+            let results = eligible_results(database, &allowed_classes, &mut class_by_car, results).await;
+            rounds_with_results[round_index] = !results.is_empty();
+
+            // drivers of the same car share the position
+            let mut positions: Vec<i64> = results.iter().map(|r| r.position).collect();
+            positions.dedup();
+            let fastest_lap = results.iter().filter_map(|r| r.best_lap).min();
+            for result in results.into_iter() {
+                let position = positions.iter().position(|p| *p == result.position).unwrap_or_default();
+                let mut points = points_table.get(position).copied().unwrap_or(0);
+                if fastest_lap.is_some() && result.best_lap == fastest_lap {
+                    points += points_fastest_lap;
+                }
+                points_by_driver.entry(driver_key(&result)).or_insert((result, points));
+            }
+        }
+
+        // pole position
+        if let Some(session) = match round.qualifying_session {
+            Some(session_id) => tbl_ses.item_by_id(session_id).await,
+            None => None,
+        } {
+            let (results, qualifying_deductions) = penalized_results(database, &session).await;
+            for (key, points) in qualifying_deductions.into_iter() {
+                *deductions.entry(key).or_insert(0) += points;
+            }
+            let results = eligible_results(database, &allowed_classes, &mut class_by_car, results).await;
+            if let Some(pole_position) = results.first().map(|r| r.position) {
+                for result in results.into_iter().filter(|r| r.position == pole_position) {
+                    points_by_driver.entry(driver_key(&result))
+                        .and_modify(|(_, points)| *points += points_pole)
+                        .or_insert((result, points_pole));
+                }
+            }
+        }
+
+        // points deductions
+        for (key, points) in deductions.into_iter() {
+            if let Some((_, driver_points)) = points_by_driver.get_mut(&key) {
+                *driver_points -= points;
+            }
+        }
+
+        for (key, (result, points)) in points_by_driver.into_iter() {
+            let standing = drivers.entry(key).or_insert_with(|| DriverStanding {
+                user: result.user,
+                driver_guid: result.driver_guid.clone(),
+                name: result.driver_name.clone(),
+                team: String::new(),
+                round_points: vec![None; rounds.len()],
+                dropped: Vec::new(),
+                points: 0,
+            });
+            standing.round_points[round_index] = Some(points);
+            if !result.team.is_empty() {
+                standing.team = result.team.clone();
+                let team_round_points = team_points.entry(result.team.clone()).or_insert_with(|| vec![None; rounds.len()]);
+                team_round_points[round_index] = Some(team_round_points[round_index].unwrap_or(0) + points);
+            }
+        }
+    }
+
+    // drivers
+    let drop_worst = championship.drop_worst().await as usize;
+    let mut driver_standings = Vec::new();
+    for (_, mut standing) in drivers.into_iter() {
+        if let Some(user_id) = standing.user {
+            if let Some(user) = tbl_usr.user_by_id(user_id).await {
+                standing.name = user.name().await;
+            }
+        }
+        (standing.dropped, standing.points) = apply_drop_worst(&standing.round_points, &rounds_with_results, drop_worst);
+        driver_standings.push(standing);
+    }
+    driver_standings.sort_by(|a, b| b.points.cmp(&a.points).then(a.name.cmp(&b.name)));
+
+    // teams
+    let mut team_standings = Vec::new();
+    for (team, round_points) in team_points.into_iter() {
+        let (dropped, points) = apply_drop_worst(&round_points, &rounds_with_results, drop_worst);
+        team_standings.push(TeamStanding { team, round_points, dropped, points });
+    }
+    team_standings.sort_by(|a, b| b.points.cmp(&a.points).then(a.team.cmp(&b.team)));
+
+    Standings {
+        drivers: driver_standings,
+        teams: team_standings,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use sslo_lib::db::DbRow;
+    use crate::db2::content::Simulator;
+
+    #[test]
+    fn drop_worst() {
+        let round_points = vec![Some(10), None, Some(5), None];
+        let rounds_with_results = vec![true, true, true, false];
+        let (dropped, points) = apply_drop_worst(&round_points, &rounds_with_results, 1);
+        assert_eq!(dropped, vec![false, true, false, false]);
+        assert_eq!(points, 15);
+        let (dropped, points) = apply_drop_worst(&round_points, &rounds_with_results, 2);
+        assert_eq!(dropped, vec![false, true, true, false]);
+        assert_eq!(points, 10);
+    }
+
+    #[test]
+    fn penalties() {
+        let row = |position: i64, guid: &str, laps: i64, time: i64| {
+            let mut row = ResultRow::new(0);
+            row.position = position;
+            row.driver_guid = guid.to_string();
+            row.lap_count = laps;
+            row.total_time = Some(time);
+            row
+        };
+        let results = || vec![
+            (row(1, "A", 10, 1_000_000), vec![(PenaltyType::Time, 5)]),
+            (row(2, "B1", 10, 1_003_000), vec![]),
+            (row(2, "B2", 10, 1_003_000), vec![(PenaltyType::PositionDrop, 5)]),
+            (row(3, "C", 9, 990_000), vec![(PenaltyType::PointsDeduction, 5)]),
+        ];
+        let guids_positions = |rows: Vec<ResultRow>| -> Vec<(String, i64)> {
+            rows.into_iter().map(|r| (r.driver_guid, r.position)).collect()
+        };
+
+        // time penalty re-classifies, the position drop moves the whole car back
+        let classification = apply_penalties(results(), true);
+        assert_eq!(guids_positions(classification), vec![("A".to_string(), 1), ("C".to_string(), 2),
+                                                         ("B1".to_string(), 3), ("B2".to_string(), 3)]);
+
+        // time penalties are ignored
+        let classification = apply_penalties(results(), false);
+        assert_eq!(guids_positions(classification), vec![("A".to_string(), 1), ("C".to_string(), 2),
+                                                         ("B1".to_string(), 3), ("B2".to_string(), 3)]);
+
+        // only the time penalty
+        let mut results = results();
+        results[2].1.clear();
+        let classification = apply_penalties(results, true);
+        assert_eq!(guids_positions(classification), vec![("B1".to_string(), 1), ("B2".to_string(), 1),
+                                                         ("A".to_string(), 2), ("C".to_string(), 3)]);
+    }
+
+    #[test(tokio::test)]
+    async fn championship_standings() {
+        let database = DatabaseManager::new_in_memory().await;
+
+        // cars
+        let db_content = database.db_content().await;
+        let gt3 = db_content.tbl_car_classes().await.create_class("GT3".to_string()).await.unwrap();
+        let gt4 = db_content.tbl_car_classes().await.create_class("GT4".to_string()).await.unwrap();
+        let car_gt3 = db_content.tbl_cars().await.create_car("GT3 Car".to_string()).await.unwrap();
+        car_gt3.set_car_class(Some(&gt3)).await.unwrap();
+        let car_gt4 = db_content.tbl_cars().await.create_car("GT4 Car".to_string()).await.unwrap();
+        car_gt4.set_car_class(Some(&gt4)).await.unwrap();
+
+        // sessions (driver guid, team, car, best lap)
+        let tbl_ses = database.db_results().await.tbl_sessions().await;
+        let mut session_ids = Vec::new();
+        let session_results = [
+            (SessionType::Qualifying, vec![("B", "Team 2", &car_gt3, 100), ("A", "Team 1", &car_gt3, 101)]),
+            (SessionType::Race, vec![("A", "Team 1", &car_gt3, 102), ("X", "", &car_gt4, 90), ("B", "Team 2", &car_gt3, 101), ("C", "Team 1", &car_gt3, 103)]),
+            (SessionType::Race, vec![("C", "Team 1", &car_gt3, 100), ("B", "Team 2", &car_gt3, 101)]),
+        ];
+        for (index, (session_type, results)) in session_results.iter().enumerate() {
+            let session = tbl_ses.create_session(Simulator::AssettoCorsa, *session_type, "".to_string(),
+                                                 format!("{}.json", index), "".to_string(), None).await.unwrap();
+            for (position, (guid, team, car, best_lap)) in results.iter().enumerate() {
+                let mut row = ResultRow::new(0);
+                row.position = position as i64 + 1;
+                row.driver_guid = guid.to_string();
+                row.driver_name = format!("Driver {}", guid);
+                row.team = team.to_string();
+                row.car = Some(car.id().await);
+                row.best_lap = Some(*best_lap);
+                session.add_result(row).await.unwrap();
+            }
+            session_ids.push(session.id().await);
+        }
+
+        // championship
+        let championship = database.db_series().await.tbl_championships().await.create_championship("GT3 Cup".to_string()).await.unwrap();
+        championship.set_points_table(&[10, 6, 4]).await.unwrap();
+        championship.set_bonus_points(3, 1).await.unwrap();
+        championship.set_car_classes(&[gt3.id().await]).await.unwrap();
+        let mut round1 = championship.create_round("Round 1".to_string()).await.unwrap();
+        round1.qualifying_session = Some(session_ids[0]);
+        round1.race_session = Some(session_ids[1]);
+        championship.update_round(round1).await.unwrap();
+        let mut round2 = championship.create_round("Round 2".to_string()).await.unwrap();
+        round2.race_session = Some(session_ids[2]);
+        championship.update_round(round2).await.unwrap();
+        championship.create_round("Round 3".to_string()).await.unwrap();
+
+        // GT4 driver X is not scored, B has pole and fastest lap in round 1
+        let standings = standings(&database, &championship).await;
+        assert_eq!(standings.drivers.len(), 3);
+        let points: Vec<(&str, i64)> = standings.drivers.iter().map(|d| (d.driver_guid.as_str(), d.points)).collect();
+        assert_eq!(points, vec![("B", 16), ("C", 15), ("A", 10)]);
+        assert_eq!(standings.drivers[0].round_points, vec![Some(10), Some(6), None]);
+        assert_eq!(standings.teams[0].team, "Team 1");
+        assert_eq!(standings.teams[0].points, 25);
+
+        // drop worst
+        championship.set_drop_worst(1).await.unwrap();
+        let standings = super::standings(&database, &championship).await;
+        let points: Vec<(&str, i64)> = standings.drivers.iter().map(|d| (d.driver_guid.as_str(), d.points)).collect();
+        assert_eq!(points, vec![("C", 11), ("A", 10), ("B", 10)]);
+        assert_eq!(standings.drivers[1].dropped, vec![false, true, false]);
+
+        // penalties: C drops one position in round 2, A gets a points deduction in round 1
+        championship.set_drop_worst(0).await.unwrap();
+        let tbl_pen = database.db_results().await.tbl_penalties().await;
+        let drop = tbl_pen.create_penalty(session_ids[2], None, "C".to_string(), PenaltyType::PositionDrop, 1,
+                                          "".to_string(), 1).await.unwrap();
+        tbl_pen.create_penalty(session_ids[1], None, "A".to_string(), PenaltyType::PointsDeduction, 3,
+                               "".to_string(), 1).await.unwrap();
+        let standings = super::standings(&database, &championship).await;
+        let points: Vec<(&str, i64)> = standings.drivers.iter().map(|d| (d.driver_guid.as_str(), d.points)).collect();
+        assert_eq!(points, vec![("B", 20), ("C", 11), ("A", 7)]);
+
+        // revoked penalties are not applied
+        drop.revoke(1, "".to_string()).await.unwrap();
+        let standings = super::standings(&database, &championship).await;
+        let points: Vec<(&str, i64)> = standings.drivers.iter().map(|d| (d.driver_guid.as_str(), d.points)).collect();
+        assert_eq!(points, vec![("B", 16), ("C", 15), ("A", 7)]);
+    }
+}
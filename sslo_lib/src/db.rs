@@ -1,15 +1,30 @@
+pub mod row;
+pub mod table;
+#[cfg(feature = "members")]
+pub mod members;
+
 use std::path::Path;
 use sqlx::SqlitePool;
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
-use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub use row::{DbRow, DbQuery};
+pub use table::{ItemData, ItemInterface, TableData, TableInterface};
+
+const IN_MEMORY_DB_PREFIX: &str = "/sslo-in-memory-";
+static IN_MEMORY_DB_SEQ: AtomicUsize = AtomicUsize::new(0);
 
 /// When db_path is None, the pool is generated in memory
 pub fn get_pool(db_path: Option<&Path>) -> SqlitePool {
 
     match db_path {
         None => {
-            let sqlite_opts = SqliteConnectOptions::from_str(":memory:")
-                .unwrap()
+            // named memdb database, so that it can be attached to connections of other pools
+            let seqno = IN_MEMORY_DB_SEQ.fetch_add(1, Ordering::Relaxed);
+            let sqlite_opts = SqliteConnectOptions::new()
+                .filename(format!("{}{}", IN_MEMORY_DB_PREFIX, seqno))
+                .vfs("memdb")
+                .create_if_missing(true)
                 .foreign_keys(true);
             let pool = SqlitePoolOptions::new()
                 .min_connections(1)
@@ -36,3 +51,36 @@ pub fn get_pool(db_path: Option<&Path>) -> SqlitePool {
         },
     }
 }
+
+/// The location of the database of a pool, to ATTACH it to a connection of another pool
+pub fn attach_uri(pool: &SqlitePool) -> String {
+    let options = pool.connect_options();
+    let filename = options.get_filename().to_string_lossy().to_string();
+    match filename.starts_with(IN_MEMORY_DB_PREFIX) {
+        true => format!("file:{}?vfs=memdb", filename),
+        false => filename,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    #[test(tokio::test)]
+    async fn attach_in_memory() {
+        let pool1 = get_pool(None);
+        let pool2 = get_pool(None);
+        sqlx::query("CREATE TABLE foo (bar INTEGER);").execute(&pool2).await.unwrap();
+        sqlx::query("INSERT INTO foo (bar) VALUES (7);").execute(&pool2).await.unwrap();
+
+        let mut conn = pool1.acquire().await.unwrap();
+        sqlx::query("ATTACH DATABASE $1 AS other;").bind(attach_uri(&pool2)).execute(&mut *conn).await.unwrap();
+        sqlx::query("UPDATE other.foo SET bar = 8;").execute(&mut *conn).await.unwrap();
+        sqlx::query("DETACH DATABASE other;").execute(&mut *conn).await.unwrap();
+        drop(conn);
+
+        let (bar,): (i64,) = sqlx::query_as("SELECT bar FROM foo;").fetch_one(&pool2).await.unwrap();
+        assert_eq!(bar, 8);
+    }
+}
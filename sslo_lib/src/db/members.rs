@@ -0,0 +1,827 @@
+pub mod users;
+pub mod cookie_logins;
+pub mod steam_accounts;
+#[cfg(feature = "members_discord")]
+pub mod discord_accounts;
+#[cfg(feature = "members_email")]
+pub mod email_accounts;
+#[cfg(feature = "members_webauthn")]
+pub mod webauthn_credentials;
+
+use std::path::Path;
+use std::sync::Arc;
+use sqlx::{SqliteConnection, SqlitePool};
+use tokio::sync::RwLock;
+use crate::db::{TableData, TableInterface};
+use crate::error::SsloError;
+use users::{UserItem, UserTable};
+use cookie_logins::{CookieLoginItem, CookieLoginTable};
+#[cfg(feature = "members_email")]
+use email_accounts::{EmailAccountItem, EmailAccountsTable};
+use steam_accounts::{SteamAccountItem, SteamAccountsTable};
+#[cfg(feature = "members_discord")]
+use discord_accounts::{DiscordAccountItem, DiscordAccountsTable};
+#[cfg(feature = "members_webauthn")]
+use webauthn_credentials::{WebauthnCredentialItem, WebauthnCredentialsTable};
+
+/// The members database (shared by league and lobby)
+pub struct MembersDbData {
+    pool: SqlitePool,
+    tbl_users: Arc<RwLock<TableData<UserItem>>>,
+    tbl_cookie_logins: Arc<RwLock<TableData<CookieLoginItem>>>,
+    tbl_steam_accounts: Arc<RwLock<TableData<SteamAccountItem>>>,
+    #[cfg(feature = "members_discord")]
+    tbl_discord_accounts: Arc<RwLock<TableData<DiscordAccountItem>>>,
+    #[cfg(feature = "members_email")]
+    tbl_email_accounts: Arc<RwLock<TableData<EmailAccountItem>>>,
+    #[cfg(feature = "members_webauthn")]
+    tbl_webauthn_credentials: Arc<RwLock<TableData<WebauthnCredentialItem>>>,
+}
+
+impl MembersDbData {
+    /// When db_path is None, the pool is generated in memory
+    pub async fn new(db_path: Option<&Path>) -> Result<Arc<RwLock<Self>>, SsloError> {
+
+        // set up db_obsolete
+        let pool = crate::db::get_pool(db_path);
+        Self::migrate(&pool).await?;
+
+        // create data object
+        Ok(Arc::new_cyclic(|me| {
+            RwLock::new(Self {
+                pool: pool.clone(),
+                tbl_users: TableData::new(pool.clone(), me.clone()),
+                tbl_cookie_logins: TableData::new(pool.clone(), me.clone()),
+                tbl_steam_accounts: TableData::new(pool.clone(), me.clone()),
+                #[cfg(feature = "members_discord")]
+                tbl_discord_accounts: TableData::new(pool.clone(), me.clone()),
+                #[cfg(feature = "members_email")]
+                tbl_email_accounts: TableData::new(pool.clone(), me.clone()),
+                #[cfg(feature = "members_webauthn")]
+                tbl_webauthn_credentials: TableData::new(pool.clone(), me.clone()),
+            })
+        }))
+    }
+
+    async fn migrate(pool: &SqlitePool) -> Result<(), SsloError> {
+        Self::convert_lobby_database(pool).await?;
+        sqlx::migrate!("../rsc/db_migrations/members").run(pool).await?;
+        Ok(())
+    }
+
+    /// Convert a database that was created by the former lobby migrations (rsc/db_migrations/lobby_members)
+    /// The lobby schema is a subset of the initial members schema,
+    /// so the missing columns and tables are added and the migration history is replaced by the initial members migration.
+    async fn convert_lobby_database(pool: &SqlitePool) -> Result<(), SsloError> {
+        let lobby_migrator = sqlx::migrate!("../rsc/db_migrations/lobby_members");
+        let members_migrator = sqlx::migrate!("../rsc/db_migrations/members");
+        let lobby_initial = lobby_migrator.iter().next().ok_or(SsloError::GeneralError("missing lobby migration".to_string()))?;
+        let members_initial = members_migrator.iter().next().ok_or(SsloError::GeneralError("missing members migration".to_string()))?;
+
+        // check if this is a lobby database
+        let (history_exists,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations';")
+            .fetch_one(pool).await?;
+        if history_exists == 0 {
+            return Ok(());
+        }
+        let history: Vec<(i64, Vec<u8>)> = sqlx::query_as("SELECT version, checksum FROM _sqlx_migrations ORDER BY version;")
+            .fetch_all(pool).await?;
+        if history != vec![(lobby_initial.version, lobby_initial.checksum.to_vec())] {
+            return Ok(());
+        }
+
+        log::warn!("converting lobby members database");
+        let mut tx = pool.begin().await?;
+        for query in [
+            "ALTER TABLE users ADD COLUMN promotion_level INTEGER NOT NULL DEFAULT 0;",
+            "ALTER TABLE users ADD COLUMN promotion_authority INTEGER NOT NULL DEFAULT 0;",
+            "ALTER TABLE users ADD COLUMN last_lap TEXT;",
+            "ALTER TABLE users ADD COLUMN last_login TEXT;",
+            "ALTER TABLE users ADD COLUMN password BLOB;",
+            "ALTER TABLE users ADD COLUMN password_last_usage TEXT;",
+            "ALTER TABLE users ADD COLUMN password_last_useragent BLOB;",
+            "CREATE TABLE email_accounts (
+                rowid INTEGER PRIMARY KEY,
+                user INTEGER,
+                email TEXT UNIQUE,
+                verified_since TEXT,
+                token BLOB UNIQUE,
+                token_user INTEGER,
+                token_creation TEXT,
+                token_consumption TEXT,
+                FOREIGN KEY(user) REFERENCES users(rowid),
+                FOREIGN KEY(token_user) REFERENCES users(rowid)
+            );",
+            "DELETE FROM _sqlx_migrations;",
+        ] {
+            sqlx::query(query).execute(&mut *tx).await?;
+        }
+        sqlx::query("INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) VALUES ($1, $2, TRUE, $3, 0);")
+            .bind(members_initial.version)
+            .bind(members_initial.description.as_ref())
+            .bind(members_initial.checksum.as_ref())
+            .execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// SQL expression that counts the login methods of the user with the id $1
+/// This allows to remove a login method with a single conditional query, so that the last login method is never removed.
+/// Only login methods of enabled features are counted.
+fn sql_count_login_methods() -> String {
+    let terms = [
+        (true, "(SELECT COUNT(*) FROM users WHERE rowid = $1 AND password IS NOT NULL)"),
+        (cfg!(feature = "members_email"), "(SELECT COUNT(*) FROM email_accounts WHERE user = $1 AND verified_since IS NOT NULL)"),
+        (true, "(SELECT COUNT(*) FROM steam_accounts WHERE user = $1)"),
+        (cfg!(feature = "members_discord"), "(SELECT COUNT(*) FROM discord_accounts WHERE user = $1)"),
+        (cfg!(feature = "members_webauthn"), "(SELECT COUNT(*) FROM webauthn_credentials WHERE user = $1)"),
+    ];
+    let terms: Vec<&str> = terms.into_iter().filter(|(enabled, _)| *enabled).map(|(_, term)| term).collect();
+    format!("({})", terms.join(" + "))
+}
+
+pub struct MembersDbInterface(Arc<RwLock<MembersDbData>>);
+
+impl MembersDbInterface {
+
+    pub fn new(data: Arc<RwLock<MembersDbData>>) -> Self {
+        Self(data)
+    }
+
+    pub async fn tbl_users(&self) -> UserTable {
+        let data = self.0.read().await;
+        UserTable::new(TableInterface::new(data.tbl_users.clone()))
+    }
+
+    pub async fn tbl_cookie_logins(&self) -> CookieLoginTable {
+        let data = self.0.read().await;
+        CookieLoginTable::new(TableInterface::new(data.tbl_cookie_logins.clone()))
+    }
+
+    pub async fn tbl_steam_accounts(&self) -> SteamAccountsTable {
+        let data = self.0.read().await;
+        SteamAccountsTable::new(TableInterface::new(data.tbl_steam_accounts.clone()))
+    }
+
+    #[cfg(feature = "members_discord")]
+    pub async fn tbl_discord_accounts(&self) -> DiscordAccountsTable {
+        let data = self.0.read().await;
+        DiscordAccountsTable::new(TableInterface::new(data.tbl_discord_accounts.clone()))
+    }
+
+    #[cfg(feature = "members_email")]
+    pub async fn tbl_email_accounts(&self) -> EmailAccountsTable {
+        let data = self.0.read().await;
+        EmailAccountsTable::new(TableInterface::new(data.tbl_email_accounts.clone()))
+    }
+
+    #[cfg(feature = "members_webauthn")]
+    pub async fn tbl_webauthn_credentials(&self) -> WebauthnCredentialsTable {
+        let data = self.0.read().await;
+        WebauthnCredentialsTable::new(TableInterface::new(data.tbl_webauthn_credentials.clone()))
+    }
+
+    /// Count the methods a user can login with
+    /// These are a password, verified email accounts, steam accounts, discord accounts and passkeys (as far as the features are enabled).
+    pub async fn count_login_methods(&self, user: &UserItem) -> Result<i64, SsloError> {
+        let data = self.0.read().await;
+        let (count,): (i64,) = sqlx::query_as(&format!("SELECT {};", sql_count_login_methods()))
+            .bind(user.id().await)
+            .fetch_one(&data.pool)
+            .await?;
+        Ok(count)
+    }
+
+    /// The connection pool of the members database
+    pub async fn pool(&self) -> SqlitePool {
+        self.0.read().await.pool.clone()
+    }
+
+    /// Merge a user into another user within a transaction of the members database
+    /// All login methods and login cookies are moved to the target user, then the merged user is deleted.
+    /// This allows to merge tables of other (attached) databases within the same transaction.
+    /// The target user keeps its own promotion, the promotion (and its history) of the merged user is dropped.
+    /// The cached items must be reloaded after the transaction is committed.
+    pub async fn merge_users_within(&self, tx: &mut SqliteConnection, from: &UserItem, into: &UserItem) -> Result<(), SsloError> {
+        let from_id = from.id().await;
+        let into_id = into.id().await;
+        if from_id == into_id {
+            return Err(SsloError::GeneralError(format!("cannot merge {} into itself", from.display().await)));
+        }
+
+        for query in [
+            "UPDATE cookie_logins SET user = $2, totp_verified = NULL WHERE user = $1;",
+            "UPDATE steam_accounts SET user = $2 WHERE user = $1;",
+            "UPDATE discord_accounts SET user = $2 WHERE user = $1;",
+            "UPDATE email_accounts SET user = $2 WHERE user = $1;",
+            "UPDATE email_accounts SET token_user = $2 WHERE token_user = $1;",
+            "UPDATE webauthn_credentials SET user = $2 WHERE user = $1;",
+            "DELETE FROM promotion_history WHERE user = $1;",
+            "UPDATE promotion_history SET granted_by = $2 WHERE granted_by = $1;",
+            "DELETE FROM webauthn_challenges WHERE user = $1;",
+            "DELETE FROM totp_recovery_codes WHERE user = $1;",
+            "DELETE FROM users WHERE rowid = $1;",
+        ] {
+            sqlx::query(query).bind(from_id).bind(into_id).execute(&mut *tx).await?;
+        }
+        Ok(())
+    }
+
+    /// Reload all cached items from the database (after changes that bypassed the items)
+    pub async fn reload_caches(&self) -> Result<(), SsloError> {
+        let data = self.0.read().await;
+        TableInterface::new(data.tbl_users.clone()).reload_cache().await?;
+        TableInterface::new(data.tbl_cookie_logins.clone()).reload_cache().await?;
+        TableInterface::new(data.tbl_steam_accounts.clone()).reload_cache().await?;
+        #[cfg(feature = "members_discord")]
+        TableInterface::new(data.tbl_discord_accounts.clone()).reload_cache().await?;
+        #[cfg(feature = "members_email")]
+        TableInterface::new(data.tbl_email_accounts.clone()).reload_cache().await?;
+        #[cfg(feature = "members_webauthn")]
+        TableInterface::new(data.tbl_webauthn_credentials.clone()).reload_cache().await?;
+        Ok(())
+    }
+
+    /// Remove all personal data of a user
+    /// All login methods and login cookies are deleted, and the user is renamed to an anonymous name.
+    /// The user itself is kept, so that results of this user stay intact.
+    pub async fn anonymize_user(&self, user: &UserItem) -> Result<(), SsloError> {
+        let user_id = user.id().await;
+        let name = users::anonymous_name(user_id);
+
+        let data = self.0.read().await;
+        let mut tx = data.pool.begin().await?;
+        for query in [
+            "DELETE FROM cookie_logins WHERE user = $1;",
+            "DELETE FROM steam_accounts WHERE user = $1;",
+            "DELETE FROM discord_accounts WHERE user = $1;",
+            "DELETE FROM email_accounts WHERE user = $1;",
+            "UPDATE email_accounts SET token_user = NULL WHERE token_user = $1;",
+            "DELETE FROM webauthn_credentials WHERE user = $1;",
+            "DELETE FROM webauthn_challenges WHERE user = $1;",
+            "DELETE FROM totp_recovery_codes WHERE user = $1;",
+            "UPDATE users SET name = $2, promotion_level = 0, promotion_authority = 0, last_login = NULL, \
+                password = NULL, password_last_usage = NULL, password_last_useragent = NULL, \
+                totp_secret = NULL, totp_enabled = NULL, totp_last_step = NULL, \
+                merge_token = NULL, merge_token_creation = NULL WHERE rowid = $1;",
+        ] {
+            sqlx::query(query).bind(user_id).bind(&name).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        drop(data);
+        self.reload_caches().await?;
+
+        log::info!("anonymized user id={}", user_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    async fn get_db() -> MembersDbInterface {
+        let data = MembersDbData::new(None).await.unwrap();
+        MembersDbInterface::new(data)
+    }
+
+    #[test(tokio::test)]
+    async fn convert_lobby_database() {
+        let pool = crate::db::get_pool(None);
+        sqlx::migrate!("../rsc/db_migrations/lobby_members").run(&pool).await.unwrap();
+        sqlx::query("INSERT INTO users (name) VALUES ('Lobby User');").execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO steam_accounts (steam_id, user) VALUES ('SomeSteamId', 1);").execute(&pool).await.unwrap();
+
+        // all members migrations are applied on top of the converted database
+        MembersDbData::migrate(&pool).await.unwrap();
+        MembersDbData::migrate(&pool).await.unwrap();
+        let (name, level): (String, i64) = sqlx::query_as("SELECT name, promotion_level FROM users WHERE rowid = 1;")
+            .fetch_one(&pool).await.unwrap();
+        assert_eq!(name, "Lobby User");
+        assert_eq!(level, 0);
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM steam_accounts WHERE user = 1;")
+            .fetch_one(&pool).await.unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test(tokio::test)]
+    async fn create_new() {
+        let data = MembersDbData::new(None).await.unwrap();
+        let db = MembersDbInterface::new(data);
+        let _ = db.tbl_users().await;
+    }
+
+    mod cookie_logins {
+        use chrono::Utc;
+        use test_log::test;
+
+        #[test(tokio::test)]
+        async fn create_new_cookie() {
+            let db = super::get_db().await;
+            let user = db.tbl_users().await.create_new_user().await.unwrap();
+            let tbl = db.tbl_cookie_logins().await;
+            let cookie = tbl.create_new_cookie(&user).await.unwrap();
+            assert_eq!(cookie.id().await, 1);
+            assert!(cookie.get_cookie().await.is_some());
+            assert!(cookie.get_cookie().await.is_none());
+
+            // retrieve from cache
+            let cookie = tbl.item_by_id(1).await.unwrap();
+            assert_eq!(cookie.id().await, 1);
+            assert!(cookie.get_cookie().await.is_none());
+        }
+
+        #[test(tokio::test)]
+        async fn cookie_flow() {
+            let now = Utc::now();
+            let db = super::get_db().await;
+            let tbl = db.tbl_cookie_logins().await;
+
+            // create new cookie
+            let user = db.tbl_users().await.create_new_user().await.unwrap();
+            assert_eq!(user.id().await, 1);
+            let item = tbl.create_new_cookie(&user).await.unwrap();
+            let cookie = item.get_cookie().await.unwrap();
+
+            // try validate cookie
+            let item = tbl.item_by_cookie("unit test".to_string(), &cookie).await.unwrap();
+            assert_eq!(item.user().await.unwrap().id().await, 1);
+            assert!(item.last_usage().await.unwrap() >= now);
+            assert_eq!(item.last_useragent().await.unwrap(), "unit test".to_string());
+        }
+
+        #[test(tokio::test)]
+        async fn delete_user_cookies() {
+            let db = super::get_db().await;
+            let user1 = db.tbl_users().await.create_new_user().await.unwrap();
+            let user2 = db.tbl_users().await.create_new_user().await.unwrap();
+            let tbl = db.tbl_cookie_logins().await;
+            let cookie = tbl.create_new_cookie(&user1).await.unwrap();
+            tbl.create_new_cookie(&user1).await.unwrap();
+            tbl.create_new_cookie(&user2).await.unwrap();
+            assert_eq!(tbl.items_by_user(&user1).await.len(), 2);
+
+            // delete only cookies of first user
+            assert_eq!(tbl.delete_user_cookies(&user1).await, 2);
+            assert_eq!(tbl.items_by_user(&user1).await.len(), 0);
+            assert_eq!(tbl.items_by_user(&user2).await.len(), 1);
+            assert!(tbl.item_by_id(cookie.id().await).await.is_none());
+        }
+
+        #[test(tokio::test)]
+        async fn delete_other_user_cookies() {
+            let db = super::get_db().await;
+            let user1 = db.tbl_users().await.create_new_user().await.unwrap();
+            let user2 = db.tbl_users().await.create_new_user().await.unwrap();
+            let tbl = db.tbl_cookie_logins().await;
+            let keep = tbl.create_new_cookie(&user1).await.unwrap();
+            for _ in 0..120 {  // more than items_by_user() returns at once
+                tbl.create_new_cookie(&user1).await.unwrap();
+            }
+            tbl.create_new_cookie(&user2).await.unwrap();
+
+            // only the kept cookie of the first user survives
+            assert_eq!(tbl.delete_other_user_cookies(&user1, &keep).await, 120);
+            let remaining = tbl.items_by_user(&user1).await;
+            assert_eq!(remaining.len(), 1);
+            assert_eq!(remaining[0].id().await, keep.id().await);
+            assert_eq!(tbl.items_by_user(&user2).await.len(), 1);
+        }
+
+        #[test(tokio::test)]
+        async fn delete_unused_cookies() {
+            let db = super::get_db().await;
+            let user = db.tbl_users().await.create_new_user().await.unwrap();
+            let tbl = db.tbl_cookie_logins().await;
+            let item_unused = tbl.create_new_cookie(&user).await.unwrap();
+            let item_used = tbl.create_new_cookie(&user).await.unwrap();
+            let cookie = item_used.get_cookie().await.unwrap();
+            let threshold = Utc::now();
+
+            // nothing is deleted before the threshold
+            assert_eq!(tbl.delete_unused_cookies(threshold - chrono::Duration::days(1)).await.unwrap(), 0);
+
+            // only the cookie that was not used after the threshold is deleted
+            tbl.item_by_cookie("unit test".to_string(), &cookie).await.unwrap();
+            assert_eq!(tbl.delete_unused_cookies(threshold).await.unwrap(), 1);
+            assert!(tbl.item_by_id(item_unused.id().await).await.is_none());
+            assert!(tbl.item_by_id(item_used.id().await).await.is_some());
+        }
+
+        #[test(tokio::test)]
+        async fn item_from_last_usage() {
+            let db = super::get_db().await;
+            let tbl = db.tbl_cookie_logins().await;
+            let user = db.tbl_users().await.create_new_user().await.unwrap();
+
+            // create first login cookie
+            let item1 = tbl.create_new_cookie(&user).await.unwrap();
+            let cookie1 = item1.get_cookie().await.unwrap();
+
+            // create second login cookie
+            let item2 = tbl.create_new_cookie(&user).await.unwrap();
+            let cookie2 = item2.get_cookie().await.unwrap();
+
+            // use cookie 2 and then, later cookie 1
+            tbl.item_by_cookie("unit test".to_string(), &cookie2).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(1100)).await;  // wait a second
+            tbl.item_by_cookie("unit test".to_string(), &cookie1).await.unwrap();
+
+            // check that cookie1 is the latest
+            let item = tbl.item_from_latest_usage(&user).await.unwrap();
+            assert_eq!(item.id().await, item1.id().await);
+
+            // use cookie 2 again and check for latest
+            tokio::time::sleep(std::time::Duration::from_millis(1100)).await;  // wait a second
+            tbl.item_by_cookie("unit test".to_string(), &cookie2).await.unwrap();
+            let item = tbl.item_from_latest_usage(&user).await.unwrap();
+            assert_eq!(item.id().await, item2.id().await);
+        }
+    }
+
+    #[cfg(feature = "members_email")]
+    mod email_accounts {
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn items_by_user() {
+            let db = get_db().await;
+            let tbl_eml = db.tbl_email_accounts().await;
+
+            // create user
+            let usr = db.tbl_users().await.create_new_user().await.unwrap();
+            assert_eq!(usr.id().await, 1);
+
+            // creates email accounts
+            let eml = tbl_eml.create_account("a.b@c.de".to_string()).await.unwrap();
+            let token = eml.create_token(Some(&usr)).await.unwrap();
+            assert!(eml.consume_token(token).await);
+            let eml = tbl_eml.create_account("foo.bar@c.de".to_string()).await.unwrap();
+            let token = eml.create_token(Some(&usr)).await.unwrap();
+            assert!(eml.consume_token(token).await);
+            tbl_eml.create_account("not.associated@elsewhere.net".to_string()).await.unwrap();
+            let eml = tbl_eml.create_account("foo.baz@c.de".to_string()).await.unwrap();
+            let token = eml.create_token(Some(&usr)).await.unwrap();
+            assert!(eml.consume_token(token).await);
+
+            // check associated accounts
+            let items = tbl_eml.items_by_user(&usr).await;
+            assert_eq!(items.len(), 3);
+        }
+    }
+
+    mod steam_accounts {
+        use chrono::Utc;
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn login_procedure() {
+            let db = get_db().await;
+            let tbl_usr = db.tbl_users().await;
+            let tbl_stm = db.tbl_steam_accounts().await;
+
+            // receive a new steam login
+            let steam_id = "SteamId1";
+
+            // get steam account
+            let steam_account = tbl_stm.item_by_steam_id(steam_id, true).await.unwrap();
+            steam_account.set_last_login(Utc::now()).await.unwrap();
+
+            // get user
+            let user = match steam_account.user().await {
+                Some(user) => user,
+                None => tbl_usr.create_new_user().await.unwrap()
+            };
+
+            // check identical user at new login
+            let steam_account = tbl_stm.item_by_steam_id(steam_id, false).await.unwrap();
+            let user2 = steam_account.user().await.unwrap();
+            assert_eq!(user.id().await, user2.id().await);
+        }
+
+        #[test(tokio::test)]
+        async fn unset_user() {
+            let db = get_db().await;
+            let tbl_stm = db.tbl_steam_accounts().await;
+
+            // a user with two steam accounts
+            let steam_account1 = tbl_stm.item_by_steam_id("SteamId1", true).await.unwrap();
+            let mut user = steam_account1.user().await.unwrap();
+            let steam_account2 = tbl_stm.item_by_steam_id("SteamId2", true).await.unwrap();
+            steam_account2.set_user(&user).await.unwrap();
+            assert_eq!(db.count_login_methods(&user).await.unwrap(), 2);
+
+            // detach one steam account
+            assert!(steam_account1.unset_user().await.unwrap());
+            assert!(!steam_account1.has_user().await);
+            assert_eq!(tbl_stm.items_by_user(&user).await.len(), 1);
+            assert_eq!(db.count_login_methods(&user).await.unwrap(), 1);
+
+            // the last login method cannot be detached
+            assert!(!steam_account2.unset_user().await.unwrap());
+            assert!(steam_account2.has_user().await);
+            assert_eq!(db.count_login_methods(&user).await.unwrap(), 1);
+
+            // a password is a login method
+            assert!(user.set_password(Some("secret".to_string())).await);
+            assert_eq!(db.count_login_methods(&user).await.unwrap(), 2);
+        }
+
+        #[test(tokio::test)]
+        async fn unset_user_concurrently() {
+            let db = get_db().await;
+            let tbl_stm = db.tbl_steam_accounts().await;
+            let steam_account1 = tbl_stm.item_by_steam_id("SteamId1", true).await.unwrap();
+            let user = steam_account1.user().await.unwrap();
+            let steam_account2 = tbl_stm.item_by_steam_id("SteamId2", true).await.unwrap();
+            steam_account2.set_user(&user).await.unwrap();
+
+            // only one of two concurrent requests can detach
+            let (res1, res2) = tokio::join!(steam_account1.unset_user(), steam_account2.unset_user());
+            assert!(res1.unwrap() ^ res2.unwrap());
+            assert_eq!(db.count_login_methods(&user).await.unwrap(), 1);
+        }
+    }
+
+    mod merge_users {
+        use test_log::test;
+        use super::*;
+
+        /// Merge within the members database only
+        async fn merge_users(db: &MembersDbInterface, from: &UserItem, into: &UserItem) -> Result<(), SsloError> {
+            let pool = db.pool().await;
+            let mut tx = pool.begin().await?;
+            db.merge_users_within(&mut tx, from, into).await?;
+            tx.commit().await?;
+            db.reload_caches().await
+        }
+
+        #[test(tokio::test)]
+        async fn merge_token() {
+            let db = get_db().await;
+            let user = db.tbl_users().await.create_new_user().await.unwrap();
+            assert!(!user.consume_merge_token("0123").await);
+
+            let token = user.create_merge_token().await.unwrap();
+            assert!(!user.consume_merge_token("0123").await);  // a failed attempt invalidates the token
+            assert!(!user.consume_merge_token(&token).await);
+
+            let token = user.create_merge_token().await.unwrap();
+            assert!(user.consume_merge_token(&token).await);
+            assert!(!user.consume_merge_token(&token).await);  // only once
+        }
+
+        #[test(tokio::test)]
+        async fn merge() {
+            let db = get_db().await;
+            let tbl_usr = db.tbl_users().await;
+            let tbl_steam = db.tbl_steam_accounts().await;
+            let tbl_cookie = db.tbl_cookie_logins().await;
+
+            // a user created from steam and another user
+            let steam_account = tbl_steam.item_by_steam_id("SomeSteamId", true).await.unwrap();
+            let from = steam_account.user().await.unwrap();
+            let into = tbl_usr.create_new_user().await.unwrap();
+            #[cfg(feature = "members_discord")]
+            let discord_account = {
+                let discord_account = db.tbl_discord_accounts().await.item_by_discord_id("SomeDiscordId", true).await.unwrap();
+                discord_account.set_user(&from).await.unwrap();
+                discord_account
+            };
+            let cookie = tbl_cookie.create_new_cookie(&from).await.unwrap();
+            let from_id = from.id().await;
+            let into_id = into.id().await;
+
+            assert!(merge_users(&db, &into, &into).await.is_err());
+            merge_users(&db, &from, &into).await.unwrap();
+
+            // cached items are updated
+            assert_eq!(steam_account.user().await.unwrap().id().await, into_id);
+            #[cfg(feature = "members_discord")]
+            assert_eq!(discord_account.user().await.unwrap().id().await, into_id);
+            assert_eq!(cookie.user().await.unwrap().id().await, into_id);
+            assert_eq!(tbl_steam.items_by_user(&into).await.len(), 1);
+            assert_eq!(tbl_cookie.items_by_user(&into).await.len(), 1);
+
+            // merged user is gone
+            assert!(tbl_usr.user_by_id(from_id).await.is_none());
+            assert!(tbl_usr.user_by_id(into_id).await.is_some());
+        }
+
+        #[test(tokio::test)]
+        async fn merge_keeps_target_promotion() {
+            use users::{Promotion, PromotionAuthority, PromotionLevel};
+            let db = get_db().await;
+            let tbl_usr = db.tbl_users().await;
+            let mut chief = tbl_usr.create_new_user().await.unwrap();
+            let mut into = tbl_usr.create_new_user().await.unwrap();
+            chief.grant_promotion(Promotion::new(PromotionLevel::Admin, PromotionAuthority::Chief), None).await.unwrap();
+            into.grant_promotion(Promotion::new(PromotionLevel::Admin, PromotionAuthority::Executing), None).await.unwrap();
+
+            // merging a higher promoted user does not escalate the target user
+            merge_users(&db, &chief, &into).await.unwrap();
+            assert_eq!(into.promotion().await, Promotion::new(PromotionLevel::Admin, PromotionAuthority::Executing));
+            let history = into.promotion_history().await;
+            assert_eq!(history.len(), 1);
+            assert_eq!(history[0].promotion_authority, PromotionAuthority::Executing);
+
+            // merging a lower promoted user does not downgrade the target user
+            let from = tbl_usr.create_new_user().await.unwrap();
+            merge_users(&db, &from, &into).await.unwrap();
+            assert_eq!(into.promotion().await, Promotion::new(PromotionLevel::Admin, PromotionAuthority::Executing));
+        }
+    }
+
+    mod anonymize_user {
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn anonymize() {
+            let db = get_db().await;
+            let tbl_usr = db.tbl_users().await;
+            let tbl_steam = db.tbl_steam_accounts().await;
+            let tbl_cookie = db.tbl_cookie_logins().await;
+
+            // a user with steam login, password and cookie
+            let steam_account = tbl_steam.item_by_steam_id("SomeSteamId", true).await.unwrap();
+            let mut user = steam_account.user().await.unwrap();
+            user.set_name("Jane Doe".to_string()).await.unwrap();
+            assert!(user.set_password(Some("secret".to_string())).await);
+            tbl_cookie.create_new_cookie(&user).await.unwrap();
+            let other = tbl_usr.create_new_user().await.unwrap();
+            tbl_cookie.create_new_cookie(&other).await.unwrap();
+            assert_eq!(db.count_login_methods(&user).await.unwrap(), 2);
+
+            db.anonymize_user(&user).await.unwrap();
+
+            // the user is kept, but without personal data
+            let user_id = user.id().await;
+            assert_eq!(user.name().await, users::anonymous_name(user_id));
+            assert!(tbl_usr.user_by_id(user_id).await.is_some());
+            assert_eq!(db.count_login_methods(&user).await.unwrap(), 0);
+            assert!(tbl_steam.item_by_steam_id("SomeSteamId", false).await.is_none());
+            assert_eq!(tbl_cookie.items_by_user(&user).await.len(), 0);
+
+            // other users are not affected
+            assert_eq!(tbl_cookie.items_by_user(&other).await.len(), 1);
+        }
+    }
+
+    #[cfg(feature = "members_discord")]
+    mod discord_accounts {
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn login_procedure() {
+            let db = get_db().await;
+            let tbl_dsc = db.tbl_discord_accounts().await;
+
+            // first login creates a user named like the Discord handle
+            let account = tbl_dsc.item_by_discord_id("80351110224678912", true).await.unwrap();
+            account.set_username("nelly".to_string()).await.unwrap();
+            assert!(!account.has_user().await);
+            let user = account.user().await.unwrap();
+            assert_eq!(user.name().await, "nelly");
+
+            // identical user at next login
+            let account = tbl_dsc.item_by_discord_id("80351110224678912", false).await.unwrap();
+            assert_eq!(account.user().await.unwrap().id().await, user.id().await);
+
+            // assign to another user
+            let other_user = db.tbl_users().await.create_new_user().await.unwrap();
+            account.set_user(&other_user).await.unwrap();
+            assert_eq!(tbl_dsc.items_by_user(&other_user).await.len(), 1);
+            assert_eq!(tbl_dsc.items_by_user(&user).await.len(), 0);
+        }
+    }
+
+    #[cfg(feature = "members_webauthn")]
+    mod webauthn_credentials {
+        use test_log::test;
+        use crate::webauthn::RelyingParty;
+        use crate::webauthn::software_authenticator::SoftwareAuthenticator;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn register_and_login() {
+            let db = get_db().await;
+            let tbl = db.tbl_webauthn_credentials().await;
+            let user = db.tbl_users().await.create_new_user().await.unwrap();
+            let other_user = db.tbl_users().await.create_new_user().await.unwrap();
+            let rp = RelyingParty::new("league.tld");
+            let mut authenticator = SoftwareAuthenticator::new();
+
+            // registration challenges are bound to the user
+            let challenge = tbl.create_challenge(Some(&user)).await.unwrap();
+            let (client_data, attestation) = authenticator.register(&rp, &challenge);
+            assert!(tbl.register_credential(&rp, &other_user, "Key".to_string(), &client_data, &attestation).await.is_err());
+            let challenge = tbl.create_challenge(Some(&user)).await.unwrap();
+            let (client_data, attestation) = authenticator.register(&rp, &challenge);
+            let item = tbl.register_credential(&rp, &user, "My Key".to_string(), &client_data, &attestation).await.unwrap();
+            assert_eq!(item.name().await, "My Key");
+            assert_eq!(item.credential_id().await, authenticator.credential_id_base64());
+            assert!(item.last_usage().await.raw().is_none());
+
+            // challenges cannot be used twice
+            assert!(tbl.register_credential(&rp, &user, "Key".to_string(), &client_data, &attestation).await.is_err());
+            assert_eq!(tbl.items_by_user(&user).await.len(), 1);
+            assert_eq!(tbl.items_by_user(&other_user).await.len(), 0);
+
+            // login
+            let challenge = tbl.create_challenge(None).await.unwrap();
+            let (client_data, auth_data, signature) = authenticator.sign(&rp, &challenge);
+            let login_user = tbl.authenticate(&rp, &authenticator.credential_id_base64(), &client_data, &auth_data, &signature).await.unwrap();
+            assert_eq!(login_user.id().await, user.id().await);
+            assert!(item.last_usage().await.raw().is_some());
+
+            // replayed login
+            assert!(tbl.authenticate(&rp, &authenticator.credential_id_base64(), &client_data, &auth_data, &signature).await.is_none());
+
+            // registration challenges cannot be used for login
+            let challenge = tbl.create_challenge(Some(&user)).await.unwrap();
+            let (client_data, auth_data, signature) = authenticator.sign(&rp, &challenge);
+            assert!(tbl.authenticate(&rp, &authenticator.credential_id_base64(), &client_data, &auth_data, &signature).await.is_none());
+
+            // cloned authenticator (sign count not increasing)
+            authenticator.sign_count = 0;  // the denied logins above did not change the stored sign count
+            let challenge = tbl.create_challenge(None).await.unwrap();
+            let (client_data, auth_data, signature) = authenticator.sign(&rp, &challenge);
+            assert!(tbl.authenticate(&rp, &authenticator.credential_id_base64(), &client_data, &auth_data, &signature).await.is_none());
+
+            // the last login method cannot be deleted
+            assert!(!tbl.delete_credential(tbl.item_by_id(item.id().await).await.unwrap()).await.unwrap());
+            assert_eq!(tbl.items_by_user(&user).await.len(), 1);
+
+            // delete
+            let mut user = user;
+            assert!(user.set_password(Some("secret".to_string())).await);
+            assert!(tbl.delete_credential(item).await.unwrap());
+            assert_eq!(tbl.items_by_user(&user).await.len(), 0);
+            let challenge = tbl.create_challenge(None).await.unwrap();
+            let (client_data, auth_data, signature) = authenticator.sign(&rp, &challenge);
+            assert!(tbl.authenticate(&rp, &authenticator.credential_id_base64(), &client_data, &auth_data, &signature).await.is_none());
+        }
+
+        async fn register(db: &MembersDbInterface, rp: &RelyingParty, authenticator: &SoftwareAuthenticator) -> WebauthnCredentialItem {
+            let tbl = db.tbl_webauthn_credentials().await;
+            let user = db.tbl_users().await.create_new_user().await.unwrap();
+            let challenge = tbl.create_challenge(Some(&user)).await.unwrap();
+            let (client_data, attestation) = authenticator.register(rp, &challenge);
+            tbl.register_credential(rp, &user, "My Key".to_string(), &client_data, &attestation).await.unwrap()
+        }
+
+        #[test(tokio::test)]
+        async fn login_replay() {
+            let db = get_db().await;
+            let tbl = db.tbl_webauthn_credentials().await;
+            let rp = RelyingParty::new("league.tld");
+            let mut authenticator = SoftwareAuthenticator::new();
+            let item = register(&db, &rp, &authenticator).await;
+
+            let challenge = tbl.create_challenge(None).await.unwrap();
+            let (client_data, auth_data, signature) = authenticator.sign(&rp, &challenge);
+            assert!(tbl.authenticate(&rp, &authenticator.credential_id_base64(), &client_data, &auth_data, &signature).await.is_some());
+            let last_usage = *item.last_usage().await.raw();
+            assert!(last_usage.is_some());
+
+            // a replayed assertion is denied without touching the stored usage
+            assert!(tbl.authenticate(&rp, &authenticator.credential_id_base64(), &client_data, &auth_data, &signature).await.is_none());
+            assert_eq!(*item.last_usage().await.raw(), last_usage);
+
+            // the authenticator can still log in with a new challenge
+            let challenge = tbl.create_challenge(None).await.unwrap();
+            let (client_data, auth_data, signature) = authenticator.sign(&rp, &challenge);
+            assert!(tbl.authenticate(&rp, &authenticator.credential_id_base64(), &client_data, &auth_data, &signature).await.is_some());
+        }
+
+        #[test(tokio::test)]
+        async fn login_challenge_mismatch() {
+            let db = get_db().await;
+            let tbl = db.tbl_webauthn_credentials().await;
+            let rp = RelyingParty::new("league.tld");
+            let mut authenticator = SoftwareAuthenticator::new();
+            let item = register(&db, &rp, &authenticator).await;
+
+            // a signed challenge that was never issued does not update the credential
+            let issued_challenge = tbl.create_challenge(None).await.unwrap();
+            let (client_data, auth_data, signature) = authenticator.sign(&rp, &crate::webauthn::generate_challenge());
+            assert!(tbl.authenticate(&rp, &authenticator.credential_id_base64(), &client_data, &auth_data, &signature).await.is_none());
+            assert!(item.last_usage().await.raw().is_none());
+
+            // an issued challenge with a signature over other client data is denied and consumed
+            let (_, auth_data, signature) = authenticator.sign(&rp, &crate::webauthn::generate_challenge());
+            let client_data = SoftwareAuthenticator::client_data("webauthn.get", &issued_challenge, &rp.origin);
+            assert!(tbl.authenticate(&rp, &authenticator.credential_id_base64(), &client_data, &auth_data, &signature).await.is_none());
+            assert!(item.last_usage().await.raw().is_none());
+            let (client_data, auth_data, signature) = authenticator.sign(&rp, &issued_challenge);
+            assert!(tbl.authenticate(&rp, &authenticator.credential_id_base64(), &client_data, &auth_data, &signature).await.is_none());
+            assert!(item.last_usage().await.raw().is_none());
+        }
+    }
+}
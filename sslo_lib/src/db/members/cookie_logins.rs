@@ -0,0 +1,403 @@
+use std::sync::Arc;
+use regex::Regex;
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use crate::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use crate::error::SsloError;
+use crate::token::{Token, TokenType};
+use super::{MembersDbData, MembersDbInterface};
+use super::users::UserItem;
+
+/// http header value to unset the login cookie
+pub const UNSET_COOKIE: &str = "cookie_login=\"\"; HttpOnly; Max-Age=-1; SameSite=Strict; Partitioned; Secure; Path=/;";
+
+crate::db_row! {
+    tablename: "cookie_logins",
+    /// Data structure that is used for database interaction (only module internal use)
+    pub struct DbDataRow {
+        user: i64 = 0,
+        token: String = String::new(),
+        creation: DateTime<Utc> = Utc::now(),
+        last_useragent: Option<String> = None,
+        last_usage: Option<DateTime<Utc>> = None,
+        totp_verified: Option<DateTime<Utc>> = None,
+    }
+    display: |row| format!("cookie_logins(id={};user-id={})", row.rowid, row.user),
+}
+
+/// This abstracts data access to shared items
+/// The extra item data holds the decrypted token,
+/// which is only available after new item is created, unset after calling get_cookie()
+pub struct CookieLoginItem(Arc<RwLock<ItemData<DbDataRow, MembersDbData, Option<String>>>>);
+
+impl ItemInterface for CookieLoginItem {
+    type Row = DbDataRow;
+    type Db = MembersDbData;
+    type Extra = Option<String>;
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, MembersDbData, Option<String>>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, MembersDbData, Option<String>>>> {
+        &self.0
+    }
+}
+
+impl CookieLoginItem {
+
+    /// Returns a string, that can be used in log messages
+    pub async fn display(&self) -> String {
+        self.0.read().await.display()
+    }
+
+    pub async fn id(&self) -> i64 { self.0.read().await.row.rowid }
+
+    pub async fn user(&self) -> Option<UserItem> {
+        let data = self.0.read().await;
+        let db_members = match data.db.upgrade() {
+            Some(db_data) => MembersDbInterface::new(db_data),
+            None => {
+                log::error!("cannot upgrade weak pointer for {}", data.row.display());
+                return None;
+            }
+        };
+        db_members.tbl_users().await.user_by_id(data.row.user).await
+    }
+
+    /// returns the cookie which can be directly send as http header
+    /// This only works once, directly after creation of the CookieLogin item
+    pub async fn get_cookie(&self) -> Option<String> {
+        let mut data = self.0.write().await;
+        match data.extra.take() {
+            None => {
+                log::warn!("cannot retrieve decrypted token for {}", data.row.display());
+                None
+            },
+            Some(decrypted_token) => {
+                let cookie = format!("cookie_login={}:{}; HttpOnly; Max-Age=31536000; SameSite=Lax; Partitioned; Secure; Path=/;",
+                                     data.row.rowid, decrypted_token);
+                Some(cookie)
+            },
+        }
+    }
+
+    /// verify that a token from within a cookie is valid (updates last usage)
+    pub async fn verify(&self, token_decrypted: String, user_agent: String) -> bool {
+        let mut data = self.0.write().await;
+
+        // verify token
+        let token = crate::token::Token::new(token_decrypted, data.row.token.clone());
+        if !token.verify() { return false; };
+
+        // update usage
+        data.row.last_usage = Some(Utc::now());
+        data.row.last_useragent = Some(user_agent);
+        return match data.store().await {
+            Ok(_) => true,
+            Err(e) => {
+                log::error!("failed to update usage for CookieLogin {}: {}", data.row.display(), e);
+                false
+            }
+        }
+    }
+
+    pub async fn creation(&self) -> DateTime<Utc> { self.0.read().await.row.creation }
+    pub async fn last_useragent(&self) -> Option<String> { self.0.read().await.row.last_useragent.clone() }
+    pub async fn last_usage(&self) -> Option<DateTime<Utc>> { self.0.read().await.row.last_usage }
+
+    /// When the login was verified with a second factor (TOTP)
+    pub async fn totp_verified(&self) -> Option<DateTime<Utc>> { self.0.read().await.row.totp_verified }
+
+    /// Mark the login as verified with a second factor (TOTP)
+    pub async fn set_totp_verified(&self) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.totp_verified = Some(Utc::now());
+        data.store().await
+    }
+}
+
+pub struct CookieLoginTable(TableInterface<CookieLoginItem>);
+
+impl CookieLoginTable {
+    pub(super) fn new(tbl: TableInterface<CookieLoginItem>) -> Self { Self(tbl) }
+
+    /// Get an item
+    /// First tries loading from cache, then from database
+    pub async fn item_by_id(&self, id: i64) -> Option<CookieLoginItem> {
+        self.0.item_by_id(id).await
+    }
+
+    /// Parsing a cookie header and return an item
+    /// This verifies the token and updates usage info
+    pub async fn item_by_cookie(&self, useragent: String, cookie: &str) -> Option<CookieLoginItem> {
+
+        // quick check
+        if !cookie.contains("cookie_login=") {
+            return None;
+        }
+
+        // chop cookie string
+        let re = Regex::new(r"^cookie_login=([0-9]+):([a-f0-9]+).*$").unwrap();
+        let groups = match re.captures(cookie) {
+            Some(x) => x,
+            None => {
+                log::warn!("Invalid cookie format: '{}'", cookie);
+                return None;
+            }
+        };
+        let cookie_id: i64 = match groups.get(1)?.as_str().parse::<i64>() {
+            Ok(id) => id,
+            Err(_) => return None,
+        };
+        let cookie_token_decrypted: String = groups.get(2)?.as_str().into();
+
+        // find id in database
+        let item = match self.item_by_id(cookie_id).await {
+            None => {
+                log::warn!("no CookieLogin for rowid={} found", cookie_id);
+                return None;
+            }
+            Some(item) => {item}
+        };
+
+        // verify token
+        if !item.verify(cookie_token_decrypted, useragent).await {
+            log::warn!("failed to verify cookie for {}", item.display().await);
+            return None;
+        }
+
+        Some(item)
+    }
+
+    pub async fn create_new_cookie(&self, user: &UserItem) -> Option<CookieLoginItem> {
+        let user_display = user.display().await;
+
+        // create a new token
+        let token = match Token::generate(TokenType::Quick) {
+            Ok(token) => token,
+            Err(e) => {
+                log::error!("Could not generate new token for {}: {}", user_display, e);
+                return None;
+            }
+        };
+
+        // create a new row
+        let mut row = DbDataRow::new(0);
+        row.user = user.id().await;
+        row.creation = Utc::now();
+        row.token = token.encrypted;
+        let item = match self.0.create_item(row).await {
+            Ok(item) => item,
+            Err(e) => {
+                log::error!("failed store new cookie for {}: {}", user_display, e);
+                return None;
+            }
+        };
+
+        // remember decrypted token
+        item.0.write().await.extra = Some(token.decrypted);
+        Some(item)
+    }
+
+    pub async fn item_from_latest_usage(&self, user: &UserItem) -> Option<CookieLoginItem> {
+        let pool = self.0.pool().await;
+        match sqlx::query_as::<sqlx::Sqlite, DbDataRow>("SELECT rowid,* FROM cookie_logins WHERE user = $1 ORDER BY last_usage DESC LIMIT 1;")
+            .bind(user.id().await)
+            .fetch_optional(&pool)
+            .await {
+            Ok(Some(row)) => Some(self.0.item_by_row(row).await),
+            Ok(None) => None,
+            Err(e) => {
+                log::error!("{}", e);
+                None
+            }
+        }
+    }
+
+    /// All login cookies of a user
+    pub async fn items_by_user(&self, user: &UserItem) -> Vec<CookieLoginItem> {
+        self.0.items_by_column("user", user.id().await).await
+    }
+
+    /// Delete all login cookies of a user (logout everywhere), returns the amount of deleted cookies
+    pub async fn delete_user_cookies(&self, user: &UserItem) -> usize {
+        let mut count = 0;
+        loop {
+            let items = self.items_by_user(user).await;
+            if items.is_empty() {
+                break;
+            }
+            for item in items.into_iter() {
+                if let Err(e) = self.0.delete_item(item).await {
+                    log::error!("failed to delete cookie of {}: {}", user.display().await, e);
+                    return count;
+                }
+                count += 1;
+            }
+        }
+        log::info!("deleted {} login cookies of {}", count, user.display().await);
+        count
+    }
+
+    /// Delete all login cookies of a user, except the given one (logout everywhere else)
+    /// Returns the amount of deleted cookies
+    pub async fn delete_other_user_cookies(&self, user: &UserItem, keep: &CookieLoginItem) -> usize {
+        let pool = self.0.pool().await;
+        let rows = sqlx::query_as::<sqlx::Sqlite, DbDataRow>("SELECT rowid,* FROM cookie_logins WHERE user = $1 AND rowid != $2;")
+            .bind(user.id().await)
+            .bind(keep.id().await)
+            .fetch_all(&pool)
+            .await;
+        let rows = match rows {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("failed to find other cookies of {}: {}", user.display().await, e);
+                return 0;
+            }
+        };
+        let mut count = 0;
+        for item in self.0.items_by_rows(rows).await.into_iter() {
+            if let Err(e) = self.0.delete_item(item).await {
+                log::error!("failed to delete cookie of {}: {}", user.display().await, e);
+                break;
+            }
+            count += 1;
+        }
+        log::info!("deleted {} other login cookies of {}", count, user.display().await);
+        count
+    }
+
+    /// Reset the second factor verification of all login cookies of a user (when TOTP is disabled)
+    pub async fn reset_totp_verified(&self, user: &UserItem) -> Result<(), SsloError> {
+        let pool = self.0.pool().await;
+        sqlx::query("UPDATE cookie_logins SET totp_verified = NULL WHERE user = $1;")
+            .bind(user.id().await)
+            .execute(&pool)
+            .await?;
+        self.0.reload_cache().await
+    }
+
+    /// Delete all login cookies that were not used since a certain time (or never used and created before)
+    /// Returns the amount of deleted cookies
+    pub async fn delete_unused_cookies(&self, unused_since: DateTime<Utc>) -> Result<usize, SsloError> {
+        let pool = self.0.pool().await;
+        let rows = sqlx::query_as::<sqlx::Sqlite, DbDataRow>("SELECT rowid,* FROM cookie_logins WHERE COALESCE(last_usage, creation) < $1;")
+            .bind(unused_since)
+            .fetch_all(&pool)
+            .await?;
+        let mut count = 0;
+        for item in self.0.items_by_rows(rows).await.into_iter() {
+            self.0.delete_item(item).await?;
+            count += 1;
+        }
+        if count > 0 {
+            log::info!("deleted {} login cookies that were unused since {}", count, unused_since);
+        }
+        Ok(count)
+    }
+
+    /// returns a http header to unset cookie
+    pub async fn delete_cookie(&self, cookie_login: CookieLoginItem) -> String {
+        // delete item
+        if let Some(user) = cookie_login.user().await {
+            log::info!("logout {}, from {}", user.display().await, cookie_login.display().await);
+        } else {
+            log::warn!("cookie deletion without associated user");
+            log::info!("logout from {}", cookie_login.display().await);
+        }
+        if let Err(e) = self.0.delete_item(cookie_login).await {
+            log::error!("failed to delete cookie: {}", e);
+        }
+        UNSET_COOKIE.to_string()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use sqlx::SqlitePool;
+    use super::*;
+
+    async fn get_pool() -> SqlitePool {
+        let pool = crate::db::get_pool(None);
+        sqlx::migrate!("../rsc/db_migrations/members").run(&pool).await.unwrap();
+        pool
+    }
+
+    mod row {
+        use super::*;
+        use test_log::test;
+
+        #[test(tokio::test)]
+        async fn new_defaults() {
+            let row = DbDataRow::new(33);
+            assert_eq!(row.rowid, 33);
+            assert_eq!(row.user, 0);
+            assert_eq!(row.token, String::new());
+            assert_eq!(row.last_usage, None);
+            assert_eq!(row.last_useragent, None);
+        }
+
+        /// Testing load and store (insert+update)
+        #[test(tokio::test)]
+        async fn load_store_delete() {
+            let pool = get_pool().await;
+
+            // fill db with some dummy data
+            let query = sqlx::query("INSERT INTO users (rowid,name) VALUES (44,'Foo');");
+            query.execute(&pool).await.unwrap();
+            let query = sqlx::query("INSERT INTO users (rowid,name) VALUES (46,'Bar');");
+            query.execute(&pool).await.unwrap();
+
+            // define some UTC times
+            let dt1: DateTime<Utc> = DateTime::parse_from_rfc3339("1001-01-01T01:01:01.1111+01:00").unwrap().into();
+            let dt2: DateTime<Utc> = DateTime::parse_from_rfc3339("2002-02-02T02:02:02.2222+02:00").unwrap().into();
+            let dt3: DateTime<Utc> = DateTime::parse_from_rfc3339("3003-03-03T03:03:03.3333+03:00").unwrap().into();
+
+            // store (insert)
+            let mut row = DbDataRow::new(0);
+            row.user = 44;
+            row.token = "MyInsecureTestToken".to_string();
+            row.creation = dt1;
+            row.last_usage = Some(dt2);
+            row.last_useragent = Some("unit test".to_string());
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.user, 44);
+            assert_eq!(row.token, "MyInsecureTestToken".to_string());
+            assert_eq!(row.creation, dt1);
+            assert_eq!(row.last_usage, Some(dt2));
+            assert_eq!(row.last_useragent, Some("unit test".to_string()));
+
+            // store (update)
+            let mut row = DbDataRow::new(1);
+            row.user = 46;
+            row.token = "MyNewInsecureTestToken".to_string();
+            row.creation = dt2;
+            row.last_usage = Some(dt3);
+            row.last_useragent = Some("new unit test".to_string());
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.user, 46);
+            assert_eq!(row.token, "MyNewInsecureTestToken".to_string());
+            assert_eq!(row.creation, dt2);
+            assert_eq!(row.last_usage, Some(dt3));
+            assert_eq!(row.last_useragent, Some("new unit test".to_string()));
+
+            // delete
+            row.delete(&pool).await.unwrap();
+            assert_eq!(row.rowid, 0);
+            let mut row = DbDataRow::new(1);
+            assert!(row.load(&pool).await.is_err());
+        }
+    }
+}
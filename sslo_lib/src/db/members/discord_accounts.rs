@@ -0,0 +1,269 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use super::{MembersDbData, MembersDbInterface};
+use super::users::UserItem;
+use crate::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use crate::error::SsloError;
+use crate::optional_date::OptionalDateTime;
+
+crate::db_row! {
+    tablename: "discord_accounts",
+    pub struct DbDataRow {
+        user: Option<i64> = None,
+        discord_id: String = String::new(),
+        username: String = String::new(),
+        creation: DateTime<Utc> = Utc::now(),
+        last_login: Option<DateTime<Utc>> = None,
+    }
+    display: |row| match row.user {
+        Some(user_id) => format!("discord_accounts(id={};user-id={};discord-id={})", row.rowid, user_id, row.discord_id),
+        None => format!("discord_accounts(id={};user-id=None;discord-id={})", row.rowid, row.discord_id),
+    },
+}
+
+/// This abstracts data access to shared items
+pub struct DiscordAccountItem(Arc<RwLock<ItemData<DbDataRow, MembersDbData>>>);
+
+impl ItemInterface for DiscordAccountItem {
+    type Row = DbDataRow;
+    type Db = MembersDbData;
+    type Extra = ();
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, MembersDbData>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, MembersDbData>>> {
+        &self.0
+    }
+}
+
+impl DiscordAccountItem {
+
+    pub async fn id(&self) -> i64 { self.0.read().await.row.rowid }
+    pub async fn display(&self) -> String { self.0.read().await.display() }
+    pub async fn discord_id(&self) -> String { self.0.read().await.row.discord_id.clone() }
+    pub async fn creation(&self) -> DateTime<Utc> { self.0.read().await.row.creation }
+
+    /// The Discord handle (as of the last login)
+    pub async fn username(&self) -> String { self.0.read().await.row.username.clone() }
+
+    pub async fn set_username(&self, username: String) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        if data.row.username == username {
+            return Ok(());
+        }
+        data.row.username = username;
+        data.store().await
+    }
+
+    /// returns the assigned user
+    /// If no user is assigned, a new user will be tried to create (named like the Discord handle)
+    pub async fn user(&self) -> Option<UserItem> {
+        {   // try reading existing user
+            let data = self.0.read().await;
+            if let Some(user_id) = data.row.user {
+                let db_members = match data.db.upgrade() {
+                    Some(db_data) => MembersDbInterface::new(db_data),
+                    None => {
+                        log::error!("cannot upgrade weak pointer for {}", data.display());
+                        return None;
+                    }
+                };
+                let tbl_usr = db_members.tbl_users().await;
+                return tbl_usr.user_by_id(user_id).await;
+            }
+        }
+
+        // create new user
+        let mut data = self.0.write().await;
+        let db_members = match data.db.upgrade() {
+            Some(db_data) => MembersDbInterface::new(db_data),
+            None => {
+                log::error!("cannot upgrade weak pointer for {}", data.display());
+                return None;
+            }
+        };
+        let tbl_usr = db_members.tbl_users().await;
+        let mut user = match tbl_usr.create_new_user().await {
+            Some(user) => user,
+            None => {
+                log::error!("failed to create new user for {}", data.display());
+                return None;
+            }
+        };
+        let name = match data.row.username.is_empty() {
+            true => format!("Discord-User-{}", data.row.discord_id),
+            false => data.row.username.clone(),
+        };
+        if user.set_name(name).await.is_err() {
+            log::error!("Could not set name for {}", user.display().await);
+        }
+        data.row.user = Some(user.id().await);
+        if let Err(e) = data.store().await {
+            log::error!("failed to store new user for {}: {}", data.display(), e);
+            return None;
+        }
+        Some(user)
+    }
+
+    /// Returns true, if a user is assigned to this discord account
+    pub async fn has_user(&self) -> bool {
+        self.0.read().await.row.user.is_some()
+    }
+
+    /// Assign a new user
+    /// Checking if user is already set, before writing to disk
+    pub async fn set_user(&self, user: &UserItem) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+
+        // check if user is already set
+        let new_user_id = user.id().await;
+        if data.row.user == Some(new_user_id) {
+            log::warn!("Ignore re-assigning {} to {}", user.display().await, data.display());
+            return Ok(());
+        }
+
+        // save
+        let old_user = data.row.user;
+        data.row.user = Some(new_user_id);
+        let res = data.store().await;
+
+        // check result
+        if res.is_ok() {
+            log::info!("assigning {} to {}", user.display().await, data.display());
+        } else {
+            data.row.user = old_user;
+        }
+        res
+    }
+
+    pub async fn last_login(&self) -> OptionalDateTime {
+        OptionalDateTime::new(self.0.read().await.row.last_login)
+    }
+
+    pub async fn set_last_login(&self, last_login: DateTime<Utc>) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.last_login = Some(last_login);
+        data.store().await
+    }
+}
+
+pub struct DiscordAccountsTable(TableInterface<DiscordAccountItem>);
+
+impl DiscordAccountsTable {
+    pub(super) fn new(tbl: TableInterface<DiscordAccountItem>) -> Self { Self(tbl) }
+
+    /// Get all discord accounts that are associated to a certain user
+    pub async fn items_by_user(&self, user: &UserItem) -> Vec<DiscordAccountItem> {
+        self.0.items_by_column("user", user.id().await).await
+    }
+
+    /// Get an item by the Discord user id (snowflake)
+    /// This first tries to load the item from cache,
+    /// and secondly load it from the database.
+    pub async fn item_by_discord_id(&self, discord_id: &str,
+                                    allow_new_item_creation: bool) -> Option<DiscordAccountItem> {
+        let pool = self.0.pool().await;
+        match DbDataRow::fetch_one_by(&pool, "discord_id", discord_id.to_string()).await {
+            Ok(row) => Some(self.0.item_by_row(row).await),
+            Err(e) if !e.is_db_not_found_type() => {
+                log::error!("{}", e);
+                None
+            },
+            Err(_) if allow_new_item_creation => {
+                let mut row = DbDataRow::new(0);
+                row.discord_id = discord_id.to_string();
+                match self.0.create_item(row).await {
+                    Ok(item) => {
+                        log::info!("New item created: {}", item.display().await);
+                        Some(item)
+                    },
+                    Err(e) => {
+                        log::error!("{}", e);
+                        None
+                    }
+                }
+            },
+            Err(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::SqlitePool;
+    use super::*;
+    use std::sync::Weak;
+    use crate::db::TableData;
+
+    async fn get_pool() -> SqlitePool {
+        let pool = crate::db::get_pool(None);
+        sqlx::migrate!("../rsc/db_migrations/members").run(&pool).await.unwrap();
+        pool
+    }
+
+    async fn get_table_interface() -> DiscordAccountsTable {
+        let pool = get_pool().await;
+        DiscordAccountsTable::new(TableInterface::new(TableData::new(pool, Weak::new())))
+    }
+
+    mod row {
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn new_defaults() {
+            let row = DbDataRow::new(33);
+            assert_eq!(row.rowid, 33);
+            assert_eq!(row.user, None);
+            assert_eq!(row.discord_id, String::new());
+            assert_eq!(row.username, String::new());
+            assert_eq!(row.last_login, None);
+        }
+
+        /// Testing load and store (insert+update)
+        #[test(tokio::test)]
+        async fn load_store() {
+            let pool = get_pool().await;
+            sqlx::query("INSERT INTO users (rowid,name) VALUES (44,'Foo');").execute(&pool).await.unwrap();
+            let dt1: DateTime<Utc> = DateTime::parse_from_rfc3339("1001-01-01T01:01:01.1111+01:00").unwrap().into();
+
+            // store (insert)
+            let mut row = DbDataRow::new(0);
+            row.user = Some(44);
+            row.discord_id = "80351110224678912".to_string();
+            row.username = "nelly".to_string();
+            row.last_login = Some(dt1);
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.user, Some(44));
+            assert_eq!(row.discord_id, "80351110224678912".to_string());
+            assert_eq!(row.username, "nelly".to_string());
+            assert_eq!(row.last_login, Some(dt1));
+        }
+    }
+
+    mod table {
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn test_new() {
+            let tbl = get_table_interface().await;
+            assert!(tbl.item_by_discord_id("DiscordId1", false).await.is_none());
+
+            let item1 = tbl.item_by_discord_id("DiscordId1", true).await.unwrap();
+            assert!(item1.id().await > 0);
+            let item2 = tbl.item_by_discord_id("DiscordId1", true).await.unwrap();
+            assert_eq!(item1.id().await, item2.id().await);
+
+            item1.set_username("nelly".to_string()).await.unwrap();
+            assert_eq!(item2.username().await, "nelly");
+        }
+    }
+}
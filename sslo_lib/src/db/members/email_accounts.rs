@@ -1,189 +1,59 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Weak};
+use std::sync::Arc;
 use chrono::{DateTime, Utc};
 use regex::Regex;
-use sqlx::{FromRow, Sqlite, SqlitePool};
 use tokio::sync::RwLock;
-use sslo_lib::error::SsloError;
-use sslo_lib::optional_date::OptionalDateTime;
-use sslo_lib::token::{Token, TokenType};
-use crate::db2::members::{MembersDbData, MembersDbInterface};
-use crate::db2::members::users::UserItem;
-
-macro_rules! tablename {
-    () => { "email_accounts" };
+use crate::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use crate::optional_date::OptionalDateTime;
+use crate::token::{Token, TokenType};
+use crate::db::members::{MembersDbData, MembersDbInterface};
+use crate::db::members::users::UserItem;
+
+crate::db_row! {
+    tablename: "email_accounts",
+    pub struct DbDataRow {
+        user: Option<i64> = None,
+        email: String = String::new(),
+        verified_since: Option<DateTime<Utc>> = None,
+        token: Option<String> = None,
+        token_user: Option<i64> = None,
+        token_creation: Option<DateTime<Utc>> = None,
+        token_consumption: Option<DateTime<Utc>> = None,
+        reset_token: Option<String> = None,
+        reset_token_creation: Option<DateTime<Utc>> = None,
+    }
+    display: |row| match row.user {
+        Some(user_id) => format!("email_accounts(id={};email={};user-id={})", row.rowid, row.email, user_id),
+        None => format!("email_accounts(id={};email={};user-id=None)", row.rowid, row.email),
+    },
+    sanitize: |row| { row.email = normalize_email(&row.email) },
 }
 
-#[derive(FromRow, Clone)]
-struct DbDataRow {
-    rowid: i64,
-    user: Option<i64>,
-    email: String,
-    verified_since: Option<DateTime<Utc>>,
-    token: Option<String>,
-    token_user: Option<i64>,
-    token_creation: Option<DateTime<Utc>>,
-    token_consumption: Option<DateTime<Utc>>,
+/// emails are stored trimmed and lower case
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
 }
 
-impl DbDataRow {
-    fn new(rowid: i64, email: String) -> Self {
-        debug_assert!(rowid >= 0);
-        Self {
-            rowid,
-            user: None,
-            email,
-            verified_since: None,
-            token: None,
-            token_user: None,
-            token_creation: None,
-            token_consumption: None,
-        }
-    }
-
-    async fn from_email(email: &str, pool: &SqlitePool) -> Result<Self, SsloError> {
-        match sqlx::query_as::<Sqlite, DbDataRow>(concat!("SELECT rowid,* FROM ", tablename!(), " WHERE email LIKE $1 LIMIT 2;"))
-            .bind(email)
-            .fetch_one(pool)
-            .await {
-            Ok(row) => {
-                Ok(row)
-            },
-            Err(sqlx::Error::RowNotFound) => {
-                Err(SsloError::DatabaseDataNotFound(tablename!(), "email", email.to_string()))
-            },
-            Err(e) => {
-                Err(SsloError::DatabaseSqlx(e))
-            }
-        }
-    }
-
-    async fn from_user(user: &UserItem, pool: &SqlitePool) -> Vec<Self> {
-        let user_id = user.id().await;
-        match sqlx::query_as::<Sqlite, DbDataRow>(concat!("SELECT rowid,* FROM ", tablename!(), " WHERE user = $1 LIMIT 100;"))
-            .bind(user_id)
-            .fetch_all(pool)
-            .await {
-            Ok(rows) => {
-                if rows.len() >= 99 {
-                    log::warn!("user rowid={} has more than 99 email accounts associated (truncating for safety)", user_id);
-                }
-                rows
-            },
-            Err(e) => {
-                log::error!("{}", e);
-                Vec::new()
-            }
-        }
-    }
-
-    async fn load(self: &mut Self, pool: &SqlitePool) -> Result<(), SsloError> {
-        match sqlx::query_as::<Sqlite, DbDataRow>(concat!("SELECT rowid,* FROM ", tablename!(), " WHERE rowid = $1 LIMIT 2;"))
-            .bind(self.rowid)
-            .fetch_one(pool)
-            .await {
-            Ok(row) => {
-                row.clone_into(self);
-                Ok(())
-            },
-            Err(sqlx::Error::RowNotFound) => {
-                Err(SsloError::DatabaseIdNotFound(tablename!(), "rowid", self.rowid))
-            },
-            Err(e) => {
-                Err(SsloError::DatabaseSqlx(e))
-            }
-        }
-    }
-
-    async fn store(self: &mut Self, pool: &SqlitePool) -> Result<(), SsloError> {
-
-        // trim email
-        self.email = self.email.trim().to_string().to_lowercase();
-
-        // define query
-        let mut query = match self.rowid {
-            0 => {
-                sqlx::query(concat!("INSERT INTO ", tablename!(),
-                "(user,\
-                  email,\
-                  verified_since,\
-                  token,\
-                  token_user, \
-                  token_creation,\
-                  token_consumption)\
-                  VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING rowid;"))
-            },
-            _ => {
-                sqlx::query(concat!("UPDATE ", tablename!(), " SET \
-                                   user=$1,\
-                                   email=$2,\
-                                   verified_since=$3,\
-                                   token=$4,\
-                                   token_user=$5,\
-                                   token_creation=$6,\
-                                   token_consumption=$7 \
-                                   WHERE rowid=$8;"))
-            }
-        };
+#[derive(Clone)]
+pub struct EmailAccountItem(Arc<RwLock<ItemData<DbDataRow, MembersDbData>>>);
 
-        // bind values
-        query = query.bind(&self.user)
-            .bind(&self.email)
-            .bind(&self.verified_since)
-            .bind(&self.token)
-            .bind(&self.token_user)
-            .bind(&self.token_creation)
-            .bind(&self.token_consumption);
-        if self.rowid != 0 {
-            query = query.bind(self.rowid);
-        }
+impl ItemInterface for EmailAccountItem {
+    type Row = DbDataRow;
+    type Db = MembersDbData;
+    type Extra = ();
 
-        // execute query
-        let res = query.execute(pool).await?;
-        if self.rowid == 0 {
-            self.rowid = res.last_insert_rowid();
-        }
-        Ok(())
-    }
-
-    /// Returns a string that can be used for integrating this row into a log message
-    fn display(&self) -> String {
-        if let Some(user_id) = self.user {
-            format!("{}(id={};email={};user-id={})", tablename!(), self.rowid, self.email, user_id)
-        } else {
-            format!("{}(id={};email={};user-id=None)", tablename!(), self.rowid, self.email)
-        }
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, MembersDbData>>>) -> Self {
+        Self(item_data)
     }
-}
-
-struct EmailAccountItemData {
-    pool: SqlitePool,
-    row: DbDataRow,
-    db_members: Weak<RwLock<MembersDbData>>,
-}
 
-impl EmailAccountItemData {
-
-    fn new(pool: &SqlitePool, row: DbDataRow, db_members: Weak<RwLock<MembersDbData>>) -> Arc<RwLock<EmailAccountItemData>> {
-        Arc::new(RwLock::new(Self {
-            pool: pool.clone(),
-            row,
-            db_members,
-        }))
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, MembersDbData>>> {
+        &self.0
     }
 }
 
-#[derive(Clone)]
-pub struct EmailAccountItem(Arc<RwLock<EmailAccountItemData>>);
-
 impl EmailAccountItem {
-    fn new(item_data: Arc<RwLock<EmailAccountItemData>>) -> Self {
-        Self(item_data)
-    }
-
     /// Returns a string, that can be used in log messages
     pub async fn display(&self) -> String {
-        self.0.read().await.row.display()
+        self.0.read().await.display()
     }
 
     pub async fn id(&self) -> i64 {
@@ -196,7 +66,7 @@ impl EmailAccountItem {
         {   // try reading existing user
             let data = self.0.read().await;
             if let Some(user_id) = data.row.user {
-                let db_members = match data.db_members.upgrade() {
+                let db_members = match data.db.upgrade() {
                     Some(db_data) => MembersDbInterface::new(db_data),
                     None => {
                         log::error!("cannot upgrade weak pointer for {}", data.row.display());
@@ -210,8 +80,7 @@ impl EmailAccountItem {
 
         // create new user
         let mut data = self.0.write().await;
-        let pool = data.pool.clone();
-        let db_members = match data.db_members.upgrade() {
+        let db_members = match data.db.upgrade() {
             Some(db_data) => MembersDbInterface::new(db_data),
             None => {
                 log::error!("cannot upgrade weak pointer for {}", data.row.display());
@@ -227,14 +96,11 @@ impl EmailAccountItem {
             }
         };
         data.row.user = Some(user.id().await);
-        match data.row.store(&pool).await {
-            Ok(_) => {},
-            Err(e) => {
-                log::error!("failed to store new user for {}", data.row.display());
-                return None;
-            }
+        if let Err(e) = data.store().await {
+            log::error!("failed to store new user for {}: {}", data.row.display(), e);
+            return None;
         }
-        return Some(user);
+        Some(user)
     }
 
 
@@ -262,12 +128,11 @@ impl EmailAccountItem {
     /// The token is stored into DB encrypted, the unencrypted token is returned.
     pub async fn create_token(&self, user: Option<&UserItem>) -> Option<String> {
         let mut item_data = self.0.write().await;
-        let pool = item_data.pool.clone();
         let row_display = item_data.row.display();
 
         // check for timeout since last token creation
         let time_now = Utc::now();
-        let time_token_outdated = time_now.clone()
+        let time_token_outdated = time_now
             .checked_add_signed(chrono::TimeDelta::hours(-1))
             .unwrap();  // subtracting one hour cannot fail, theoretically
         if let Some(token_creation) = item_data.row.token_creation {
@@ -296,7 +161,7 @@ impl EmailAccountItem {
         };
         item_data.row.token_creation = Some(time_now);
         item_data.row.token_consumption = None;
-        match item_data.row.store(&pool).await {
+        match item_data.store().await {
             Ok(_) => {
                 log::info!("New token generated for {}", row_display);
                 Some(token.decrypted)
@@ -312,9 +177,8 @@ impl EmailAccountItem {
     pub async fn consume_token(&self, token: String) -> bool {
         let mut item_data = self.0.write().await;
         let row_display = item_data.row.display();
-        let pool = item_data.pool.clone();
         let time_now = Utc::now();
-        let time_token_outdated = time_now.clone()
+        let time_token_outdated = time_now
             .checked_add_signed(chrono::TimeDelta::hours(-1))
             .unwrap();  // subtracting one hour cannot fail, theoretically
 
@@ -360,7 +224,7 @@ impl EmailAccountItem {
         item_data.row.user = item_data.row.token_user;  // set requested user
         item_data.row.token_consumption = Some(time_now);
         item_data.row.verified_since = Some(time_now);
-        if let Err(e) = item_data.row.store(&pool).await {
+        if let Err(e) = item_data.store().await {
             log::error!("failed to store verified email token for{}: {}", row_display, e);
             return false;
         }
@@ -370,6 +234,100 @@ impl EmailAccountItem {
         true
     }
 
+    /// Creates a new token to reset the password of the assigned user, that shall be sent via email
+    /// This only works for verified email accounts with an assigned user.
+    /// No new token is created while a previous reset token is still valid (one hour).
+    /// The token is stored into DB encrypted, the unencrypted token is returned.
+    pub async fn create_reset_token(&self) -> Option<String> {
+        let mut item_data = self.0.write().await;
+        let row_display = item_data.row.display();
+
+        // only for verified accounts
+        if item_data.row.verified_since.is_none() || item_data.row.user.is_none() {
+            log::warn!("Not generating password reset token for unverified {}", &row_display);
+            return None;
+        }
+
+        // check for timeout since last token creation
+        let time_now = Utc::now();
+        let time_token_outdated = time_now
+            .checked_add_signed(chrono::TimeDelta::hours(-1))
+            .unwrap();  // subtracting one hour cannot fail, theoretically
+        if let Some(reset_token_creation) = item_data.row.reset_token_creation {
+            if reset_token_creation > time_token_outdated && item_data.row.reset_token.is_some() {
+                log::warn!("Not generating new password reset token for {} because last token is still active.", &row_display);
+                return None;
+            }
+        }
+
+        // generate new token
+        let token = match Token::generate(TokenType::Strong) {
+            Ok(t) => t,
+            Err(e) => {
+                log::error!("Could not generate new password reset token for {}: {}", row_display, e);
+                return None;
+            }
+        };
+
+        // store
+        item_data.row.reset_token = Some(token.encrypted);
+        item_data.row.reset_token_creation = Some(time_now);
+        match item_data.store().await {
+            Ok(_) => {
+                log::info!("New password reset token generated for {}", row_display);
+                Some(token.decrypted)
+            },
+            Err(e) => {
+                log::error!("failed to store new password reset token for {}: {}", row_display, e);
+                None
+            }
+        }
+    }
+
+    /// This consumes a password reset token which has been sent via email
+    /// Returns true if the token is valid (a token can only be used once)
+    pub async fn consume_reset_token(&self, token: String) -> bool {
+        let mut item_data = self.0.write().await;
+        let row_display = item_data.row.display();
+        let time_token_outdated = Utc::now()
+            .checked_add_signed(chrono::TimeDelta::hours(-1))
+            .unwrap();  // subtracting one hour cannot fail, theoretically
+
+        // ensure encrypted token is set
+        let token_encrypted = match item_data.row.reset_token.as_ref() {
+            Some(t) => t.clone(),
+            None => {
+                log::warn!("deny password reset because no reset token set for {}", row_display);
+                return false;
+            }
+        };
+
+        // ensure token is not outdated
+        match item_data.row.reset_token_creation.as_ref() {
+            Some(creation) if creation >= &time_token_outdated => {},
+            _ => {
+                log::warn!("deny password reset for {}, because token is outdated", row_display);
+                return false;
+            }
+        }
+
+        // verify token
+        if !Token::new(token, token_encrypted).verify() {
+            log::warn!("deny password reset because token verification failed for {}", row_display);
+            return false;
+        }
+
+        // invalidate token
+        item_data.row.reset_token = None;
+        if let Err(e) = item_data.store().await {
+            log::error!("failed to store consumed password reset token for {}: {}", row_display, e);
+            return false;
+        }
+
+        log::info!("successfully verified password reset token for {}", row_display);
+        true
+    }
+
     pub async fn token_creation(&self) -> OptionalDateTime {
         let item_data = self.0.read().await;
         OptionalDateTime::new(item_data.row.token_creation)
@@ -382,10 +340,10 @@ impl EmailAccountItem {
 
     pub async fn token_user(&self) -> Option<UserItem> {
         let item_data = self.0.read().await;
-        match item_data.row.token_user.clone() {
+        match item_data.row.token_user {
             None => None,
             Some(user_id) => {
-                let db_members = match item_data.db_members.upgrade() {
+                let db_members = match item_data.db.upgrade() {
                     Some(db_data) => MembersDbInterface::new(db_data),
                     None => {
                         log::error!("cannot upgrade weak pointer for {}", item_data.row.display());
@@ -399,37 +357,16 @@ impl EmailAccountItem {
     }
 }
 
-pub(super) struct EmailAccountsTableData {
-    pool: SqlitePool,
-    item_cache_by_id: HashMap<i64, Arc<RwLock<EmailAccountItemData>>>,
-    item_cache_by_email: HashMap<String, Arc<RwLock<EmailAccountItemData>>>,
-    db_members: Weak<RwLock<MembersDbData>>,
-}
-
-impl EmailAccountsTableData {
-    pub(super) fn new(pool: SqlitePool, db_members: Weak<RwLock<MembersDbData>>) -> Arc<RwLock<Self>> {
-        Arc::new(RwLock::new( Self {
-            pool,
-            item_cache_by_id: HashMap::new(),
-            item_cache_by_email: HashMap::new(),
-            db_members,
-        }))
-    }
-}
-
-pub struct EmailAccountsTable(
-    Arc<RwLock<EmailAccountsTableData>>
-);
+pub struct EmailAccountsTable(TableInterface<EmailAccountItem>);
 
 impl EmailAccountsTable {
 
-    pub(super) fn new(data: Arc<RwLock<EmailAccountsTableData>>) -> Self {
-        Self(data)
+    pub(super) fn new(tbl: TableInterface<EmailAccountItem>) -> Self {
+        Self(tbl)
     }
 
     /// creates a new email account
     pub async fn create_account(&self, email: String) -> Option<EmailAccountItem> {
-        let mut tbl_data = self.0.write().await;
 
         // check email
         let email = email.trim().to_string();
@@ -439,102 +376,26 @@ impl EmailAccountsTable {
             return None;
         }
 
-        // create new row
-        let mut row = DbDataRow::new(0, email);
-        match row.store(&tbl_data.pool).await {
-            Ok(_) => {},
+        // create new item
+        let mut row = DbDataRow::new(0);
+        row.email = email;
+        match self.0.create_item(row).await {
+            Ok(item) => Some(item),
             Err(e) => {
-                log::error!("{}", e.to_string());
-                return None;
+                log::error!("{}", e);
+                None
             },
         }
-
-        // create item
-        let row_id = row.rowid;
-        let row_email = row.email.clone();
-        let item_data = EmailAccountItemData::new(&tbl_data.pool, row, tbl_data.db_members.clone());
-        let item = EmailAccountItem::new(item_data.clone());
-        tbl_data.item_cache_by_id.insert(row_id, item_data.clone());
-        tbl_data.item_cache_by_email.insert(row_email, item_data);
-        Some(item)
     }
 
     /// Get an item by id (may return unverified emails)
     pub async fn item_by_id(&self, id: i64) -> Option<EmailAccountItem> {
-
-        // try cache hit
-        {
-            let tbl_data = self.0.read().await;
-            if let Some(item_data) = tbl_data.item_cache_by_id.get(&id) {
-                return Some(EmailAccountItem::new(item_data.clone()));
-            }
-        }
-
-        // try loading from DB if not found in cache
-        {
-            let mut tbl_data = self.0.write().await;
-
-            // load from db_obsolete
-            let mut row = DbDataRow::new(id, "".to_string());
-            match row.load(&tbl_data.pool).await {
-                Ok(_) => { },
-                Err(e) => {
-                    if e.is_db_not_found_type() {
-                        log::warn!("{}", e);
-                    } else {
-                        log::error!("{}", e.to_string());
-                    }
-                    return None;
-                },
-            }
-            debug_assert_eq!(row.rowid, id);
-
-            // create item
-            let email = row.email.clone();
-            let item_data = EmailAccountItemData::new(&tbl_data.pool, row, tbl_data.db_members.clone());
-            let item = EmailAccountItem::new(item_data.clone());
-            tbl_data.item_cache_by_id.insert(id, item_data.clone());
-            tbl_data.item_cache_by_email.insert(email, item_data);
-            Some(item)
-        }
+        self.0.item_by_id(id).await
     }
 
     /// Get an item by email address (also returns unverified emails)
     pub async fn item_by_email_ignore_verification(&self, email: &str) -> Option<EmailAccountItem> {
-
-        // try cache hit
-        {
-            let tbl_data = self.0.read().await;
-            if let Some(item_data) = tbl_data.item_cache_by_email.get(email) {
-                return Some(EmailAccountItem::new(item_data.clone()));
-            }
-        }
-
-        // try loading from DB if not found in cache
-        {
-            let mut tbl_data = self.0.write().await;
-
-            // load from db_obsolete
-            let mut row = match DbDataRow::from_email(email, &tbl_data.pool).await {
-                Ok(row) => row,
-                Err(e) => {
-                    if e.is_db_not_found_type() {
-                        log::warn!("{}", e);
-                    } else {
-                        log::error!("{}", e.to_string());
-                    }
-                    return None;
-                },
-            };
-
-            // create item
-            let id = row.rowid;
-            let item_data = EmailAccountItemData::new(&tbl_data.pool, row, tbl_data.db_members.clone());
-            let item = EmailAccountItem::new(item_data.clone());
-            tbl_data.item_cache_by_id.insert(id, item_data.clone());
-            tbl_data.item_cache_by_email.insert(email.to_string(), item_data);
-            Some(item)
-        }
+        self.0.item_by_column("email", normalize_email(email)).await
     }
 
     /// Get an item by email address (only verified emails)
@@ -550,44 +411,21 @@ impl EmailAccountsTable {
 
     /// Get all email accounts that are associated to a certain user
     pub async fn items_by_user(&self, user: &UserItem) -> Vec<EmailAccountItem> {
-        let mut tbl_data = self.0.write().await;
-        let pool = tbl_data.pool.clone();
-        let mut item_list: Vec<EmailAccountItem> = Vec::new();
-
-        for row in DbDataRow::from_user(user, &pool).await.into_iter() {
-            let rowid = row.rowid;
-            let item = match tbl_data.item_cache_by_id.get(&rowid) {
-                Some(item_data) => EmailAccountItem::new(item_data.clone()),
-                None => {
-                    let item_data = EmailAccountItemData::new(&pool, row, tbl_data.db_members.clone());
-                    let item = EmailAccountItem::new(item_data.clone());
-                    tbl_data.item_cache_by_id.insert(rowid, item_data.clone());
-                    item
-                }
-            };
-            item_list.push(item);
-        }
-
-        return item_list;
+        self.0.items_by_column("user", user.id().await).await
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Weak;
     use sqlx::SqlitePool;
+    use crate::db::TableData;
     use super::*;
-    use test_log::test;
 
     async fn get_pool() -> SqlitePool {
-        let pool = sslo_lib::db::get_pool(None);
-        sqlx::migrate!("../rsc/db_migrations/league_members").run(&pool).await.unwrap();
-        return pool;
-    }
-
-    async fn get_table_interface() -> EmailAccountsTable {
-        let pool = get_pool().await;
-        let tbl_data = EmailAccountsTableData::new(pool, Weak::new());
-        EmailAccountsTable::new(tbl_data.clone())
+        let pool = crate::db::get_pool(None);
+        sqlx::migrate!("../rsc/db_migrations/members").run(&pool).await.unwrap();
+        pool
     }
 
     mod db_row {
@@ -596,10 +434,10 @@ mod tests {
 
         #[test(tokio::test)]
         async fn new_defaults() {
-            let row = DbDataRow::new(123, "foo".to_string());
+            let row = DbDataRow::new(123);
             assert_eq!(row.rowid, 123);
             assert_eq!(row.user, None);
-            assert_eq!(row.email, "foo");
+            assert_eq!(row.email, "");
             assert_eq!(row.token, None);
             assert_eq!(row.token_creation, None);
             assert_eq!(row.token_consumption, None);
@@ -613,11 +451,12 @@ mod tests {
             let token = "as3245lkds".to_string();
 
             // generate some test data
-            let mut query = sqlx::query("INSERT INTO users (rowid,name) VALUES (123,'Foo');");
+            let query = sqlx::query("INSERT INTO users (rowid,name) VALUES (123,'Foo');");
             query.execute(&pool).await.unwrap();
 
             // store
-            let mut row = DbDataRow::new(0, "a.b@c.de".to_string());
+            let mut row = DbDataRow::new(0);
+            row.email = "a.b@c.de".to_string();
             row.user = Some(123);
             row.token = Some(token.clone());
             row.token_creation = Some(dt1);
@@ -628,7 +467,7 @@ mod tests {
             assert_eq!(row.rowid, 1);
 
             // load
-            let mut row = DbDataRow::new(1, "".to_string());
+            let mut row = DbDataRow::new(1);
             row.load(&pool).await.unwrap();
             assert_eq!(row.rowid, 1);
             assert_eq!(row.user, Some(123));
@@ -638,8 +477,8 @@ mod tests {
             assert_eq!(row.token_consumption, Some(dt2));
 
             // from email
-            assert!(DbDataRow::from_email("wrong.email@nothing.com", &pool).await.is_err());
-            let row = DbDataRow::from_email("a.b@c.de", &pool).await.unwrap();
+            assert!(DbDataRow::fetch_one_by(&pool, "email", "wrong.email@nothing.com".to_string()).await.is_err());
+            let row = DbDataRow::fetch_one_by(&pool, "email", "a.b@c.de".to_string()).await.unwrap();
             assert_eq!(row.rowid, 1);
             assert_eq!(row.user, Some(123));
             assert_eq!(row.email, "a.b@c.de".to_string());
@@ -653,19 +492,23 @@ mod tests {
             let pool = get_pool().await;
 
             // create first email
-            let mut row = DbDataRow::new(0, "a.b@c.de".to_string());
+            let mut row = DbDataRow::new(0);
+            row.email = "a.b@c.de".to_string();
             row.store(&pool).await.unwrap();
 
             // create same email shall fail
-            let mut row = DbDataRow::new(0, "a.b@c.de".to_string());
+            let mut row = DbDataRow::new(0);
+            row.email = "a.b@c.de".to_string();
             assert!(row.store(&pool).await.is_err());
 
             // create same email shall fail, trimmed
-            let mut row = DbDataRow::new(0, " a.b@c.de\n".to_string());
+            let mut row = DbDataRow::new(0);
+            row.email = " a.b@c.de\n".to_string();
             assert!(row.store(&pool).await.is_err());
 
             // create same email shall fail, case insensitive
-            let mut row = DbDataRow::new(0, "a.B@c.de".to_string());
+            let mut row = DbDataRow::new(0);
+            row.email = "a.B@c.de".to_string();
             assert!(row.store(&pool).await.is_err());
         }
 
@@ -676,10 +519,11 @@ mod tests {
         use test_log::test;
 
         async fn create_new_item(pool: &SqlitePool, email: String) -> EmailAccountItem {
-            let mut row = DbDataRow::new(0, email);
+            let mut row = DbDataRow::new(0);
+            row.email = email;
             row.store(pool).await.unwrap();
-            let data = EmailAccountItemData::new(pool, row, Weak::new());
-            EmailAccountItem::new(data)
+            let data = ItemData::new(pool, row, Weak::new());
+            EmailAccountItem::from_item_data(data)
         }
 
         #[test(tokio::test)]
@@ -755,6 +599,35 @@ mod tests {
             }
         }
 
+        #[test(tokio::test)]
+        async fn reset_token_process() {
+            let pool = get_pool().await;
+            sqlx::query("INSERT INTO users (rowid,name) VALUES (1,'Foo');").execute(&pool).await.unwrap();
+            let item = create_new_item(&pool, "a.b@c.de".to_string()).await;
+
+            // deny for unverified account
+            assert!(item.create_reset_token().await.is_none());
+            let token = item.create_token(None).await.unwrap();
+            assert!(item.consume_token(token).await);
+            assert!(item.create_reset_token().await.is_none());  // no user assigned
+            item.0.write().await.row.user = Some(1);
+
+            // create token (only once within an hour)
+            let token = item.create_reset_token().await.unwrap();
+            assert!(token.len() > 20);
+            assert!(item.create_reset_token().await.is_none());
+
+            // wrong token fails, correct token can only be used once
+            assert!(!item.consume_reset_token(format!("{}X", token)).await);
+            assert!(item.consume_reset_token(token.clone()).await);
+            assert!(!item.consume_reset_token(token).await);
+
+            // outdated token fails
+            let token = item.create_reset_token().await.unwrap();
+            item.0.write().await.row.reset_token_creation = Some(Utc::now() - chrono::TimeDelta::minutes(61));
+            assert!(!item.consume_reset_token(token).await);
+        }
+
         #[test(tokio::test)]
         async fn token_outdated_verification() {
             let pool = get_pool().await;
@@ -765,17 +638,17 @@ mod tests {
 
             // manipulate token creation time
             let time_now = Utc::now();
-            let time_token_outdated = time_now.clone().checked_add_signed(chrono::TimeDelta::hours(-1)).unwrap();
+            let time_token_outdated = time_now.checked_add_signed(chrono::TimeDelta::hours(-1)).unwrap();
             // let token = item.create_token().await.unwrap();  // not generating new token, because last token is still active
             let id = item.id().await;
-            let mut row = DbDataRow::new(id, "".to_string());
+            let mut row = DbDataRow::new(id);
             row.load(&pool).await.unwrap();
             row.token_creation = Some(time_token_outdated);
             row.store(&pool).await.unwrap();
 
             // verify denied token verification
-            let item_data = EmailAccountItemData::new(&pool, row, Weak::new());
-            let item = EmailAccountItem::new(item_data.clone());
+            let item_data = ItemData::new(&pool, row, Weak::new());
+            let item = EmailAccountItem::from_item_data(item_data.clone());
             assert!(!item.consume_token(token).await);
             assert!(item.verified_since().await.raw().is_none());
         }
@@ -787,8 +660,7 @@ mod tests {
 
         async fn create_table() -> EmailAccountsTable {
             let pool = get_pool().await;
-            let table_data = EmailAccountsTableData::new(pool, Weak::new());
-            EmailAccountsTable::new(table_data)
+            EmailAccountsTable::new(TableInterface::new(TableData::new(pool, Weak::new())))
         }
 
         #[test(tokio::test)]
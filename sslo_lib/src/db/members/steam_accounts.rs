@@ -0,0 +1,333 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use super::{MembersDbData, MembersDbInterface};
+use super::users::UserItem;
+use crate::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use crate::error::SsloError;
+use crate::optional_date::OptionalDateTime;
+
+crate::db_row! {
+    tablename: "steam_accounts",
+    pub struct DbDataRow {
+        user: Option<i64> = None,
+        steam_id: String = String::new(),
+        creation: DateTime<Utc> = Utc::now(),
+        last_login: Option<DateTime<Utc>> = None,
+    }
+    display: |row| match row.user {
+        Some(user_id) => format!("steam_accounts(id={};user-id={};steam-id={})", row.rowid, user_id, row.steam_id),
+        None => format!("steam_accounts(id={};user-id=None;steam-id={})", row.rowid, row.steam_id),
+    },
+}
+
+/// This abstracts data access to shared items
+pub struct SteamAccountItem(Arc<RwLock<ItemData<DbDataRow, MembersDbData>>>);
+
+impl ItemInterface for SteamAccountItem {
+    type Row = DbDataRow;
+    type Db = MembersDbData;
+    type Extra = ();
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, MembersDbData>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, MembersDbData>>> {
+        &self.0
+    }
+}
+
+impl SteamAccountItem {
+
+    pub async fn id(&self) -> i64 { self.0.read().await.row.rowid }
+    pub async fn display(&self) -> String { self.0.read().await.display() }
+    pub async fn steam_id(&self) -> String { self.0.read().await.row.steam_id.clone() }
+    pub async fn creation(&self) -> DateTime<Utc> { self.0.read().await.row.creation }
+
+    /// returns the assigned user
+    /// If no user is assigned, a new user will be tried to create
+    pub async fn user(&self) -> Option<UserItem> {
+        {   // try reading existing user
+            let data = self.0.read().await;
+            if let Some(user_id) = data.row.user {
+                let db_members = match data.db.upgrade() {
+                    Some(db_data) => MembersDbInterface::new(db_data),
+                    None => {
+                        log::error!("cannot upgrade weak pointer for {}", data.display());
+                        return None;
+                    }
+                };
+                let tbl_usr = db_members.tbl_users().await;
+                return tbl_usr.user_by_id(user_id).await;
+            }
+        }
+
+        // create new user
+        let mut data = self.0.write().await;
+        let db_members = match data.db.upgrade() {
+            Some(db_data) => MembersDbInterface::new(db_data),
+            None => {
+                log::error!("cannot upgrade weak pointer for {}", data.display());
+                return None;
+            }
+        };
+        let tbl_usr = db_members.tbl_users().await;
+        let mut user = match tbl_usr.create_new_user().await {
+            Some(user) => user,
+            None => {
+                log::error!("failed to create new user for {}", data.display());
+                return None;
+            }
+        };
+        if user.set_name(format!("Steam-User-{}", data.row.steam_id)).await.is_err() {
+            log::error!("Could not set name for {}", user.display().await);
+        }
+        data.row.user = Some(user.id().await);
+        if let Err(e) = data.store().await {
+            log::error!("failed to store new user for {}: {}", data.display(), e);
+            return None;
+        }
+        Some(user)
+    }
+
+    /// Returns true, if a user is assigned to this steam account
+    pub async fn has_user(&self) -> bool {
+        let data = self.0.read().await;
+        data.row.user.is_some()
+    }
+
+    /// Assign a new user
+    /// Checking if user is already set, before writing to disk
+    pub async fn set_user(&self, user: &UserItem) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+
+        // check if user is already set
+        let new_user_id = user.id().await;
+        if let Some(&existing_user) = data.row.user.as_ref() {
+            if existing_user == new_user_id {
+                log::warn!("Ignore re-assigning {} to {}", user.display().await, data.display());
+                return Ok(());
+            }
+        }
+
+        // save
+        let old_user = data.row.user;
+        data.row.user = Some(new_user_id);
+        let res = data.store().await;
+
+        // check result
+        if res.is_ok() {
+            log::info!("assigning {} to {}", user.display().await, data.display());
+        } else {
+            data.row.user = old_user;
+        }
+
+        res
+    }
+
+    /// Detach this steam account from its user
+    /// The last login method of a user is not detached (checked within the same query), then false is returned.
+    pub async fn unset_user(&self) -> Result<bool, SsloError> {
+        let mut data = self.0.write().await;
+        let old_user = match data.row.user {
+            Some(user_id) => user_id,
+            None => return Ok(true),
+        };
+        if let Some(pool) = data.pool.as_ref() {
+            let query = format!("UPDATE steam_accounts SET user = NULL WHERE rowid = $2 AND user = $1 AND {} > 1;",
+                                super::sql_count_login_methods());
+            let res = sqlx::query(&query)
+                .bind(old_user)
+                .bind(data.row.rowid)
+                .execute(pool)
+                .await?;
+            if res.rows_affected() == 0 {
+                log::warn!("not detaching user id={} from {}, because it is the last login method", old_user, data.display());
+                return Ok(false);
+            }
+        }
+        data.row.user = None;
+        log::info!("detached user id={} from {}", old_user, data.display());
+        Ok(true)
+    }
+
+    pub async fn last_login(&self) -> OptionalDateTime {
+        let data = self.0.read().await;
+        OptionalDateTime::new(data.row.last_login)
+    }
+
+    pub async fn set_last_login(&self, last_login: DateTime<Utc>) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.last_login = Some(last_login);
+        data.store().await
+    }
+
+}
+
+pub struct SteamAccountsTable(TableInterface<SteamAccountItem>);
+
+impl SteamAccountsTable {
+    pub(super) fn new(tbl: TableInterface<SteamAccountItem>) -> Self { Self(tbl) }
+
+    pub async fn item_by_id(&self, id: i64) -> Option<SteamAccountItem> {
+        self.0.item_by_id(id).await
+    }
+
+    /// Get all steam accounts that are associated to a certain user
+    pub async fn items_by_user(&self, user: &UserItem) -> Vec<SteamAccountItem> {
+        self.0.items_by_column("user", user.id().await).await
+    }
+
+    /// Get an item
+    /// This first tries to load the item from cache,
+    /// and secondly load it from the database.
+    pub async fn item_by_steam_id(&self, steam_id: &str,
+                                  allow_new_item_creation: bool) -> Option<SteamAccountItem> {
+        let pool = self.0.pool().await;
+        match DbDataRow::fetch_one_by(&pool, "steam_id", steam_id.to_string()).await {
+            Ok(row) => Some(self.0.item_by_row(row).await),
+            Err(e) if !e.is_db_not_found_type() => {
+                log::error!("{}", e);
+                None
+            },
+            Err(_) if allow_new_item_creation => {
+                let mut row = DbDataRow::new(0);
+                row.steam_id = steam_id.to_string();
+                match self.0.create_item(row).await {
+                    Ok(item) => {
+                        log::info!("New item created: {}", item.display().await);
+                        Some(item)
+                    },
+                    Err(e) => {
+                        log::error!("{}", e);
+                        None
+                    }
+                }
+            },
+            Err(_) => None,
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::SqlitePool;
+    use super::*;
+    use std::sync::Weak;
+    use crate::db::TableData;
+
+    async fn get_pool() -> SqlitePool {
+        let pool = crate::db::get_pool(None);
+        sqlx::migrate!("../rsc/db_migrations/members").run(&pool).await.unwrap();
+        pool
+    }
+
+    async fn create_new_item(pool: &SqlitePool) -> SteamAccountItem {
+        let row = DbDataRow::new(0);
+        let data = ItemData::new(pool, row, Weak::new());
+        SteamAccountItem::from_item_data(data)
+    }
+
+    async fn get_table_interface() -> SteamAccountsTable {
+        let pool = get_pool().await;
+        SteamAccountsTable::new(TableInterface::new(TableData::new(pool, Weak::new())))
+    }
+
+    mod row {
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn new_defaults() {
+            let row = DbDataRow::new(33);
+            assert_eq!(row.rowid, 33);
+            assert_eq!(row.user, None);
+            assert_eq!(row.steam_id, String::new());
+            assert_eq!(row.last_login, None);
+        }
+
+        /// Testing load and store (insert+update)
+        #[test(tokio::test)]
+        async fn load_store() {
+            let pool = get_pool().await;
+
+            // genertae some test data
+            let query = sqlx::query("INSERT INTO users (rowid,name) VALUES (44,'Foo');");
+            query.execute(&pool).await.unwrap();
+            let query = sqlx::query("INSERT INTO users (rowid,name) VALUES (46,'Foo');");
+            query.execute(&pool).await.unwrap();
+
+            // define some UTC times
+            let dt1: DateTime<Utc> = DateTime::parse_from_rfc3339("1001-01-01T01:01:01.1111+01:00").unwrap().into();
+            let dt2: DateTime<Utc> = DateTime::parse_from_rfc3339("2002-02-02T02:02:02.2222+02:00").unwrap().into();
+            let dt3: DateTime<Utc> = DateTime::parse_from_rfc3339("3003-03-03T03:03:03.3333+03:00").unwrap().into();
+
+            // store (insert)
+            let mut row = DbDataRow::new(0);
+            row.user = Some(44);
+            row.steam_id = "SomeSteam64GUID".to_string();
+            row.creation = dt1;
+            row.last_login = Some(dt2);
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.user, Some(44));
+            assert_eq!(row.steam_id, "SomeSteam64GUID".to_string());
+            assert_eq!(row.creation, dt1);
+            assert_eq!(row.last_login, Some(dt2));
+
+            // store (update)
+            let mut row = DbDataRow::new(1);
+            row.user = Some(46);
+            row.steam_id = "NewSomeSteam64GUID".to_string();
+            row.creation = dt2;
+            row.last_login = Some(dt3);
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.user, Some(46));
+            assert_eq!(row.steam_id, "NewSomeSteam64GUID".to_string());
+            assert_eq!(row.creation, dt2);
+            assert_eq!(row.last_login, Some(dt3));
+        }
+    }
+
+    mod item {
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn test() {
+            let earlier: DateTime<Utc> = Utc::now();
+            let pool = get_pool().await;
+            let item = create_new_item(&pool).await;
+            assert!(item.creation().await > earlier);
+        }
+    }
+
+    mod table {
+        use test_log::test;
+        use super::*;
+
+        #[test(tokio::test)]
+        async fn test_new() {
+            let tbl = get_table_interface().await;
+
+            let item0 = tbl.item_by_steam_id("SteamId1", false).await;
+            assert!(item0.is_none());
+
+            let item1 = tbl.item_by_steam_id("SteamId1", true).await.unwrap();
+            assert!(item1.id().await > 0);
+            let item2 = tbl.item_by_steam_id("SteamId1", true).await.unwrap();
+            assert_eq!(item1.id().await, item2.id().await);
+        }
+    }
+}
@@ -0,0 +1,1185 @@
+use std::ops::Sub;
+use tokio::sync::RwLock;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use crate::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use crate::error::SsloError;
+use crate::optional_date::OptionalDateTime;
+use crate::db::members::MembersDbData;
+use crate::token::{Token, TokenType};
+
+#[derive(PartialEq)]
+pub enum Activity {
+    None = 0,
+    Obsolete = 1,
+    Recent = 2,
+}
+
+impl Activity {
+    pub fn from_date_time(last_activity: Option<DateTime<Utc>>) -> Self {
+
+        // determine date of recent activity
+        let t_now = chrono::Utc::now();
+        let t_recent = t_now.sub(chrono::Duration::days(90));
+
+        // return enum
+        match last_activity {
+            None => Activity::None,
+            Some(t_login) if t_login < t_recent => Activity::Obsolete,
+            Some(_) => Activity::Recent,
+        }
+    }
+}
+
+pub struct UserActivity {
+    driving_activity: Activity,
+    login_activity: Activity,
+}
+
+impl Default for UserActivity {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UserActivity {
+
+    pub fn new() -> Self {
+        Self {
+            driving_activity: Activity::None,
+            login_activity: Activity::None
+        }
+    }
+
+    /// Activity, based on the last driven lap
+    pub fn driving(&self) -> &Activity { &self.driving_activity }
+
+    /// Activity, based on the last login
+    pub fn login(&self) -> &Activity { &self.login_activity }
+
+    pub fn label(&self) -> &'static str {
+        match self.login_activity {
+            Activity::None => {
+                match self.driving_activity {
+                    Activity::None => {"Wildcard Pedestrian"}
+                    Activity::Obsolete => {"Wildcard Veteran"}
+                    Activity::Recent => {"Wildcard Driver"}
+                }
+            },
+            Activity::Obsolete => {
+                match self.driving_activity {
+                    Activity::None => {"Ghost Pedestrian"}
+                    Activity::Obsolete => {"Ghost Veteran"}
+                    Activity::Recent => {"Ghost Driver"}
+                }
+            },
+            Activity::Recent => {
+                match self.driving_activity {
+                    Activity::None => {"League Pedestrian"}
+                    Activity::Obsolete => {"League Veteran"}
+                    Activity::Recent => {"League Driver"}
+                }
+            },
+        }
+    }
+}
+
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(sqlx::Type)]
+#[derive(Debug)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[repr(u32)]
+pub enum PromotionAuthority {
+
+    /// Only executing his promotion (cannot promote others)
+    Executing = 0,
+
+    /// Can also promote other users (up to one level below)
+    Chief = 1,
+}
+
+
+/// Levels are ordered, a higher level includes all permissions of the lower levels
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(sqlx::Type)]
+#[derive(Debug)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[repr(u32)]
+pub enum PromotionLevel {
+    /// no further user rights
+    None = 0,
+
+    /// graceful server control
+    Steward = 1,
+
+    /// force server control, update downloads
+    Marshal = 2,
+
+    /// schedule races
+    Officer = 3,
+
+    /// correct results, pronounce penalties
+    Commissar = 4,
+
+    /// manage series, edit presets
+    Director = 5,
+
+    /// almost all permissions (except root)
+    Admin = 6,
+}
+
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Promotion {
+    pub level: PromotionLevel,
+    pub authority: PromotionAuthority,
+}
+
+impl Promotion {
+
+    pub fn new(level: PromotionLevel, authority: PromotionAuthority) -> Self {
+        Self { level, authority }
+    }
+
+    pub fn new_lowest() -> Self {
+        Self {
+            level: PromotionLevel::None,
+            authority: PromotionAuthority::Executing,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self.level {
+            PromotionLevel::None => match self.authority {
+                PromotionAuthority::Executing => {""}
+                PromotionAuthority::Chief => {"Chief"}
+            },
+            PromotionLevel::Steward => match self.authority {
+                PromotionAuthority::Executing => {"Executing Steward"}
+                PromotionAuthority::Chief => {"Chief Steward"}
+            },
+            PromotionLevel::Marshal => match self.authority {
+                PromotionAuthority::Executing => {"Executing Marshal"}
+                PromotionAuthority::Chief => {"Chief Marshal"}
+            },
+            PromotionLevel::Officer => match self.authority {
+                PromotionAuthority::Executing => {"Executing Officer"}
+                PromotionAuthority::Chief => {"Chief Officer"}
+            },
+            PromotionLevel::Commissar => match self.authority {
+                PromotionAuthority::Executing => {"Executing Commissar"}
+                PromotionAuthority::Chief => {"Chief Commissar"}
+            },
+            PromotionLevel::Director => match self.authority {
+                PromotionAuthority::Executing => {"Executing Director"}
+                PromotionAuthority::Chief => {"Chief Director"}
+            },
+            PromotionLevel::Admin => match self.authority {
+                PromotionAuthority::Executing => {"Executing Administrator"}
+                PromotionAuthority::Chief => {"Chief Administrator"}
+            },
+        }
+    }
+
+    /// A short badge of the promotion (Chief authority is marked with a star)
+    pub fn symbol(&self) -> &'static str {
+        match self.level {
+            PromotionLevel::None => match self.authority {
+                PromotionAuthority::Executing => {""}
+                PromotionAuthority::Chief => {"★"}
+            },
+            PromotionLevel::Steward => match self.authority {
+                PromotionAuthority::Executing => {"⚐"}
+                PromotionAuthority::Chief => {"★⚐"}
+            },
+            PromotionLevel::Marshal => match self.authority {
+                PromotionAuthority::Executing => {"⚑"}
+                PromotionAuthority::Chief => {"★⚑"}
+            },
+            PromotionLevel::Officer => match self.authority {
+                PromotionAuthority::Executing => {"✦"}
+                PromotionAuthority::Chief => {"★✦"}
+            },
+            PromotionLevel::Commissar => match self.authority {
+                PromotionAuthority::Executing => {"⚖"}
+                PromotionAuthority::Chief => {"★⚖"}
+            },
+            PromotionLevel::Director => match self.authority {
+                PromotionAuthority::Executing => {"♜"}
+                PromotionAuthority::Chief => {"★♜"}
+            },
+            PromotionLevel::Admin => match self.authority {
+                PromotionAuthority::Executing => {"♛"}
+                PromotionAuthority::Chief => {"★♛"}
+            },
+        }
+    }
+
+    /// Check if this promotion allows to change the promotion of another user
+    /// Only a Chief can promote, and only up to one level below the own level and below the own authority.
+    /// Users at the same or a higher level cannot be changed.
+    pub fn may_grant(&self, current: &Promotion, new: &Promotion) -> bool {
+        self.authority == PromotionAuthority::Chief
+            && current.level < self.level
+            && new.level < self.level
+            && new.authority < self.authority
+    }
+}
+
+crate::db_row! {
+    tablename: "users",
+    pub struct DbDataRow {
+        name: String = "".to_string(),
+        promotion_authority: PromotionAuthority = PromotionAuthority::Executing,
+        promotion_level: PromotionLevel = PromotionLevel::None,
+        last_lap: Option<DateTime<Utc>> = None,
+        last_login: Option<DateTime<Utc>> = None,
+        password: Option<String> = None,
+        password_last_usage: Option<DateTime<Utc>> = None,
+        password_last_useragent: Option<String> = None,
+        totp_secret: Option<String> = None,
+        totp_enabled: Option<DateTime<Utc>> = None,
+        totp_last_step: Option<i64> = None,
+        merge_token: Option<String> = None,
+        merge_token_creation: Option<DateTime<Utc>> = None,
+    }
+    display: |row| format!("users(id={};name={})", row.rowid, row.name),
+}
+
+crate::db_row! {
+    tablename: "promotion_history",
+    /// A change of a user promotion
+    pub struct PromotionHistoryRow {
+        pub user: i64 = 0,
+        pub granted_by: Option<i64> = None,
+        pub promotion_level: PromotionLevel = PromotionLevel::None,
+        pub promotion_authority: PromotionAuthority = PromotionAuthority::Executing,
+        pub date: DateTime<Utc> = Utc::now(),
+    }
+}
+
+#[cfg(feature = "members_totp")]
+crate::db_row! {
+    tablename: "totp_recovery_codes",
+    /// A one-time code to login without TOTP authenticator (the code is stored encrypted)
+    struct TotpRecoveryCodeRow {
+        user: i64 = 0,
+        code: String = String::new(),
+    }
+}
+
+/// The name of a user after all personal data has been removed
+pub fn anonymous_name(user_id: i64) -> String {
+    format!("Deleted-User-{}", user_id)
+}
+
+/// minutes until an unused merge token expires
+pub const MERGE_TOKEN_VALIDITY_MINUTES: i64 = 10;
+
+/// amount of recovery codes that are created when TOTP is enabled
+#[cfg(feature = "members_totp")]
+pub const TOTP_RECOVERY_CODES: usize = 10;
+
+
+/// This abstracts data access to shared database items
+#[derive(Clone)]
+pub struct UserItem(Arc<RwLock<ItemData<DbDataRow, MembersDbData>>>);
+
+impl ItemInterface for UserItem {
+    type Row = DbDataRow;
+    type Db = MembersDbData;
+    type Extra = ();
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, MembersDbData>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, MembersDbData>>> {
+        &self.0
+    }
+}
+
+impl UserItem {
+
+    /// Returns a string, that can be used in log messages
+    pub async fn display(&self) -> String {
+        self.0.read().await.display()
+    }
+
+    pub async fn id(&self) -> i64 {
+        self.0.read().await.row.rowid
+    }
+
+
+    pub async fn name(&self) -> String {
+        self.0.read().await.row.name.clone()
+    }
+
+    pub async fn html_name(&self) -> String {
+        let mut html = String::new();
+        html_escape::encode_safe_to_string(&self.0.read().await.row.name, &mut html);
+        html
+    }
+
+    pub async fn set_name(&mut self, name: String) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        let name = name.trim().to_string();
+        let display_before = data.row.display();
+        data.row.name = name;
+        let display_after = data.row.display();
+        log::info!("Change name from {} to {}", display_before, display_after);
+        data.store().await
+    }
+
+    pub async fn activity(&self) -> UserActivity {
+        let data = self.0.read().await;
+        UserActivity {
+            driving_activity: Activity::from_date_time(data.row.last_lap),
+            login_activity: Activity::from_date_time(data.row.last_login),
+        }
+    }
+
+    pub async fn promotion(&self) -> Promotion {
+        let data = self.0.read().await;
+        Promotion::new(data.row.promotion_level.clone(), data.row.promotion_authority.clone())
+    }
+    pub async fn set_promotion(&mut self, promotion: Promotion) {
+        let mut item_data = self.0.write().await;
+        item_data.row.promotion_level = promotion.level;
+        item_data.row.promotion_authority = promotion.authority;
+        if let Err(e) = item_data.store().await {
+            log::error!("Failed to set promotion for {}: {}", item_data.display(), e);
+        };
+    }
+
+    /// Change the promotion and append it to the promotion history
+    /// granted_by is None for offline administration
+    pub async fn grant_promotion(&mut self, promotion: Promotion, granted_by: Option<i64>) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+
+        // change the user and the history within a single transaction
+        if let Some(pool) = data.pool.as_ref() {
+            let mut tx = pool.begin().await?;
+            sqlx::query("UPDATE users SET promotion_level = $2, promotion_authority = $3 WHERE rowid = $1;")
+                .bind(data.row.rowid)
+                .bind(&promotion.level)
+                .bind(&promotion.authority)
+                .execute(&mut *tx).await?;
+            sqlx::query("INSERT INTO promotion_history (user, granted_by, promotion_level, promotion_authority, date) VALUES ($1, $2, $3, $4, $5);")
+                .bind(data.row.rowid)
+                .bind(granted_by)
+                .bind(&promotion.level)
+                .bind(&promotion.authority)
+                .bind(Utc::now())
+                .execute(&mut *tx).await?;
+            tx.commit().await?;
+        }
+
+        log::info!("{} was promoted to {:?}/{:?} by user {:?}", data.display(), promotion.level, promotion.authority, granted_by);
+        data.row.promotion_level = promotion.level;
+        data.row.promotion_authority = promotion.authority;
+        Ok(())
+    }
+
+    /// All changes of the promotion, oldest first
+    pub async fn promotion_history(&self) -> Vec<PromotionHistoryRow> {
+        let data = self.0.read().await;
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return Vec::new(),
+        };
+        match PromotionHistoryRow::fetch_all_by(pool, "user", data.row.rowid, 10000).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("Failed to read promotion history of {}: {}", data.display(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    pub async fn last_lap(&self) -> OptionalDateTime { OptionalDateTime::new(self.0.read().await.row.last_lap) }
+    pub async fn set_last_lap(&mut self, last_lap: DateTime<Utc>) {
+        let mut data = self.0.write().await;
+        data.row.last_lap = Some(last_lap);
+        if let Err(e) = data.store().await {
+            log::error!("Failed to set last lap for {}: {}", data.display(), e);
+        }
+    }
+
+    pub async fn last_login(&self) -> OptionalDateTime { OptionalDateTime::new(self.0.read().await.row.last_login) }
+    pub async fn set_last_login(&mut self, last_login: DateTime<Utc>) {
+        let mut data = self.0.write().await;
+        data.row.last_login = Some(last_login);
+        if let Err(e) = data.store().await {
+            log::error!("Failed to set last login for {}: {}", data.display(), e);
+        }
+    }
+
+    /// Consume a cleartext password, and store encrypted
+    /// This checks if the current password is valid
+    pub async fn update_password(&mut self, old_password: Option<String>, new_password: Option<String>) -> bool {
+        let mut data = self.0.write().await;
+
+        // verify old password
+        if let Some(old_password_encrypted) = data.row.password.as_ref() {
+            if let Some(old_password_decrypted) = old_password {
+                match argon2::verify_encoded(old_password_encrypted, &old_password_decrypted.into_bytes()) {
+                    Ok(true) => {},
+                    Ok(false) => {
+                        log::warn!("deny update password, because invalid old password given for {}", data.row.display());
+                        return false;
+                    },
+                    Err(e) => {
+                        log::error!("Argon2 failure at verifying passwords: {}", e);
+                        return false;
+                    }
+                }
+            } else {
+                log::warn!("deny update password, because no old password given for {}", data.row.display());
+                return false;
+            }
+        }
+
+        Self::store_password(&mut data, new_password).await
+    }
+
+    /// Set a new password without verifying the old password
+    /// This is a privileged operation (e.g. after a verified password reset), None removes the password
+    pub async fn set_password(&mut self, new_password: Option<String>) -> bool {
+        let mut data = self.0.write().await;
+        Self::store_password(&mut data, new_password).await
+    }
+
+    /// Encrypt and store a new password (the caller must hold the write lock)
+    async fn store_password(data: &mut ItemData<DbDataRow, MembersDbData>, new_password: Option<String>) -> bool {
+
+        // encrypt new password
+        let mut new_password_encrypted: Option<String> = None;
+        if let Some(some_new_password) = new_password {
+            let mut salt: Vec<u8> = vec![0u8; 64];
+            rand::thread_rng().fill_bytes(&mut salt);
+            new_password_encrypted = match argon2::hash_encoded(&some_new_password.into_bytes(), &salt, &argon2::Config::default()) {
+                Ok(p) => Some(p),
+                Err(e) => {
+                    log::error!("Argon2 failed to encrypt password for {}: {}", data.row.display(), e);
+                    return false;
+                }
+            };
+        }
+
+        // update password
+        data.row.password = new_password_encrypted;
+        data.row.password_last_usage = None;
+        data.row.password_last_useragent = None;
+        if let Err(e) = data.store().await {
+            log::error!("failed to store updated password for {}: {}", data.row.display(), e);
+            return false;
+        }
+
+        log::info!("password updated for user {}", data.row.display());
+        true
+    }
+
+    /// The user can login with a password
+    pub async fn has_password(&self) -> bool {
+        self.0.read().await.row.password.is_some()
+    }
+
+    /// Consumes a cleartext password
+    pub async fn verify_password(&self, password: String, user_agent: String) -> bool {
+
+        {   // separate scope with read-lock for quick return at verification fail
+            let data = self.0.read().await;
+            if let Some(old_password_encrypted) = data.row.password.as_ref() {
+                match argon2::verify_encoded(old_password_encrypted, &password.into_bytes()) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        return false;
+                    }
+                    Err(e) => {
+                        log::error!("Argon2 failure at verifying passwords for {}: {}", data.row.display(), e);
+                        return false;
+                    }
+                }
+            } else {
+                log::warn!("deny verifying password, because no password set for {}", data.row.display());
+                return false;
+            }
+        }
+
+        // update usage
+        let mut data = self.0.write().await;
+        data.row.password_last_usage = Some(Utc::now());
+        data.row.password_last_useragent = Some(user_agent);
+        if let Err(e) = data.store().await {
+            log::error!("failed to update password usage for {}: {}", data.row.display(), e);
+            return false;
+        }
+
+        // done
+        log::info!("successful password verification for {}", data.row.display());
+        true
+    }
+
+    /// Create a token that proves the ownership of this user, when merging it into another user
+    /// The token is stored into DB encrypted, the unencrypted token is returned (short enough to be typed).
+    pub async fn create_merge_token(&self) -> Option<String> {
+        let mut data = self.0.write().await;
+        let token = match Token::generate_sized(TokenType::Quick, 8) {
+            Ok(token) => token,
+            Err(e) => {
+                log::error!("Could not generate merge token for {}: {}", data.display(), e);
+                return None;
+            }
+        };
+        data.row.merge_token = Some(token.encrypted);
+        data.row.merge_token_creation = Some(Utc::now());
+        if let Err(e) = data.store().await {
+            log::error!("failed to store merge token for {}: {}", data.display(), e);
+            return None;
+        }
+        log::info!("new merge token generated for {}", data.display());
+        Some(token.decrypted)
+    }
+
+    /// Verify a merge token (a token can only be used once)
+    pub async fn consume_merge_token(&self, token: &str) -> bool {
+        let mut data = self.0.write().await;
+        let token_encrypted = match data.row.merge_token.take() {
+            Some(token_encrypted) => token_encrypted,
+            None => {
+                log::warn!("deny merging {}, because no merge token set", data.display());
+                return false;
+            }
+        };
+        if let Err(e) = data.store().await {
+            log::error!("failed to store consumed merge token for {}: {}", data.display(), e);
+            return false;
+        }
+
+        let time_token_outdated = Utc::now() - chrono::Duration::minutes(MERGE_TOKEN_VALIDITY_MINUTES);
+        match data.row.merge_token_creation {
+            Some(creation) if creation >= time_token_outdated => {},
+            _ => {
+                log::warn!("deny merging {}, because merge token is outdated", data.display());
+                return false;
+            }
+        }
+        if !Token::new(token.trim().to_string(), token_encrypted).verify() {
+            log::warn!("deny merging {}, because merge token verification failed", data.display());
+            return false;
+        }
+        true
+    }
+}
+
+
+#[cfg(feature = "members_totp")]
+impl UserItem {
+
+    /// Since when TOTP is enabled (None while not enabled or enrollment is pending)
+    pub async fn totp_enabled(&self) -> OptionalDateTime {
+        OptionalDateTime::new(self.0.read().await.row.totp_enabled)
+    }
+
+    /// Start a new TOTP enrollment (key is the server key to encrypt the secret)
+    /// Returns the new secret, which must be confirmed by a code before TOTP is enabled
+    pub async fn totp_enroll(&mut self, key: &str) -> Result<Vec<u8>, SsloError> {
+        let mut data = self.0.write().await;
+        if data.row.totp_enabled.is_some() {
+            return Err(SsloError::GeneralError(format!("TOTP already enabled for {}", data.display())));
+        }
+        let secret = crate::totp::generate_secret();
+        data.row.totp_secret = Some(crate::totp::encrypt_secret(key, &secret)?);
+        data.row.totp_last_step = None;
+        data.store().await?;
+        log::info!("TOTP enrollment started for {}", data.display());
+        Ok(secret)
+    }
+
+    /// Confirm a pending TOTP enrollment with a code from the authenticator
+    /// Returns new recovery codes (in plain text, only available once) or None if the code is invalid
+    pub async fn totp_confirm(&mut self, key: &str, code: &str) -> Result<Option<Vec<String>>, SsloError> {
+        let mut data = self.0.write().await;
+        if data.row.totp_enabled.is_some() {
+            return Err(SsloError::GeneralError(format!("TOTP already enabled for {}", data.display())));
+        }
+        let secret = match data.row.totp_secret.as_ref() {
+            Some(encrypted) => crate::totp::decrypt_secret(key, encrypted)?,
+            None => return Err(SsloError::GeneralError(format!("No TOTP enrollment pending for {}", data.display()))),
+        };
+        let step = match crate::totp::verify(&secret, code, Utc::now().timestamp()) {
+            Some(step) => step,
+            None => {
+                log::warn!("deny TOTP confirmation for {}, because of invalid code", data.display());
+                return Ok(None);
+            }
+        };
+
+        data.row.totp_enabled = Some(Utc::now());
+        data.row.totp_last_step = Some(step);
+        data.store().await?;
+        let codes = Self::create_recovery_codes(&data.row.rowid, data.pool.as_ref()).await?;
+        log::info!("TOTP enabled for {}", data.display());
+        Ok(Some(codes))
+    }
+
+    /// Disable TOTP and delete all recovery codes
+    pub async fn totp_disable(&mut self) -> Result<(), SsloError> {
+        let mut data = self.0.write().await;
+        data.row.totp_secret = None;
+        data.row.totp_enabled = None;
+        data.row.totp_last_step = None;
+        data.store().await?;
+        if let Some(pool) = data.pool.as_ref() {
+            sqlx::query("DELETE FROM totp_recovery_codes WHERE user = $1;")
+                .bind(data.row.rowid)
+                .execute(pool)
+                .await?;
+        }
+        log::info!("TOTP disabled for {}", data.display());
+        Ok(())
+    }
+
+    /// Verify a TOTP code or a recovery code (a recovery code can only be used once)
+    pub async fn totp_verify(&mut self, key: &str, code: &str) -> bool {
+        let mut data = self.0.write().await;
+        if data.row.totp_enabled.is_none() {
+            log::warn!("deny TOTP verification, because TOTP not enabled for {}", data.display());
+            return false;
+        }
+
+        // verify TOTP code
+        let secret = match data.row.totp_secret.as_ref().map(|encrypted| crate::totp::decrypt_secret(key, encrypted)) {
+            Some(Ok(secret)) => secret,
+            Some(Err(e)) => {
+                log::error!("Could not decrypt TOTP secret of {}: {}", data.display(), e);
+                return false;
+            },
+            None => return false,
+        };
+        if let Some(step) = crate::totp::verify(&secret, code, Utc::now().timestamp()) {
+            if data.row.totp_last_step.is_some_and(|last_step| step <= last_step) {
+                log::warn!("deny TOTP verification for {}, because code was already used", data.display());
+                return false;
+            }
+            data.row.totp_last_step = Some(step);
+            if let Err(e) = data.store().await {
+                log::error!("failed to store TOTP usage for {}: {}", data.display(), e);
+                return false;
+            }
+            return true;
+        }
+
+        // verify recovery code
+        let pool = match data.pool.as_ref() {
+            Some(pool) => pool,
+            None => return false,
+        };
+        let rows = match TotpRecoveryCodeRow::fetch_all_by(pool, "user", data.row.rowid, TOTP_RECOVERY_CODES as u32).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("Could not read recovery codes of {}: {}", data.display(), e);
+                return false;
+            }
+        };
+        for mut row in rows.into_iter() {
+            if Token::new(code.trim().to_string(), row.code.clone()).verify() {
+                if let Err(e) = row.delete(pool).await {
+                    log::error!("failed to delete used recovery code of {}: {}", data.display(), e);
+                    return false;
+                }
+                log::info!("recovery code used by {}", data.display());
+                return true;
+            }
+        }
+
+        log::warn!("deny TOTP verification for {}, because of invalid code", data.display());
+        false
+    }
+
+    /// Amount of unused recovery codes
+    pub async fn totp_recovery_codes_left(&self) -> usize {
+        let data = self.0.read().await;
+        match data.pool.as_ref() {
+            Some(pool) => TotpRecoveryCodeRow::fetch_all_by(pool, "user", data.row.rowid, TOTP_RECOVERY_CODES as u32).await
+                .map(|rows| rows.len())
+                .unwrap_or(0),
+            None => 0,
+        }
+    }
+
+    /// Replace all recovery codes by new ones (returned in plain text)
+    pub async fn totp_renew_recovery_codes(&mut self) -> Result<Vec<String>, SsloError> {
+        let data = self.0.write().await;
+        if data.row.totp_enabled.is_none() {
+            return Err(SsloError::GeneralError(format!("TOTP not enabled for {}", data.display())));
+        }
+        let codes = Self::create_recovery_codes(&data.row.rowid, data.pool.as_ref()).await?;
+        log::info!("renewed recovery codes of {}", data.display());
+        Ok(codes)
+    }
+
+    async fn create_recovery_codes(user_id: &i64, pool: Option<&sqlx::SqlitePool>) -> Result<Vec<String>, SsloError> {
+        let pool = match pool {
+            Some(pool) => pool,
+            None => return Err(SsloError::GeneralError("no database for recovery codes".to_string())),
+        };
+        sqlx::query("DELETE FROM totp_recovery_codes WHERE user = $1;")
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+        let mut codes = Vec::new();
+        for _ in 0..TOTP_RECOVERY_CODES {
+            let token = Token::generate_sized(TokenType::Strong, 5)
+                .map_err(|e| SsloError::GeneralError(format!("Could not generate recovery code: {}", e)))?;
+            let mut row = TotpRecoveryCodeRow::new(0);
+            row.user = *user_id;
+            row.code = token.encrypted;
+            row.store(pool).await?;
+            codes.push(token.decrypted);
+        }
+        Ok(codes)
+    }
+}
+
+
+pub struct UserTable(TableInterface<UserItem>);
+
+impl UserTable {
+
+    pub(super) fn new(tbl: TableInterface<UserItem>) -> Self {
+        Self(tbl)
+    }
+
+    /// Create a new user
+    pub async fn create_new_user(&self) -> Option<UserItem> {
+        match self.0.create_item(DbDataRow::new(0)).await {
+            Ok(item) => {
+                log::info!("new user created: {}", item.display().await);
+                Some(item)
+            },
+            Err(e) => {
+                log::error!("Could not create a new user: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Get a dummy user
+    /// This can be used to handle unknown users (will not be stored into db)
+    pub async fn user_dummy(&self) -> UserItem {
+        UserItem::from_item_data(ItemData::new_dummy(DbDataRow::new(0)))
+    }
+
+    /// Get an item
+    /// This first tries to load the item from cache,
+    /// and secondly load it from the database.
+    pub async fn user_by_id(&self, id: i64) -> Option<UserItem> {
+
+        // sanity check
+        debug_assert!(id > 0);
+        if id <= 0 {
+            log::error!("Deny to retrieve user with nagive rowid={}", id);
+            return None;
+        }
+
+        self.0.item_by_id(id).await
+    }
+
+    /// Get all users
+    pub async fn users_all(&self) -> Vec<UserItem> {
+        self.0.items_all().await
+    }
+
+    /// Get all users that have at least a certain promotion level
+    pub async fn users_by_promotion(&self, level: PromotionLevel) -> Vec<UserItem> {
+        let pool = self.0.pool().await;
+        match sqlx::query_as::<sqlx::Sqlite, DbDataRow>("SELECT rowid,* FROM users WHERE promotion_level >= $1 ORDER BY rowid;")
+            .bind(level)
+            .fetch_all(&pool)
+            .await {
+            Ok(rows) => self.0.items_by_rows(rows).await,
+            Err(e) => {
+                log::error!("Could not query users by promotion: {}", e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Weak;
+    use sqlx::SqlitePool;
+    use crate::db::TableData;
+    use super::*;
+    use test_log::test;
+
+    async fn get_pool() -> SqlitePool {
+        let pool = crate::db::get_pool(None);
+        sqlx::migrate!("../rsc/db_migrations/members").run(&pool).await.unwrap();
+        pool
+    }
+
+    async fn get_table_interface() -> UserTable {
+        let pool = get_pool().await;
+        UserTable::new(TableInterface::new(TableData::new(pool, Weak::new())))
+    }
+
+    mod table {
+        use test_log::test;
+        use super::super::{Promotion, PromotionAuthority, PromotionLevel};
+
+        #[test(tokio::test)]
+        async fn create_new_user() {
+            let tbl = super::get_table_interface().await;
+            assert_eq!(tbl.0.cache_len().await, 0);
+            let item = tbl.create_new_user().await.unwrap();
+            assert_eq!(tbl.0.cache_len().await, 1);
+            assert_eq!(item.id().await, 1);
+        }
+
+        #[test(tokio::test)]
+        async fn user_by_id() {
+            let tbl = super::get_table_interface().await;
+
+            // check if cache is empty
+            assert_eq!(tbl.0.cache_len().await, 0);
+
+            // append items to db_obsolete
+            let mut item = tbl.create_new_user().await.unwrap();
+            item.set_name("Bob".to_string()).await.unwrap();
+            let mut item = tbl.create_new_user().await.unwrap();
+            item.set_name("Dylan".to_string()).await.unwrap();
+
+            // check if cache is filled
+            assert_eq!(tbl.0.cache_len().await, 2);
+
+            // retrieve item
+            let item1 = tbl.user_by_id(1).await.unwrap();
+            assert_eq!(item1.id().await, 1);
+            assert_eq!(item1.name().await, "Bob");
+            let item2 = tbl.user_by_id(2).await.unwrap();
+            assert_eq!(item2.id().await, 2);
+            assert_eq!(item2.name().await, "Dylan");
+        }
+
+        #[test(tokio::test)]
+        async fn users_by_promotion() {
+            let tbl = super::get_table_interface().await;
+            tbl.create_new_user().await.unwrap();
+            let mut steward = tbl.create_new_user().await.unwrap();
+            steward.set_promotion(Promotion::new(PromotionLevel::Steward, PromotionAuthority::Executing)).await;
+            let mut commissar = tbl.create_new_user().await.unwrap();
+            commissar.set_promotion(Promotion::new(PromotionLevel::Commissar, PromotionAuthority::Chief)).await;
+
+            let users = tbl.users_by_promotion(PromotionLevel::Steward).await;
+            assert_eq!(users.len(), 2);
+            assert_eq!(users[0].id().await, 2);
+            assert_eq!(tbl.users_by_promotion(PromotionLevel::Commissar).await.len(), 1);
+            assert!(tbl.users_by_promotion(PromotionLevel::Admin).await.is_empty());
+        }
+
+        #[test(tokio::test)]
+        async fn grant_promotion() {
+            let tbl = super::get_table_interface().await;
+            let chief = tbl.create_new_user().await.unwrap();
+            let mut user = tbl.create_new_user().await.unwrap();
+            assert!(user.promotion_history().await.is_empty());
+
+            user.grant_promotion(Promotion::new(PromotionLevel::Marshal, PromotionAuthority::Executing), Some(chief.id().await)).await.unwrap();
+            user.grant_promotion(Promotion::new(PromotionLevel::Officer, PromotionAuthority::Chief), None).await.unwrap();
+            assert_eq!(user.promotion().await, Promotion::new(PromotionLevel::Officer, PromotionAuthority::Chief));
+            let history = user.promotion_history().await;
+            assert_eq!(history.len(), 2);
+            assert_eq!(history[0].granted_by, Some(1));
+            assert_eq!(history[0].promotion_level, PromotionLevel::Marshal);
+            assert_eq!(history[1].granted_by, None);
+            assert_eq!(history[1].promotion_authority, PromotionAuthority::Chief);
+        }
+    }
+
+    #[test]
+    fn promotion_rules() {
+        let chief_officer = Promotion::new(PromotionLevel::Officer, PromotionAuthority::Chief);
+        let executing_officer = Promotion::new(PromotionLevel::Officer, PromotionAuthority::Executing);
+        let none = Promotion::new_lowest();
+        let marshal = Promotion::new(PromotionLevel::Marshal, PromotionAuthority::Chief);
+        let executing_marshal = Promotion::new(PromotionLevel::Marshal, PromotionAuthority::Executing);
+        assert!(chief_officer.may_grant(&none, &executing_marshal));
+        assert!(!chief_officer.may_grant(&none, &marshal));  // cannot grant chief authority
+        assert!(chief_officer.may_grant(&marshal, &none));
+        assert!(!chief_officer.may_grant(&none, &executing_officer));
+        assert!(!chief_officer.may_grant(&executing_officer, &none));
+        assert!(!executing_officer.may_grant(&none, &marshal));
+
+        assert_eq!(none.symbol(), "");
+        assert_eq!(chief_officer.symbol(), "★✦");
+        assert_eq!(executing_officer.symbol(), "✦");
+    }
+
+    mod item {
+        use std::sync::Weak;
+        use chrono::{DateTime, Utc};
+        use sqlx::SqlitePool;
+        use super::super::*;
+        use test_log::test;
+
+        async fn create_new_item(pool: &SqlitePool) -> UserItem {
+            let row = DbDataRow::new(0);
+            let data = ItemData::new(pool, row, Weak::new());
+            UserItem::from_item_data(data)
+        }
+
+        async fn load_item_from_db(id: i64, pool: &SqlitePool) -> UserItem {
+            let mut row = DbDataRow::new(id);
+            row.load(pool).await.unwrap();
+            let data = ItemData::new(pool, row, Weak::new());
+            UserItem::from_item_data(data)
+        }
+
+        /// test item generation and property access
+        #[test(tokio::test)]
+        async fn new_item() {
+            let pool = super::get_pool().await;
+
+            // create item
+            let row = DbDataRow::new(0);
+            let data = ItemData::new(&pool, row, Weak::new());
+            let item = UserItem::from_item_data(data);
+            assert_eq!(item.id().await, 0);
+            assert_eq!(item.name().await, "");
+        }
+
+        #[test(tokio::test)]
+        async fn name_and_id() {
+            let pool = super::get_pool().await;
+
+            // create item
+            let mut item = create_new_item(&pool.clone()).await;
+            assert_eq!(item.id().await, 0);
+
+            // modify item
+            assert_eq!(item.name().await, "");
+            item.set_name(" Ronald Antonio \"Ronnie\" O'Sullivan\n".to_string()).await.unwrap();
+            assert_eq!(item.id().await, 1);
+            assert_eq!(item.name().await, "Ronald Antonio \"Ronnie\" O'Sullivan");
+
+            // check if arrived in database
+            let item = load_item_from_db(1, &pool).await;
+            assert_eq!(item.id().await, 1);
+            assert_eq!(item.name().await, "Ronald Antonio \"Ronnie\" O'Sullivan");
+
+            // check html name
+            assert_eq!(item.html_name().await, "Ronald Antonio &quot;Ronnie&quot; O&#x27;Sullivan");
+        }
+
+        #[test(tokio::test)]
+        async fn promotion() {
+
+            // create item
+            let pool = super::get_pool().await;
+            let mut item = create_new_item(&pool.clone()).await;
+
+            // modify item (ne before, eq after)
+            let prom = item.promotion().await;
+            assert_ne!(prom.level, PromotionLevel::Marshal);
+            assert_ne!(prom.authority, PromotionAuthority::Chief);
+            item.set_promotion(Promotion::new(PromotionLevel::Marshal, PromotionAuthority::Chief)).await;
+            let prom = item.promotion().await;
+            assert_eq!(prom.level, PromotionLevel::Marshal);
+            assert_eq!(prom.authority, PromotionAuthority::Chief);
+
+            // check if stored into db_obsolete correctly
+            let item = load_item_from_db(item.id().await, &pool).await;
+            let prom = item.promotion().await;
+            assert_eq!(prom.level, PromotionLevel::Marshal);
+            assert_eq!(prom.authority, PromotionAuthority::Chief);
+
+            // check ordering of levels
+            assert!(PromotionLevel::Director > PromotionLevel::Commissar);
+            assert!(PromotionLevel::None < PromotionLevel::Steward);
+        }
+
+        #[test(tokio::test)]
+        async fn last_lap() {
+
+            // create item
+            let pool = super::get_pool().await;
+            let mut item = create_new_item(&pool.clone()).await;
+
+            // prepare test data
+            let dt: DateTime<Utc> = DateTime::parse_from_rfc3339("1001-01-01T01:01:01.1111+01:00").unwrap().into();
+
+            // modify item (ne before, eq after)
+            assert_eq!(item.last_lap().await.raw(), &None);
+            item.set_last_lap(dt).await;
+            assert_eq!(item.last_lap().await.raw(), &Some(dt));
+
+            // check if stored into db_obsolete correctly
+            let item = load_item_from_db(item.id().await, &pool).await;
+            assert_eq!(item.last_lap().await.raw(), &Some(dt));
+        }
+
+        #[test(tokio::test)]
+        async fn password() {
+
+            // create item
+            let pool = super::get_pool().await;
+            let mut item = create_new_item(&pool.clone()).await;
+
+            // set password
+            assert!(item.update_password(None, Some("unsecure_test_password".to_string())).await);
+            assert!(item.verify_password("unsecure_test_password".to_string(), "unit test".to_string()).await);
+
+            // check if stored into db_obsolete correctly
+            let mut item = load_item_from_db(item.id().await, &pool).await;
+            assert!(item.verify_password("unsecure_test_password".to_string(), "unit test".to_string()).await);
+
+            // update without old password must fail
+            assert!(!item.update_password(None, Some("unsecure_updated_test_password".to_string())).await);
+
+            // update password
+            assert!(item.update_password(Some("unsecure_test_password".to_string()), Some("unsecure_updated_test_password".to_string())).await);
+
+            // check if stored into db_obsolete correctly
+            let item = load_item_from_db(item.id().await, &pool).await;
+            assert!(item.verify_password("unsecure_updated_test_password".to_string(), "unit test".to_string()).await);
+
+            // verify wrong password must fail
+            assert!(!item.verify_password("foobar".to_string(), "unit test".to_string()).await);
+
+            // set password without old password (privileged)
+            let mut item = load_item_from_db(item.id().await, &pool).await;
+            assert!(item.set_password(Some("unsecure_reset_test_password".to_string())).await);
+            assert!(item.verify_password("unsecure_reset_test_password".to_string(), "unit test".to_string()).await);
+        }
+
+        #[cfg(feature = "members_totp")]
+        #[test(tokio::test)]
+        async fn totp() {
+            let pool = super::get_pool().await;
+            let mut item = create_new_item(&pool.clone()).await;
+            item.set_name("Foo".to_string()).await.unwrap();
+            let key = "unit test key";
+
+            // enrollment must be confirmed by a valid code
+            assert!(item.totp_confirm(key, "123456").await.is_err());
+            let secret = item.totp_enroll(key).await.unwrap();
+            assert!(item.totp_enabled().await.raw().is_none());
+            assert!(!item.totp_verify(key, "123456").await);
+            let code = crate::totp::code(&secret, crate::totp::time_step(Utc::now().timestamp()) - 1);
+            assert!(item.totp_confirm("wrong key", &code).await.is_err());
+            let wrong_code = if code == "000000" { "000001" } else { "000000" };
+            assert_eq!(item.totp_confirm(key, wrong_code).await.unwrap(), None);
+            let recovery_codes = item.totp_confirm(key, &code).await.unwrap().unwrap();
+            assert_eq!(recovery_codes.len(), TOTP_RECOVERY_CODES);
+            assert!(item.totp_enabled().await.raw().is_some());
+            assert!(item.totp_enroll(key).await.is_err());
+
+            // codes cannot be used twice
+            assert!(!item.totp_verify(key, &code).await);
+            let code = crate::totp::code(&secret, crate::totp::time_step(Utc::now().timestamp()) + 1);
+            assert!(item.totp_verify(key, &code).await);
+            assert!(!item.totp_verify(key, &code).await);
+
+            // recovery codes
+            assert_eq!(item.totp_recovery_codes_left().await, TOTP_RECOVERY_CODES);
+            assert!(item.totp_verify(key, &recovery_codes[3]).await);
+            assert!(!item.totp_verify(key, &recovery_codes[3]).await);
+            assert_eq!(item.totp_recovery_codes_left().await, TOTP_RECOVERY_CODES - 1);
+
+            // disable
+            item.totp_disable().await.unwrap();
+            assert!(item.totp_enabled().await.raw().is_none());
+            assert_eq!(item.totp_recovery_codes_left().await, 0);
+            assert!(!item.totp_verify(key, &recovery_codes[4]).await);
+        }
+
+    }
+
+    mod row {
+        use chrono::{DateTime, Utc};
+        use super::*;
+        use test_log::test;
+
+        #[test(tokio::test)]
+        async fn new_defaults() {
+            let row = DbDataRow::new(33);
+            assert_eq!(row.rowid, 33);
+            assert_eq!(row.name, "".to_string());
+            assert_eq!(row.promotion_authority, PromotionAuthority::Executing);
+            assert_eq!(row.promotion_level, PromotionLevel::None);
+            assert_eq!(row.last_lap, None);
+            assert_eq!(row.last_login, None);
+            assert_eq!(row.password, None);
+            assert_eq!(row.password_last_usage, None);
+            assert_eq!(row.password_last_useragent, None);
+        }
+
+        /// Testing load and store (insert+update)
+        #[test(tokio::test)]
+        async fn load_store() {
+            let pool = super::get_pool().await;
+
+            // define some UTC times
+            let dt1: DateTime<Utc> = DateTime::parse_from_rfc3339("1001-01-01T01:01:01.1111+01:00").unwrap().into();
+            let dt2: DateTime<Utc> = DateTime::parse_from_rfc3339("2002-02-02T02:02:02.2222+02:00").unwrap().into();
+            let dt3: DateTime<Utc> = DateTime::parse_from_rfc3339("3003-03-03T03:03:03.3333+03:00").unwrap().into();
+            let dt5: DateTime<Utc> = DateTime::parse_from_rfc3339("5005-05-05T05:05:05.5555+05:00").unwrap().into();
+
+            // store (insert)
+            let mut row = DbDataRow::new(0);
+            row.name = "RowName".to_string();
+            row.promotion_authority = PromotionAuthority::Chief;
+            row.promotion_level = PromotionLevel::Commissar;
+            row.last_lap = Some(dt1);
+            row.last_login = Some(dt2);
+            row.password = Some("IAmThePassword".to_string());
+            row.password_last_usage = Some(dt5);
+            row.password_last_useragent = Some("IAmTheUserAgent".to_string());
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.name, "RowName".to_string());
+            assert_eq!(row.promotion_authority, PromotionAuthority::Chief);
+            assert_eq!(row.promotion_level, PromotionLevel::Commissar);
+            assert_eq!(row.last_lap, Some(dt1));
+            assert_eq!(row.last_login, Some(dt2));
+            assert_eq!(row.password, Some("IAmThePassword".to_string()));
+            assert_eq!(row.password_last_usage, Some(dt5));
+            assert_eq!(row.password_last_useragent, Some("IAmTheUserAgent".to_string()));
+
+            // store (update)
+            let mut row = DbDataRow::new(1);
+            row.name = "RowNameNew".to_string();
+            row.promotion_authority = PromotionAuthority::Executing;
+            row.promotion_level = PromotionLevel::Admin;
+            row.last_lap = Some(dt2);
+            row.last_login = Some(dt3);
+            row.password = Some("IAmThePasswordNew".to_string());
+            row.password_last_usage = Some(dt1);
+            row.password_last_useragent = Some("IAmTheUserAgentNew".to_string());
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.rowid, 1);
+            assert_eq!(row.name, "RowNameNew".to_string());
+            assert_eq!(row.promotion_authority, PromotionAuthority::Executing);
+            assert_eq!(row.promotion_level, PromotionLevel::Admin);
+            assert_eq!(row.last_lap, Some(dt2));
+            assert_eq!(row.last_login, Some(dt3));
+            assert_eq!(row.password, Some("IAmThePasswordNew".to_string()));
+            assert_eq!(row.password_last_usage, Some(dt1));
+            assert_eq!(row.password_last_useragent, Some("IAmTheUserAgentNew".to_string()));
+        }
+    }
+}
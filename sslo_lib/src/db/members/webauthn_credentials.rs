@@ -0,0 +1,298 @@
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::RwLock;
+use super::{MembersDbData, MembersDbInterface};
+use super::users::UserItem;
+use crate::db::{DbRow, ItemData, ItemInterface, TableInterface};
+use crate::error::SsloError;
+use crate::optional_date::OptionalDateTime;
+use crate::webauthn::RelyingParty;
+
+/// seconds until an unused challenge expires
+pub const CHALLENGE_VALIDITY_SECONDS: i64 = 300;
+
+crate::db_row! {
+    tablename: "webauthn_credentials",
+    pub struct DbDataRow {
+        user: i64 = 0,
+        credential_id: String = String::new(),
+        public_key: Vec<u8> = Vec::new(),
+        sign_count: i64 = 0,
+        name: String = String::new(),
+        creation: DateTime<Utc> = Utc::now(),
+        last_usage: Option<DateTime<Utc>> = None,
+    }
+    display: |row| format!("webauthn_credentials(id={};user-id={};name={})", row.rowid, row.user, row.name),
+}
+
+crate::db_row! {
+    tablename: "webauthn_challenges",
+    /// A challenge that must be signed by the authenticator (can only be used once)
+    struct ChallengeRow {
+        challenge: String = String::new(),
+        user: Option<i64> = None,
+        creation: DateTime<Utc> = Utc::now(),
+    }
+}
+
+/// This abstracts data access to shared items
+pub struct WebauthnCredentialItem(Arc<RwLock<ItemData<DbDataRow, MembersDbData>>>);
+
+impl ItemInterface for WebauthnCredentialItem {
+    type Row = DbDataRow;
+    type Db = MembersDbData;
+    type Extra = ();
+
+    fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, MembersDbData>>>) -> Self {
+        Self(item_data)
+    }
+
+    fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, MembersDbData>>> {
+        &self.0
+    }
+}
+
+impl WebauthnCredentialItem {
+
+    pub async fn id(&self) -> i64 { self.0.read().await.row.rowid }
+    pub async fn display(&self) -> String { self.0.read().await.display() }
+    pub async fn credential_id(&self) -> String { self.0.read().await.row.credential_id.clone() }
+    pub async fn name(&self) -> String { self.0.read().await.row.name.clone() }
+    pub async fn creation(&self) -> DateTime<Utc> { self.0.read().await.row.creation }
+
+    pub async fn last_usage(&self) -> OptionalDateTime {
+        OptionalDateTime::new(self.0.read().await.row.last_usage)
+    }
+
+    /// returns the owning user
+    pub async fn user(&self) -> Option<UserItem> {
+        let data = self.0.read().await;
+        let db_members = match data.db.upgrade() {
+            Some(db_data) => MembersDbInterface::new(db_data),
+            None => {
+                log::error!("cannot upgrade weak pointer for {}", data.display());
+                return None;
+            }
+        };
+        db_members.tbl_users().await.user_by_id(data.row.user).await
+    }
+
+    /// Verify a login assertion and track the usage
+    /// The challenge must already be consumed, so that a replayed assertion cannot change the stored state.
+    async fn verify_assertion(&self, rp: &RelyingParty, challenge: &str, client_data_json: &[u8],
+                              authenticator_data: &[u8], signature: &[u8]) -> bool {
+        let mut data = self.0.write().await;
+        let assertion = match rp.verify_assertion(&data.row.public_key, client_data_json, authenticator_data, signature) {
+            Ok(assertion) => assertion,
+            Err(e) => {
+                log::warn!("deny login with {}: {}", data.display(), e);
+                return false;
+            }
+        };
+        if assertion.challenge != challenge {
+            log::warn!("deny login with {}, because of a challenge mismatch", data.display());
+            return false;
+        }
+
+        // a signature counter that does not increase indicates a cloned authenticator
+        let sign_count = i64::from(assertion.sign_count);
+        if (sign_count > 0 || data.row.sign_count > 0) && sign_count <= data.row.sign_count {
+            log::warn!("deny login with {}, because sign count {} is not above {}", data.display(), sign_count, data.row.sign_count);
+            return false;
+        }
+
+        data.row.sign_count = sign_count;
+        data.row.last_usage = Some(Utc::now());
+        if let Err(e) = data.store().await {
+            log::error!("failed to store usage of {}: {}", data.display(), e);
+            return false;
+        }
+        true
+    }
+}
+
+pub struct WebauthnCredentialsTable(TableInterface<WebauthnCredentialItem>);
+
+impl WebauthnCredentialsTable {
+    pub(super) fn new(tbl: TableInterface<WebauthnCredentialItem>) -> Self { Self(tbl) }
+
+    /// Get all credentials of a user
+    pub async fn items_by_user(&self, user: &UserItem) -> Vec<WebauthnCredentialItem> {
+        self.0.items_by_column("user", user.id().await).await
+    }
+
+    pub async fn item_by_id(&self, id: i64) -> Option<WebauthnCredentialItem> {
+        self.0.item_by_id(id).await
+    }
+
+    /// Get a credential by the (base64url encoded) credential id
+    pub async fn item_by_credential_id(&self, credential_id: &str) -> Option<WebauthnCredentialItem> {
+        self.0.item_by_column("credential_id", credential_id.to_string()).await
+    }
+
+    /// Create a new challenge for registering a credential (with user) or for a login (without user)
+    pub async fn create_challenge(&self, user: Option<&UserItem>) -> Result<String, SsloError> {
+        let pool = self.0.pool().await;
+
+        // forget expired challenges
+        sqlx::query("DELETE FROM webauthn_challenges WHERE creation < $1;")
+            .bind(Utc::now() - Duration::seconds(CHALLENGE_VALIDITY_SECONDS))
+            .execute(&pool)
+            .await?;
+
+        let mut row = ChallengeRow::new(0);
+        row.challenge = crate::webauthn::generate_challenge();
+        if let Some(user) = user {
+            row.user = Some(user.id().await);
+        }
+        row.store(&pool).await?;
+        Ok(row.challenge)
+    }
+
+    /// Returns true if the challenge was issued for the user (or for a login) and is not expired
+    async fn consume_challenge(&self, challenge: &str, user_id: Option<i64>) -> bool {
+        let pool = self.0.pool().await;
+        let mut row = match ChallengeRow::fetch_one_by(&pool, "challenge", challenge.to_string()).await {
+            Ok(row) => row,
+            Err(e) => {
+                log::warn!("unknown WebAuthn challenge: {}", e);
+                return false;
+            }
+        };
+        if let Err(e) = row.delete(&pool).await {
+            log::error!("failed to delete WebAuthn challenge: {}", e);
+            return false;
+        }
+        if row.user != user_id {
+            log::warn!("WebAuthn challenge was issued for user {:?}, not for {:?}", row.user, user_id);
+            return false;
+        }
+        if row.creation + Duration::seconds(CHALLENGE_VALIDITY_SECONDS) < Utc::now() {
+            log::warn!("WebAuthn challenge expired");
+            return false;
+        }
+        true
+    }
+
+    /// Verify the response of a registration and store the new credential
+    pub async fn register_credential(&self, rp: &RelyingParty, user: &UserItem, name: String,
+                                     client_data_json: &[u8], attestation_object: &[u8],
+    ) -> Result<WebauthnCredentialItem, SsloError> {
+        let credential = rp.verify_registration(client_data_json, attestation_object)?;
+        if !self.consume_challenge(&credential.challenge, Some(user.id().await)).await {
+            return Err(SsloError::WebauthnVerificationFailed("invalid challenge".to_string()));
+        }
+
+        let mut row = DbDataRow::new(0);
+        row.user = user.id().await;
+        row.credential_id = crate::webauthn::base64url_encode(&credential.credential_id);
+        row.public_key = credential.public_key;
+        row.sign_count = i64::from(credential.sign_count);
+        row.name = name;
+        let item = self.0.create_item(row).await?;
+        log::info!("{} registered {}", user.display().await, item.display().await);
+        Ok(item)
+    }
+
+    /// Verify the response of a login and return the authenticated user
+    pub async fn authenticate(&self, rp: &RelyingParty, credential_id: &str, client_data_json: &[u8],
+                              authenticator_data: &[u8], signature: &[u8]) -> Option<UserItem> {
+        let item = match self.item_by_credential_id(credential_id).await {
+            Some(item) => item,
+            None => {
+                log::warn!("deny login with unknown WebAuthn credential '{}'", credential_id);
+                return None;
+            }
+        };
+
+        // the challenge is consumed before the assertion is verified and stored
+        let challenge = match rp.assertion_challenge(client_data_json) {
+            Ok(challenge) => challenge,
+            Err(e) => {
+                log::warn!("deny login with {}: {}", item.display().await, e);
+                return None;
+            }
+        };
+        if !self.consume_challenge(&challenge, None).await {
+            log::warn!("deny login with {}, because of invalid challenge", item.display().await);
+            return None;
+        }
+        if !item.verify_assertion(rp, &challenge, client_data_json, authenticator_data, signature).await {
+            return None;
+        }
+        item.user().await
+    }
+
+    /// Remove a credential
+    /// The last login method of a user is not removed (checked within the same query), then false is returned.
+    pub async fn delete_credential(&self, item: WebauthnCredentialItem) -> Result<bool, SsloError> {
+        let display = item.display().await;
+        let (id, user) = {
+            let data = item.0.read().await;
+            (data.row.rowid, data.row.user)
+        };
+        let query = format!("DELETE FROM webauthn_credentials WHERE rowid = $2 AND user = $1 AND {} > 1;",
+                            super::sql_count_login_methods());
+        let res = sqlx::query(&query)
+            .bind(user)
+            .bind(id)
+            .execute(&self.0.pool().await)
+            .await?;
+        if res.rows_affected() == 0 {
+            log::warn!("not deleting {}, because it is the last login method", display);
+            return Ok(false);
+        }
+        self.0.uncache_item(item).await;
+        log::info!("deleted {}", display);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::SqlitePool;
+    use super::*;
+
+    async fn get_pool() -> SqlitePool {
+        let pool = crate::db::get_pool(None);
+        sqlx::migrate!("../rsc/db_migrations/members").run(&pool).await.unwrap();
+        pool
+    }
+
+    mod row {
+        use test_log::test;
+        use super::*;
+
+        /// Testing load and store (insert+update)
+        #[test(tokio::test)]
+        async fn load_store() {
+            let pool = get_pool().await;
+            sqlx::query("INSERT INTO users (rowid,name) VALUES (44,'Foo');").execute(&pool).await.unwrap();
+
+            // store (insert)
+            let mut row = DbDataRow::new(0);
+            row.user = 44;
+            row.credential_id = "SomeCredentialId".to_string();
+            row.public_key = vec![4, 1, 2, 3];
+            row.sign_count = 7;
+            row.name = "My Key".to_string();
+            row.store(&pool).await.unwrap();
+
+            // load
+            let mut row = DbDataRow::new(1);
+            row.load(&pool).await.unwrap();
+            assert_eq!(row.user, 44);
+            assert_eq!(row.credential_id, "SomeCredentialId".to_string());
+            assert_eq!(row.public_key, vec![4, 1, 2, 3]);
+            assert_eq!(row.sign_count, 7);
+            assert_eq!(row.name, "My Key".to_string());
+            assert_eq!(row.last_usage, None);
+
+            // credential ids are unique
+            let mut row = DbDataRow::new(0);
+            row.user = 44;
+            row.credential_id = "SomeCredentialId".to_string();
+            assert!(row.store(&pool).await.is_err());
+        }
+    }
+}
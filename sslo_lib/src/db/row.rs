@@ -0,0 +1,259 @@
+use std::fmt::Display;
+use std::future::Future;
+use sqlx::{Encode, Sqlite, SqlitePool, Type};
+use sqlx::query::Query;
+use sqlx::sqlite::{SqliteArguments, SqliteRow};
+use crate::error::SsloError;
+
+/// The query type that is used to bind the columns of a row
+pub type DbQuery<'q> = Query<'q, Sqlite, SqliteArguments<'q>>;
+
+
+/// Data structure that is used for database interaction of a single table row
+///
+/// Do not implement this manually, but use the db_row! macro.
+/// The row is always identified by the sqlite rowid column,
+/// a rowid of zero means that the row is not yet stored into the database.
+pub trait DbRow: for<'r> sqlx::FromRow<'r, SqliteRow> + Clone + Send + Sync + Unpin + 'static {
+
+    /// name of the database table
+    const TABLENAME: &'static str;
+
+    /// all column names of the table (except rowid)
+    const COLUMNS: &'static [&'static str];
+
+    /// Create a new (empty/default) data row
+    fn new(rowid: i64) -> Self;
+
+    fn rowid(&self) -> i64;
+
+    fn set_rowid(&mut self, rowid: i64);
+
+    /// Bind the values of all columns (in order of COLUMNS) to a query
+    fn bind_columns<'q>(&'q self, query: DbQuery<'q>) -> DbQuery<'q>;
+
+    /// Returns a string that can be used for integrating this row into a log message
+    fn display(&self) -> String {
+        format!("{}(id={})", Self::TABLENAME, self.rowid())
+    }
+
+    /// Called before the row is written into the database (e.g. to normalize data)
+    fn sanitize(&mut self) {}
+
+    /// Read the data from the database
+    /// This consumes a Row object and returns a new row object on success
+    fn load(&mut self, pool: &SqlitePool) -> impl Future<Output = Result<(), SsloError>> + Send {
+        async move {
+            let sql = format!("SELECT rowid,* FROM {} WHERE rowid = $1 LIMIT 2;", Self::TABLENAME);
+            match sqlx::query_as::<Sqlite, Self>(&sql)
+                .bind(self.rowid())
+                .fetch_one(pool)
+                .await {
+                Ok(row) => {
+                    *self = row;
+                    Ok(())
+                },
+                Err(sqlx::Error::RowNotFound) => {
+                    Err(SsloError::DatabaseIdNotFound(Self::TABLENAME, "rowid", self.rowid()))
+                },
+                Err(e) => {
+                    Err(SsloError::DatabaseSqlx(e))
+                }
+            }
+        }
+    }
+
+    /// Write the data into the database
+    /// When rowid is unequal to '0', an UPDATE is executed,
+    /// When rowid is zero, an insert is executed and rowid is updated
+    /// When INSERT fails, rowid will stay at zero
+    fn store(&mut self, pool: &SqlitePool) -> impl Future<Output = Result<(), SsloError>> + Send {
+        async move {
+            self.sanitize();
+
+            // define query
+            let sql = match self.rowid() {
+                0 => {
+                    let placeholders: Vec<String> = (1..=Self::COLUMNS.len()).map(|i| format!("${}", i)).collect();
+                    format!("INSERT INTO {} ({}) VALUES ({}) RETURNING rowid;",
+                            Self::TABLENAME,
+                            Self::COLUMNS.join(","),
+                            placeholders.join(","))
+                },
+                _ => {
+                    let assignments: Vec<String> = Self::COLUMNS.iter().enumerate()
+                        .map(|(i, column)| format!("{}=${}", column, i + 1))
+                        .collect();
+                    format!("UPDATE {} SET {} WHERE rowid=${};",
+                            Self::TABLENAME,
+                            assignments.join(","),
+                            Self::COLUMNS.len() + 1)
+                }
+            };
+
+            // bind values
+            let rowid = self.rowid();
+            let mut query = self.bind_columns(sqlx::query(&sql));
+            if rowid != 0 {
+                query = query.bind(rowid);
+            }
+
+            // execute query
+            let res = query.execute(pool).await?;
+            if rowid == 0 {
+                self.set_rowid(res.last_insert_rowid());
+            }
+            Ok(())
+        }
+    }
+
+    /// Remove the row from the database
+    /// On success, rowid is reset to zero
+    fn delete(&mut self, pool: &SqlitePool) -> impl Future<Output = Result<(), SsloError>> + Send {
+        async move {
+            let sql = format!("DELETE FROM {} WHERE rowid = $1;", Self::TABLENAME);
+            match sqlx::query(&sql)
+                .bind(self.rowid())
+                .execute(pool)
+                .await {
+                Ok(res) if res.rows_affected() == 0 => {
+                    Err(SsloError::DatabaseIdNotFound(Self::TABLENAME, "rowid", self.rowid()))
+                },
+                Ok(_) => {
+                    self.set_rowid(0);
+                    Ok(())
+                },
+                Err(e) => {
+                    Err(SsloError::DatabaseSqlx(e))
+                }
+            }
+        }
+    }
+
+    /// directly retrieve a single row from the database, where a column matches a certain value
+    fn fetch_one_by<V>(pool: &SqlitePool, column: &'static str, value: V) -> impl Future<Output = Result<Self, SsloError>> + Send
+    where V: for<'q> Encode<'q, Sqlite> + Type<Sqlite> + Display + Send + 'static
+    {
+        async move {
+            let sql = format!("SELECT rowid,* FROM {} WHERE {} = $1 LIMIT 2;", Self::TABLENAME, column);
+            let value_display = value.to_string();
+            match sqlx::query_as::<Sqlite, Self>(&sql)
+                .bind(value)
+                .fetch_one(pool)
+                .await {
+                Ok(row) => Ok(row),
+                Err(sqlx::Error::RowNotFound) => {
+                    Err(SsloError::DatabaseDataNotFound(Self::TABLENAME, column, value_display))
+                },
+                Err(e) => {
+                    Err(SsloError::DatabaseSqlx(e))
+                }
+            }
+        }
+    }
+
+    /// directly retrieve all rows of the table
+    /// The amount of returned rows is limited for safety
+    fn fetch_all(pool: &SqlitePool, limit: u32) -> impl Future<Output = Result<Vec<Self>, SsloError>> + Send {
+        async move {
+            let sql = format!("SELECT rowid,* FROM {} ORDER BY rowid LIMIT $1;", Self::TABLENAME);
+            let rows = sqlx::query_as::<Sqlite, Self>(&sql)
+                .bind(limit)
+                .fetch_all(pool)
+                .await?;
+            if rows.len() >= limit as usize {
+                log::warn!("more than {} rows in {} (truncating for safety)", limit, Self::TABLENAME);
+            }
+            Ok(rows)
+        }
+    }
+
+    /// directly retrieve all rows from the database, where a column matches a certain value
+    /// The amount of returned rows is limited for safety
+    fn fetch_all_by<V>(pool: &SqlitePool, column: &'static str, value: V, limit: u32) -> impl Future<Output = Result<Vec<Self>, SsloError>> + Send
+    where V: for<'q> Encode<'q, Sqlite> + Type<Sqlite> + Display + Send + 'static
+    {
+        async move {
+            let sql = format!("SELECT rowid,* FROM {} WHERE {} = $1 ORDER BY rowid LIMIT $2;", Self::TABLENAME, column);
+            let value_display = value.to_string();
+            let rows = sqlx::query_as::<Sqlite, Self>(&sql)
+                .bind(value)
+                .bind(limit)
+                .fetch_all(pool)
+                .await?;
+            if rows.len() >= limit as usize {
+                log::warn!("more than {} rows in {} for {}={} (truncating for safety)",
+                           limit, Self::TABLENAME, column, value_display);
+            }
+            Ok(rows)
+        }
+    }
+}
+
+
+/// Define a database row structure and implement the DbRow trait for it
+///
+/// The rowid column is added automatically.
+/// Every column must be given with a default value, which is used for new rows.
+/// The optional display and sanitize arguments (after the struct) override the according DbRow methods.
+///
+/// ```ignore
+/// sslo_lib::db_row! {
+///     tablename: "users",
+///     struct DbDataRow {
+///         name: String = String::new(),
+///         last_login: Option<DateTime<Utc>> = None,
+///     }
+///     display: |row| format!("users(id={};name={})", row.rowid, row.name),
+/// }
+/// ```
+#[macro_export]
+macro_rules! db_row {
+    (
+        tablename: $tablename:literal,
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($field_vis:vis $field:ident : $field_type:ty = $field_default:expr),* $(,)?
+        }
+        $(display: |$display_row:ident| $display_body:expr,)?
+        $(sanitize: |$sanitize_row:ident| $sanitize_body:expr,)?
+    ) => {
+        $(#[$meta])*
+        #[derive(sqlx::FromRow, Clone)]
+        $vis struct $name {
+            rowid: i64,
+            $($field_vis $field: $field_type,)*
+        }
+
+        impl $crate::db::DbRow for $name {
+            const TABLENAME: &'static str = $tablename;
+            const COLUMNS: &'static [&'static str] = &[$(stringify!($field)),*];
+
+            fn new(rowid: i64) -> Self {
+                debug_assert!(rowid >= 0);
+                Self {
+                    rowid,
+                    $($field: $field_default,)*
+                }
+            }
+
+            fn rowid(&self) -> i64 { self.rowid }
+
+            fn set_rowid(&mut self, rowid: i64) { self.rowid = rowid; }
+
+            fn bind_columns<'q>(&'q self, query: $crate::db::DbQuery<'q>) -> $crate::db::DbQuery<'q> {
+                query$(.bind(&self.$field))*
+            }
+
+            $(fn display(&self) -> String {
+                let $display_row = self;
+                $display_body
+            })?
+
+            $(fn sanitize(&mut self) {
+                let $sanitize_row = self;
+                $sanitize_body;
+            })?
+        }
+    };
+}
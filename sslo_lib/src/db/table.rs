@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::{Arc, Weak};
+use sqlx::{Encode, Sqlite, SqlitePool, Type};
+use tokio::sync::RwLock;
+use crate::db::DbRow;
+use crate::error::SsloError;
+
+
+/// The actual data of an item that is shared by Arc<RwLock<ItemData>>
+///
+/// R is the database row,
+/// D is the database that owns the table (to access other tables from an item),
+/// X is optional item specific data, which is not stored into the database.
+pub struct ItemData<R: DbRow, D, X = ()> {
+
+    /// dummy items do not have a pool (they are never stored)
+    pub pool: Option<SqlitePool>,
+
+    pub row: R,
+
+    pub db: Weak<RwLock<D>>,
+
+    pub extra: X,
+}
+
+impl<R: DbRow, D, X: Default> ItemData<R, D, X> {
+
+    pub fn new(pool: &SqlitePool, row: R, db: Weak<RwLock<D>>) -> Arc<RwLock<Self>> {
+        Arc::new(RwLock::new(Self {
+            pool: Some(pool.clone()),
+            row,
+            db,
+            extra: X::default(),
+        }))
+    }
+
+    /// Creates an item, which is actually not stored in the database
+    pub fn new_dummy(row: R) -> Arc<RwLock<Self>> {
+        Arc::new(RwLock::new(Self {
+            pool: None,
+            row,
+            db: Weak::new(),
+            extra: X::default(),
+        }))
+    }
+}
+
+impl<R: DbRow, D, X> ItemData<R, D, X> {
+
+    /// Returns a string that can be used for integrating this item into a log message
+    pub fn display(&self) -> String {
+        self.row.display()
+    }
+
+    /// Write the row into the database (dummy items are silently ignored)
+    pub async fn store(&mut self) -> Result<(), SsloError> {
+        match self.pool.clone() {
+            None => Ok(()),
+            Some(pool) => self.row.store(&pool).await,
+        }
+    }
+
+    /// Remove the row from the database (dummy items are silently ignored)
+    pub async fn delete(&mut self) -> Result<(), SsloError> {
+        match self.pool.clone() {
+            None => Ok(()),
+            Some(pool) => self.row.delete(&pool).await,
+        }
+    }
+}
+
+
+/// The shared data of an item
+pub type SharedItemData<I> = Arc<RwLock<ItemData<<I as ItemInterface>::Row, <I as ItemInterface>::Db, <I as ItemInterface>::Extra>>>;
+
+
+/// Interface to shared item data
+/// This shall be implemented by the public item types (e.g. UserItem(Arc<RwLock<ItemData>>))
+pub trait ItemInterface: Sized + Send + Sync + 'static {
+    type Row: DbRow;
+    type Db: Send + Sync + 'static;
+    type Extra: Default + Send + Sync + 'static;
+
+    /// Set up an object from shared data (assumed to be retrieved from database)
+    fn from_item_data(item_data: SharedItemData<Self>) -> Self;
+
+    /// Access the shared data
+    fn item_data(&self) -> &SharedItemData<Self>;
+}
+
+/// The data of a table, that is shared by Arc<RwLock<TableData>>
+pub struct TableData<I: ItemInterface> {
+    pool: SqlitePool,
+    item_cache: HashMap<i64, SharedItemData<I>>,
+    db: Weak<RwLock<I::Db>>,
+}
+
+impl<I: ItemInterface> TableData<I> {
+    pub fn new(pool: SqlitePool, db: Weak<RwLock<I::Db>>) -> Arc<RwLock<Self>> {
+        Arc::new(RwLock::new(Self {
+            pool,
+            item_cache: HashMap::new(),
+            db,
+        }))
+    }
+
+    /// get an item from cache or create a new cached item from a row
+    fn cached_item(&mut self, row: I::Row) -> I {
+        let rowid = row.rowid();
+        if let Some(item_data) = self.item_cache.get(&rowid) {
+            return I::from_item_data(item_data.clone());
+        }
+        let item_data = ItemData::new(&self.pool, row, self.db.clone());
+        self.item_cache.insert(rowid, item_data.clone());
+        I::from_item_data(item_data)
+    }
+}
+
+
+/// Generic table interface, that caches all items that are retrieved from the database
+pub struct TableInterface<I: ItemInterface>(Arc<RwLock<TableData<I>>>);
+
+impl<I: ItemInterface> TableInterface<I> {
+
+    pub fn new(data: Arc<RwLock<TableData<I>>>) -> Self {
+        Self(data)
+    }
+
+    /// The pool of the database, for custom queries
+    pub async fn pool(&self) -> SqlitePool {
+        self.0.read().await.pool.clone()
+    }
+
+    /// The database that owns this table
+    pub async fn db(&self) -> Weak<RwLock<I::Db>> {
+        self.0.read().await.db.clone()
+    }
+
+    /// Amount of currently cached items
+    pub async fn cache_len(&self) -> usize {
+        self.0.read().await.item_cache.len()
+    }
+
+    /// Get an item
+    /// This first tries to load the item from cache,
+    /// and secondly load it from the database.
+    pub async fn item_by_id(&self, id: i64) -> Option<I> {
+
+        {   // try cache hit
+            let tbl_data = self.0.read().await;
+            if let Some(item_data) = tbl_data.item_cache.get(&id) {
+                return Some(I::from_item_data(item_data.clone()));
+            }
+        }
+
+        {   // try loading from DB if not found in cache
+            let mut tbl_data = self.0.write().await;
+
+            // load from db
+            let mut row = I::Row::new(id);
+            match row.load(&tbl_data.pool).await {
+                Ok(_) => { },
+                Err(e) => {
+                    if e.is_db_not_found_type() {
+                        log::warn!("{}", e);
+                    } else {
+                        log::error!("failed to load {}: {}", row.display(), e);
+                    }
+                    return None;
+                },
+            }
+            debug_assert_eq!(row.rowid(), id);
+
+            // create item
+            Some(tbl_data.cached_item(row))
+        }
+    }
+
+    /// Get an item from a row that has been retrieved from the database
+    /// When the item is already cached, the cached item is returned (and the row is dropped)
+    pub async fn item_by_row(&self, row: I::Row) -> I {
+        let mut tbl_data = self.0.write().await;
+        tbl_data.cached_item(row)
+    }
+
+    /// Get items from rows that have been retrieved from the database
+    pub async fn items_by_rows(&self, rows: Vec<I::Row>) -> Vec<I> {
+        let mut tbl_data = self.0.write().await;
+        rows.into_iter().map(|row| tbl_data.cached_item(row)).collect()
+    }
+
+    /// Get an item where a (unique) column matches a certain value
+    pub async fn item_by_column<V>(&self, column: &'static str, value: V) -> Option<I>
+    where V: for<'q> Encode<'q, Sqlite> + Type<Sqlite> + Display + Send + 'static
+    {
+        let pool = self.pool().await;
+        match I::Row::fetch_one_by(&pool, column, value).await {
+            Ok(row) => Some(self.item_by_row(row).await),
+            Err(e) => {
+                if e.is_db_not_found_type() {
+                    log::debug!("{}", e);
+                } else {
+                    log::error!("{}", e);
+                }
+                None
+            }
+        }
+    }
+
+    /// Get all items where a column matches a certain value (limited to 100 items)
+    pub async fn items_by_column<V>(&self, column: &'static str, value: V) -> Vec<I>
+    where V: for<'q> Encode<'q, Sqlite> + Type<Sqlite> + Display + Send + 'static
+    {
+        let pool = self.pool().await;
+        match I::Row::fetch_all_by(&pool, column, value, 100).await {
+            Ok(rows) => self.items_by_rows(rows).await,
+            Err(e) => {
+                log::error!("{}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Get all items of the table (limited to 1000 items)
+    pub async fn items_all(&self) -> Vec<I> {
+        let pool = self.pool().await;
+        match I::Row::fetch_all(&pool, 1000).await {
+            Ok(rows) => self.items_by_rows(rows).await,
+            Err(e) => {
+                log::error!("{}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Store a new row into the database and return the according item
+    pub async fn create_item(&self, mut row: I::Row) -> Result<I, SsloError> {
+        let mut tbl_data = self.0.write().await;
+        debug_assert_eq!(row.rowid(), 0);
+        row.store(&tbl_data.pool).await?;
+        Ok(tbl_data.cached_item(row))
+    }
+
+    /// Re-read all cached items from the database
+    /// This is required after rows have been modified by custom queries.
+    /// Items that do not exist anymore are removed from the cache.
+    pub async fn reload_cache(&self) -> Result<(), SsloError> {
+        let (pool, cached) = {
+            let tbl_data = self.0.read().await;
+            let cached: Vec<(i64, SharedItemData<I>)> = tbl_data.item_cache.iter()
+                .map(|(id, item_data)| (*id, item_data.clone()))
+                .collect();
+            (tbl_data.pool.clone(), cached)
+        };
+
+        let mut vanished = Vec::new();
+        for (id, item_data) in cached.into_iter() {
+            match item_data.write().await.row.load(&pool).await {
+                Ok(_) => {},
+                Err(e) if e.is_db_not_found_type() => vanished.push(id),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut tbl_data = self.0.write().await;
+        for id in vanished.iter() {
+            tbl_data.item_cache.remove(id);
+        }
+        Ok(())
+    }
+
+    /// Remove an item from the cache, after its row was deleted by a custom query
+    pub async fn uncache_item(&self, item: I) {
+        let mut item_data = item.item_data().write().await;
+        let id = item_data.row.rowid();
+        self.0.write().await.item_cache.remove(&id);
+        item_data.row.set_rowid(0);
+    }
+
+    /// Delete an item from the database (and from the cache)
+    pub async fn delete_item(&self, item: I) -> Result<(), SsloError> {
+        let mut item_data = item.item_data().write().await;
+        let id = item_data.row.rowid();
+        self.0.write().await.item_cache.remove(&id);
+        item_data.delete().await
+    }
+}
+
+impl<I: ItemInterface> Clone for TableInterface<I> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::db_row! {
+        tablename: "test_items",
+        struct DbDataRow {
+            name: String = String::new(),
+            value: Option<i64> = None,
+        }
+        display: |row| format!("test_items(id={};name={})", row.rowid, row.name),
+        sanitize: |row| { row.name = row.name.trim().to_string() },
+    }
+
+    struct TestItem(Arc<RwLock<ItemData<DbDataRow, ()>>>);
+
+    impl ItemInterface for TestItem {
+        type Row = DbDataRow;
+        type Db = ();
+        type Extra = ();
+        fn from_item_data(item_data: Arc<RwLock<ItemData<DbDataRow, ()>>>) -> Self { Self(item_data) }
+        fn item_data(&self) -> &Arc<RwLock<ItemData<DbDataRow, ()>>> { &self.0 }
+    }
+
+    async fn get_pool() -> SqlitePool {
+        let pool = crate::db::get_pool(None);
+        sqlx::query("CREATE TABLE test_items (rowid INTEGER PRIMARY KEY, name TEXT NOT NULL, value INTEGER);")
+            .execute(&pool).await.unwrap();
+        pool
+    }
+
+    async fn get_table() -> TableInterface<TestItem> {
+        TableInterface::new(TableData::new(get_pool().await, Weak::new()))
+    }
+
+    #[tokio::test]
+    async fn row_new_defaults() {
+        let row = DbDataRow::new(33);
+        assert_eq!(row.rowid, 33);
+        assert_eq!(row.name, "");
+        assert_eq!(row.value, None);
+        assert_eq!(row.display(), "test_items(id=33;name=)");
+        assert_eq!(DbDataRow::COLUMNS, &["name", "value"]);
+    }
+
+    #[tokio::test]
+    async fn row_load_store_delete() {
+        let pool = get_pool().await;
+
+        // store (insert)
+        let mut row = DbDataRow::new(0);
+        row.name = " Foo\n".to_string();
+        row.value = Some(44);
+        row.store(&pool).await.unwrap();
+        assert_eq!(row.rowid, 1);
+        assert_eq!(row.name, "Foo");
+
+        // load
+        let mut row = DbDataRow::new(1);
+        row.load(&pool).await.unwrap();
+        assert_eq!(row.name, "Foo");
+        assert_eq!(row.value, Some(44));
+
+        // store (update)
+        row.value = None;
+        row.store(&pool).await.unwrap();
+        let mut row = DbDataRow::new(1);
+        row.load(&pool).await.unwrap();
+        assert_eq!(row.rowid, 1);
+        assert_eq!(row.value, None);
+
+        // fetch by column
+        let row = DbDataRow::fetch_one_by(&pool, "name", "Foo".to_string()).await.unwrap();
+        assert_eq!(row.rowid, 1);
+        assert!(DbDataRow::fetch_one_by(&pool, "name", "Bar".to_string()).await.err().unwrap().is_db_not_found_type());
+
+        // delete
+        let mut row = DbDataRow::new(1);
+        row.delete(&pool).await.unwrap();
+        assert_eq!(row.rowid, 0);
+        let mut row = DbDataRow::new(1);
+        assert!(row.load(&pool).await.unwrap_err().is_db_not_found_type());
+        assert!(row.delete(&pool).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn table_cache() {
+        let tbl = get_table().await;
+        assert_eq!(tbl.cache_len().await, 0);
+
+        // create items
+        let mut row = DbDataRow::new(0);
+        row.name = "Foo".to_string();
+        let item1 = tbl.create_item(row).await.unwrap();
+        let mut row = DbDataRow::new(0);
+        row.name = "Bar".to_string();
+        row.value = Some(3);
+        tbl.create_item(row).await.unwrap();
+        assert_eq!(tbl.cache_len().await, 2);
+
+        // modify item, and check that cached item is shared
+        {
+            let mut item_data = item1.0.write().await;
+            item_data.row.value = Some(7);
+            item_data.store().await.unwrap();
+        }
+        let item = tbl.item_by_id(1).await.unwrap();
+        assert_eq!(item.0.read().await.row.value, Some(7));
+        assert!(tbl.item_by_id(3).await.is_none());
+
+        // find by column
+        let item = tbl.item_by_column("name", "Bar".to_string()).await.unwrap();
+        assert_eq!(item.0.read().await.row.rowid, 2);
+        assert_eq!(tbl.items_by_column("value", 7).await.len(), 1);
+        assert_eq!(tbl.items_all().await.len(), 2);
+
+        // delete
+        tbl.delete_item(item1).await.unwrap();
+        assert_eq!(tbl.cache_len().await, 1);
+        assert!(tbl.item_by_id(1).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn table_reload_cache() {
+        let tbl = get_table().await;
+        let mut row = DbDataRow::new(0);
+        row.name = "Foo".to_string();
+        let item1 = tbl.create_item(row).await.unwrap();
+        let mut row = DbDataRow::new(0);
+        row.name = "Bar".to_string();
+        tbl.create_item(row).await.unwrap();
+
+        // modify rows behind the cache
+        let pool = tbl.pool().await;
+        sqlx::query("UPDATE test_items SET value = 9 WHERE rowid = 1;").execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM test_items WHERE rowid = 2;").execute(&pool).await.unwrap();
+        assert_eq!(item1.0.read().await.row.value, None);
+
+        tbl.reload_cache().await.unwrap();
+        assert_eq!(item1.0.read().await.row.value, Some(9));
+        assert_eq!(tbl.cache_len().await, 1);
+        assert!(tbl.item_by_id(2).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn table_load_uncached() {
+        let pool = get_pool().await;
+        sqlx::query("INSERT INTO test_items (rowid, name) VALUES (5, 'Foo');").execute(&pool).await.unwrap();
+        let tbl: TableInterface<TestItem> = TableInterface::new(TableData::new(pool, Weak::new()));
+        assert_eq!(tbl.cache_len().await, 0);
+        let item = tbl.item_by_id(5).await.unwrap();
+        assert_eq!(item.0.read().await.row.name, "Foo");
+        assert_eq!(tbl.cache_len().await, 1);
+    }
+}
@@ -39,6 +39,22 @@ pub enum SsloError {
 
     #[error("error at database migration: {0}")]
     DatabaseMigrationError(#[from] sqlx::migrate::MigrateError),
+
+    /// url, error description
+    #[error("federation request to '{0}' failed: {1}")]
+    FederationRequestFailed(String, String),
+
+    /// url, error description
+    #[error("OAuth2 request to '{0}' failed: {1}")]
+    OAuthRequestFailed(String, String),
+
+    /// file name, error description
+    #[error("failed to import result file '{0}': {1}")]
+    ResultImportFailed(String, String),
+
+    /// error description
+    #[error("WebAuthn verification failed: {0}")]
+    WebauthnVerificationFailed(String),
 }
 
 impl SsloError {
@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use crate::db::members::users::{PromotionAuthority, PromotionLevel};
+
+/// http header that contains the name of the requesting lobby
+pub const HEADER_LOBBY: &str = "X-SSLO-Lobby";
+
+/// http header that contains the unix timestamp (seconds) of the request
+pub const HEADER_TIMESTAMP: &str = "X-SSLO-Timestamp";
+
+/// http header that contains the hex encoded HMAC-SHA256 signature
+pub const HEADER_SIGNATURE: &str = "X-SSLO-Signature";
+
+/// maximum allowed deviation of the request timestamp (to limit replay attacks)
+pub const MAX_TIMESTAMP_DEVIATION_SECONDS: i64 = 60;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The user information that a league shares with its lobbies
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FederatedUser {
+    pub id: i64,
+    pub name: String,
+    pub promotion_level: PromotionLevel,
+    pub promotion_authority: PromotionAuthority,
+    pub last_lap: Option<DateTime<Utc>>,
+    pub last_login: Option<DateTime<Utc>>,
+}
+
+fn mac(secret: &str, lobby: &str, timestamp: i64, path: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take keys of any size");
+    mac.update(lobby.as_bytes());
+    mac.update(b"\n");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b"\n");
+    mac.update(path.as_bytes());
+    mac
+}
+
+/// Create the signature for a request
+/// The path shall contain the query (e.g. '/api/v0/federation/user/id/1')
+pub fn sign(secret: &str, lobby: &str, timestamp: i64, path: &str) -> String {
+    hex::encode(mac(secret, lobby, timestamp, path).finalize().into_bytes())
+}
+
+/// Returns the headers (name, value) that a lobby shall send with a request to the league
+pub fn signed_headers(secret: &str, lobby: &str, path: &str) -> [(&'static str, String); 3] {
+    let timestamp = Utc::now().timestamp();
+    [
+        (HEADER_LOBBY, lobby.to_string()),
+        (HEADER_TIMESTAMP, timestamp.to_string()),
+        (HEADER_SIGNATURE, sign(secret, lobby, timestamp, path)),
+    ]
+}
+
+/// Verify the signature of a request
+/// This also checks that the timestamp is not too far away from now
+pub fn verify(secret: &str, lobby: &str, timestamp: i64, path: &str, signature: &str) -> bool {
+
+    // check timestamp
+    let deviation = (Utc::now().timestamp() - timestamp).abs();
+    if deviation > MAX_TIMESTAMP_DEVIATION_SECONDS {
+        log::warn!("deny federation request from lobby '{}', because timestamp deviates by {}s", lobby, deviation);
+        return false;
+    }
+
+    // check signature
+    let signature = match hex::decode(signature) {
+        Ok(s) => s,
+        Err(_) => {
+            log::warn!("deny federation request from lobby '{}', because signature is not hex encoded", lobby);
+            return false;
+        }
+    };
+    if mac(secret, lobby, timestamp, path).verify_slice(&signature).is_err() {
+        log::warn!("deny federation request from lobby '{}', because of invalid signature", lobby);
+        return false;
+    }
+
+    true
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_verify() {
+        let now = Utc::now().timestamp();
+        let signature = sign("secret", "lobby1", now, "/api/v0/federation/user/id/1");
+        assert_eq!(signature.len(), 64);
+        assert!(verify("secret", "lobby1", now, "/api/v0/federation/user/id/1", &signature));
+
+        // manipulations
+        assert!(!verify("secreT", "lobby1", now, "/api/v0/federation/user/id/1", &signature));
+        assert!(!verify("secret", "lobby2", now, "/api/v0/federation/user/id/1", &signature));
+        assert!(!verify("secret", "lobby1", now + 1, "/api/v0/federation/user/id/1", &signature));
+        assert!(!verify("secret", "lobby1", now, "/api/v0/federation/user/id/2", &signature));
+        assert!(!verify("secret", "lobby1", now, "/api/v0/federation/user/id/1", "nohex"));
+    }
+
+    #[test]
+    fn outdated() {
+        let past = Utc::now().timestamp() - MAX_TIMESTAMP_DEVIATION_SECONDS - 5;
+        let signature = sign("secret", "lobby1", past, "/foo");
+        assert!(!verify("secret", "lobby1", past, "/foo", &signature));
+    }
+
+    #[test]
+    fn headers() {
+        let headers = signed_headers("secret", "lobby1", "/foo");
+        assert_eq!(headers[0], (HEADER_LOBBY, "lobby1".to_string()));
+        let timestamp: i64 = headers[1].1.parse().unwrap();
+        assert!(verify("secret", "lobby1", timestamp, "/foo", &headers[2].1));
+    }
+
+    #[test]
+    fn user_json() {
+        let user = FederatedUser {
+            id: 3,
+            name: "Foo".to_string(),
+            promotion_level: PromotionLevel::Marshal,
+            promotion_authority: PromotionAuthority::Chief,
+            last_lap: None,
+            last_login: Some(Utc::now()),
+        };
+        let json = serde_json::to_string(&user).unwrap();
+        let user2: FederatedUser = serde_json::from_str(&json).unwrap();
+        assert_eq!(user, user2);
+    }
+}
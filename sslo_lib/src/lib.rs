@@ -3,3 +3,9 @@ pub mod token;
 pub mod db;
 pub mod error;
 pub mod optional_date;
+#[cfg(feature = "federation")]
+pub mod federation;
+#[cfg(feature = "members_totp")]
+pub mod totp;
+#[cfg(feature = "members_webauthn")]
+pub mod webauthn;
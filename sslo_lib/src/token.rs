@@ -44,11 +44,17 @@ impl Token {
     }
 
     pub fn generate(token_type: TokenType) -> Result<Self, Box<dyn Error>> {
+        Self::generate_sized(token_type, 64)
+    }
+
+    /// Generate a token with a certain amount of random bytes
+    /// (shorter tokens can be typed by users, eg. recovery codes)
+    pub fn generate_sized(token_type: TokenType, byte_count: usize) -> Result<Self, Box<dyn Error>> {
 
         // create a random token
-        let mut token: [u8; 64] = [0; 64];
+        let mut token: Vec<u8> = vec![0; byte_count];
         rand::thread_rng().fill_bytes(&mut token);
-        let decrypted: String = hex::encode(token);
+        let decrypted: String = hex::encode(&token);
 
         // create a salt
         let mut salt: Vec<u8> = vec![0u8; 64];
@@ -88,6 +94,13 @@ mod tests {
         assert!(token.verify());
     }
 
+    #[test]
+    fn sized() {
+        let token = Token::generate_sized(TokenType::Strong, 5).unwrap();
+        assert_eq!(token.decrypted.len(), 10);
+        assert!(token.verify());
+    }
+
     #[test]
     fn manipulation() {
         let mut token = Token::generate(TokenType::Strong).unwrap();
@@ -0,0 +1,178 @@
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use aes_gcm::aead::Aead;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use crate::error::SsloError;
+
+/// amount of digits of a code
+pub const DIGITS: u32 = 6;
+
+/// seconds of a time step
+pub const PERIOD: i64 = 30;
+
+/// amount of time steps that a code is accepted before/after the current time step (clock drift)
+pub const SKEW: i64 = 1;
+
+/// length of a generated secret in bytes
+pub const SECRET_LENGTH: usize = 20;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+type HmacSha1 = Hmac<Sha1>;
+
+/// Create a new random secret
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; SECRET_LENGTH];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+/// RFC 4648 base32 encoding without padding (as used by authenticator apps)
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits: u32 = 0;
+    for byte in data {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(char::from(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize]));
+        }
+    }
+    if bits > 0 {
+        encoded.push(char::from(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize]));
+    }
+    encoded
+}
+
+fn uri_encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(char::from(byte)),
+            _ => encoded += &format!("%{:02X}", byte),
+        }
+    }
+    encoded
+}
+
+/// The otpauth URI that can be shown as QR code to enroll an authenticator app
+pub fn provisioning_uri(secret: &[u8], issuer: &str, account: &str) -> String {
+    format!("otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            uri_encode(issuer), uri_encode(account), base32_encode(secret), uri_encode(issuer), DIGITS, PERIOD)
+}
+
+/// The HOTP code of a counter value (RFC 4226)
+pub fn code(secret: &[u8], counter: i64) -> String {
+    let mut mac = <HmacSha1 as Mac>::new_from_slice(secret).expect("HMAC can take keys of any size");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([hash[offset] & 0x7f, hash[offset + 1], hash[offset + 2], hash[offset + 3]]);
+    format!("{:0width$}", binary % 10u32.pow(DIGITS), width = DIGITS as usize)
+}
+
+/// The time step of a unix timestamp
+pub fn time_step(timestamp: i64) -> i64 {
+    timestamp.div_euclid(PERIOD)
+}
+
+/// Verify a code for a unix timestamp (RFC 6238)
+/// Returns the matching time step, which shall be remembered to prevent replays
+pub fn verify(secret: &[u8], code_input: &str, timestamp: i64) -> Option<i64> {
+    let code_input = code_input.trim();
+    if code_input.len() != DIGITS as usize {
+        return None;
+    }
+    let current_step = time_step(timestamp);
+    (current_step - SKEW ..= current_step + SKEW).find(|step| code(secret, *step) == code_input)
+}
+
+fn cipher(key: &str) -> Aes256Gcm {
+    let key_hash = Sha256::digest(key.as_bytes());
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_hash))
+}
+
+/// Encrypt a secret for storing it into the database (AES-256-GCM, hex encoded nonce and ciphertext)
+pub fn encrypt_secret(key: &str, secret: &[u8]) -> Result<String, SsloError> {
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = cipher(key).encrypt(Nonce::from_slice(&nonce), secret)
+        .map_err(|e| SsloError::GeneralError(format!("failed to encrypt TOTP secret: {}", e)))?;
+    Ok(hex::encode(nonce) + &hex::encode(ciphertext))
+}
+
+/// Decrypt a secret from the database
+pub fn decrypt_secret(key: &str, encrypted: &str) -> Result<Vec<u8>, SsloError> {
+    let data = hex::decode(encrypted)
+        .map_err(|e| SsloError::GeneralError(format!("failed to decode TOTP secret: {}", e)))?;
+    if data.len() < 12 {
+        return Err(SsloError::GeneralError("encrypted TOTP secret too short".to_string()));
+    }
+    cipher(key).decrypt(Nonce::from_slice(&data[..12]), &data[12..])
+        .map_err(|e| SsloError::GeneralError(format!("failed to decrypt TOTP secret: {}", e)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// test secret from RFC 6238 appendix B (SHA1)
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn rfc6238_vectors() {
+        // the RFC uses 8 digits, the last 6 digits must match
+        for (timestamp, expected) in [(59, "94287082"), (1111111109, "07081804"), (1111111111, "14050471"),
+                                      (1234567890, "89005924"), (2000000000, "69279037")] {
+            assert_eq!(code(RFC_SECRET, time_step(timestamp)), expected[2..]);
+        }
+    }
+
+    #[test]
+    fn verification() {
+        let timestamp = 1234567890;
+        let current = code(RFC_SECRET, time_step(timestamp));
+        assert_eq!(verify(RFC_SECRET, &current, timestamp), Some(time_step(timestamp)));
+        assert_eq!(verify(RFC_SECRET, &format!(" {}\n", current), timestamp), Some(time_step(timestamp)));
+
+        // clock drift of one step is accepted
+        let previous = code(RFC_SECRET, time_step(timestamp) - 1);
+        assert_eq!(verify(RFC_SECRET, &previous, timestamp), Some(time_step(timestamp) - 1));
+        let outdated = code(RFC_SECRET, time_step(timestamp) - 2);
+        assert_eq!(verify(RFC_SECRET, &outdated, timestamp), None);
+
+        // invalid codes
+        assert_eq!(verify(RFC_SECRET, "", timestamp), None);
+        assert_eq!(verify(RFC_SECRET, "12345", timestamp), None);
+    }
+
+    #[test]
+    fn base32() {
+        assert_eq!(base32_encode(b""), "");
+        assert_eq!(base32_encode(b"f"), "MY");
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_encode(RFC_SECRET), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+    }
+
+    #[test]
+    fn uri() {
+        let uri = provisioning_uri(RFC_SECRET, "SSLO League", "Foo Bar");
+        assert_eq!(uri, "otpauth://totp/SSLO%20League:Foo%20Bar?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=SSLO%20League&algorithm=SHA1&digits=6&period=30");
+    }
+
+    #[test]
+    fn encryption() {
+        let secret = generate_secret();
+        assert_eq!(secret.len(), SECRET_LENGTH);
+        let encrypted = encrypt_secret("my key", &secret).unwrap();
+        assert_ne!(encrypted, encrypt_secret("my key", &secret).unwrap());  // random nonce
+        assert_eq!(decrypt_secret("my key", &encrypted).unwrap(), secret);
+        assert!(decrypt_secret("other key", &encrypted).is_err());
+        assert!(decrypt_secret("my key", "00").is_err());
+    }
+}
@@ -0,0 +1,361 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ciborium::Value;
+use p256::ecdsa::{Signature, VerifyingKey};
+use p256::ecdsa::signature::Verifier;
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use crate::error::SsloError;
+
+/// COSE algorithm identifier of ECDSA with P-256 and SHA-256 (the only supported algorithm)
+pub const ALGORITHM_ES256: i64 = -7;
+
+/// length of a generated challenge in bytes
+pub const CHALLENGE_LENGTH: usize = 32;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// The relying party (the league server) that credentials are bound to
+pub struct RelyingParty {
+
+    /// the domain name (eg. "league.tld")
+    pub id: String,
+
+    /// the origin of the web page (eg. "https://league.tld:8443")
+    pub origin: String,
+}
+
+/// A credential that has been verified at registration
+pub struct NewCredential {
+    pub credential_id: Vec<u8>,
+
+    /// SEC1 encoded P-256 public key
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+
+    /// the challenge that was signed (must be checked by the caller)
+    pub challenge: String,
+}
+
+/// A verified login assertion
+pub struct Assertion {
+    pub sign_count: u32,
+
+    /// the challenge that was signed (must be checked by the caller)
+    pub challenge: String,
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony: String,
+    challenge: String,
+    origin: String,
+}
+
+fn error(description: &str) -> SsloError {
+    SsloError::WebauthnVerificationFailed(description.to_string())
+}
+
+impl RelyingParty {
+
+    /// Relying party for the public URL of a server (eg. "https://league.tld:8443/")
+    /// A URL without scheme is assumed to be https, a path is ignored.
+    pub fn new(public_url: &str) -> Self {
+        let (scheme, rest) = public_url.split_once("://").unwrap_or(("https", public_url));
+        let host = rest.split('/').next().unwrap_or_default();
+        let id = match host.rsplit_once(':') {
+            Some((domain, port)) if port.chars().all(|c| c.is_ascii_digit()) => domain,
+            _ => host,
+        };
+        Self {
+            id: id.to_string(),
+            origin: format!("{}://{}", scheme, host),
+        }
+    }
+
+    fn verify_client_data(&self, client_data_json: &[u8], ceremony: &str) -> Result<ClientData, SsloError> {
+        let client_data: ClientData = serde_json::from_slice(client_data_json)
+            .map_err(|e| error(&format!("invalid client data: {}", e)))?;
+        if client_data.ceremony != ceremony {
+            return Err(error(&format!("unexpected ceremony '{}'", client_data.ceremony)));
+        }
+        if client_data.origin != self.origin {
+            return Err(error(&format!("unexpected origin '{}'", client_data.origin)));
+        }
+        Ok(client_data)
+    }
+
+    /// check the relying party hash and the flags, returns the sign count
+    fn verify_authenticator_data(&self, authenticator_data: &[u8]) -> Result<u32, SsloError> {
+        if authenticator_data.len() < 37 {
+            return Err(error("authenticator data too short"));
+        }
+        if authenticator_data[..32] != Sha256::digest(self.id.as_bytes())[..] {
+            return Err(error("credential is bound to another relying party"));
+        }
+        let flags = authenticator_data[32];
+        if flags & FLAG_USER_PRESENT == 0 || flags & FLAG_USER_VERIFIED == 0 {
+            return Err(error("user not verified"));
+        }
+        Ok(u32::from_be_bytes([authenticator_data[33], authenticator_data[34], authenticator_data[35], authenticator_data[36]]))
+    }
+
+    /// Verify the response of navigator.credentials.create()
+    pub fn verify_registration(&self, client_data_json: &[u8], attestation_object: &[u8]) -> Result<NewCredential, SsloError> {
+        let client_data = self.verify_client_data(client_data_json, "webauthn.create")?;
+
+        // the attestation statement is not verified (attestation "none" is requested)
+        let attestation: Value = ciborium::de::from_reader(attestation_object)
+            .map_err(|e| error(&format!("invalid attestation object: {}", e)))?;
+        let auth_data = attestation.as_map()
+            .and_then(|map| map.iter().find(|(key, _)| key.as_text() == Some("authData")))
+            .and_then(|(_, value)| value.as_bytes())
+            .ok_or_else(|| error("attestation object without authenticator data"))?;
+        let sign_count = self.verify_authenticator_data(auth_data)?;
+        if auth_data[32] & FLAG_ATTESTED_CREDENTIAL_DATA == 0 || auth_data.len() < 55 {
+            return Err(error("no attested credential data"));
+        }
+
+        // attested credential data: aaguid (16 bytes), id length (2 bytes), id, public key
+        let id_length = usize::from(u16::from_be_bytes([auth_data[53], auth_data[54]]));
+        let credential_id = auth_data.get(55..55 + id_length).ok_or_else(|| error("credential id too short"))?.to_vec();
+        let public_key = cose_public_key(&auth_data[55 + id_length..])?;
+
+        Ok(NewCredential {
+            credential_id,
+            public_key,
+            sign_count,
+            challenge: client_data.challenge,
+        })
+    }
+
+    /// The challenge of a navigator.credentials.get() response (the assertion itself is not verified)
+    pub fn assertion_challenge(&self, client_data_json: &[u8]) -> Result<String, SsloError> {
+        Ok(self.verify_client_data(client_data_json, "webauthn.get")?.challenge)
+    }
+
+    /// Verify the response of navigator.credentials.get() against a registered public key
+    pub fn verify_assertion(&self, public_key: &[u8], client_data_json: &[u8], authenticator_data: &[u8], signature: &[u8]) -> Result<Assertion, SsloError> {
+        let client_data = self.verify_client_data(client_data_json, "webauthn.get")?;
+        let sign_count = self.verify_authenticator_data(authenticator_data)?;
+
+        let key = VerifyingKey::from_sec1_bytes(public_key).map_err(|_| error("invalid public key"))?;
+        let signature = Signature::from_der(signature).map_err(|_| error("invalid signature encoding"))?;
+        let mut message = authenticator_data.to_vec();
+        message.extend_from_slice(&Sha256::digest(client_data_json));
+        key.verify(&message, &signature).map_err(|_| error("invalid signature"))?;
+
+        Ok(Assertion {
+            sign_count,
+            challenge: client_data.challenge,
+        })
+    }
+}
+
+/// Convert a COSE EC2 key (ES256) into a SEC1 encoded public key
+fn cose_public_key(data: &[u8]) -> Result<Vec<u8>, SsloError> {
+    let key: Value = ciborium::de::from_reader(data).map_err(|e| error(&format!("invalid public key: {}", e)))?;
+    let map = key.as_map().ok_or_else(|| error("invalid public key"))?;
+    let get = |label: i64| map.iter()
+        .find(|(key, _)| key.as_integer().is_some_and(|k| i128::from(k) == i128::from(label)))
+        .map(|(_, value)| value);
+    let integer = |label: i64| get(label).and_then(|v| v.as_integer()).map(i128::from);
+    if integer(1) != Some(2) || integer(3) != Some(i128::from(ALGORITHM_ES256)) || integer(-1) != Some(1) {
+        return Err(error("unsupported public key algorithm"));
+    }
+    let x = get(-2).and_then(|v| v.as_bytes()).ok_or_else(|| error("public key without x coordinate"))?;
+    let y = get(-3).and_then(|v| v.as_bytes()).ok_or_else(|| error("public key without y coordinate"))?;
+    let mut sec1 = vec![0x04];
+    sec1.extend_from_slice(x);
+    sec1.extend_from_slice(y);
+    VerifyingKey::from_sec1_bytes(&sec1).map_err(|_| error("invalid public key"))?;
+    Ok(sec1)
+}
+
+/// Create a new random challenge (base64url encoded)
+pub fn generate_challenge() -> String {
+    let mut challenge = [0u8; CHALLENGE_LENGTH];
+    rand::thread_rng().fill_bytes(&mut challenge);
+    base64url_encode(&challenge)
+}
+
+/// base64url encoding without padding (as used by WebAuthn)
+pub fn base64url_encode(data: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(data)
+}
+
+pub fn base64url_decode(data: &str) -> Result<Vec<u8>, SsloError> {
+    URL_SAFE_NO_PAD.decode(data.trim_end_matches('='))
+        .map_err(|e| error(&format!("invalid base64url encoding: {}", e)))
+}
+
+
+/// An authenticator in software, to test the WebAuthn ceremonies
+#[cfg(test)]
+pub(crate) mod software_authenticator {
+    use ciborium::Value;
+    use p256::ecdsa::{Signature, SigningKey};
+    use p256::ecdsa::signature::Signer;
+    use rand::RngCore;
+    use sha2::{Digest, Sha256};
+    use super::{base64url_encode, RelyingParty};
+
+    pub struct SoftwareAuthenticator {
+        pub credential_id: Vec<u8>,
+        key: SigningKey,
+        pub sign_count: u32,
+    }
+
+    impl SoftwareAuthenticator {
+
+        pub fn new() -> Self {
+            let mut credential_id = vec![0u8; 16];
+            rand::thread_rng().fill_bytes(&mut credential_id);
+            Self {
+                credential_id,
+                key: SigningKey::random(&mut rand::thread_rng()),
+                sign_count: 0,
+            }
+        }
+
+        fn authenticator_data(&self, rp: &RelyingParty, flags: u8) -> Vec<u8> {
+            let mut data = Sha256::digest(rp.id.as_bytes()).to_vec();
+            data.push(flags);
+            data.extend_from_slice(&self.sign_count.to_be_bytes());
+            data
+        }
+
+        pub fn client_data(ceremony: &str, challenge: &str, origin: &str) -> Vec<u8> {
+            format!("{{\"type\":\"{}\",\"challenge\":\"{}\",\"origin\":\"{}\",\"crossOrigin\":false}}",
+                    ceremony, challenge, origin).into_bytes()
+        }
+
+        /// Returns client data json and attestation object
+        pub fn register(&self, rp: &RelyingParty, challenge: &str) -> (Vec<u8>, Vec<u8>) {
+            let point = self.key.verifying_key().to_encoded_point(false);
+            let cose_key = Value::Map(vec![
+                (Value::from(1), Value::from(2)),
+                (Value::from(3), Value::from(-7)),
+                (Value::from(-1), Value::from(1)),
+                (Value::from(-2), Value::Bytes(point.x().unwrap().to_vec())),
+                (Value::from(-3), Value::Bytes(point.y().unwrap().to_vec())),
+            ]);
+            let mut auth_data = self.authenticator_data(rp, 0x45);
+            auth_data.extend_from_slice(&[0u8; 16]);
+            auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            auth_data.extend_from_slice(&self.credential_id);
+            ciborium::ser::into_writer(&cose_key, &mut auth_data).unwrap();
+
+            let attestation = Value::Map(vec![
+                (Value::from("fmt"), Value::from("none")),
+                (Value::from("attStmt"), Value::Map(vec![])),
+                (Value::from("authData"), Value::Bytes(auth_data)),
+            ]);
+            let mut attestation_object = Vec::new();
+            ciborium::ser::into_writer(&attestation, &mut attestation_object).unwrap();
+            (Self::client_data("webauthn.create", challenge, &rp.origin), attestation_object)
+        }
+
+        /// Returns client data json, authenticator data and signature
+        pub fn sign(&mut self, rp: &RelyingParty, challenge: &str) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+            self.sign_count += 1;
+            let client_data = Self::client_data("webauthn.get", challenge, &rp.origin);
+            let auth_data = self.authenticator_data(rp, 0x05);
+            let mut message = auth_data.clone();
+            message.extend_from_slice(&Sha256::digest(&client_data));
+            let signature: Signature = self.key.sign(&message);
+            (client_data, auth_data, signature.to_der().as_bytes().to_vec())
+        }
+
+        pub fn credential_id_base64(&self) -> String {
+            base64url_encode(&self.credential_id)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::software_authenticator::SoftwareAuthenticator;
+
+    #[test]
+    fn relying_party() {
+        let rp = RelyingParty::new("https://league.tld:8443");
+        assert_eq!(rp.id, "league.tld");
+        assert_eq!(rp.origin, "https://league.tld:8443");
+        let rp = RelyingParty::new("https://league.tld/");
+        assert_eq!(rp.id, "league.tld");
+        assert_eq!(rp.origin, "https://league.tld");
+        let rp = RelyingParty::new("http://localhost:8080/league");
+        assert_eq!(rp.id, "localhost");
+        assert_eq!(rp.origin, "http://localhost:8080");
+        let rp = RelyingParty::new("league.tld");
+        assert_eq!(rp.id, "league.tld");
+        assert_eq!(rp.origin, "https://league.tld");
+    }
+
+    #[test]
+    fn base64url() {
+        let challenge = generate_challenge();
+        assert_eq!(base64url_decode(&challenge).unwrap().len(), CHALLENGE_LENGTH);
+        assert_eq!(base64url_encode(&[0xfb, 0xff]), "-_8");
+        assert_eq!(base64url_decode("-_8=").unwrap(), vec![0xfb, 0xff]);
+        assert!(base64url_decode("no base64!").is_err());
+    }
+
+    #[test]
+    fn registration() {
+        let rp = RelyingParty::new("league.tld");
+        let authenticator = SoftwareAuthenticator::new();
+        let challenge = generate_challenge();
+
+        let (client_data, attestation_object) = authenticator.register(&rp, &challenge);
+        let credential = rp.verify_registration(&client_data, &attestation_object).unwrap();
+        assert_eq!(credential.credential_id, authenticator.credential_id);
+        assert_eq!(credential.challenge, challenge);
+        assert_eq!(credential.sign_count, 0);
+
+        // other relying party
+        let rp_other = RelyingParty::new("other.tld");
+        assert!(rp_other.verify_registration(&client_data, &attestation_object).is_err());
+        let (client_data_other, attestation_object_other) = authenticator.register(&rp_other, &challenge);
+        assert!(rp.verify_registration(&client_data_other, &attestation_object_other).is_err());
+
+        // wrong ceremony
+        let client_data = SoftwareAuthenticator::client_data("webauthn.get", &challenge, &rp.origin);
+        assert!(rp.verify_registration(&client_data, &attestation_object).is_err());
+    }
+
+    #[test]
+    fn assertion() {
+        let rp = RelyingParty::new("league.tld");
+        let mut authenticator = SoftwareAuthenticator::new();
+        let (client_data, attestation_object) = authenticator.register(&rp, &generate_challenge());
+        let credential = rp.verify_registration(&client_data, &attestation_object).unwrap();
+
+        let challenge = generate_challenge();
+        let (client_data, auth_data, signature) = authenticator.sign(&rp, &challenge);
+        let assertion = rp.verify_assertion(&credential.public_key, &client_data, &auth_data, &signature).unwrap();
+        assert_eq!(assertion.challenge, challenge);
+        assert_eq!(assertion.sign_count, 1);
+
+        // manipulated data
+        let mut client_data_manipulated = client_data.clone();
+        client_data_manipulated.push(b' ');
+        assert!(rp.verify_assertion(&credential.public_key, &client_data_manipulated, &auth_data, &signature).is_err());
+        let mut auth_data_manipulated = auth_data.clone();
+        auth_data_manipulated[36] = 99;
+        assert!(rp.verify_assertion(&credential.public_key, &client_data, &auth_data_manipulated, &signature).is_err());
+
+        // other key
+        let other = SoftwareAuthenticator::new();
+        let (other_client_data, other_attestation) = other.register(&rp, &challenge);
+        let other_credential = rp.verify_registration(&other_client_data, &other_attestation).unwrap();
+        assert!(rp.verify_assertion(&other_credential.public_key, &client_data, &auth_data, &signature).is_err());
+    }
+}
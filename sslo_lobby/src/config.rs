@@ -10,6 +10,9 @@ pub struct Config {
 
     /// Configuration for the http(s) server(s)
     pub http: Http,
+
+    /// The league this lobby belongs to (users are synchronized from the league at login)
+    pub league: Option<League>,
 }
 
 
@@ -56,3 +59,18 @@ pub struct Http {
     /// Path to the TLS key file in PEM format
     pub tls_key: PathBuf,
 }
+
+
+#[derive(Deserialize, Clone)]
+/// Connection to the league (see sslo_lib::federation)
+pub struct League {
+
+    /// The base url of the league (e.g. 'https://my-league.tld')
+    pub url: String,
+
+    /// The name, this lobby uses to identify itself at the league
+    pub lobby_name: String,
+
+    /// The shared secret (must be identical to the secret in the league config)
+    pub secret: String,
+}
@@ -1,10 +1,8 @@
-pub mod members;
-
 use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use sslo_lib::error::SsloError;
-use members::{MembersDbData, MembersDbInterface};
+use sslo_lib::db::members::{MembersDbData, MembersDbInterface};
 
 struct DatabaseManagerData {
     db_members: Arc<RwLock<MembersDbData>>,
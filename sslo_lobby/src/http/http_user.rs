@@ -3,8 +3,8 @@ use axum::http::header;
 use axum::http::request::Parts;
 use chrono::Utc;
 use crate::app_state::AppState;
-use crate::db::members::users::UserItem;
-use crate::db::members::cookie_logins::CookieLoginItem;
+use sslo_lib::db::members::users::UserItem;
+use sslo_lib::db::members::cookie_logins::CookieLoginItem;
 
 /// Representing the current user of the http service
 pub struct HttpUser {
@@ -66,7 +66,7 @@ where
                     if let Some(mut cl_user) = cl.user().await {
 
                         // track user login
-                        // TODO ???.set_last_login(Utc::now()).await;
+                        cl_user.set_last_login(Utc::now()).await;
 
                         // create http user
                         let http_user = HttpUser {
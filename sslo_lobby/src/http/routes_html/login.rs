@@ -3,10 +3,11 @@ use axum::http;
 use axum::http::header::{REFRESH, SET_COOKIE};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
+use chrono::Utc;
 use crate::http::HtmlTemplate;
 use crate::app_state::AppState;
-use crate::db::members::users::UserItem;
-use crate::db::members::steam_accounts::SteamAccountItem;
+use sslo_lib::db::members::users::UserItem;
+use sslo_lib::db::members::steam_accounts::SteamAccountItem;
 use crate::http::http_user::HttpUserExtractor;
 
 pub async fn handler(HttpUserExtractor(http_user): HttpUserExtractor,
@@ -106,8 +107,20 @@ pub async fn handler_steam(HttpUserExtractor(http_user): HttpUserExtractor,
 
     // get user
     let mut user : Option<UserItem> = None;
-    if let Some(some_steam_account) = get_steam_account(app_state, uri).await {
+    if let Some(some_steam_account) = get_steam_account(app_state.clone(), uri).await {
+        if let Err(e) = some_steam_account.set_last_login(Utc::now()).await {
+            log::error!("Could not update last login of {}: {}", some_steam_account.display().await, e);
+        }
         user = some_steam_account.user().await;  // if no user is assigned, a new user is created
+
+        // synchronize user from league
+        if let (Some(league), Some(some_user)) = (app_state.config.league.as_ref(), user.as_mut()) {
+            match crate::league::user_by_steam_id(league, &some_steam_account.steam_id().await).await {
+                Ok(Some(league_user)) => crate::league::apply_to_user(league_user, some_user).await,
+                Ok(None) => crate::league::revoke_from_user(some_user).await,
+                Err(e) => log::error!("{}", e),
+            }
+        }
     }
 
     // create login cookie
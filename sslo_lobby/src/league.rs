@@ -0,0 +1,110 @@
+use sslo_lib::db::members::users::{Promotion, PromotionLevel, UserItem};
+use sslo_lib::error::SsloError;
+use sslo_lib::federation::FederatedUser;
+use crate::config::League;
+
+/// Request a user from the league
+/// Returns None if the league does not know the user
+async fn request_user(league: &League, path: &str) -> Result<Option<FederatedUser>, SsloError> {
+    let url = format!("{}{}", league.url.trim_end_matches('/'), path);
+    let mut request = reqwest::Client::new().get(&url);
+    for (name, value) in sslo_lib::federation::signed_headers(&league.secret, &league.lobby_name, path) {
+        request = request.header(name, value);
+    }
+
+    let response = request.send().await
+        .map_err(|e| SsloError::FederationRequestFailed(url.clone(), e.to_string()))?;
+    match response.status() {
+        reqwest::StatusCode::OK => {
+            let user = response.json::<FederatedUser>().await
+                .map_err(|e| SsloError::FederationRequestFailed(url.clone(), e.to_string()))?;
+            Ok(Some(user))
+        },
+        reqwest::StatusCode::NOT_FOUND => Ok(None),
+        status => Err(SsloError::FederationRequestFailed(url, status.to_string())),
+    }
+}
+
+/// Resolve a steam-id to a user of the league
+pub async fn user_by_steam_id(league: &League, steam_id: &str) -> Result<Option<FederatedUser>, SsloError> {
+    request_user(league, &format!("/api/v0/federation/user/steam/{}", steam_id)).await
+}
+
+/// Apply the name and promotion of a league user to a lobby user
+pub async fn apply_to_user(league_user: FederatedUser, user: &mut UserItem) {
+    if let Err(e) = user.set_name(league_user.name).await {
+        log::error!("Could not update name of {} from league: {}", user.display().await, e);
+    }
+    user.set_promotion(Promotion::new(league_user.promotion_level, league_user.promotion_authority)).await;
+    if let Some(last_lap) = league_user.last_lap {
+        user.set_last_lap(last_lap).await;
+    }
+    log::info!("synchronized {} from league user-id={}", user.display().await, league_user.id);
+}
+
+/// Revoke league promotions from a lobby user (e.g. when the league does not know the user anymore)
+pub async fn revoke_from_user(user: &mut UserItem) {
+    if user.promotion().await.level != PromotionLevel::None {
+        log::info!("revoke league promotion from {}", user.display().await);
+        user.set_promotion(Promotion::new_lowest()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::OriginalUri;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::response::{IntoResponse, Response};
+    use axum::{routing, Json, Router};
+    use sslo_lib::db::members::users::PromotionAuthority;
+    use sslo_lib::federation::{HEADER_LOBBY, HEADER_SIGNATURE, HEADER_TIMESTAMP};
+    use super::*;
+    use test_log::test;
+
+    /// minimal league server, that only knows steam-id '123'
+    async fn handler(OriginalUri(uri): OriginalUri, headers: HeaderMap) -> Response {
+        let header = |name: &str| headers.get(name).unwrap().to_str().unwrap().to_string();
+        let timestamp: i64 = header(HEADER_TIMESTAMP).parse().unwrap();
+        if !sslo_lib::federation::verify("secret", &header(HEADER_LOBBY), timestamp, uri.path(), &header(HEADER_SIGNATURE)) {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+        if uri.path() != "/api/v0/federation/user/steam/123" {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        Json(FederatedUser {
+            id: 7,
+            name: "Foo".to_string(),
+            promotion_level: PromotionLevel::Marshal,
+            promotion_authority: PromotionAuthority::Chief,
+            last_lap: None,
+            last_login: None,
+        }).into_response()
+    }
+
+    async fn start_league() -> String {
+        let router = Router::new().route("/api/v0/federation/user/steam/:steam_id", routing::get(handler));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        url
+    }
+
+    #[test(tokio::test)]
+    async fn resolve_steam_id() {
+        let mut league = League {
+            url: start_league().await,
+            lobby_name: "lobby1".to_string(),
+            secret: "secret".to_string(),
+        };
+
+        // known and unknown user
+        let user = user_by_steam_id(&league, "123").await.unwrap().unwrap();
+        assert_eq!(user.id, 7);
+        assert_eq!(user.promotion_level, PromotionLevel::Marshal);
+        assert!(user_by_steam_id(&league, "456").await.unwrap().is_none());
+
+        // wrong secret
+        league.secret = "wrong".to_string();
+        assert!(user_by_steam_id(&league, "123").await.is_err());
+    }
+}
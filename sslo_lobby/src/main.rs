@@ -9,6 +9,7 @@ mod http;
 mod config;
 mod app_state;
 mod db;
+mod league;
 
 #[derive(Parser)]
 struct CliArgs {